@@ -1,6 +1,6 @@
 use crate::{get_keypair, is_hidden, keys_sync};
 use anchor_client::Cluster;
-use anchor_lang_idl::types::Idl;
+use anchor_lang_idl::{lint::IdlLintLevels, types::Idl};
 use anyhow::{anyhow, bail, Context, Error, Result};
 use clap::{Parser, ValueEnum};
 use dirs::home_dir;
@@ -356,6 +356,7 @@ impl<T> std::ops::DerefMut for WithPath<T> {
 pub struct Config {
     pub toolchain: ToolchainConfig,
     pub features: FeaturesConfig,
+    pub lints: IdlLintLevels,
     pub registry: RegistryConfig,
     pub provider: ProviderConfig,
     pub programs: ProgramsConfig,
@@ -573,6 +574,7 @@ impl Config {
 struct _Config {
     toolchain: Option<ToolchainConfig>,
     features: Option<FeaturesConfig>,
+    lints: Option<IdlLintLevels>,
     programs: Option<BTreeMap<String, BTreeMap<String, serde_json::Value>>>,
     registry: Option<RegistryConfig>,
     provider: Provider,
@@ -666,6 +668,7 @@ impl fmt::Display for Config {
         let cfg = _Config {
             toolchain: Some(self.toolchain.clone()),
             features: Some(self.features.clone()),
+            lints: (!self.lints.is_empty()).then(|| self.lints.clone()),
             registry: Some(self.registry.clone()),
             provider: Provider {
                 cluster: self.provider.cluster.clone(),
@@ -695,6 +698,7 @@ impl FromStr for Config {
         Ok(Config {
             toolchain: cfg.toolchain.unwrap_or_default(),
             features: cfg.features.unwrap_or_default(),
+            lints: cfg.lints.unwrap_or_default(),
             registry: cfg.registry.unwrap_or_default(),
             provider: ProviderConfig {
                 cluster: cfg.provider.cluster,
@@ -787,6 +791,7 @@ pub struct TestValidator {
     pub startup_wait: i32,
     pub shutdown_wait: i32,
     pub upgradeable: bool,
+    pub fixtures: FixturesConfig,
 }
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
@@ -801,10 +806,41 @@ pub struct _TestValidator {
     pub shutdown_wait: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub upgradeable: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fixtures: Option<FixturesConfig>,
 }
 
 pub const STARTUP_WAIT: i32 = 5000;
 pub const SHUTDOWN_WAIT: i32 = 2000;
+pub const FIXTURES_DIR: &str = "tests/fixtures";
+pub const FIXTURES_MAX_AGE_DAYS: u64 = 30;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FixturesConfig {
+    // Directory of the fixtures of the cloned accounts, relative to the workspace root.
+    #[serde(default = "default_fixtures_dir")]
+    pub dir: String,
+    // Number of days after which the fixtures are reported as stale.
+    #[serde(default = "default_fixtures_max_age_days")]
+    pub max_age_days: u64,
+}
+
+impl Default for FixturesConfig {
+    fn default() -> Self {
+        Self {
+            dir: default_fixtures_dir(),
+            max_age_days: default_fixtures_max_age_days(),
+        }
+    }
+}
+
+fn default_fixtures_dir() -> String {
+    FIXTURES_DIR.to_owned()
+}
+
+fn default_fixtures_max_age_days() -> u64 {
+    FIXTURES_MAX_AGE_DAYS
+}
 
 impl From<_TestValidator> for TestValidator {
     fn from(_test_validator: _TestValidator) -> Self {
@@ -814,6 +850,7 @@ impl From<_TestValidator> for TestValidator {
             genesis: _test_validator.genesis,
             validator: _test_validator.validator.map(Into::into),
             upgradeable: _test_validator.upgradeable.unwrap_or(false),
+            fixtures: _test_validator.fixtures.unwrap_or_default(),
         }
     }
 }
@@ -826,6 +863,7 @@ impl From<TestValidator> for _TestValidator {
             genesis: test_validator.genesis,
             validator: test_validator.validator.map(Into::into),
             upgradeable: Some(test_validator.upgradeable),
+            fixtures: Some(test_validator.fixtures),
         }
     }
 }
@@ -987,6 +1025,9 @@ impl Merge for _TestToml {
                             None => my_test.genesis = Some(other_genesis),
                         }
                     }
+                    if let Some(fixtures) = other_test.fixtures {
+                        my_test.fixtures = Some(fixtures);
+                    }
                     let mut my_validator = my_test.validator.take();
                     match &mut my_validator {
                         None => my_validator = other_test.validator,
@@ -1469,6 +1510,7 @@ home_path!(WalletPath, ".config/solana/id.json");
 #[cfg(test)]
 mod tests {
     use super::*;
+    use anchor_lang_idl::lint::{IdlLint, IdlLintLevel};
 
     const BASE_CONFIG: &str = "
         [provider]
@@ -1533,4 +1575,25 @@ mod tests {
         let config = Config::from_str(&string).unwrap();
         assert!(!config.features.skip_lint);
     }
+
+    #[test]
+    fn parse_lints() {
+        let string = BASE_CONFIG.to_owned()
+            + "[lints]\nunchecked-writable = \"deny\"\nunused-instruction-arg = \"allow\"";
+        let config = Config::from_str(&string).unwrap();
+        assert_eq!(
+            config.lints,
+            IdlLintLevels::from([
+                (IdlLint::UncheckedWritable, IdlLintLevel::Deny),
+                (IdlLint::UnusedInstructionArg, IdlLintLevel::Allow),
+            ])
+        );
+        assert!(config.to_string().contains("[lints]"));
+    }
+
+    #[test]
+    fn parse_lints_invalid_level() {
+        let string = BASE_CONFIG.to_owned() + "[lints]\nunchecked-writable = \"error\"";
+        assert!(Config::from_str(&string).is_err());
+    }
 }
@@ -0,0 +1,549 @@
+//! Code coverage of the workspace programs with `anchor test --coverage`.
+//!
+//! The tests run against the in-process SVM, where the programs that are given a native processor
+//! run natively instead of from their SBF binaries. The tests are built with
+//! `-C instrument-coverage`, so running them writes the profiles of the programs, which are then
+//! turned into lcov and HTML reports for each program in `.anchor/coverage/<program>`.
+//!
+//! The code that Anchor generates, e.g. the instruction dispatch and `try_accounts`, is attributed
+//! to the attributes that generate it, and is excluded from the reports by default.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::config::Program;
+
+/// Directory of the coverage profiles and reports, relative to the workspace root.
+pub const COVERAGE_DIR: &str = ".anchor/coverage";
+
+/// Attributes of items that Anchor generates code for.
+const GENERATING_ATTRIBUTES: &[&str] = &[
+    "program",
+    "account",
+    "event",
+    "error_code",
+    "constant",
+    "instruction",
+    "zero_copy",
+];
+
+/// Derives that Anchor generates code for.
+const GENERATING_DERIVES: &[&str] = &[
+    "Accounts",
+    "AnchorSerialize",
+    "AnchorDeserialize",
+    "InitSpace",
+];
+
+/// Macros that Anchor generates code for.
+const GENERATING_MACROS: &[&str] = &["declare_id!", "declare_program!"];
+
+/// Environment variables of the test scripts, which build the tests with coverage
+/// instrumentation and write the profiles to the coverage directory.
+pub fn env() -> Result<Vec<(&'static str, String)>> {
+    let profraw_dir = Path::new(COVERAGE_DIR).join("profraw");
+    if profraw_dir.exists() {
+        fs::remove_dir_all(&profraw_dir)?;
+    }
+    fs::create_dir_all(&profraw_dir)?;
+
+    let rustflags = match std::env::var("RUSTFLAGS") {
+        Ok(rustflags) if !rustflags.is_empty() => format!("{rustflags} -C instrument-coverage"),
+        _ => "-C instrument-coverage".to_owned(),
+    };
+    Ok(vec![
+        ("RUSTFLAGS", rustflags),
+        (
+            "LLVM_PROFILE_FILE",
+            profraw_dir
+                .canonicalize()?
+                .join("%p-%m.profraw")
+                .display()
+                .to_string(),
+        ),
+        // Keep the instrumented build apart from the regular one
+        ("CARGO_TARGET_DIR", target_dir()?.display().to_string()),
+        ("CARGO_INCREMENTAL", "0".to_owned()),
+    ])
+}
+
+/// Write the coverage reports of the programs from the profiles of the tests.
+pub fn report(programs: &[Program], include_generated: bool) -> Result<()> {
+    let coverage_dir = Path::new(COVERAGE_DIR);
+    let profraws = fs::read_dir(coverage_dir.join("profraw"))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .filter(|path| {
+            path.as_ref()
+                .map_or(true, |path| path.extension() == Some("profraw".as_ref()))
+        })
+        .collect::<std::io::Result<Vec<_>>>()?;
+    if profraws.is_empty() {
+        return Err(anyhow!(
+            "No coverage data was collected. Coverage is only measured for Rust tests that run the \
+            programs natively, see `TestGenesis::start_with_processors`."
+        ));
+    }
+
+    let profdata = coverage_dir.join("coverage.profdata");
+    run(Command::new(llvm_tool("llvm-profdata")?)
+        .args(["merge", "-sparse", "-o"])
+        .arg(&profdata)
+        .args(&profraws))?;
+
+    let objects = instrumented_objects(&target_dir()?.join("debug").join("deps"))?;
+    let (first, rest) = objects
+        .split_first()
+        .ok_or_else(|| anyhow!("No instrumented test binaries found"))?;
+    let mut export = Command::new(llvm_tool("llvm-cov")?);
+    export
+        .args(["export", "-format=lcov"])
+        .arg(format!("-instr-profile={}", profdata.display()))
+        .arg(first);
+    for object in rest {
+        export.arg("-object").arg(object);
+    }
+    let lcov = run(&mut export)?;
+    let files = parse_lcov(&lcov)?;
+
+    for program in programs {
+        let mut program_files = files
+            .iter()
+            .filter(|file| resolve_path(&file.path).starts_with(&program.path))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !include_generated {
+            for file in &mut program_files {
+                let source = fs::read_to_string(resolve_path(&file.path))?;
+                file.exclude_lines(&generated_lines(&source));
+            }
+        }
+
+        let program_dir = coverage_dir.join(&program.lib_name);
+        if program_dir.exists() {
+            fs::remove_dir_all(&program_dir)?;
+        }
+        fs::create_dir_all(program_dir.join("html"))?;
+        fs::write(
+            program_dir.join("lcov.info"),
+            program_files
+                .iter()
+                .map(FileCoverage::to_lcov)
+                .collect::<String>(),
+        )?;
+        write_html(&program_dir.join("html"), &program.path, &program_files)?;
+
+        let (hit, found) = program_files
+            .iter()
+            .map(FileCoverage::line_summary)
+            .fold((0, 0), |(hit, found), (h, f)| (hit + h, found + f));
+        println!(
+            "{}: {hit}/{found} lines covered ({})",
+            program.lib_name,
+            percentage(hit, found)
+        );
+    }
+    println!("Coverage reports written to {COVERAGE_DIR}");
+
+    Ok(())
+}
+
+/// Coverage of a source file, as reported in lcov format.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FileCoverage {
+    pub path: PathBuf,
+    /// Line and hit count of the functions, by name.
+    pub functions: BTreeMap<String, (u32, u64)>,
+    /// Hit count of the lines.
+    pub lines: BTreeMap<u32, u64>,
+    pub branches: Vec<BranchCoverage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BranchCoverage {
+    pub line: u32,
+    pub block: String,
+    pub branch: String,
+    /// Hit count of the branch, `None` if its condition was never evaluated.
+    pub taken: Option<u64>,
+}
+
+impl FileCoverage {
+    /// Remove the lines from the coverage, along with their functions and branches.
+    pub fn exclude_lines(&mut self, lines: &BTreeSet<u32>) {
+        self.functions.retain(|_, (line, _)| !lines.contains(line));
+        self.lines.retain(|line, _| !lines.contains(line));
+        self.branches.retain(|branch| !lines.contains(&branch.line));
+    }
+
+    /// Number of covered lines and number of instrumented lines.
+    pub fn line_summary(&self) -> (usize, usize) {
+        let hit = self.lines.values().filter(|count| **count > 0).count();
+        (hit, self.lines.len())
+    }
+
+    /// Format the coverage as an lcov record.
+    pub fn to_lcov(&self) -> String {
+        let mut lcov = format!("SF:{}\n", self.path.display());
+        for (name, (line, _)) in &self.functions {
+            writeln!(lcov, "FN:{line},{name}").unwrap();
+        }
+        for (name, (_, count)) in &self.functions {
+            writeln!(lcov, "FNDA:{count},{name}").unwrap();
+        }
+        writeln!(lcov, "FNF:{}", self.functions.len()).unwrap();
+        let functions_hit = self.functions.values().filter(|(_, count)| *count > 0);
+        writeln!(lcov, "FNH:{}", functions_hit.count()).unwrap();
+        for branch in &self.branches {
+            let taken = branch
+                .taken
+                .map_or_else(|| "-".to_owned(), |taken| taken.to_string());
+            writeln!(
+                lcov,
+                "BRDA:{},{},{},{taken}",
+                branch.line, branch.block, branch.branch
+            )
+            .unwrap();
+        }
+        writeln!(lcov, "BRF:{}", self.branches.len()).unwrap();
+        let branches_hit = self
+            .branches
+            .iter()
+            .filter(|branch| matches!(branch.taken, Some(taken) if taken > 0));
+        writeln!(lcov, "BRH:{}", branches_hit.count()).unwrap();
+        for (line, count) in &self.lines {
+            writeln!(lcov, "DA:{line},{count}").unwrap();
+        }
+        let (hit, found) = self.line_summary();
+        writeln!(lcov, "LF:{found}\nLH:{hit}\nend_of_record").unwrap();
+        lcov
+    }
+}
+
+/// Parse the coverage of the files from lcov format.
+pub fn parse_lcov(lcov: &str) -> Result<Vec<FileCoverage>> {
+    fn parse<T: std::str::FromStr>(value: &str, line: &str) -> Result<T> {
+        value
+            .parse()
+            .map_err(|_| anyhow!("Invalid lcov line: {line}"))
+    }
+
+    let mut files = vec![];
+    let mut file: Option<FileCoverage> = None;
+    for line in lcov.lines() {
+        if line == "end_of_record" {
+            files.extend(file.take());
+            continue;
+        }
+        let (key, value) = match line.split_once(':') {
+            Some(entry) => entry,
+            None => continue,
+        };
+        if key == "SF" {
+            file = Some(FileCoverage {
+                path: value.into(),
+                ..Default::default()
+            });
+            continue;
+        }
+        let file = match file.as_mut() {
+            Some(file) => file,
+            None => return Err(anyhow!("Invalid lcov line outside of a record: {line}")),
+        };
+
+        let fields = value.split(',').collect::<Vec<_>>();
+        match (key, fields.as_slice()) {
+            ("FN", [line_number, .., _]) => {
+                let (_, name) = value.rsplit_once(',').unwrap();
+                let function = file.functions.entry(name.to_owned()).or_default();
+                function.0 = parse(line_number, line)?;
+            }
+            ("FNDA", [count, _, ..]) => {
+                let (_, name) = value.split_once(',').unwrap();
+                let function = file.functions.entry(name.to_owned()).or_default();
+                function.1 = parse(count, line)?;
+            }
+            ("DA", [line_number, count, ..]) => {
+                *file.lines.entry(parse(line_number, line)?).or_default() +=
+                    parse::<u64>(count, line)?;
+            }
+            ("BRDA", [line_number, block, branch, taken]) => {
+                file.branches.push(BranchCoverage {
+                    line: parse(line_number, line)?,
+                    block: block.to_string(),
+                    branch: branch.to_string(),
+                    taken: match *taken {
+                        "-" => None,
+                        taken => Some(parse(taken, line)?),
+                    },
+                });
+            }
+            // The summaries are computed when the coverage is written
+            _ => {}
+        }
+    }
+
+    Ok(files)
+}
+
+/// Lines of the code that Anchor generates, i.e. the lines of the attributes and macros that
+/// generate code, and the structs that derive `Accounts`, whose constraints are checked by the
+/// generated `try_accounts`.
+pub fn generated_lines(source: &str) -> BTreeSet<u32> {
+    let mut lines = BTreeSet::new();
+    // Depth of the braces of the `Accounts` struct, if the line is in one
+    let mut accounts_struct: Option<(usize, bool)> = None;
+    for (index, line) in source.lines().enumerate() {
+        let number = index as u32 + 1;
+        let line = line.trim();
+
+        if let Some((depth, opened)) = accounts_struct.as_mut() {
+            lines.insert(number);
+            for c in line.chars() {
+                match c {
+                    '{' => {
+                        *depth += 1;
+                        *opened = true;
+                    }
+                    '}' => *depth = depth.saturating_sub(1),
+                    _ => {}
+                }
+            }
+            if (*opened && *depth == 0) || (!*opened && line.ends_with(';')) {
+                accounts_struct = None;
+            }
+            continue;
+        }
+
+        if GENERATING_MACROS.iter().any(|name| line.starts_with(name)) {
+            lines.insert(number);
+            continue;
+        }
+        let attribute = match line.strip_prefix("#[") {
+            Some(attribute) => attribute,
+            None => continue,
+        };
+        let (path, args) = attribute
+            .split_once(|c: char| !(c.is_alphanumeric() || c == '_' || c == ':'))
+            .unwrap_or((attribute, ""));
+        let name = path.rsplit("::").next().unwrap_or(path);
+        if GENERATING_ATTRIBUTES.contains(&name) {
+            lines.insert(number);
+        } else if name == "derive" {
+            let derives = args
+                .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == ':'))
+                .filter_map(|derive| derive.rsplit("::").next())
+                .collect::<Vec<_>>();
+            if derives
+                .iter()
+                .any(|derive| GENERATING_DERIVES.contains(derive))
+            {
+                lines.insert(number);
+            }
+            if derives.contains(&"Accounts") {
+                accounts_struct = Some((0, false));
+            }
+        }
+    }
+
+    lines
+}
+
+/// Write the HTML report of the files of a program.
+fn write_html(dir: &Path, program_path: &Path, files: &[FileCoverage]) -> Result<()> {
+    const STYLE: &str = "<style>body{font-family:monospace}table{border-collapse:collapse}\
+        td{padding:0 8px}.hit{background:#dfd}.miss{background:#fdd}</style>";
+
+    let mut index = format!("<html><head>{STYLE}</head><body><table>");
+    index.push_str("<tr><th>File</th><th>Lines</th><th>Coverage</th></tr>");
+    for (i, file) in files.iter().enumerate() {
+        let path = resolve_path(&file.path);
+        let name = path
+            .strip_prefix(program_path)
+            .unwrap_or(&path)
+            .display()
+            .to_string();
+        let (hit, found) = file.line_summary();
+        write!(
+            index,
+            "<tr><td><a href=\"{i}.html\">{}</a></td><td>{hit}/{found}</td><td>{}</td></tr>",
+            escape_html(&name),
+            percentage(hit, found)
+        )?;
+
+        let source = fs::read_to_string(&path).unwrap_or_default();
+        let mut page = format!(
+            "<html><head>{STYLE}</head><body><h3>{}</h3><table>",
+            escape_html(&name)
+        );
+        for (index, line) in source.lines().enumerate() {
+            let number = index as u32 + 1;
+            let (class, count) = match file.lines.get(&number) {
+                Some(0) => ("miss", "0".to_owned()),
+                Some(count) => ("hit", count.to_string()),
+                None => ("", String::new()),
+            };
+            write!(
+                page,
+                "<tr class=\"{class}\"><td>{number}</td><td>{count}</td><td><pre>{}</pre></td></tr>",
+                escape_html(line)
+            )?;
+        }
+        page.push_str("</table></body></html>");
+        fs::write(dir.join(format!("{i}.html")), page)?;
+    }
+    index.push_str("</table></body></html>");
+    fs::write(dir.join("index.html"), index)?;
+
+    Ok(())
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn percentage(hit: usize, found: usize) -> String {
+    match found {
+        0 => "-".to_owned(),
+        _ => format!("{:.1}%", hit as f64 * 100.0 / found as f64),
+    }
+}
+
+/// Target directory of the instrumented build.
+fn target_dir() -> Result<PathBuf> {
+    Ok(std::env::current_dir()?.join("target").join("coverage"))
+}
+
+/// Instrumented test binaries in the `deps` directory of the target directory.
+fn instrumented_objects(deps_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut objects = vec![];
+    for entry in
+        fs::read_dir(deps_dir).with_context(|| format!("Failed to read {}", deps_dir.display()))?
+    {
+        let path = entry?.path();
+        let is_executable = match path.extension() {
+            None => true,
+            Some(extension) => extension == std::env::consts::EXE_EXTENSION,
+        };
+        if path.is_file() && is_executable {
+            objects.push(path);
+        }
+    }
+    objects.sort();
+
+    Ok(objects)
+}
+
+/// Path of an LLVM tool of the `llvm-tools` component of the Rust toolchain, whose version
+/// matches the LLVM version of `rustc`.
+fn llvm_tool(name: &str) -> Result<PathBuf> {
+    let libdir = run(Command::new("rustc").args(["--print", "target-libdir"]))?;
+    let path = Path::new(libdir.trim())
+        .with_file_name("bin")
+        .join(name)
+        .with_extension(std::env::consts::EXE_EXTENSION);
+    if path.exists() {
+        Ok(path)
+    } else {
+        Err(anyhow!(
+            "{name} not found, install it with `rustup component add llvm-tools-preview`"
+        ))
+    }
+}
+
+fn resolve_path(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_owned())
+}
+
+fn run(command: &mut Command) -> Result<String> {
+    let output = command
+        .stderr(Stdio::inherit())
+        .output()
+        .with_context(|| format!("Failed to run {:?}", command.get_program()))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{:?} failed with {}",
+            command.get_program(),
+            output.status
+        ));
+    }
+
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lcov() {
+        let lcov = "\
+SF:programs/foo/src/lib.rs
+FN:10,_RNvCs_3foo10initialize
+FN:3,_RNvCs_3foo5entry
+FNDA:0,_RNvCs_3foo10initialize
+FNDA:2,_RNvCs_3foo5entry
+FNF:2
+FNH:1
+BRDA:11,0,0,1
+BRDA:11,0,1,-
+BRF:2
+BRH:1
+DA:3,2
+DA:10,0
+DA:11,0
+LF:3
+LH:1
+end_of_record
+";
+        let mut files = parse_lcov(lcov).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].to_lcov(), lcov);
+        assert_eq!(files[0].line_summary(), (1, 3));
+
+        files[0].exclude_lines(&BTreeSet::from([3]));
+        assert_eq!(files[0].line_summary(), (0, 2));
+        assert_eq!(files[0].functions.len(), 1);
+        assert_eq!(files[0].branches.len(), 2);
+    }
+
+    #[test]
+    fn test_generated_lines() {
+        let source = r#"use anchor_lang::prelude::*;
+
+declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+#[program]
+pub mod foo {
+    use super::*;
+
+    pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Data {
+    pub value: u64,
+}
+"#;
+        assert_eq!(
+            generated_lines(source),
+            BTreeSet::from([3, 5, 14, 15, 16, 17, 18, 20, 21])
+        );
+    }
+}
@@ -0,0 +1,611 @@
+//! Native program deployment with the BPF upgradeable loader.
+//!
+//! Programs are deployed by writing the program binary into a buffer account, and then either
+//! deploying a new program from the buffer, or upgrading an existing program with it.
+//!
+//! Buffers are written in parallel, and an existing buffer that is owned by the payer and has the
+//! size of the program is reused, in which case only the chunks that differ from the program are
+//! written. This makes it possible to resume a deployment that failed while writing the buffer
+//! by deploying with the same buffer keypair again.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anchor_lang::AccountDeserialize;
+use solana_client::client_error::ClientError;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::bpf_loader_upgradeable::{self, UpgradeableLoaderState};
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::message::Message;
+use solana_sdk::packet::PACKET_DATA_SIZE;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+use solana_sdk::transaction::Transaction;
+use thiserror::Error;
+
+/// Number of buffer writes that are sent at the same time.
+pub const DEFAULT_WRITE_PARALLELISM: usize = 8;
+
+/// Age after which the blockhash of the buffer writes is refreshed.
+const BLOCKHASH_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Error)]
+pub enum DeployError {
+    #[error("RPC error: {0}")]
+    Rpc(Box<ClientError>),
+    #[error("Program {0} is not an upgradeable program")]
+    NotUpgradeable(Pubkey),
+    #[error("Program {0} is immutable, its upgrade authority was burned")]
+    Immutable(Pubkey),
+    #[error("Program {program} has upgrade authority {authority}, not the payer {payer}")]
+    InvalidUpgradeAuthority {
+        program: Pubkey,
+        authority: Pubkey,
+        payer: Pubkey,
+    },
+    #[error("Program {0} is not deployed, its keypair is required for the initial deploy")]
+    MissingProgramKeypair(Pubkey),
+    #[error("Buffer {buffer} is not a buffer with the authority {payer}")]
+    InvalidBuffer { buffer: Pubkey, payer: Pubkey },
+    #[error(
+        "Failed to write {} chunk(s) of buffer {buffer}:{}",
+        errors.len(),
+        format_write_errors(errors)
+    )]
+    WriteFailed {
+        buffer: Pubkey,
+        errors: Vec<(usize, String)>,
+    },
+    #[error("Program is too large: {0} bytes")]
+    ProgramTooLarge(usize),
+}
+
+impl From<ClientError> for DeployError {
+    fn from(e: ClientError) -> Self {
+        Self::Rpc(Box::new(e))
+    }
+}
+
+fn format_write_errors(errors: &[(usize, String)]) -> String {
+    errors
+        .iter()
+        .map(|(offset, e)| format!("\n  offset {offset}: {e}"))
+        .collect()
+}
+
+pub type DeployResult<T> = Result<T, DeployError>;
+
+/// Progress of a deployment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeployProgress {
+    /// The given number of buffer chunks out of the total have been written.
+    Write { written: usize, total: usize },
+    /// The program is being deployed or upgraded from the buffer.
+    Deploy,
+}
+
+/// Deploys programs with the BPF upgradeable loader.
+///
+/// The payer pays for all transactions and is the upgrade authority of the deployed programs.
+pub struct Deployer<'a> {
+    client: &'a RpcClient,
+    payer: &'a Keypair,
+    priority_fee: u64,
+    max_retries: u32,
+    parallelism: usize,
+    progress: Box<dyn Fn(DeployProgress) + Send + Sync + 'a>,
+}
+
+impl<'a> Deployer<'a> {
+    pub fn new(client: &'a RpcClient, payer: &'a Keypair) -> Self {
+        Self {
+            client,
+            payer,
+            priority_fee: 0,
+            max_retries: 5,
+            parallelism: DEFAULT_WRITE_PARALLELISM,
+            progress: Box::new(|_| {}),
+        }
+    }
+
+    /// Set the priority fee in micro-lamports per compute unit (default: 0).
+    pub fn priority_fee(mut self, priority_fee: u64) -> Self {
+        self.priority_fee = priority_fee;
+        self
+    }
+
+    /// Set how many times the writes of the buffer chunks that failed are retried (default: 5).
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Set the number of buffer writes that are sent at the same time (default:
+    /// [`DEFAULT_WRITE_PARALLELISM`]).
+    pub fn parallelism(mut self, parallelism: usize) -> Self {
+        self.parallelism = parallelism.max(1);
+        self
+    }
+
+    /// Set the function that is called with the progress of the deployment.
+    pub fn on_progress(mut self, progress: impl Fn(DeployProgress) + Send + Sync + 'a) -> Self {
+        self.progress = Box::new(progress);
+        self
+    }
+
+    /// Deploy the program binary, or upgrade the program if it's already deployed.
+    ///
+    /// The program keypair is only required for the initial deploy.
+    pub fn deploy(
+        &self,
+        program_id: &Pubkey,
+        program_keypair: Option<&Keypair>,
+        program_data: &[u8],
+        buffer: &Keypair,
+    ) -> DeployResult<Signature> {
+        // Check the program before writing the buffer, to fail early
+        let deployed = self.check_program(program_id)?;
+        if deployed.is_none() && program_keypair.is_none() {
+            return Err(DeployError::MissingProgramKeypair(*program_id));
+        }
+
+        self.write_buffer(buffer, program_data)?;
+        (self.progress)(DeployProgress::Deploy);
+
+        match (deployed, program_keypair) {
+            (Some(program_data_len), _) => {
+                self.upgrade(program_id, program_data_len, program_data, buffer)
+            }
+            (None, Some(program_keypair)) => self.deploy_new(program_keypair, program_data, buffer),
+            (None, None) => unreachable!(),
+        }
+    }
+
+    /// Check that the program can be upgraded by the payer if it's deployed.
+    ///
+    /// Returns the length of the program data if it's deployed.
+    fn check_program(&self, program_id: &Pubkey) -> DeployResult<Option<usize>> {
+        let state = match get_program_state(self.client, program_id)? {
+            Some(state) => state,
+            None => return Ok(None),
+        };
+        match state.upgrade_authority {
+            Some(authority) if authority == self.payer.pubkey() => Ok(Some(state.data_len)),
+            Some(authority) => Err(DeployError::InvalidUpgradeAuthority {
+                program: *program_id,
+                authority,
+                payer: self.payer.pubkey(),
+            }),
+            None => Err(DeployError::Immutable(*program_id)),
+        }
+    }
+
+    /// Write the program data into the buffer, creating the buffer if it doesn't exist.
+    ///
+    /// The payer is the authority of the buffer.
+    pub fn write_buffer(&self, buffer: &Keypair, program_data: &[u8]) -> DeployResult<()> {
+        let buffer_address = buffer.pubkey();
+        let payer_address = self.payer.pubkey();
+        let buffer_len = UpgradeableLoaderState::size_of_buffer(program_data.len());
+        let data_offset = UpgradeableLoaderState::size_of_buffer_metadata();
+
+        // Reuse the buffer if it's owned by the payer and has the size of the program, otherwise
+        // close it, which requires the payer to be the authority of the buffer
+        let mut buffer_data = None;
+        if let Some(account) = self
+            .client
+            .get_account_with_commitment(&buffer_address, self.client.commitment())?
+            .value
+        {
+            match UpgradeableLoaderState::try_deserialize(&mut &account.data[..]) {
+                Ok(UpgradeableLoaderState::Buffer {
+                    authority_address: Some(authority),
+                }) if authority == payer_address => {
+                    if account.data.len() == buffer_len {
+                        buffer_data = Some(account.data[data_offset..].to_vec());
+                    } else {
+                        let ix = bpf_loader_upgradeable::close(
+                            &buffer_address,
+                            &payer_address,
+                            &payer_address,
+                        );
+                        self.send(vec![ix], &[])?;
+                    }
+                }
+                _ => {
+                    return Err(DeployError::InvalidBuffer {
+                        buffer: buffer_address,
+                        payer: payer_address,
+                    })
+                }
+            }
+        }
+
+        let buffer_data = match buffer_data {
+            Some(buffer_data) => buffer_data,
+            None => {
+                let lamports = self
+                    .client
+                    .get_minimum_balance_for_rent_exemption(buffer_len)?;
+                let ixs = bpf_loader_upgradeable::create_buffer(
+                    &payer_address,
+                    &buffer_address,
+                    &payer_address,
+                    lamports,
+                    program_data.len(),
+                )
+                .map_err(|_| DeployError::ProgramTooLarge(program_data.len()))?;
+                self.send(ixs, &[buffer])?;
+                vec![0; program_data.len()]
+            }
+        };
+
+        let chunk_size = self.max_chunk_size(&buffer_address);
+        let mut offsets = changed_chunks(&buffer_data, program_data, chunk_size);
+        let total = program_data.len().div_ceil(chunk_size);
+        let mut failed = vec![];
+        for retry in 0..=self.max_retries {
+            let written = total - offsets.len();
+            (self.progress)(DeployProgress::Write { written, total });
+            if offsets.is_empty() {
+                return Ok(());
+            }
+
+            failed = self.write_chunks(
+                &buffer_address,
+                program_data,
+                chunk_size,
+                &offsets,
+                written,
+                total,
+            )?;
+            if failed.is_empty() || retry == self.max_retries {
+                break;
+            }
+
+            // Compare with the buffer to find out which of the failed writes didn't land
+            let account = self.client.get_account(&buffer_address)?;
+            offsets = changed_chunks(&account.data[data_offset..], program_data, chunk_size)
+                .into_iter()
+                .filter(|offset| failed.iter().any(|(failed, _)| failed == offset))
+                .collect();
+        }
+
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(DeployError::WriteFailed {
+                buffer: buffer_address,
+                errors: failed,
+            })
+        }
+    }
+
+    /// Write the chunks at the given offsets in parallel.
+    ///
+    /// Returns the offsets of the chunks whose write failed, with the errors of the writes.
+    fn write_chunks(
+        &self,
+        buffer_address: &Pubkey,
+        program_data: &[u8],
+        chunk_size: usize,
+        offsets: &[usize],
+        written: usize,
+        total: usize,
+    ) -> DeployResult<Vec<(usize, String)>> {
+        let blockhash = Mutex::new((self.client.get_latest_blockhash()?, Instant::now()));
+        let next = AtomicUsize::new(0);
+        let written = AtomicUsize::new(written);
+        let failed = Mutex::new(vec![]);
+
+        std::thread::scope(|scope| {
+            for _ in 0..self.parallelism.min(offsets.len()) {
+                scope.spawn(|| {
+                    // Each worker has its own client, as the blocking client sends its requests
+                    // from a single-threaded runtime
+                    let client =
+                        RpcClient::new_with_commitment(self.client.url(), self.client.commitment());
+                    while let Some(&offset) = offsets.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        let end = (offset + chunk_size).min(program_data.len());
+                        let ix = bpf_loader_upgradeable::write(
+                            buffer_address,
+                            &self.payer.pubkey(),
+                            offset as u32,
+                            program_data[offset..end].to_vec(),
+                        );
+                        let result = self.get_blockhash(&blockhash).and_then(|blockhash| {
+                            let tx = self.transaction(vec![ix], &[], blockhash);
+                            Ok(client.send_and_confirm_transaction(&tx)?)
+                        });
+                        match result {
+                            Ok(_) => {
+                                let written = written.fetch_add(1, Ordering::Relaxed) + 1;
+                                (self.progress)(DeployProgress::Write { written, total });
+                            }
+                            Err(e) => failed.lock().unwrap().push((offset, e.to_string())),
+                        }
+                    }
+                });
+            }
+        });
+
+        let mut failed = failed.into_inner().unwrap();
+        failed.sort_unstable_by_key(|(offset, _)| *offset);
+        Ok(failed)
+    }
+
+    /// Get the shared blockhash of the buffer writes, refreshing it if it's too old.
+    fn get_blockhash(&self, blockhash: &Mutex<(Hash, Instant)>) -> DeployResult<Hash> {
+        let mut blockhash = blockhash.lock().unwrap();
+        if blockhash.1.elapsed() > BLOCKHASH_REFRESH_INTERVAL {
+            *blockhash = (self.client.get_latest_blockhash()?, Instant::now());
+        }
+        Ok(blockhash.0)
+    }
+
+    fn deploy_new(
+        &self,
+        program_keypair: &Keypair,
+        program_data: &[u8],
+        buffer: &Keypair,
+    ) -> DeployResult<Signature> {
+        let lamports = self
+            .client
+            .get_minimum_balance_for_rent_exemption(UpgradeableLoaderState::size_of_program())?;
+        let ixs = bpf_loader_upgradeable::deploy_with_max_program_len(
+            &self.payer.pubkey(),
+            &program_keypair.pubkey(),
+            &buffer.pubkey(),
+            &self.payer.pubkey(),
+            lamports,
+            program_data.len(),
+        )
+        .map_err(|_| DeployError::ProgramTooLarge(program_data.len()))?;
+        self.send(ixs, &[program_keypair])
+    }
+
+    fn upgrade(
+        &self,
+        program_id: &Pubkey,
+        program_data_len: usize,
+        program_data: &[u8],
+        buffer: &Keypair,
+    ) -> DeployResult<Signature> {
+        let payer_address = self.payer.pubkey();
+        let ixs = upgrade_instructions(
+            program_id,
+            &buffer.pubkey(),
+            &payer_address,
+            &payer_address,
+            program_data_len,
+            program_data.len(),
+        )?;
+        self.send(ixs, &[])
+    }
+
+    /// Set the authority of a buffer whose authority is the payer, e.g. to upgrade the program
+    /// with a multisig.
+    pub fn set_buffer_authority(
+        &self,
+        buffer: &Pubkey,
+        new_authority: &Pubkey,
+    ) -> DeployResult<Signature> {
+        let ix = bpf_loader_upgradeable::set_buffer_authority(
+            buffer,
+            &self.payer.pubkey(),
+            new_authority,
+        );
+        self.send(vec![ix], &[])
+    }
+
+    /// Send and confirm a transaction that is signed by the payer and the given signers.
+    fn send(&self, ixs: Vec<Instruction>, signers: &[&Keypair]) -> DeployResult<Signature> {
+        let blockhash = self.client.get_latest_blockhash()?;
+        let tx = self.transaction(ixs, signers, blockhash);
+        self.client
+            .send_and_confirm_transaction_with_spinner(&tx)
+            .map_err(Into::into)
+    }
+
+    fn transaction(
+        &self,
+        ixs: Vec<Instruction>,
+        signers: &[&Keypair],
+        blockhash: Hash,
+    ) -> Transaction {
+        let ixs = self.with_priority_fee(ixs);
+        let mut all_signers = vec![self.payer];
+        all_signers.extend(signers);
+        Transaction::new_signed_with_payer(
+            &ixs,
+            Some(&self.payer.pubkey()),
+            &all_signers,
+            blockhash,
+        )
+    }
+
+    fn with_priority_fee(&self, mut ixs: Vec<Instruction>) -> Vec<Instruction> {
+        if self.priority_fee > 0 {
+            ixs.insert(
+                0,
+                ComputeBudgetInstruction::set_compute_unit_price(self.priority_fee),
+            );
+        }
+        ixs
+    }
+
+    /// Maximum size of a chunk that fits in a single write transaction.
+    fn max_chunk_size(&self, buffer_address: &Pubkey) -> usize {
+        let ix = bpf_loader_upgradeable::write(buffer_address, &self.payer.pubkey(), 0, vec![]);
+        let message = Message::new_with_blockhash(
+            &self.with_priority_fee(vec![ix]),
+            Some(&self.payer.pubkey()),
+            &Hash::default(),
+        );
+        let tx_size =
+            bincode::serialized_size(&Transaction::new_unsigned(message)).unwrap() as usize;
+        // The length of the instruction data takes an extra byte once it exceeds 127 bytes
+        PACKET_DATA_SIZE - tx_size - 1
+    }
+}
+
+/// State of a deployed upgradeable program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProgramState {
+    /// Length of the program data, without the metadata of the program data account.
+    pub data_len: usize,
+    /// Upgrade authority of the program, `None` if the program is immutable.
+    pub upgrade_authority: Option<Pubkey>,
+}
+
+/// Get the state of the program, `None` if the program is not deployed.
+pub fn get_program_state(
+    client: &RpcClient,
+    program_id: &Pubkey,
+) -> DeployResult<Option<ProgramState>> {
+    let program = match client
+        .get_account_with_commitment(program_id, client.commitment())?
+        .value
+    {
+        Some(program) => program,
+        None => return Ok(None),
+    };
+    if program.owner != bpf_loader_upgradeable::ID {
+        return Err(DeployError::NotUpgradeable(*program_id));
+    }
+    let programdata_address = match UpgradeableLoaderState::try_deserialize(&mut &program.data[..])
+    {
+        Ok(UpgradeableLoaderState::Program {
+            programdata_address,
+        }) => programdata_address,
+        _ => return Err(DeployError::NotUpgradeable(*program_id)),
+    };
+
+    let program_data = client.get_account(&programdata_address)?;
+    match UpgradeableLoaderState::try_deserialize(&mut &program_data.data[..]) {
+        Ok(UpgradeableLoaderState::ProgramData {
+            upgrade_authority_address,
+            ..
+        }) => Ok(Some(ProgramState {
+            data_len: program_data.data.len()
+                - UpgradeableLoaderState::size_of_programdata_metadata(),
+            upgrade_authority: upgrade_authority_address,
+        })),
+        _ => Err(DeployError::NotUpgradeable(*program_id)),
+    }
+}
+
+/// Instructions to upgrade the program with the buffer, which must be signed by the authority.
+///
+/// The program data account is extended first if the new program doesn't fit, which is paid for
+/// by the authority. The lamports of the buffer are transferred to the spill account.
+pub fn upgrade_instructions(
+    program_id: &Pubkey,
+    buffer: &Pubkey,
+    authority: &Pubkey,
+    spill: &Pubkey,
+    program_data_len: usize,
+    new_program_data_len: usize,
+) -> DeployResult<Vec<Instruction>> {
+    let mut ixs = vec![];
+    if new_program_data_len > program_data_len {
+        let additional_bytes = u32::try_from(new_program_data_len - program_data_len)
+            .map_err(|_| DeployError::ProgramTooLarge(new_program_data_len))?;
+        ixs.push(bpf_loader_upgradeable::extend_program(
+            program_id,
+            Some(authority),
+            additional_bytes,
+        ));
+    }
+    ixs.push(bpf_loader_upgradeable::upgrade(
+        program_id, buffer, authority, spill,
+    ));
+    Ok(ixs)
+}
+
+/// Offsets of the chunks of the program data that differ from the buffer data.
+fn changed_chunks(buffer_data: &[u8], program_data: &[u8], chunk_size: usize) -> Vec<usize> {
+    (0..program_data.len())
+        .step_by(chunk_size)
+        .filter(|&offset| {
+            let end = (offset + chunk_size).min(program_data.len());
+            buffer_data.get(offset..end) != Some(&program_data[offset..end])
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_chunks() {
+        let program_data = (0..=255).collect::<Vec<u8>>();
+
+        // Empty buffer
+        let buffer_data = vec![0; program_data.len()];
+        assert_eq!(
+            changed_chunks(&buffer_data, &program_data, 100),
+            vec![0, 100, 200]
+        );
+
+        // Partially written buffer
+        let mut buffer_data = program_data.clone();
+        buffer_data[150] = 0;
+        buffer_data[255] = 0;
+        assert_eq!(
+            changed_chunks(&buffer_data, &program_data, 100),
+            vec![100, 200]
+        );
+
+        // Fully written buffer
+        assert!(changed_chunks(&program_data, &program_data, 100).is_empty());
+    }
+
+    #[test]
+    fn test_upgrade_instructions() {
+        let program_id = Pubkey::new_unique();
+        let buffer = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+
+        let ixs =
+            upgrade_instructions(&program_id, &buffer, &authority, &authority, 100, 100).unwrap();
+        assert_eq!(ixs.len(), 1);
+        assert!(bpf_loader_upgradeable::is_upgrade_instruction(&ixs[0].data));
+
+        // Larger programs require extending the program data account
+        let ixs =
+            upgrade_instructions(&program_id, &buffer, &authority, &authority, 100, 150).unwrap();
+        assert_eq!(ixs.len(), 2);
+        assert!(ixs[0]
+            .accounts
+            .iter()
+            .any(|meta| meta.pubkey == authority && meta.is_signer));
+        assert!(bpf_loader_upgradeable::is_upgrade_instruction(&ixs[1].data));
+    }
+
+    #[test]
+    fn test_max_chunk_size() {
+        let client = RpcClient::new("http://localhost:8899".to_string());
+        let payer = Keypair::new();
+        let buffer = Pubkey::new_unique();
+
+        let deployer = Deployer::new(&client, &payer);
+        let chunk_size = deployer.max_chunk_size(&buffer);
+        let ix = bpf_loader_upgradeable::write(&buffer, &payer.pubkey(), 0, vec![0; chunk_size]);
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&payer.pubkey()),
+            &[&payer],
+            Hash::default(),
+        );
+        assert!(bincode::serialized_size(&tx).unwrap() as usize <= PACKET_DATA_SIZE);
+
+        // The priority fee instruction takes up space
+        let deployer = Deployer::new(&client, &payer).priority_fee(1);
+        assert!(deployer.max_chunk_size(&buffer) < chunk_size);
+    }
+}
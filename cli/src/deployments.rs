@@ -0,0 +1,337 @@
+//! Deployment state of the workspace programs on a cluster.
+//!
+//! Every deployment is recorded in `.anchor/deployments/<cluster>.json`, which stores the program
+//! id, the hashes of the deployed binary and IDL, the slot of the deployment, the upgrade
+//! authority and the git revision of the workspace. The state is tied to the genesis hash of the
+//! cluster, so it's discarded once a local validator is restarted.
+//!
+//! Deployments are planned by comparing the local binaries against the on-chain program data,
+//! which makes it possible to skip the programs that haven't changed.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anchor_client::Cluster;
+use anchor_lang::AccountDeserialize;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::bpf_loader_upgradeable::{self, UpgradeableLoaderState};
+use solana_sdk::hash::{hash, Hash};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::config::Program;
+
+/// Directory of the deployment state files, relative to the workspace root.
+pub const DEPLOYMENTS_DIR: &str = ".anchor/deployments";
+
+/// Deployment state of the workspace on a cluster.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeploymentState {
+    /// Genesis hash of the cluster the programs are deployed to.
+    pub genesis_hash: String,
+    /// Deployments by program name.
+    pub programs: BTreeMap<String, DeploymentRecord>,
+    /// Hash of the last migration script that ran successfully.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub migration_hash: Option<String>,
+    /// Versions of the applied Rust migrations.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub applied_migrations: Vec<u64>,
+}
+
+/// Last deployment of a program.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeploymentRecord {
+    pub program_id: String,
+    /// Hash of the program binary, see [`hash_program`].
+    pub binary_hash: String,
+    /// Hash of the IDL of the deployed program, if it has one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idl_hash: Option<String>,
+    /// Slot in which the program was last deployed or upgraded.
+    pub slot: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upgrade_authority: Option<String>,
+    /// Git revision of the workspace at the time of the deployment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_revision: Option<String>,
+}
+
+impl DeploymentState {
+    /// Path of the state file of the cluster.
+    pub fn path(cluster: &Cluster) -> PathBuf {
+        let name = cluster
+            .to_string()
+            .trim_start_matches("https://")
+            .trim_start_matches("http://")
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect::<String>();
+        Path::new(DEPLOYMENTS_DIR).join(name).with_extension("json")
+    }
+
+    /// Load the state of the cluster, which is empty if it doesn't exist or belongs to a previous
+    /// instance of the cluster.
+    pub fn load(cluster: &Cluster, genesis_hash: &Hash) -> Result<Self> {
+        let genesis_hash = genesis_hash.to_string();
+        let path = Self::path(cluster);
+        let state = match fs::read(&path) {
+            Ok(state) => serde_json::from_slice::<Self>(&state)
+                .with_context(|| format!("Invalid deployment state: {}", path.display()))?,
+            Err(_) => Self::default(),
+        };
+        if state.genesis_hash == genesis_hash {
+            Ok(state)
+        } else {
+            Ok(Self {
+                genesis_hash,
+                ..Default::default()
+            })
+        }
+    }
+
+    /// Save the state of the cluster.
+    pub fn save(&self, cluster: &Cluster) -> Result<()> {
+        let path = Self::path(cluster);
+        fs::create_dir_all(DEPLOYMENTS_DIR)?;
+        fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write deployment state: {}", path.display()))
+    }
+}
+
+/// Action to take for a program in a deployment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeployAction {
+    /// The program is not deployed yet.
+    Deploy,
+    /// The on-chain program differs from the local binary.
+    Upgrade,
+    /// The on-chain program is the same as the local binary.
+    Skip,
+}
+
+impl fmt::Display for DeployAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Deploy => write!(f, "deploy"),
+            Self::Upgrade => write!(f, "upgrade"),
+            Self::Skip => write!(f, "skip (unchanged)"),
+        }
+    }
+}
+
+/// Action to take for the IDL account of a program in a deployment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdlAction {
+    /// The program has no IDL account.
+    Init,
+    /// The on-chain IDL differs from the local IDL.
+    Upgrade,
+    /// The on-chain IDL is the same as the local IDL.
+    Skip,
+}
+
+impl fmt::Display for IdlAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Init => write!(f, "init"),
+            Self::Upgrade => write!(f, "upgrade"),
+            Self::Skip => write!(f, "skip (unchanged)"),
+        }
+    }
+}
+
+/// State of a deployed upgradeable program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OnChainProgram {
+    /// Hash of the program data, see [`hash_program`].
+    pub hash: Hash,
+    /// Slot in which the program was last deployed or upgraded.
+    pub slot: u64,
+    pub upgrade_authority: Option<Pubkey>,
+}
+
+/// Fetch the on-chain program, `None` if the program is not deployed.
+pub fn fetch_program(client: &RpcClient, program_id: &Pubkey) -> Result<Option<OnChainProgram>> {
+    let program = match client
+        .get_account_with_commitment(program_id, client.commitment())?
+        .value
+    {
+        Some(program) => program,
+        None => return Ok(None),
+    };
+    if program.owner != bpf_loader_upgradeable::ID {
+        return Err(anyhow!("Program {program_id} is not upgradeable"));
+    }
+    let programdata_address = match UpgradeableLoaderState::try_deserialize(&mut &program.data[..])
+    {
+        Ok(UpgradeableLoaderState::Program {
+            programdata_address,
+        }) => programdata_address,
+        _ => return Err(anyhow!("Invalid program account: {program_id}")),
+    };
+
+    let program_data = client.get_account_data(&programdata_address)?;
+    match UpgradeableLoaderState::try_deserialize(&mut &program_data[..]) {
+        Ok(UpgradeableLoaderState::ProgramData {
+            slot,
+            upgrade_authority_address,
+        }) => Ok(Some(OnChainProgram {
+            hash: hash_program(
+                &program_data[UpgradeableLoaderState::size_of_programdata_metadata()..],
+            ),
+            slot,
+            upgrade_authority: upgrade_authority_address,
+        })),
+        _ => Err(anyhow!(
+            "Invalid program data account: {programdata_address}"
+        )),
+    }
+}
+
+/// Hash of a program binary.
+///
+/// Trailing zeros are ignored because the program data account is usually larger than the
+/// deployed binary, and the rest of the account is zeroed.
+pub fn hash_program(program_data: &[u8]) -> Hash {
+    let len = program_data
+        .iter()
+        .rposition(|byte| *byte != 0)
+        .map_or(0, |index| index + 1);
+    hash(&program_data[..len])
+}
+
+/// Order the programs so that every program is deployed after the workspace programs it depends
+/// on, as declared by `path` dependencies in its manifest.
+///
+/// The order of the given programs is kept for programs that don't depend on each other.
+pub fn order_programs(programs: Vec<Program>) -> Result<Vec<Program>> {
+    let mut dependencies = BTreeMap::new();
+    for program in programs.iter().filter(|program| !program.solidity) {
+        let manifest = cargo_toml::Manifest::from_path(program.path.join("Cargo.toml"))
+            .with_context(|| format!("Failed to read the manifest of {}", program.lib_name))?;
+        let program_deps = manifest
+            .dependencies
+            .values()
+            .filter_map(|dep| dep.detail()?.path.as_ref())
+            .filter_map(|path| program.path.join(path).canonicalize().ok())
+            .filter_map(|path| programs.iter().find(|program| program.path == path))
+            .map(|dep| dep.lib_name.clone())
+            .collect();
+        dependencies.insert(program.lib_name.clone(), program_deps);
+    }
+
+    let names = programs
+        .iter()
+        .map(|program| program.lib_name.clone())
+        .collect::<Vec<_>>();
+    let order = dependency_order(&names, &dependencies)?;
+    let mut programs = programs.into_iter().map(Some).collect::<Vec<_>>();
+    Ok(order
+        .into_iter()
+        .filter_map(|index| programs[index].take())
+        .collect())
+}
+
+/// Indices of the nodes in dependency order, keeping the given order when possible.
+fn dependency_order(
+    names: &[String],
+    dependencies: &BTreeMap<String, Vec<String>>,
+) -> Result<Vec<usize>> {
+    let mut order: Vec<usize> = Vec::with_capacity(names.len());
+    while order.len() < names.len() {
+        let next = (0..names.len()).find(|index| {
+            !order.contains(index)
+                && dependencies
+                    .get(&names[*index])
+                    .into_iter()
+                    .flatten()
+                    .filter(|dep| *dep != &names[*index])
+                    .all(|dep| order.iter().any(|ordered| &names[*ordered] == dep))
+        });
+        match next {
+            Some(index) => order.push(index),
+            None => {
+                let cycle = (0..names.len())
+                    .filter(|index| !order.contains(index))
+                    .map(|index| names[index].as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                return Err(anyhow!("Circular dependency between programs: {cycle}"));
+            }
+        }
+    }
+
+    Ok(order)
+}
+
+/// Git revision of the workspace, with a `-dirty` suffix if it has uncommitted changes.
+pub fn git_revision() -> Option<String> {
+    let git = |args: &[&str]| {
+        std::process::Command::new("git")
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_owned())
+    };
+
+    let revision = git(&["rev-parse", "HEAD"])?;
+    match git(&["status", "--porcelain"]) {
+        Some(status) if !status.is_empty() => Some(format!("{revision}-dirty")),
+        _ => Some(revision),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_dependency_order() {
+        let programs = names(&["a", "b", "c", "d"]);
+        let dependencies = BTreeMap::from([
+            ("a".into(), names(&["c"])),
+            ("b".into(), vec![]),
+            ("c".into(), names(&["d"])),
+        ]);
+        assert_eq!(
+            dependency_order(&programs, &dependencies).unwrap(),
+            vec![1, 3, 2, 0]
+        );
+
+        let dependencies =
+            BTreeMap::from([("a".into(), names(&["b"])), ("b".into(), names(&["a"]))]);
+        assert!(dependency_order(&programs, &dependencies).is_err());
+    }
+
+    #[test]
+    fn test_hash_program() {
+        assert_eq!(hash_program(&[1, 2, 0, 3, 0, 0]), hash(&[1, 2, 0, 3]));
+        assert_eq!(hash_program(&[0, 0]), hash(&[]));
+    }
+
+    #[test]
+    fn test_state_path() {
+        assert_eq!(
+            DeploymentState::path(&Cluster::Devnet),
+            Path::new(".anchor/deployments/devnet.json")
+        );
+        assert_eq!(
+            DeploymentState::path(&Cluster::Custom(
+                "http://127.0.0.1:8899".into(),
+                "ws://127.0.0.1:8900".into()
+            )),
+            Path::new(".anchor/deployments/127-0-0-1-8899.json")
+        );
+    }
+}
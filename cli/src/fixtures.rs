@@ -0,0 +1,333 @@
+//! Fixtures of the accounts cloned by the test validator.
+//!
+//! `anchor test fixtures pull` downloads the `[[test.validator.clone]]` accounts once into the
+//! fixtures directory (`tests/fixtures` by default), which is meant to be committed with the
+//! workspace. When the fixtures exist, `anchor test` loads the cloned accounts from them with
+//! `--account` and `--bpf-program` instead of fetching them from the cluster, so tests can run
+//! offline.
+//!
+//! The fixtures directory contains a `manifest.json` file that records where and when the
+//! accounts were pulled, and the files of the accounts:
+//!
+//! - `accounts/<address>.json`: accounts in the JSON format of `solana account --output json`,
+//!   which includes upgradeable programs and their program data accounts.
+//! - `programs/<address>.so`: binaries of the non-upgradeable programs.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anchor_client::solana_account_decoder::{encode_ui_account, UiAccountEncoding};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_response::RpcKeyedAccount;
+use solana_sdk::account::{Account, ReadableAccount};
+use solana_sdk::bpf_loader;
+use solana_sdk::bpf_loader_upgradeable::{self, UpgradeableLoaderState};
+use solana_sdk::pubkey::Pubkey;
+
+/// Name of the manifest file of the fixtures.
+pub const MANIFEST: &str = "manifest.json";
+
+/// Version of the fixtures format.
+pub const FIXTURES_VERSION: u32 = 1;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Manifest of the pulled fixtures.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FixturesManifest {
+    /// Version of the fixtures format.
+    pub version: u32,
+    /// Url of the cluster the accounts were pulled from.
+    pub url: String,
+    /// Slot at which the accounts were pulled.
+    pub slot: u64,
+    /// Unix timestamp of the pull.
+    pub pulled_at: u64,
+    /// Fixtures by account address.
+    pub accounts: BTreeMap<String, Fixture>,
+}
+
+/// Fixture of a cloned account.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Fixture {
+    pub kind: FixtureKind,
+    /// Path of the fixture file, relative to the fixtures directory.
+    pub path: String,
+    /// Address of the program data account of an upgradeable program.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub program_data: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FixtureKind {
+    /// Account JSON file, loaded with `--account`.
+    Account,
+    /// Program binary, loaded with `--bpf-program`.
+    Program,
+}
+
+impl FixturesManifest {
+    /// Load the manifest of the fixtures directory, `None` if the fixtures weren't pulled.
+    pub fn load(dir: impl AsRef<Path>) -> Result<Option<Self>> {
+        let path = dir.as_ref().join(MANIFEST);
+        let manifest = match fs::read(&path) {
+            Ok(manifest) => manifest,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let manifest = serde_json::from_slice::<Self>(&manifest)
+            .with_context(|| format!("Invalid fixtures manifest: {}", path.display()))?;
+        if manifest.version != FIXTURES_VERSION {
+            return Err(anyhow!(
+                "Unsupported fixtures version {} in {}, run `anchor test fixtures pull` to pull them again",
+                manifest.version,
+                path.display()
+            ));
+        }
+
+        Ok(Some(manifest))
+    }
+
+    /// Time elapsed since the fixtures were pulled.
+    pub fn age(&self) -> Duration {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        Duration::from_secs(now.saturating_sub(self.pulled_at))
+    }
+
+    /// Staleness report of the fixtures, `None` if they are not older than `max_age_days`.
+    pub fn staleness(&self, max_age_days: u64) -> Option<String> {
+        let age_days = self.age().as_secs() / SECONDS_PER_DAY;
+        (age_days > max_age_days).then(|| {
+            format!(
+                "The fixtures of the {} cloned accounts were pulled {age_days} days ago from {} at \
+                slot {}, which is older than {max_age_days} days. Run `anchor test fixtures pull` \
+                to refresh them.",
+                self.accounts.len(),
+                self.url,
+                self.slot,
+            )
+        })
+    }
+
+    /// Fixture files of the account, including the program data of upgradeable programs.
+    pub fn resolve(&self, address: &str) -> Result<Vec<(&str, &Fixture)>> {
+        let (address, fixture) = self.accounts.get_key_value(address).ok_or_else(|| {
+            anyhow!(
+                "Cloned account {address} is not in the fixtures, run `anchor test fixtures pull`"
+            )
+        })?;
+        let mut fixtures = vec![(address.as_str(), fixture)];
+        if let Some(program_data) = &fixture.program_data {
+            fixtures.extend(self.resolve(program_data)?);
+        }
+
+        Ok(fixtures)
+    }
+
+    /// `solana-test-validator` flags that load the cloned accounts from the fixtures.
+    pub fn validator_flags<'a>(
+        &self,
+        dir: impl AsRef<Path>,
+        addresses: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Vec<String>> {
+        let mut flags = Vec::new();
+        for address in addresses {
+            for (address, fixture) in self.resolve(address)? {
+                flags.push(match fixture.kind {
+                    FixtureKind::Account => "--account".to_owned(),
+                    FixtureKind::Program => "--bpf-program".to_owned(),
+                });
+                flags.push(address.to_owned());
+                flags.push(dir.as_ref().join(&fixture.path).display().to_string());
+            }
+        }
+
+        Ok(flags)
+    }
+}
+
+/// Fetch the accounts, followed by the program data accounts of the upgradeable programs among
+/// them. Returns the accounts with the highest slot they were fetched at.
+pub fn fetch_accounts(
+    client: &RpcClient,
+    addresses: &[Pubkey],
+) -> Result<(Vec<(Pubkey, Account)>, u64)> {
+    let mut pubkeys = addresses.to_vec();
+    let mut accounts = Vec::with_capacity(pubkeys.len());
+    let mut slot = 0;
+    let mut i = 0;
+    while i < pubkeys.len() {
+        let pubkey = pubkeys[i];
+        i += 1;
+        if accounts.iter().any(|(address, _)| *address == pubkey) {
+            continue;
+        }
+        let response = client.get_account_with_commitment(&pubkey, client.commitment())?;
+        slot = slot.max(response.context.slot);
+        let account = response
+            .value
+            .ok_or_else(|| anyhow!("Account {} not found", pubkey))?;
+
+        if let Some(programdata_address) = program_data_address(&account) {
+            pubkeys.push(programdata_address);
+        }
+        accounts.push((pubkey, account));
+    }
+
+    Ok((accounts, slot))
+}
+
+/// Write the account in the JSON format of `solana account --output json`.
+pub fn write_account(path: impl AsRef<Path>, pubkey: &Pubkey, account: &Account) -> Result<()> {
+    let keyed_account = RpcKeyedAccount {
+        pubkey: pubkey.to_string(),
+        account: encode_ui_account(pubkey, account, UiAccountEncoding::Base64, None, None),
+    };
+    fs::write(&path, serde_json::to_string(&keyed_account)?)
+        .with_context(|| format!("Failed to write account: {}", path.as_ref().display()))
+}
+
+/// Pull the accounts into the fixtures directory, replacing the previous fixtures.
+pub fn pull(client: &RpcClient, addresses: &[Pubkey], dir: &Path) -> Result<FixturesManifest> {
+    let (accounts, slot) = fetch_accounts(client, addresses)?;
+
+    let accounts_dir = dir.join("accounts");
+    let programs_dir = dir.join("programs");
+    for dir in [&accounts_dir, &programs_dir] {
+        if dir.exists() {
+            fs::remove_dir_all(dir)?;
+        }
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut manifest = FixturesManifest {
+        version: FIXTURES_VERSION,
+        url: client.url(),
+        slot,
+        pulled_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+        accounts: BTreeMap::new(),
+    };
+    for (pubkey, account) in &accounts {
+        let (kind, path) = if account.executable && account.owner == bpf_loader::id() {
+            let path = programs_dir.join(format!("{pubkey}.so"));
+            fs::write(&path, account.data())?;
+            (FixtureKind::Program, path)
+        } else {
+            let path = accounts_dir.join(format!("{pubkey}.json"));
+            write_account(&path, pubkey, account)?;
+            (FixtureKind::Account, path)
+        };
+        manifest.accounts.insert(
+            pubkey.to_string(),
+            Fixture {
+                kind,
+                path: relative_path(&path, dir),
+                program_data: program_data_address(account).map(|address| address.to_string()),
+            },
+        );
+    }
+    fs::write(dir.join(MANIFEST), serde_json::to_string_pretty(&manifest)?)?;
+
+    Ok(manifest)
+}
+
+fn program_data_address(account: &Account) -> Option<Pubkey> {
+    if account.owner != bpf_loader_upgradeable::id() {
+        return None;
+    }
+    match account.deserialize_data() {
+        Ok(UpgradeableLoaderState::Program {
+            programdata_address,
+        }) => Some(programdata_address),
+        _ => None,
+    }
+}
+
+fn relative_path(path: &Path, dir: &Path) -> String {
+    path.strip_prefix(dir)
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| path.to_owned())
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(pulled_at: u64) -> FixturesManifest {
+        let fixture = |kind, path: &str, program_data: Option<&str>| Fixture {
+            kind,
+            path: path.into(),
+            program_data: program_data.map(Into::into),
+        };
+        FixturesManifest {
+            version: FIXTURES_VERSION,
+            url: "https://api.mainnet-beta.solana.com".into(),
+            slot: 1,
+            pulled_at,
+            accounts: BTreeMap::from([
+                (
+                    "Program".into(),
+                    fixture(
+                        FixtureKind::Account,
+                        "accounts/Program.json",
+                        Some("ProgramData"),
+                    ),
+                ),
+                (
+                    "ProgramData".into(),
+                    fixture(FixtureKind::Account, "accounts/ProgramData.json", None),
+                ),
+                (
+                    "Legacy".into(),
+                    fixture(FixtureKind::Program, "programs/Legacy.so", None),
+                ),
+            ]),
+        }
+    }
+
+    #[test]
+    fn test_validator_flags() {
+        let manifest = manifest(0);
+        assert_eq!(
+            manifest
+                .validator_flags("fixtures", ["Program", "Legacy"])
+                .unwrap(),
+            [
+                "--account",
+                "Program",
+                "fixtures/accounts/Program.json",
+                "--account",
+                "ProgramData",
+                "fixtures/accounts/ProgramData.json",
+                "--bpf-program",
+                "Legacy",
+                "fixtures/programs/Legacy.so",
+            ]
+        );
+        assert!(manifest.validator_flags("fixtures", ["Missing"]).is_err());
+    }
+
+    #[test]
+    fn test_staleness() {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        assert!(manifest(now - SECONDS_PER_DAY).staleness(30).is_none());
+        assert!(manifest(now - 31 * SECONDS_PER_DAY).staleness(30).is_some());
+    }
+}
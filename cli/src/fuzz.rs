@@ -0,0 +1,1316 @@
+//! Randomized testing harnesses of the workspace programs with `anchor fuzz`.
+//!
+//! `anchor fuzz init` generates the `fuzz` crate from the IDLs of the programs:
+//!
+//! - `src/lib.rs`: runtime of the harnesses, which processes sequences of instructions generated
+//!   from random bytes with `arbitrary` against an in-process SVM, where the program runs
+//!   natively. The bytes come from a seeded SplitMix generator, without coverage guidance.
+//! - `src/programs/<program>.rs`: instructions of the program and their arguments. The accounts
+//!   of an instruction are derived from their seeds when they are PDAs, picked among the keypairs
+//!   of the harness when they are signers, and picked among the known addresses otherwise.
+//! - `src/bin/<program>.rs`: harness of the program, with its invariants.
+//!
+//! The runtime and the instructions are overwritten every time `anchor fuzz init` runs, while
+//! the harnesses are only created if they don't exist, as they contain the user's invariants.
+//!
+//! `anchor fuzz run` runs the harness of a program. The sequences that make the program panic,
+//! or that break an invariant, are saved to `fuzz/crashes/<program>`, from where they can be
+//! reproduced with `anchor fuzz run --reproduce`.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use std::process::Stdio;
+
+use anchor_lang_idl::types::{
+    Idl, IdlArrayLen, IdlDefinedFields, IdlGenericArg, IdlInstruction, IdlInstructionAccount,
+    IdlInstructionAccountItem, IdlSeed, IdlType, IdlTypeDef, IdlTypeDefGeneric, IdlTypeDefTy,
+};
+use anyhow::{anyhow, bail, Result};
+use heck::ToPascalCase;
+
+use crate::config::{Manifest, Program};
+use crate::{create_files, override_or_create_files, Files, VERSION};
+
+/// Directory of the randomized testing crate, relative to the workspace root.
+pub const FUZZ_DIR: &str = "fuzz";
+
+/// Generate the `fuzz` crate with a harness for every program that has an IDL.
+pub fn init(programs: &[Program]) -> Result<()> {
+    let fuzz_dir = Path::new(FUZZ_DIR);
+    let fuzz_path = std::env::current_dir()?.join(fuzz_dir);
+
+    let mut dependencies = String::new();
+    let mut modules = String::new();
+    let mut generated = Files::new();
+    let mut harnesses = Files::new();
+    for program in programs {
+        let idl = match &program.idl {
+            Some(idl) if !program.solidity => idl,
+            _ => {
+                eprintln!(
+                    "Warning: Skipping program `{}` without an IDL",
+                    program.lib_name
+                );
+                continue;
+            }
+        };
+        let name = &program.lib_name;
+
+        let package = Manifest::from_path(program.path.join("Cargo.toml"))?
+            .package
+            .as_ref()
+            .map(|package| package.name.clone())
+            .ok_or_else(|| anyhow!("package section not provided"))?;
+        let path = pathdiff::diff_paths(&program.path, &fuzz_path)
+            .ok_or_else(|| anyhow!("Invalid program path: {}", program.path.display()))?;
+        let path = path
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let package = match package == *name {
+            true => String::new(),
+            false => format!(r#"package = "{package}", "#),
+        };
+        writeln!(
+            dependencies,
+            r#"{name} = {{ {package}path = "{path}", features = ["no-entrypoint"] }}"#
+        )?;
+        writeln!(modules, "pub mod {name};")?;
+
+        generated.push((
+            fuzz_dir
+                .join("src")
+                .join("programs")
+                .join(format!("{name}.rs")),
+            program_module(idl, name)?,
+        ));
+        harnesses.push((
+            fuzz_dir.join("src").join("bin").join(format!("{name}.rs")),
+            harness(name),
+        ));
+    }
+    if generated.is_empty() {
+        bail!("No program IDLs found, run `anchor build` to generate them");
+    }
+
+    generated.extend([
+        (fuzz_dir.join("Cargo.toml"), cargo_toml(&dependencies)),
+        (fuzz_dir.join("src").join("lib.rs"), RUNTIME.to_owned()),
+        (
+            fuzz_dir.join("src").join("programs").join("mod.rs"),
+            format!("{GENERATED_HEADER}\n{modules}"),
+        ),
+    ]);
+    override_or_create_files(&generated)?;
+
+    create_files(&harnesses)?;
+    let gitignore = fuzz_dir.join(".gitignore");
+    if !gitignore.exists() {
+        fs::write(gitignore, "target\n")?;
+    }
+
+    let exit = std::process::Command::new("rustfmt")
+        .args(["--edition", "2021"])
+        .args(
+            generated
+                .iter()
+                .chain(&harnesses)
+                .map(|(path, _)| path)
+                .filter(|path| path.extension() == Some("rs".as_ref())),
+        )
+        .stderr(Stdio::inherit())
+        .output();
+    match exit {
+        Ok(exit) if exit.status.success() => {}
+        _ => eprintln!("Warning: Failed to format the `fuzz` crate with `rustfmt`"),
+    }
+
+    println!("Generated `fuzz` crate at {}", fuzz_dir.display());
+    Ok(())
+}
+
+const GENERATED_HEADER: &str = "// Generated by `anchor fuzz init`, which overwrites this file.\n";
+
+fn cargo_toml(dependencies: &str) -> String {
+    format!(
+        r#"[package]
+name = "fuzz"
+version = "0.1.0"
+description = "Randomized testing harnesses of the workspace programs"
+edition = "2021"
+publish = false
+
+# Built apart from the workspace of the programs
+[workspace]
+
+[dependencies]
+anchor-client = {{ version = "{VERSION}", features = ["program-test"] }}
+arbitrary = {{ version = "1", features = ["derive"] }}
+tokio = {{ version = "1", features = ["rt-multi-thread"] }}
+{dependencies}
+[profile.release]
+debug-assertions = true
+overflow-checks = true
+"#
+    )
+}
+
+fn harness(name: &str) -> String {
+    format!(
+        r#"//! Randomized testing harness of program `{name}`, run with `anchor fuzz run {name}`.
+
+use fuzz::{{programs::{name}::FuzzInstruction, FuzzContext}};
+
+/// Invariants of the program, which are checked after every instruction that succeeds.
+///
+/// Accounts can be fetched with `ctx.program().account::<T>(address)`, e.g. at the PDAs of the
+/// instruction, or at the keypairs of the harness with `fuzz::keypair(index)`.
+fn invariants(_ctx: &FuzzContext, _instruction: &FuzzInstruction) -> Result<(), String> {{
+    Ok(())
+}}
+
+fn main() {{
+    fuzz::run("{name}", invariants);
+}}
+"#
+    )
+}
+
+/// Generate the module of the instructions of a program, which are fuzzed by its harness.
+pub fn program_module(idl: &Idl, lib_name: &str) -> Result<String> {
+    if idl.instructions.is_empty() {
+        bail!("Program `{}` has no instructions", idl.metadata.name);
+    }
+
+    let mut module = format!(
+        r#"//! Instructions of program `{}`, generated from its IDL.
+{GENERATED_HEADER}
+#![allow(dead_code, unused_variables)]
+
+use anchor_client::{{
+    anchor_lang::{{prelude::borsh, AnchorSerialize}},
+    native_processor,
+    solana_sdk::{{
+        instruction::{{AccountMeta, Instruction}},
+        pubkey::Pubkey,
+    }},
+    NativeProcessor,
+}};
+use arbitrary::Arbitrary;
+
+use crate::{{FuzzContext, FuzzInstructions}};
+
+pub const PROGRAM_ID: Pubkey = ::{lib_name}::ID;
+
+#[derive(Debug, Arbitrary)]
+pub enum FuzzInstruction {{
+"#,
+        idl.metadata.name
+    );
+    for ix in &idl.instructions {
+        let name = ix.name.to_pascal_case();
+        writeln!(
+            module,
+            "{name} {{ accounts: {name}Accounts, args: {name}Args }},"
+        )?;
+    }
+    module.push_str(&format!(
+        r#"}}
+
+impl FuzzInstructions for FuzzInstruction {{
+    fn program_id() -> Pubkey {{
+        PROGRAM_ID
+    }}
+
+    fn processors() -> Vec<(Pubkey, Option<NativeProcessor>)> {{
+        vec![(PROGRAM_ID, native_processor!(::{lib_name}::entry))]
+    }}
+
+    fn instruction(&self, ctx: &mut FuzzContext) -> Instruction {{
+        let (accounts, data) = match self {{
+"#
+    ));
+    for ix in &idl.instructions {
+        writeln!(
+            module,
+            "Self::{} {{ accounts, args }} => (accounts.to_account_metas(ctx, args), args.data()),",
+            ix.name.to_pascal_case()
+        )?;
+    }
+    module.push_str(
+        r#"        };
+        Instruction {
+            program_id: PROGRAM_ID,
+            accounts,
+            data,
+        }
+    }
+}
+"#,
+    );
+
+    for ix in &idl.instructions {
+        module.push_str(&instruction(idl, ix)?);
+    }
+
+    module.push_str("\npub mod types {\nuse super::*;\n");
+    for ty_def in &idl.types {
+        module.push_str(&type_def(ty_def)?);
+    }
+    module.push_str("}\n");
+
+    Ok(module)
+}
+
+/// Generate the accounts and the arguments of an instruction.
+fn instruction(idl: &Idl, ix: &IdlInstruction) -> Result<String> {
+    let name = ix.name.to_pascal_case();
+    let mut code = String::new();
+
+    // Arguments
+    let mut fields = String::new();
+    for arg in &ix.args {
+        writeln!(
+            fields,
+            "pub {}: {},",
+            ident(&arg.name),
+            rust_type(&arg.ty, "types::")?
+        )?;
+    }
+    let discriminator = ix
+        .discriminator
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ");
+    write!(
+        code,
+        r#"
+#[derive(Debug, Arbitrary, AnchorSerialize)]
+pub struct {name}Args {{
+{fields}}}
+
+impl {name}Args {{
+    fn data(&self) -> Vec<u8> {{
+        let mut data = vec![{discriminator}];
+        data.extend(crate::serialize(self));
+        data
+    }}
+}}
+"#
+    )?;
+
+    // Accounts
+    let mut accounts = vec![];
+    flatten_accounts(&ix.accounts, "", &mut accounts);
+    let names = accounts
+        .iter()
+        .map(|account| account.name.as_str())
+        .collect::<Vec<_>>();
+    let mut resolutions = accounts
+        .iter()
+        .map(|account| resolve_account(idl, ix, account, &names))
+        .collect::<Result<Vec<_>>>()?;
+
+    // Derive the PDAs after the accounts their seeds depend on, or pick them among the known
+    // addresses if their seeds depend on each other.
+    let mut order = vec![];
+    while order.len() < accounts.len() {
+        let ready = (0..accounts.len())
+            .filter(|i| !order.contains(i))
+            .filter(|&i| match &resolutions[i] {
+                Resolution::Pda { dependencies, .. } => dependencies
+                    .iter()
+                    .all(|dependency| order.iter().any(|&j| accounts[j].name == *dependency)),
+                _ => true,
+            })
+            .collect::<Vec<_>>();
+        if ready.is_empty() {
+            let cyclic = (0..accounts.len())
+                .filter(|i| !order.contains(i))
+                .collect::<Vec<_>>();
+            for &i in &cyclic {
+                resolutions[i] = Resolution::Address;
+            }
+            order.extend(cyclic);
+        }
+        order.extend(ready);
+    }
+
+    let mut fields = String::new();
+    let mut lets = String::new();
+    for &i in &order {
+        let account = &accounts[i];
+        let name = ident(&account.name);
+        let index = match account.account.optional {
+            true => name.clone(),
+            false => format!("self.{name}"),
+        };
+        let expr = match &resolutions[i] {
+            Resolution::Fixed(address) => format!(r#"Pubkey::from_str_const("{address}")"#),
+            Resolution::Pda { seeds, program, .. } => {
+                format!("ctx.pda(&[{}], &{program})", seeds.join(", "))
+            }
+            Resolution::Signer => format!("ctx.signer({index})"),
+            Resolution::Address => format!("ctx.address({index})"),
+        };
+        // Optional accounts that are not passed are replaced with the program ID
+        let (field, expr) = match (account.account.optional, resolutions[i].is_picked()) {
+            (false, false) => (None, expr),
+            (false, true) => (Some("u8"), expr),
+            (true, false) => (
+                Some("bool"),
+                format!("match self.{name} {{ true => {expr}, false => PROGRAM_ID }}"),
+            ),
+            (true, true) => (
+                Some("Option<u8>"),
+                format!("match self.{name} {{ Some({name}) => {expr}, None => PROGRAM_ID }}"),
+            ),
+        };
+        if let Some(field) = field {
+            writeln!(fields, "pub {name}: {field},")?;
+        }
+        writeln!(lets, "let {name} = {expr};")?;
+    }
+
+    let mut metas = String::new();
+    for (account, resolution) in accounts.iter().zip(&resolutions) {
+        let name = ident(&account.name);
+        let passed = match (account.account.optional, resolution.is_picked()) {
+            (false, _) => "true".to_owned(),
+            (true, false) => format!("self.{name}"),
+            (true, true) => format!("self.{name}.is_some()"),
+        };
+        let flag = |flag: bool| match flag {
+            true => passed.clone(),
+            false => "false".to_owned(),
+        };
+        writeln!(
+            metas,
+            "AccountMeta {{ pubkey: {name}, is_signer: {}, is_writable: {} }},",
+            flag(matches!(resolution, Resolution::Signer)),
+            flag(account.account.writable),
+        )?;
+    }
+
+    write!(
+        code,
+        r#"
+#[derive(Debug, Arbitrary)]
+pub struct {name}Accounts {{
+{fields}}}
+
+impl {name}Accounts {{
+    fn to_account_metas(&self, ctx: &mut FuzzContext, args: &{name}Args) -> Vec<AccountMeta> {{
+{lets}
+        vec![
+{metas}        ]
+    }}
+}}
+"#
+    )?;
+
+    Ok(code)
+}
+
+/// Account of an instruction, where the accounts of composite accounts are prefixed with the name
+/// of the composite account.
+struct FlatAccount<'a> {
+    name: String,
+    prefix: String,
+    account: &'a IdlInstructionAccount,
+}
+
+fn flatten_accounts<'a>(
+    items: &'a [IdlInstructionAccountItem],
+    prefix: &str,
+    accounts: &mut Vec<FlatAccount<'a>>,
+) {
+    for item in items {
+        match item {
+            IdlInstructionAccountItem::Single(account) => accounts.push(FlatAccount {
+                name: format!("{prefix}{}", account.name),
+                prefix: prefix.to_owned(),
+                account,
+            }),
+            IdlInstructionAccountItem::Composite(composite) => flatten_accounts(
+                &composite.accounts,
+                &format!("{prefix}{}_", composite.name),
+                accounts,
+            ),
+        }
+    }
+}
+
+/// How the address of an account is resolved.
+enum Resolution {
+    /// Fixed address, e.g. of a program.
+    Fixed(String),
+    /// PDA derived from seeds, which depend on other accounts.
+    Pda {
+        seeds: Vec<String>,
+        program: String,
+        dependencies: Vec<String>,
+    },
+    /// One of the keypairs of the harness, which signs the instruction.
+    Signer,
+    /// One of the addresses known to the harness.
+    Address,
+}
+
+impl Resolution {
+    /// Whether the account is picked by the harness, from an index that is generated.
+    fn is_picked(&self) -> bool {
+        matches!(self, Self::Signer | Self::Address)
+    }
+}
+
+fn resolve_account(
+    idl: &Idl,
+    ix: &IdlInstruction,
+    account: &FlatAccount,
+    names: &[&str],
+) -> Result<Resolution> {
+    if let Some(address) = &account.account.address {
+        return Ok(Resolution::Fixed(address.to_owned()));
+    }
+    if account.account.signer {
+        return Ok(Resolution::Signer);
+    }
+    let pda = match &account.account.pda {
+        Some(pda) => pda,
+        None => return Ok(Resolution::Address),
+    };
+
+    let mut dependencies = vec![];
+    let mut seed = |seed: &IdlSeed| -> Result<Option<String>> {
+        match seed {
+            IdlSeed::Const(seed) => Ok(Some(const_bytes(&seed.value))),
+            IdlSeed::Arg(seed) => arg_bytes(idl, ix, &seed.path),
+            IdlSeed::Account(seed) => {
+                let path = seed.path.replace('.', "_");
+                let name = [format!("{}{path}", account.prefix), path]
+                    .into_iter()
+                    .find(|name| names.contains(&name.as_str()));
+                Ok(name.map(|name| {
+                    let seed = format!("{}.as_ref()", ident(&name));
+                    dependencies.push(name);
+                    seed
+                }))
+            }
+        }
+    };
+
+    let mut seeds = vec![];
+    for s in &pda.seeds {
+        match seed(s)? {
+            Some(s) => seeds.push(s),
+            None => return Ok(Resolution::Address),
+        }
+    }
+    let program = match &pda.program {
+        None => "PROGRAM_ID".to_owned(),
+        Some(IdlSeed::Const(seed)) => match <[u8; 32]>::try_from(seed.value.as_slice()) {
+            Ok(bytes) => format!("Pubkey::new_from_array({bytes:?})"),
+            Err(_) => return Ok(Resolution::Address),
+        },
+        Some(program) => match seed(program)? {
+            Some(seed) => format!("Pubkey::try_from({seed}).unwrap_or(PROGRAM_ID)"),
+            None => return Ok(Resolution::Address),
+        },
+    };
+
+    Ok(Resolution::Pda {
+        seeds,
+        program,
+        dependencies,
+    })
+}
+
+fn const_bytes(value: &[u8]) -> String {
+    match value.iter().all(|b| b.is_ascii_graphic()) && !value.contains(&b'"') {
+        true => format!(r#"&b"{}"[..]"#, String::from_utf8_lossy(value)),
+        false => format!("&{value:?}[..]"),
+    }
+}
+
+/// Seed of an instruction argument, `None` if the argument can't be resolved.
+fn arg_bytes(idl: &Idl, ix: &IdlInstruction, path: &str) -> Result<Option<String>> {
+    let mut segments = path.split('.');
+    let first = segments.next().unwrap_or_default();
+    let mut ty = match ix.args.iter().find(|arg| arg.name == first) {
+        Some(arg) => &arg.ty,
+        None => return Ok(None),
+    };
+    let mut expr = format!("args.{}", ident(first));
+    for segment in segments {
+        let fields = match ty {
+            IdlType::Defined { name, .. } => {
+                match idl.types.iter().find(|ty_def| ty_def.name == *name) {
+                    Some(IdlTypeDef {
+                        ty:
+                            IdlTypeDefTy::Struct {
+                                fields: Some(IdlDefinedFields::Named(fields)),
+                            },
+                        ..
+                    }) => fields,
+                    _ => return Ok(None),
+                }
+            }
+            _ => return Ok(None),
+        };
+        ty = match fields.iter().find(|field| field.name == segment) {
+            Some(field) => &field.ty,
+            None => return Ok(None),
+        };
+        write!(expr, ".{}", ident(segment))?;
+    }
+
+    Ok(Some(match ty {
+        IdlType::Pubkey => format!("{expr}.0.as_ref()"),
+        IdlType::String => format!("{expr}.as_bytes()"),
+        IdlType::Bool => format!("&[{expr} as u8][..]"),
+        IdlType::U8
+        | IdlType::I8
+        | IdlType::U16
+        | IdlType::I16
+        | IdlType::U32
+        | IdlType::I32
+        | IdlType::U64
+        | IdlType::I64
+        | IdlType::U128
+        | IdlType::I128 => format!("&{expr}.to_le_bytes()[..]"),
+        IdlType::Bytes => format!("&{expr}[..]"),
+        IdlType::Vec(ty) | IdlType::Array(ty, _) if **ty == IdlType::U8 => format!("&{expr}[..]"),
+        _ => format!("&crate::serialize(&{expr})[..]"),
+    }))
+}
+
+/// Generate a type of the IDL, where pubkeys are generated by the harness.
+fn type_def(ty_def: &IdlTypeDef) -> Result<String> {
+    let name = type_name(&ty_def.name);
+    let generics = match ty_def.generics.is_empty() {
+        true => String::new(),
+        false => {
+            let generics = ty_def
+                .generics
+                .iter()
+                .map(|generic| match generic {
+                    IdlTypeDefGeneric::Type { name } => name.to_owned(),
+                    IdlTypeDefGeneric::Const { name, ty } => format!("const {name}: {ty}"),
+                })
+                .collect::<Vec<_>>();
+            format!("<{}>", generics.join(", "))
+        }
+    };
+
+    let derive = "#[derive(Debug, Arbitrary, AnchorSerialize)]";
+    Ok(match &ty_def.ty {
+        IdlTypeDefTy::Struct { fields } => match fields {
+            None => format!("\n{derive}\npub struct {name}{generics};\n"),
+            Some(IdlDefinedFields::Named(_)) => format!(
+                "\n{derive}\npub struct {name}{generics} {{\n{}}}\n",
+                defined_fields(fields, "pub ")?
+            ),
+            Some(IdlDefinedFields::Tuple(_)) => format!(
+                "\n{derive}\npub struct {name}{generics}({});\n",
+                defined_fields(fields, "pub ")?
+            ),
+        },
+        IdlTypeDefTy::Enum { variants } => {
+            let mut code = format!("\n{derive}\npub enum {name}{generics} {{\n");
+            for variant in variants {
+                let fields = match &variant.fields {
+                    None => String::new(),
+                    Some(IdlDefinedFields::Named(_)) => {
+                        format!(" {{ {} }}", defined_fields(&variant.fields, "")?)
+                    }
+                    Some(IdlDefinedFields::Tuple(_)) => {
+                        format!("({})", defined_fields(&variant.fields, "")?)
+                    }
+                };
+                writeln!(code, "{}{fields},", type_name(&variant.name))?;
+            }
+            code.push_str("}\n");
+            code
+        }
+        IdlTypeDefTy::Type { alias } => {
+            format!("\npub type {name}{generics} = {};\n", rust_type(alias, "")?)
+        }
+    })
+}
+
+fn defined_fields(fields: &Option<IdlDefinedFields>, vis: &str) -> Result<String> {
+    Ok(match fields {
+        None => String::new(),
+        Some(IdlDefinedFields::Named(fields)) => fields
+            .iter()
+            .map(|field| {
+                rust_type(&field.ty, "").map(|ty| format!("{vis}{}: {ty},\n", ident(&field.name)))
+            })
+            .collect::<Result<String>>()?,
+        Some(IdlDefinedFields::Tuple(types)) => types
+            .iter()
+            .map(|ty| rust_type(ty, "").map(|ty| format!("{vis}{ty}")))
+            .collect::<Result<Vec<_>>>()?
+            .join(", "),
+    })
+}
+
+/// Rust type of an IDL type, where defined types are prefixed with `prefix`.
+fn rust_type(ty: &IdlType, prefix: &str) -> Result<String> {
+    Ok(match ty {
+        IdlType::Bool => "bool".into(),
+        IdlType::U8 => "u8".into(),
+        IdlType::I8 => "i8".into(),
+        IdlType::U16 => "u16".into(),
+        IdlType::I16 => "i16".into(),
+        IdlType::U32 => "u32".into(),
+        IdlType::I32 => "i32".into(),
+        IdlType::F32 => "f32".into(),
+        IdlType::U64 => "u64".into(),
+        IdlType::I64 => "i64".into(),
+        IdlType::F64 => "f64".into(),
+        IdlType::U128 => "u128".into(),
+        IdlType::I128 => "i128".into(),
+        IdlType::U256 | IdlType::I256 => "[u8; 32]".into(),
+        IdlType::Bytes => "Vec<u8>".into(),
+        IdlType::String => "String".into(),
+        IdlType::Pubkey => "crate::FuzzPubkey".into(),
+        IdlType::Option(ty) => format!("Option<{}>", rust_type(ty, prefix)?),
+        IdlType::Vec(ty) | IdlType::Set(ty) => format!("Vec<{}>", rust_type(ty, prefix)?),
+        IdlType::Array(ty, len) => {
+            let len = match len {
+                IdlArrayLen::Generic(len) => len.to_owned(),
+                IdlArrayLen::Value(len) => len.to_string(),
+            };
+            format!("[{}; {len}]", rust_type(ty, prefix)?)
+        }
+        IdlType::Tuple(types) => format!(
+            "({})",
+            types
+                .iter()
+                .map(|ty| rust_type(ty, prefix).map(|ty| format!("{ty},")))
+                .collect::<Result<String>>()?
+        ),
+        // Maps are serialized like vectors of their entries
+        IdlType::Map { key, value } => format!(
+            "Vec<({}, {})>",
+            rust_type(key, prefix)?,
+            rust_type(value, prefix)?
+        ),
+        IdlType::Defined { name, generics } => {
+            let generics = match generics.is_empty() {
+                true => String::new(),
+                false => {
+                    let generics = generics
+                        .iter()
+                        .map(|generic| match generic {
+                            IdlGenericArg::Type { ty } => rust_type(ty, prefix),
+                            IdlGenericArg::Const { value } => Ok(value.to_owned()),
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+                    format!("<{}>", generics.join(", "))
+                }
+            };
+            format!("{prefix}{}{generics}", type_name(name))
+        }
+        IdlType::Generic(name) => name.to_owned(),
+        _ => bail!("Unsupported IDL type: {ty:?}"),
+    })
+}
+
+/// Name of a defined type, without the path of the module it is defined in.
+fn type_name(name: &str) -> String {
+    name.rsplit("::").next().unwrap_or(name).to_owned()
+}
+
+/// Identifier of a field or an account, which is a raw identifier if it is a keyword.
+fn ident(name: &str) -> String {
+    match syn::parse_str::<syn::Ident>(name) {
+        Ok(_) => name.to_owned(),
+        Err(_) => format!("r#{name}"),
+    }
+}
+
+/// Runtime of the harnesses, `fuzz/src/lib.rs`.
+const RUNTIME: &str = r##"//! Runtime of the randomized testing harnesses of the workspace programs.
+//!
+//! A harness generates sequences of instructions from random bytes with [`arbitrary`], and
+//! processes every sequence in a new in-process SVM where the program runs natively. A sequence
+//! crashes when the program panics, when the processing fails for another reason than a failed
+//! transaction, or when an invariant doesn't hold after an instruction. The bytes of the crashing
+//! sequences are saved to `crashes/<program>`, and can be reproduced with
+//! `anchor fuzz run <program> --reproduce <file>`.
+//!
+//! Generated by `anchor fuzz init`, which overwrites this file.
+
+pub mod programs;
+
+use std::{
+    collections::BTreeSet,
+    fmt, fs,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::{Arc, Mutex},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anchor_client::{
+    anchor_lang::AnchorSerialize,
+    solana_sdk::{
+        account::Account,
+        commitment_config::CommitmentConfig,
+        hash::hash,
+        instruction::Instruction,
+        native_token::LAMPORTS_PER_SOL,
+        pubkey::Pubkey,
+        signature::{keypair_from_seed, Keypair, Signer},
+        system_program,
+    },
+    Client, ClientError, Cluster, NativeProcessor, Program, ProgramTestTransport, TestGenesis,
+};
+use arbitrary::{Arbitrary, Unstructured};
+use tokio::runtime::{Builder, Runtime};
+
+/// Number of keypairs of the harness, of which the first half is funded at genesis.
+pub const KEYPAIRS: usize = 8;
+
+/// Lamports of the funded keypairs.
+const KEYPAIR_LAMPORTS: u64 = 100 * LAMPORTS_PER_SOL;
+
+/// Maximum number of instructions of a sequence.
+const MAX_INSTRUCTIONS: usize = 16;
+
+/// Default number of sequences of a run.
+const ITERATIONS: u64 = 1000;
+
+/// Message of the last panic, which is recorded instead of printed.
+static PANIC: Mutex<Option<String>> = Mutex::new(None);
+
+/// Invariants of a program, which are checked after every instruction that succeeds.
+pub type Invariants<I> = fn(&FuzzContext, &I) -> Result<(), String>;
+
+/// Instructions of a program, generated from its IDL.
+pub trait FuzzInstructions: for<'a> Arbitrary<'a> + fmt::Debug {
+    /// Address of the program.
+    fn program_id() -> Pubkey;
+
+    /// Processors of the programs that run natively.
+    fn processors() -> Vec<(Pubkey, Option<NativeProcessor>)>;
+
+    /// Instruction to process, which is signed by the keypairs of [`FuzzContext::signer`].
+    fn instruction(&self, ctx: &mut FuzzContext) -> Instruction;
+}
+
+/// Pubkey that is one of the keypairs of the harness most of the time, and random otherwise.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct FuzzPubkey(pub Pubkey);
+
+impl fmt::Debug for FuzzPubkey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl<'a> Arbitrary<'a> for FuzzPubkey {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        match u.ratio(3, 4)? {
+            true => Ok(Self(keypair(u.choose_index(KEYPAIRS)?).pubkey())),
+            false => Ok(Self(Pubkey::new_from_array(u.arbitrary()?))),
+        }
+    }
+}
+
+impl AnchorSerialize for FuzzPubkey {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_all(self.0.as_ref())
+    }
+}
+
+/// Serialize the arguments of an instruction. Arguments that can't be serialized, e.g. NaN
+/// floats, are serialized as empty data, which the program fails to deserialize.
+pub fn serialize(value: &impl AnchorSerialize) -> Vec<u8> {
+    value.try_to_vec().unwrap_or_default()
+}
+
+/// Keypair of the harness, which is the same across runs.
+pub fn keypair(index: usize) -> Keypair {
+    keypair_from_seed(&[index as u8; 32]).expect("Invalid seed")
+}
+
+/// State of the SVM that a sequence is processed in.
+pub struct FuzzContext {
+    program: Program<Rc<Keypair>>,
+    keypairs: Vec<Keypair>,
+    addresses: Vec<Pubkey>,
+    signers: Vec<usize>,
+}
+
+impl FuzzContext {
+    fn start<I: FuzzInstructions>(
+        runtime: &Runtime,
+        genesis: &TestGenesis,
+    ) -> Result<Self, ClientError> {
+        let keypairs = (0..KEYPAIRS).map(keypair).collect::<Vec<_>>();
+        let mut program_test = genesis.program_test_with_processors(&I::processors())?;
+        for keypair in &keypairs[..KEYPAIRS / 2] {
+            program_test.add_account(
+                keypair.pubkey(),
+                Account::new(KEYPAIR_LAMPORTS, 0, &system_program::ID),
+            );
+        }
+        let transport =
+            Arc::new(runtime.block_on(ProgramTestTransport::start_async(program_test)));
+        let client = Client::new_with_options(
+            Cluster::Localnet,
+            Rc::new(transport.payer()),
+            CommitmentConfig::processed(),
+        );
+        let program = client.program_with_transport(I::program_id(), transport)?;
+
+        let mut addresses = vec![I::program_id(), system_program::ID];
+        for program in &genesis.programs {
+            if !addresses.contains(&program.address) {
+                addresses.push(program.address);
+            }
+        }
+
+        Ok(Self {
+            program,
+            keypairs,
+            addresses,
+            signers: vec![],
+        })
+    }
+
+    /// Client of the program, e.g. to fetch its accounts.
+    pub fn program(&self) -> &Program<Rc<Keypair>> {
+        &self.program
+    }
+
+    /// Pubkey of a keypair of the harness, which signs the instruction.
+    pub fn signer(&mut self, index: u8) -> Pubkey {
+        let index = usize::from(index) % KEYPAIRS;
+        if !self.signers.contains(&index) {
+            self.signers.push(index);
+        }
+        self.keypairs[index].pubkey()
+    }
+
+    /// One of the keypairs of the harness, the programs, or the PDAs of the sequence.
+    pub fn address(&self, index: u8) -> Pubkey {
+        let index = usize::from(index) % (KEYPAIRS + self.addresses.len());
+        match index.checked_sub(KEYPAIRS) {
+            Some(index) => self.addresses[index],
+            None => self.keypairs[index].pubkey(),
+        }
+    }
+
+    /// Derive a PDA, which is then one of the known addresses. Seeds that are invalid, e.g.
+    /// too long, derive the default pubkey.
+    pub fn pda(&mut self, seeds: &[&[u8]], program_id: &Pubkey) -> Pubkey {
+        let pda = Pubkey::try_find_program_address(seeds, program_id)
+            .map(|(pda, _)| pda)
+            .unwrap_or_default();
+        if !self.addresses.contains(&pda) {
+            self.addresses.push(pda);
+        }
+        pda
+    }
+
+    fn process<I: FuzzInstructions>(&mut self, instruction: &I) -> Result<(), ClientError> {
+        self.signers.clear();
+        let instruction = instruction.instruction(self);
+        let mut request = self.program.request().instruction(instruction);
+        for &index in &self.signers {
+            request = request.signer(self.keypairs[index].insecure_clone());
+        }
+        request.send().map(|_| ())
+    }
+}
+
+/// Crash of a sequence.
+struct Crash {
+    message: String,
+    report: String,
+}
+
+/// Process the sequence that is generated from the bytes.
+fn process_sequence<I: FuzzInstructions>(
+    runtime: &Runtime,
+    genesis: &TestGenesis,
+    data: &[u8],
+    invariants: Invariants<I>,
+    verbose: bool,
+) -> Result<(), Crash> {
+    let mut u = Unstructured::new(data);
+    let sequence: Vec<I> = match u
+        .int_in_range(1..=MAX_INSTRUCTIONS)
+        .and_then(|len| (0..len).map(|_| I::arbitrary(&mut u)).collect())
+    {
+        Ok(sequence) => sequence,
+        Err(_) => return Ok(()),
+    };
+    let mut ctx = FuzzContext::start::<I>(runtime, genesis)
+        .unwrap_or_else(|e| exit(&format!("Failed to start the SVM: {e}")));
+    take_panic();
+
+    let crash = |i: usize, message: String| Crash {
+        report: format!("Crash at instruction #{i}: {message}\n\nSequence:\n{sequence:#?}\n"),
+        message,
+    };
+    for (i, instruction) in sequence.iter().enumerate() {
+        // The SVM reports the panics of the programs as failed transactions, which are
+        // crashes nonetheless
+        let result = panic::catch_unwind(AssertUnwindSafe(|| ctx.process(instruction)));
+        let result = match (result, take_panic()) {
+            (_, Some(panic)) => return Err(crash(i, panic)),
+            (Ok(Err(e)), None) if is_transaction_error(&e) => Err(e),
+            (Ok(Err(e)), None) => return Err(crash(i, e.to_string())),
+            (Ok(Ok(())), None) => Ok(()),
+            (Err(_), None) => return Err(crash(i, "Unknown panic".into())),
+        };
+        if verbose {
+            match &result {
+                Ok(()) => println!("#{i} {instruction:?}: Ok"),
+                Err(e) => println!("#{i} {instruction:?}: {e}"),
+            }
+        }
+        if result.is_err() {
+            continue;
+        }
+
+        match panic::catch_unwind(AssertUnwindSafe(|| invariants(&ctx, instruction))) {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => return Err(crash(i, format!("Invariant violated: {e}"))),
+            Err(_) => return Err(crash(i, take_panic().unwrap_or_default())),
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether processing failed because the transaction failed, which random instructions often do.
+fn is_transaction_error(e: &ClientError) -> bool {
+    matches!(e, ClientError::SolanaClientError(e) if e.get_transaction_error().is_some())
+}
+
+fn take_panic() -> Option<String> {
+    PANIC.lock().unwrap_or_else(|e| e.into_inner()).take()
+}
+
+/// Run the harness of a program with the arguments of `anchor fuzz run`.
+pub fn run<I: FuzzInstructions>(name: &str, invariants: Invariants<I>) {
+    let args = Args::parse();
+    let genesis = TestGenesis::from_env().unwrap_or_else(|e| exit(&e.to_string()));
+    // The banks of all the sequences run on the same runtime
+    let runtime = Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap_or_else(|e| exit(&format!("Failed to start the runtime: {e}")));
+    // Only log the errors of the SVM, unless configured otherwise
+    if std::env::var_os("RUST_LOG").is_none() {
+        std::env::set_var("RUST_LOG", "error");
+    }
+    panic::set_hook(Box::new(|info| {
+        *PANIC.lock().unwrap_or_else(|e| e.into_inner()) = Some(info.to_string());
+    }));
+
+    if let Some(path) = args.reproduce {
+        let data = fs::read(&path)
+            .unwrap_or_else(|e| exit(&format!("Failed to read {}: {e}", path.display())));
+        match process_sequence(&runtime, &genesis, &data, invariants, true) {
+            Ok(()) => println!("The sequence doesn't crash"),
+            Err(crash) => {
+                println!("\n{}", crash.report);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    println!("Testing `{name}` with seed {}", args.seed);
+    let crashes_dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("crashes")
+        .join(name);
+    let mut rng = Rng(args.seed);
+    let mut crashes = BTreeSet::new();
+    let start = Instant::now();
+    for _ in 0..args.iterations {
+        let data = rng.bytes();
+        let crash = match process_sequence(&runtime, &genesis, &data, invariants, false) {
+            Ok(()) => continue,
+            Err(crash) => crash,
+        };
+        if !crashes.insert(crash.message.clone()) {
+            continue;
+        }
+
+        let path = crashes_dir.join(hash(&data).to_string());
+        fs::create_dir_all(&crashes_dir)
+            .and_then(|_| fs::write(path.with_extension("bin"), &data))
+            .and_then(|_| fs::write(path.with_extension("txt"), &crash.report))
+            .unwrap_or_else(|e| exit(&format!("Failed to save the crash: {e}")));
+        println!("Crash: {}", crash.message);
+        println!("Saved to {}\n", path.with_extension("bin").display());
+    }
+
+    println!(
+        "Ran {} sequences in {:.1?}, found {} crashes",
+        args.iterations,
+        start.elapsed(),
+        crashes.len()
+    );
+    if !crashes.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+fn exit(message: &str) -> ! {
+    eprintln!("{message}");
+    std::process::exit(1)
+}
+
+/// Arguments of the harness, passed by `anchor fuzz run`.
+struct Args {
+    iterations: u64,
+    seed: u64,
+    reproduce: Option<PathBuf>,
+}
+
+impl Args {
+    fn parse() -> Self {
+        let mut args = Self {
+            iterations: ITERATIONS,
+            seed: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|time| time.as_nanos() as u64)
+                .unwrap_or_default(),
+            reproduce: None,
+        };
+        let mut argv = std::env::args().skip(1);
+        while let Some(arg) = argv.next() {
+            let value = argv
+                .next()
+                .unwrap_or_else(|| exit(&format!("Missing value of {arg}")));
+            let invalid = |_| exit(&format!("Invalid value of {arg}: {value}"));
+            match arg.as_str() {
+                "--iterations" => args.iterations = value.parse().unwrap_or_else(invalid),
+                "--seed" => args.seed = value.parse().unwrap_or_else(invalid),
+                "--reproduce" => args.reproduce = Some(value.into()),
+                _ => exit(&format!("Unexpected argument: {arg}")),
+            }
+        }
+        args
+    }
+}
+
+/// SplitMix64 generator of the bytes of the sequences.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn bytes(&mut self) -> Vec<u8> {
+        let len = 64 + self.next() as usize % 4032;
+        (0..len.div_ceil(8))
+            .flat_map(|_| self.next().to_le_bytes())
+            .take(len)
+            .collect()
+    }
+}
+"##;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_idl() -> Idl {
+        serde_json::from_value(serde_json::json!({
+            "address": "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS",
+            "metadata": { "name": "foo", "version": "0.1.0", "spec": "0.1.0" },
+            "instructions": [{
+                "name": "open_vault",
+                "discriminator": [1, 2, 3, 4, 5, 6, 7, 8],
+                "accounts": [
+                    {
+                        "name": "common",
+                        "accounts": [{ "name": "payer", "writable": true, "signer": true }]
+                    },
+                    {
+                        "name": "vault",
+                        "writable": true,
+                        "pda": {
+                            "seeds": [
+                                { "kind": "const", "value": [118, 97, 117, 108, 116] },
+                                { "kind": "arg", "path": "params.owner" },
+                                { "kind": "account", "path": "common.payer" }
+                            ]
+                        }
+                    },
+                    {
+                        "name": "a",
+                        "pda": { "seeds": [{ "kind": "account", "path": "b" }] }
+                    },
+                    {
+                        "name": "b",
+                        "pda": { "seeds": [{ "kind": "account", "path": "a" }] }
+                    },
+                    { "name": "extra", "optional": true },
+                    {
+                        "name": "system_program",
+                        "address": "11111111111111111111111111111111"
+                    }
+                ],
+                "args": [
+                    { "name": "params", "type": { "defined": { "name": "Params" } } },
+                    { "name": "type", "type": "u8" }
+                ]
+            }],
+            "types": [{
+                "name": "Params",
+                "type": {
+                    "kind": "struct",
+                    "fields": [
+                        { "name": "owner", "type": "pubkey" },
+                        { "name": "amounts", "type": { "array": ["u64", 4] } }
+                    ]
+                }
+            }]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_program_module() {
+        let module = program_module(&sample_idl(), "foo").unwrap();
+        syn::parse_file(&module).unwrap();
+        assert!(module.contains(
+            "ctx.pda(&[&b\"vault\"[..], args.params.owner.0.as_ref(), common_payer.as_ref()], \
+            &PROGRAM_ID)"
+        ));
+        assert!(module.contains("pub common_payer: u8,"));
+        assert!(module.contains("pub extra: Option<u8>,"));
+        assert!(module.contains("pub r#type: u8,"));
+        assert!(module.contains("pub params: types::Params,"));
+        assert!(module.contains("pub amounts: [u64; 4],"));
+        // PDAs whose seeds depend on each other are picked among the known addresses
+        assert!(module.contains("let a = ctx.address(self.a);"));
+        assert!(module.contains("let b = ctx.address(self.b);"));
+    }
+
+    #[test]
+    fn test_program_module_without_instructions() {
+        let idl: Idl = serde_json::from_value(serde_json::json!({
+            "address": "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS",
+            "metadata": { "name": "foo", "version": "0.1.0", "spec": "0.1.0" },
+            "instructions": []
+        }))
+        .unwrap();
+        assert!(program_module(&idl, "foo").is_err());
+    }
+
+    #[test]
+    fn test_runtime() {
+        syn::parse_file(RUNTIME).unwrap();
+        syn::parse_file(&harness("foo")).unwrap();
+    }
+
+    /// Check that the generated crate compiles against this version of `anchor-client`.
+    #[test]
+    #[ignore = "compiles the generated `fuzz` crate, run with `--ignored`"]
+    fn test_generated_crate_compiles() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+        let dir = std::env::temp_dir().join(format!("anchor-fuzz-check-{}", std::process::id()));
+        let program_dir = dir.join("foo");
+        let fuzz_dir = dir.join(FUZZ_DIR);
+        let files = [
+            (
+                program_dir.join("Cargo.toml"),
+                format!(
+                    r#"[package]
+name = "foo"
+version = "0.1.0"
+edition = "2021"
+
+[features]
+no-entrypoint = []
+
+[dependencies]
+anchor-lang = {{ path = "{}" }}
+"#,
+                    root.join("lang").display()
+                ),
+            ),
+            (
+                program_dir.join("src").join("lib.rs"),
+                r#"use anchor_lang::prelude::*;
+
+declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+#[program]
+pub mod foo {
+    use super::*;
+
+    pub fn open_vault(_ctx: Context<OpenVault>) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct OpenVault {}
+"#
+                .to_owned(),
+            ),
+            (
+                fuzz_dir.join("Cargo.toml"),
+                format!(
+                    "{}\n[patch.crates-io]\nanchor-client = {{ path = \"{}\" }}\n",
+                    cargo_toml(r#"foo = { path = "../foo", features = ["no-entrypoint"] }"#),
+                    root.join("client").display()
+                ),
+            ),
+            (fuzz_dir.join("src").join("lib.rs"), RUNTIME.to_owned()),
+            (
+                fuzz_dir.join("src").join("programs").join("mod.rs"),
+                "pub mod foo;\n".to_owned(),
+            ),
+            (
+                fuzz_dir.join("src").join("programs").join("foo.rs"),
+                program_module(&sample_idl(), "foo").unwrap(),
+            ),
+            (
+                fuzz_dir.join("src").join("bin").join("foo.rs"),
+                harness("foo"),
+            ),
+            // Same dependency versions as the workspace
+            (
+                fuzz_dir.join("Cargo.lock"),
+                fs::read_to_string(root.join("Cargo.lock")).unwrap(),
+            ),
+        ];
+        for (path, content) in &files {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+
+        let status = std::process::Command::new("cargo")
+            .args(["check", "--bins"])
+            .env("CARGO_TARGET_DIR", root.join("target").join("fuzz-check"))
+            .current_dir(&fuzz_dir)
+            .status()
+            .unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(status.success());
+    }
+}
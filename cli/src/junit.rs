@@ -0,0 +1,141 @@
+//! JUnit XML reports of the test suites that `anchor test --jobs` runs in parallel.
+//!
+//! Every test suite is reported as a `<testsuite>` with a single `<testcase>`, since the results
+//! of the individual tests are only known to the test script. The output of the script is
+//! attached to the test case, so that the logs of every suite can be inspected separately.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::Result;
+
+/// Path of the JUnit report, relative to the workspace root.
+pub const REPORT_PATH: &str = ".anchor/test-report.xml";
+
+/// Result of a test suite.
+#[derive(Debug)]
+pub struct SuiteReport {
+    /// Name of the suite, which is the path of its config relative to the workspace root.
+    pub name: String,
+    /// Time it took to run the suite, including starting its validator.
+    pub time: Duration,
+    /// Why the suite failed, if it did.
+    pub failure: Option<String>,
+    /// Output of the test script.
+    pub output: String,
+}
+
+/// Write the JUnit report of the given test suites, where `time` is the wall-clock time it took
+/// to run all of them.
+pub fn write(path: impl AsRef<Path>, suites: &[SuiteReport], time: Duration) -> Result<()> {
+    let path = path.as_ref();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, report(suites, time))?;
+    Ok(())
+}
+
+fn report(suites: &[SuiteReport], time: Duration) -> String {
+    let failures = suites
+        .iter()
+        .filter(|suite| suite.failure.is_some())
+        .count();
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        r#"<testsuites name="anchor test" tests="{}" failures="{failures}" time="{:.3}">"#,
+        suites.len(),
+        time.as_secs_f64(),
+    );
+    for suite in suites {
+        let name = escape(&suite.name);
+        let time = suite.time.as_secs_f64();
+        let _ = writeln!(
+            xml,
+            r#"  <testsuite name="{name}" tests="1" failures="{}" time="{time:.3}">"#,
+            usize::from(suite.failure.is_some()),
+        );
+        let _ = writeln!(
+            xml,
+            r#"    <testcase name="test" classname="{name}" time="{time:.3}">"#,
+        );
+        if let Some(failure) = &suite.failure {
+            let _ = writeln!(xml, r#"      <failure message="{}"/>"#, escape(failure));
+        }
+        let _ = writeln!(
+            xml,
+            "      <system-out>{}</system-out>",
+            escape(&suite.output)
+        );
+        xml.push_str("    </testcase>\n");
+        xml.push_str("  </testsuite>\n");
+    }
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+/// Escape text for XML, removing the characters that XML doesn't allow, e.g. the escape codes of
+/// colored output.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c < ' ' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let suites = [
+            SuiteReport {
+                name: "Anchor.toml".into(),
+                time: Duration::from_millis(1500),
+                failure: None,
+                output: "  1 passing\n".into(),
+            },
+            SuiteReport {
+                name: "tests/<a>/Test.toml".into(),
+                time: Duration::from_millis(250),
+                failure: Some("Test script exited with code 1".into()),
+                output: "\u{1b}[31m1 failing\u{1b}[0m & \"done\"\n".into(),
+            },
+        ];
+        assert_eq!(
+            report(&suites, Duration::from_millis(1600)),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="anchor test" tests="2" failures="1" time="1.600">
+  <testsuite name="Anchor.toml" tests="1" failures="0" time="1.500">
+    <testcase name="test" classname="Anchor.toml" time="1.500">
+      <system-out>  1 passing
+</system-out>
+    </testcase>
+  </testsuite>
+  <testsuite name="tests/&lt;a&gt;/Test.toml" tests="1" failures="1" time="0.250">
+    <testcase name="test" classname="tests/&lt;a&gt;/Test.toml" time="0.250">
+      <failure message="Test script exited with code 1"/>
+      <system-out>[31m1 failing[0m &amp; &quot;done&quot;
+</system-out>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#
+        );
+    }
+}
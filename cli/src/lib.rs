@@ -1,20 +1,35 @@
 use crate::config::{
-    get_default_ledger_path, AnchorPackage, BootstrapMode, BuildConfig, Config, ConfigOverride,
-    Manifest, PackageManager, ProgramArch, ProgramDeployment, ProgramWorkspace, ScriptsConfig,
-    TestValidator, WithPath, SHUTDOWN_WAIT, STARTUP_WAIT,
+    _TestValidator, _Validator, get_default_ledger_path, AnchorPackage, BootstrapMode, BuildConfig,
+    Config, ConfigOverride, FixturesConfig, Manifest, PackageManager, Program, ProgramArch,
+    ProgramDeployment, ProgramWorkspace, ScriptsConfig, TestValidator, WithPath, SHUTDOWN_WAIT,
+    STARTUP_WAIT,
 };
-use anchor_client::Cluster;
-use anchor_lang::idl::{IdlAccount, IdlInstruction, ERASED_AUTHORITY};
-use anchor_lang::{AccountDeserialize, AnchorDeserialize, AnchorSerialize, Discriminator};
-use anchor_lang_idl::convert::convert_idl;
-use anchor_lang_idl::types::{Idl, IdlArrayLen, IdlDefinedFields, IdlType, IdlTypeDefTy};
+use crate::deploy::{get_program_state, upgrade_instructions, DeployProgress, Deployer};
+use crate::deployments::{
+    fetch_program, git_revision, hash_program, order_programs, DeployAction, DeploymentRecord,
+    DeploymentState, IdlAction,
+};
+use crate::fixtures::{fetch_accounts, write_account, FixtureKind, FixturesManifest};
+use anchor_client::solana_account_decoder::{UiAccountEncoding, UiDataSliceConfig};
+use anchor_client::{
+    decode_idl_data, encode_idl_data, migrate, Cluster, TestGenesis, TestGenesisAccount,
+    TestGenesisProgram, IDL_READ_CHUNK_SIZE, TEST_GENESIS_ENV,
+};
+use anchor_lang::idl::{
+    metadata_key, IdlAccount, IdlInstruction, ProgramMetadata, ERASED_AUTHORITY,
+};
+use anchor_lang::{AccountDeserialize, AnchorSerialize, Discriminator};
+use anchor_lang_idl::bundle::IdlBundle;
+use anchor_lang_idl::codec::decode_account;
+use anchor_lang_idl::convert::{convert_idl, convert_idl_to_codama, convert_idl_to_legacy};
+use anchor_lang_idl::diff::diff_idls;
+use anchor_lang_idl::types::Idl;
 use anyhow::{anyhow, Context, Result};
 use checks::{check_anchor_version, check_deps, check_idl_build_feature, check_overflow};
-use clap::{CommandFactory, Parser};
+use clap::{CommandFactory, Parser, ValueEnum};
 use dirs::home_dir;
 use flate2::read::GzDecoder;
-use flate2::read::ZlibDecoder;
-use flate2::write::{GzEncoder, ZlibEncoder};
+use flate2::write::GzEncoder;
 use flate2::Compression;
 use heck::{ToKebabCase, ToLowerCamelCase, ToPascalCase, ToSnakeCase};
 use regex::{Regex, RegexBuilder};
@@ -23,15 +38,18 @@ use reqwest::blocking::Client;
 use rust_template::{ProgramTemplate, TestTemplate};
 use semver::{Version, VersionReq};
 use serde::Deserialize;
-use serde_json::{json, Map, Value as JsonValue};
 use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcAccountInfoConfig;
+use solana_sdk::account::Account;
 use solana_sdk::account_utils::StateMut;
 use solana_sdk::bpf_loader;
 use solana_sdk::bpf_loader_deprecated;
 use solana_sdk::bpf_loader_upgradeable::{self, UpgradeableLoaderState};
 use solana_sdk::commitment_config::CommitmentConfig;
 use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::hash::{hash, Hash};
 use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::message::Message;
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::signature::Keypair;
 use solana_sdk::signature::Signer;
@@ -51,6 +69,12 @@ use tar::Archive;
 
 mod checks;
 pub mod config;
+pub mod coverage;
+pub mod deploy;
+pub mod deployments;
+pub mod fixtures;
+pub mod fuzz;
+pub mod junit;
 pub mod rust_template;
 pub mod solidity_template;
 
@@ -192,7 +216,10 @@ pub enum Command {
     },
     #[clap(name = "test", alias = "t")]
     /// Runs integration tests.
+    #[clap(args_conflicts_with_subcommands = true)]
     Test {
+        #[clap(subcommand)]
+        subcmd: Option<TestCommand>,
         /// Build and test only this program
         #[clap(short, long)]
         program_name: Option<String>,
@@ -218,10 +245,26 @@ pub enum Command {
         /// Architecture to use when building the program
         #[clap(value_enum, long, default_value = "sbf")]
         arch: ProgramArch,
+        /// Runtime to run the tests against
+        #[clap(value_enum, long, default_value = "validator")]
+        runtime: TestRuntime,
+        /// Measure the code coverage of the programs and write lcov and HTML
+        /// reports to `.anchor/coverage`. The tests run with the SVM runtime,
+        /// where the programs that are given a native processor run natively.
+        #[clap(long)]
+        coverage: bool,
+        /// Include the code generated by Anchor in the coverage reports
+        #[clap(long, requires = "coverage")]
+        include_generated: bool,
         /// Flag to keep the local validator running after tests
         /// to be able to check the transactions.
         #[clap(long)]
         detach: bool,
+        /// Run up to this many test suites in parallel, each against its own
+        /// local validator with dynamically allocated ports and ledger, and
+        /// write a JUnit report of the results to `.anchor/test-report.xml`.
+        #[clap(short, long, conflicts_with = "detach")]
+        jobs: Option<usize>,
         /// Run the test suites under the specified path
         #[clap(long)]
         run: Vec<String>,
@@ -233,6 +276,11 @@ pub enum Command {
         #[clap(required = false, last = true)]
         cargo_args: Vec<String>,
     },
+    /// Randomized testing harnesses of the programs.
+    Fuzz {
+        #[clap(subcommand)]
+        subcmd: FuzzCommand,
+    },
     /// Creates a new program.
     New {
         /// Program name
@@ -265,27 +313,59 @@ pub enum Command {
         /// If true, deploy from path target/verifiable
         #[clap(short, long)]
         verifiable: bool,
-        /// Arguments to pass to the underlying `solana program deploy` command.
-        #[clap(required = false, last = true)]
-        solana_args: Vec<String>,
+        /// Keypair of the buffer account (filepath). A partially written
+        /// buffer is reused, which allows resuming a failed deployment.
+        #[clap(long)]
+        buffer: Option<String>,
+        /// Max times to retry failed buffer writes.
+        #[clap(long, default_value = "5")]
+        max_retries: u32,
+        #[clap(long)]
+        priority_fee: Option<u64>,
+        /// Only print the deployment plan, without deploying.
+        #[clap(long)]
+        plan: bool,
+        /// Deploy the programs even if they are unchanged on chain.
+        #[clap(long)]
+        force: bool,
+        /// Also initialize or upgrade the IDL accounts of the programs.
+        #[clap(long)]
+        idl: bool,
     },
     /// Runs the deploy migration script.
-    Migrate,
+    Migrate {
+        /// Run the migration script even if it already ran on the cluster.
+        #[clap(long)]
+        force: bool,
+    },
     /// Deploys, initializes an IDL, and migrates all in one command.
     /// Upgrades a single program. The configured wallet must be the upgrade
     /// authority.
+    #[clap(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
     Upgrade {
+        #[clap(subcommand)]
+        subcmd: Option<UpgradeCommand>,
         /// The program to upgrade.
-        #[clap(short, long)]
-        program_id: Pubkey,
+        #[clap(short, long, required = true)]
+        program_id: Option<Pubkey>,
         /// Filepath to the new program binary.
-        program_filepath: String,
-        /// Max times to retry on failure.
-        #[clap(long, default_value = "0")]
+        #[clap(required = true)]
+        program_filepath: Option<String>,
+        /// Keypair of the buffer account (filepath). A partially written
+        /// buffer is reused, which allows resuming a failed upgrade.
+        #[clap(long)]
+        buffer: Option<String>,
+        /// Only write the buffer, without upgrading the program.
+        #[clap(long)]
+        buffer_only: bool,
+        /// Authority to set on the written buffer, e.g. a multisig.
+        #[clap(long, requires = "buffer_only")]
+        buffer_authority: Option<Pubkey>,
+        /// Max times to retry failed buffer writes.
+        #[clap(long, default_value = "5")]
         max_retries: u32,
-        /// Arguments to pass to the underlying `solana program deploy` command.
-        #[clap(required = false, last = true)]
-        solana_args: Vec<String>,
+        #[clap(long)]
+        priority_fee: Option<u64>,
     },
     #[cfg(feature = "dev")]
     /// Runs an airdrop loop, continuously funding the configured wallet.
@@ -436,10 +516,14 @@ pub enum IdlCommand {
     },
     /// Upgrades the IDL to the new file. An alias for first writing and then
     /// then setting the idl buffer account.
+    #[clap(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
     Upgrade {
-        program_id: Pubkey,
-        #[clap(short, long)]
-        filepath: String,
+        #[clap(subcommand)]
+        subcmd: Option<IdlUpgradeCommand>,
+        #[clap(required = true)]
+        program_id: Option<Pubkey>,
+        #[clap(short, long, required = true)]
+        filepath: Option<String>,
         #[clap(long)]
         priority_fee: Option<u64>,
     },
@@ -515,6 +599,9 @@ pub enum IdlCommand {
         /// Address to use (defaults to `metadata.address` value)
         #[clap(short, long)]
         program_id: Option<Pubkey>,
+        /// Format to convert the IDL to
+        #[clap(long, value_enum, default_value = "new")]
+        to: IdlFormat,
     },
     /// Generate TypeScript type for the IDL
     Type {
@@ -524,6 +611,252 @@ pub enum IdlCommand {
         #[clap(short, long)]
         out: Option<String>,
     },
+    /// Compare two IDLs and report compatible and breaking changes.
+    /// Exits with an error if there are breaking changes.
+    Diff {
+        /// Path to the old IDL file
+        old: String,
+        /// Path to the new IDL file
+        new: String,
+        /// Output the changes as JSON
+        #[clap(long)]
+        json: bool,
+    },
+    /// Generate JSON Schemas of the IDL types, accounts and instruction arguments
+    Schema {
+        /// Path to the IDL file
+        path: String,
+        /// Output file for the schemas (stdout if not specified)
+        #[clap(short, long)]
+        out: Option<String>,
+    },
+    /// Bundle the IDLs of all programs in the workspace
+    Bundle {
+        /// Output file for the bundle (stdout if not specified)
+        #[clap(short, long)]
+        out: Option<String>,
+    },
+    /// Generate a client crate from the IDL
+    Client {
+        /// Path to the IDL file
+        path: String,
+        /// Language of the generated client
+        #[clap(value_enum, long, default_value = "rust")]
+        lang: ClientLang,
+        /// Output directory of the client crate
+        #[clap(short, long)]
+        out: String,
+    },
+    /// Commands for interacting with the metadata of a program, e.g. its
+    /// IDL, security.txt, source URL or build hash.
+    Metadata {
+        #[clap(subcommand)]
+        subcmd: IdlMetadataCommand,
+    },
+}
+
+#[derive(Debug, Parser)]
+pub enum IdlMetadataCommand {
+    /// Writes a metadata entry of the program, creating its account if it
+    /// doesn't exist. Canonical metadata can only be written by the upgrade
+    /// authority of the program.
+    Write {
+        program_id: Pubkey,
+        /// Key of the metadata, e.g. `idl`, `security.txt`, `source-url` or `build-hash`
+        #[clap(short, long)]
+        key: String,
+        /// File with the metadata. IDL files are stored in the same format as
+        /// the data of the IDL account.
+        #[clap(short, long)]
+        filepath: String,
+        /// Write non-canonical metadata whose address is derived from the
+        /// wallet, e.g. if the upgrade authority of the program was burned.
+        #[clap(long)]
+        non_canonical: bool,
+        #[clap(long)]
+        priority_fee: Option<u64>,
+    },
+    /// Fetches a metadata entry of the program.
+    Fetch {
+        program_id: Pubkey,
+        /// Key of the metadata
+        #[clap(short, long)]
+        key: String,
+        /// Authority of non-canonical metadata. If none is given, then the
+        /// canonical metadata is fetched.
+        #[clap(long)]
+        authority: Option<Pubkey>,
+        /// Output file for the metadata (stdout if not specified)
+        #[clap(short, long)]
+        out: Option<String>,
+    },
+    /// Sets a new authority on a metadata entry of the program.
+    SetAuthority {
+        program_id: Pubkey,
+        /// Key of the metadata
+        #[clap(short, long)]
+        key: String,
+        /// Set the authority of the non-canonical metadata of the wallet.
+        #[clap(long)]
+        non_canonical: bool,
+        /// New authority of the metadata.
+        #[clap(short, long)]
+        new_authority: Pubkey,
+        #[clap(long)]
+        priority_fee: Option<u64>,
+    },
+    /// Closes a metadata entry of the program.
+    Close {
+        program_id: Pubkey,
+        /// Key of the metadata
+        #[clap(short, long)]
+        key: String,
+        /// Close the non-canonical metadata of the wallet.
+        #[clap(long)]
+        non_canonical: bool,
+        #[clap(long)]
+        priority_fee: Option<u64>,
+    },
+}
+
+#[derive(Debug, Parser)]
+pub enum TestCommand {
+    /// Commands for the fixtures of the cloned accounts.
+    Fixtures {
+        #[clap(subcommand)]
+        subcmd: FixturesCommand,
+    },
+}
+
+#[derive(Debug, Parser)]
+pub enum FixturesCommand {
+    /// Downloads the accounts of `[[test.validator.clone]]` into the fixtures
+    /// directory, so that tests can run without access to the cluster.
+    Pull {
+        /// Url of the cluster to pull the accounts from. Defaults to the
+        /// `[test.validator]` url.
+        #[clap(long)]
+        url: Option<String>,
+    },
+}
+
+#[derive(Debug, Parser)]
+pub enum FuzzCommand {
+    /// Generates the `fuzz` crate, with a randomized testing harness for every
+    /// program from its IDL. The invariants of existing harnesses are kept.
+    Init,
+    /// Runs the randomized testing harness of a program, and saves the sequences of
+    /// instructions that crash to `fuzz/crashes/<program>`.
+    Run {
+        /// Program to test
+        program_name: String,
+        /// Number of sequences of instructions to run
+        #[clap(long, default_value = "1000")]
+        iterations: u64,
+        /// Seed of the random sequences, to repeat a run
+        #[clap(long)]
+        seed: Option<u64>,
+        /// Only run the crashing sequence of the given file
+        #[clap(long)]
+        reproduce: Option<String>,
+        /// Flag to skip building the programs in the workspace
+        #[clap(long)]
+        skip_build: bool,
+    },
+}
+
+#[derive(Debug, Parser)]
+pub enum UpgradeCommand {
+    /// Writes the program buffer and prints the unsigned upgrade
+    /// instructions for the upgrade authority (e.g. a multisig), without
+    /// executing them.
+    Propose {
+        /// The program to upgrade.
+        #[clap(short, long)]
+        program_id: Pubkey,
+        /// Filepath to the new program binary.
+        program_filepath: String,
+        /// Keypair of the buffer account (filepath). A partially written
+        /// buffer is reused, which allows resuming a failed write.
+        #[clap(long)]
+        buffer: Option<String>,
+        /// Max times to retry failed buffer writes.
+        #[clap(long, default_value = "5")]
+        max_retries: u32,
+        #[clap(long)]
+        priority_fee: Option<u64>,
+        #[clap(flatten)]
+        proposal: ProposalArgs,
+    },
+}
+
+#[derive(Debug, Parser)]
+pub enum IdlUpgradeCommand {
+    /// Writes the IDL buffer and prints the unsigned upgrade instructions
+    /// for the IDL authority (e.g. a multisig), without executing them.
+    Propose {
+        program_id: Pubkey,
+        #[clap(short, long)]
+        filepath: String,
+        #[clap(long)]
+        priority_fee: Option<u64>,
+        #[clap(flatten)]
+        proposal: ProposalArgs,
+    },
+}
+
+/// Output options of an upgrade proposal.
+#[derive(Debug, Parser)]
+pub struct ProposalArgs {
+    /// Authority that executes the upgrade, e.g. the vault of a multisig.
+    /// Defaults to the current on-chain authority.
+    #[clap(long)]
+    pub authority: Option<Pubkey>,
+    /// Account that receives the lamports of the buffer. Defaults to the
+    /// authority.
+    #[clap(long)]
+    pub spill: Option<Pubkey>,
+    /// Encoding of the serialized output.
+    #[clap(long, value_enum, default_value = "base58")]
+    pub encoding: ProposalEncoding,
+    /// Output an unsigned transaction message, paid for by the authority,
+    /// instead of the instructions.
+    #[clap(long)]
+    pub message: bool,
+}
+
+/// Encoding of the serialized instructions of an upgrade proposal
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Parser, ValueEnum)]
+pub enum ProposalEncoding {
+    Base58,
+    Base64,
+}
+
+/// Output format of `anchor idl convert`
+#[derive(Clone, Debug, Eq, PartialEq, Parser, ValueEnum)]
+pub enum IdlFormat {
+    /// Current IDL spec
+    New,
+    /// Legacy IDL spec (pre Anchor v0.30)
+    Legacy,
+    /// Codama root node
+    Codama,
+}
+
+/// Language of the client generated by `anchor idl client`
+#[derive(Clone, Debug, Eq, PartialEq, Parser, ValueEnum)]
+pub enum ClientLang {
+    /// Rust crate that depends on `anchor-lang`
+    Rust,
+}
+
+/// Runtime of `anchor test`
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Parser, ValueEnum)]
+pub enum TestRuntime {
+    /// Local `solana-test-validator`
+    Validator,
+    /// In-process SVM of the Rust tests, see `anchor_client::TestGenesis`
+    Svm,
 }
 
 #[derive(Debug, Parser)]
@@ -845,45 +1178,91 @@ fn process_command(opts: Opts) -> Result<()> {
             program_name,
             program_keypair,
             verifiable,
-            solana_args,
+            buffer,
+            max_retries,
+            priority_fee,
+            plan,
+            force,
+            idl,
         } => deploy(
             &opts.cfg_override,
             program_name,
             program_keypair,
             verifiable,
-            solana_args,
+            buffer,
+            max_retries,
+            priority_fee,
+            plan,
+            force,
+            idl,
         ),
         Command::Expand {
             program_name,
             cargo_args,
         } => expand(&opts.cfg_override, program_name, &cargo_args),
         Command::Upgrade {
+            subcmd: Some(subcmd),
+            ..
+        } => upgrade_subcommand(&opts.cfg_override, subcmd),
+        Command::Upgrade {
+            subcmd: None,
             program_id,
             program_filepath,
+            buffer,
+            buffer_only,
+            buffer_authority,
             max_retries,
-            solana_args,
-        } => upgrade(
-            &opts.cfg_override,
-            program_id,
-            program_filepath,
-            max_retries,
-            solana_args,
-        ),
+            priority_fee,
+        } => {
+            // Presence is enforced by clap when no subcommand is given
+            let program_id = program_id.expect("program id is required");
+            let program_filepath = program_filepath.expect("program filepath is required");
+            if buffer_only {
+                upgrade_write_buffer(
+                    &opts.cfg_override,
+                    program_id,
+                    program_filepath,
+                    buffer,
+                    buffer_authority,
+                    max_retries,
+                    priority_fee,
+                )
+                .map(|_| ())
+            } else {
+                upgrade(
+                    &opts.cfg_override,
+                    program_id,
+                    program_filepath,
+                    buffer,
+                    max_retries,
+                    priority_fee,
+                )
+            }
+        }
         Command::Idl { subcmd } => idl(&opts.cfg_override, subcmd),
-        Command::Migrate => migrate(&opts.cfg_override),
+        Command::Migrate { force } => migrate(&opts.cfg_override, force),
+        Command::Test {
+            subcmd: Some(subcmd),
+            ..
+        } => test_subcommand(&opts.cfg_override, subcmd),
         Command::Test {
+            subcmd: None,
             program_name,
             skip_deploy,
             skip_local_validator,
             skip_build,
             no_idl,
             detach,
+            jobs,
             run,
             args,
             env,
             cargo_args,
             skip_lint,
             arch,
+            runtime,
+            coverage,
+            include_generated,
         } => test(
             &opts.cfg_override,
             program_name,
@@ -893,12 +1272,17 @@ fn process_command(opts: Opts) -> Result<()> {
             skip_lint,
             no_idl,
             detach,
+            jobs,
             run,
             args,
             env,
             cargo_args,
             arch,
+            runtime,
+            coverage,
+            include_generated,
         ),
+        Command::Fuzz { subcmd } => fuzz(&opts.cfg_override, subcmd),
         #[cfg(feature = "dev")]
         Command::Airdrop { .. } => airdrop(&opts.cfg_override),
         Command::Cluster { subcmd } => cluster(subcmd),
@@ -1377,6 +1761,8 @@ pub fn build(
     check_anchor_version(&cfg).ok();
     check_deps(&cfg).ok();
 
+    // Only bundle the IDLs if they are written to the default directory
+    let write_bundle = !no_idl && idl.is_none();
     let idl_out = match idl {
         Some(idl) => Some(PathBuf::from(idl)),
         None => Some(cfg_parent.join("target").join("idl")),
@@ -1453,6 +1839,19 @@ pub fn build(
 
     set_workspace_dir_or_exit();
 
+    if write_bundle {
+        match generate_idl_bundle(&cfg) {
+            Ok(bundle) => {
+                if let Err(e) = bundle.verify() {
+                    eprintln!("Warning: {e}");
+                }
+                let bundle_out = Path::new("target").join("idl-bundle.json");
+                fs::write(bundle_out, serde_json::to_string_pretty(&bundle)?)?;
+            }
+            Err(e) => eprintln!("Warning: Failed to bundle the IDLs: {e}"),
+        }
+    }
+
     Ok(())
 }
 
@@ -2290,10 +2689,27 @@ fn idl(cfg_override: &ConfigOverride, subcmd: IdlCommand) -> Result<()> {
             priority_fee,
         } => idl_set_buffer(cfg_override, program_id, buffer, print_only, priority_fee).map(|_| ()),
         IdlCommand::Upgrade {
+            subcmd:
+                Some(IdlUpgradeCommand::Propose {
+                    program_id,
+                    filepath,
+                    priority_fee,
+                    proposal,
+                }),
+            ..
+        } => idl_upgrade_propose(cfg_override, program_id, filepath, priority_fee, &proposal),
+        IdlCommand::Upgrade {
+            subcmd: None,
             program_id,
             filepath,
             priority_fee,
-        } => idl_upgrade(cfg_override, program_id, filepath, priority_fee),
+        } => idl_upgrade(
+            cfg_override,
+            // Presence is enforced by clap when no subcommand is given
+            program_id.expect("program id is required"),
+            filepath.expect("filepath is required"),
+            priority_fee,
+        ),
         IdlCommand::SetAuthority {
             program_id,
             address,
@@ -2334,8 +2750,14 @@ fn idl(cfg_override: &ConfigOverride, subcmd: IdlCommand) -> Result<()> {
             path,
             out,
             program_id,
-        } => idl_convert(path, out, program_id),
+            to,
+        } => idl_convert(path, out, program_id, to),
         IdlCommand::Type { path, out } => idl_type(path, out),
+        IdlCommand::Diff { old, new, json } => idl_diff(old, new, json),
+        IdlCommand::Schema { path, out } => idl_schema(path, out),
+        IdlCommand::Bundle { out } => idl_bundle(cfg_override, out),
+        IdlCommand::Client { path, lang, out } => idl_client(path, lang, out),
+        IdlCommand::Metadata { subcmd } => idl_metadata(cfg_override, subcmd),
     }
 }
 
@@ -2359,22 +2781,84 @@ fn fetch_idl(cfg_override: &ConfigOverride, idl_addr: Pubkey) -> Result<serde_js
 
     let client = create_client(url);
 
-    let mut account = client.get_account(&idl_addr)?;
-    if account.executable {
-        let idl_addr = IdlAccount::address(&idl_addr);
-        account = client.get_account(&idl_addr)?;
+    // Only fetch the metadata of the account to check whether it's a program
+    let account = get_account_data_slice(&client, &idl_addr, 0, 0)?;
+    let idl = if account.executable {
+        // Fall back to the canonical IDL metadata of the program if it has no IDL account
+        let idl_address = IdlAccount::address(&idl_addr);
+        match get_account_data_slice(&client, &idl_address, 0, 0) {
+            Ok(_) => decode_idl_data(IdlDataReader::new(&client, idl_address)?)?,
+            Err(_) => {
+                let metadata_address = ProgramMetadata::address(&idl_addr, metadata_key::IDL, None);
+                decode_idl_data(&fetch_metadata(&client, &metadata_address)?[..])?
+            }
+        }
+    } else {
+        decode_idl_data(IdlDataReader::new(&client, idl_addr)?)?
+    };
+
+    serde_json::from_slice(&idl).map_err(Into::into)
+}
+
+/// Reads the IDL data of an IDL account in chunks of [`IDL_READ_CHUNK_SIZE`] bytes, fetching the
+/// next chunk only once the previous one has been consumed.
+struct IdlDataReader<'a> {
+    client: &'a RpcClient,
+    idl_address: Pubkey,
+    offset: usize,
+    end: usize,
+    chunk: std::io::Cursor<Vec<u8>>,
+}
+
+impl<'a> IdlDataReader<'a> {
+    fn new(client: &'a RpcClient, idl_address: Pubkey) -> Result<Self> {
+        let header = get_account_data_slice(client, &idl_address, 0, IdlAccount::DATA_OFFSET)?;
+        let idl_account = IdlAccount::try_deserialize(&mut &header.data[..])?;
+        Ok(Self {
+            client,
+            idl_address,
+            offset: IdlAccount::DATA_OFFSET,
+            end: IdlAccount::DATA_OFFSET + idl_account.data_len as usize,
+            chunk: Default::default(),
+        })
     }
+}
+
+impl Read for IdlDataReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.chunk.position() as usize == self.chunk.get_ref().len() && self.offset < self.end {
+            let length = IDL_READ_CHUNK_SIZE.min(self.end - self.offset);
+            let account =
+                get_account_data_slice(self.client, &self.idl_address, self.offset, length)
+                    .map_err(std::io::Error::other)?;
+            if account.data.len() != length {
+                return Err(std::io::ErrorKind::UnexpectedEof.into());
+            }
 
-    // Cut off account discriminator.
-    let mut d: &[u8] = &account.data[IdlAccount::DISCRIMINATOR.len()..];
-    let idl_account: IdlAccount = AnchorDeserialize::deserialize(&mut d)?;
+            self.offset += length;
+            self.chunk = std::io::Cursor::new(account.data);
+        }
+
+        self.chunk.read(buf)
+    }
+}
 
-    let compressed_len: usize = idl_account.data_len.try_into().unwrap();
-    let compressed_bytes = &account.data[44..44 + compressed_len];
-    let mut z = ZlibDecoder::new(compressed_bytes);
-    let mut s = Vec::new();
-    z.read_to_end(&mut s)?;
-    serde_json::from_slice(&s[..]).map_err(Into::into)
+fn get_account_data_slice(
+    client: &RpcClient,
+    address: &Pubkey,
+    offset: usize,
+    length: usize,
+) -> Result<Account> {
+    let config = RpcAccountInfoConfig {
+        encoding: Some(UiAccountEncoding::Base64),
+        data_slice: Some(UiDataSliceConfig { offset, length }),
+        commitment: Some(client.commitment()),
+        min_context_slot: None,
+    };
+    client
+        .get_account_with_config(address, config)?
+        .value
+        .ok_or_else(|| anyhow!("Account not found: {address}"))
 }
 
 fn get_idl_account(client: &RpcClient, idl_address: &Pubkey) -> Result<IdlAccount> {
@@ -2530,6 +3014,59 @@ fn idl_upgrade(
     Ok(())
 }
 
+/// Write the IDL into a buffer owned by the IDL authority, and print the instructions to upgrade
+/// the IDL for the authority to sign.
+fn idl_upgrade_propose(
+    cfg_override: &ConfigOverride,
+    program_id: Pubkey,
+    idl_filepath: String,
+    priority_fee: Option<u64>,
+    proposal: &ProposalArgs,
+) -> Result<()> {
+    let idl_address = IdlAccount::address(&program_id);
+    let authority = match proposal.authority {
+        Some(authority) => authority,
+        None => with_workspace(cfg_override, |cfg| {
+            let url = cluster_url(cfg, &cfg.test_validator);
+            let client = create_client(url);
+            get_idl_account(&client, &idl_address).map(|idl| idl.authority)
+        })?,
+    };
+
+    let buffer = idl_write_buffer(cfg_override, program_id, idl_filepath, priority_fee)?;
+    idl_set_authority(
+        cfg_override,
+        program_id,
+        Some(buffer),
+        authority,
+        false,
+        priority_fee,
+    )?;
+    println!("Buffer: {buffer}");
+
+    let ixs = vec![
+        Instruction {
+            program_id,
+            accounts: vec![
+                AccountMeta::new(buffer, false),
+                AccountMeta::new(idl_address, false),
+                AccountMeta::new(authority, true),
+            ],
+            data: serialize_idl_ix(IdlInstruction::SetBuffer)?,
+        },
+        Instruction {
+            program_id,
+            accounts: vec![
+                AccountMeta::new(buffer, false),
+                AccountMeta::new_readonly(authority, true),
+                AccountMeta::new(proposal.spill.unwrap_or(authority), false),
+            ],
+            data: serialize_idl_ix(IdlInstruction::Close)?,
+        },
+    ];
+    print_proposal(&ixs, &authority, proposal)
+}
+
 fn idl_authority(cfg_override: &ConfigOverride, program_id: Pubkey) -> Result<()> {
     with_workspace(cfg_override, |cfg| {
         let url = cluster_url(cfg, &cfg.test_validator);
@@ -2705,12 +3242,7 @@ fn idl_write(
     let client = create_client(url);
 
     // Serialize and compress the idl.
-    let idl_data = {
-        let json_bytes = serde_json::to_vec(idl)?;
-        let mut e = ZlibEncoder::new(Vec::new(), Compression::default());
-        e.write_all(&json_bytes)?;
-        e.finish()?
-    };
+    let idl_data = serialize_idl(idl)?;
 
     println!("Idl data length: {:?} bytes", idl_data.len());
 
@@ -2718,14 +3250,11 @@ fn idl_write(
     let mut offset = 0;
     while offset < idl_data.len() {
         println!("Step {offset}/{} ", idl_data.len());
+        let end = std::cmp::min(offset + MAX_WRITE_SIZE, idl_data.len());
         // Instruction data.
-        let data = {
-            let start = offset;
-            let end = std::cmp::min(offset + MAX_WRITE_SIZE, idl_data.len());
-            serialize_idl_ix(anchor_lang::idl::IdlInstruction::Write {
-                data: idl_data[start..end].to_vec(),
-            })?
-        };
+        let data = serialize_idl_ix(anchor_lang::idl::IdlInstruction::Write {
+            data: idl_data[offset..end].to_vec(),
+        })?;
         // Instruction accounts.
         let accounts = vec![
             AccountMeta::new(idl_address, false),
@@ -2755,6 +3284,17 @@ fn idl_write(
             match client.send_and_confirm_transaction_with_spinner(&tx) {
                 Ok(_) => break,
                 Err(e) => {
+                    // The transaction might have landed even though confirming it failed, in
+                    // which case retrying would append the same chunk twice
+                    let written = get_idl_account(&client, &idl_address)?.data_len as usize;
+                    if written == end {
+                        break;
+                    }
+                    if written != offset {
+                        return Err(anyhow!(
+                            "Unexpected IDL data length: expected {offset}, found {written}"
+                        ));
+                    }
                     if retries == 19 {
                         return Err(anyhow!("Error: {e}. Failed to send transaction."));
                     }
@@ -2763,7 +3303,7 @@ fn idl_write(
             }
         }
 
-        offset += MAX_WRITE_SIZE;
+        offset = end;
     }
     Ok(())
 }
@@ -2820,9 +3360,25 @@ fn generate_idl(
 ) -> Result<Idl> {
     check_idl_build_feature()?;
 
+    // Types that are defined by the other programs of the workspace are marked with their origin
+    let lib_name = Manifest::discover()?.and_then(|manifest| manifest.lib_name().ok());
+    let programs = cfg
+        .programs
+        .get(&cfg.provider.cluster)
+        .map(|programs| {
+            programs
+                .iter()
+                .filter(|(name, _)| Some(*name) != lib_name.as_ref())
+                .map(|(name, deployment)| (name.to_owned(), deployment.address.to_string()))
+                .collect()
+        })
+        .unwrap_or_default();
+
     anchor_lang_idl::build::IdlBuilder::new()
         .resolution(cfg.features.resolution)
         .skip_lint(cfg.features.skip_lint || skip_lint)
+        .lints(cfg.lints.clone())
+        .programs(programs)
         .no_docs(no_docs)
         .cargo_args(cargo_args.into())
         .build()
@@ -2838,31 +3394,309 @@ fn idl_fetch(cfg_override: &ConfigOverride, address: Pubkey, out: Option<String>
     Ok(())
 }
 
-fn idl_convert(path: String, out: Option<String>, program_id: Option<Pubkey>) -> Result<()> {
-    let idl = fs::read(path)?;
-
-    // Set the `metadata.address` field based on the given `program_id`
-    let idl = match program_id {
-        Some(program_id) => {
-            let mut idl = serde_json::from_slice::<serde_json::Value>(&idl)?;
-            idl.as_object_mut()
-                .ok_or_else(|| anyhow!("IDL must be an object"))?
-                .insert(
-                    "metadata".into(),
-                    serde_json::json!({ "address": program_id.to_string() }),
-                );
-            serde_json::to_vec(&idl)?
-        }
-        _ => idl,
-    };
-
-    let idl = convert_idl(&idl)?;
-    let out = match out {
-        None => OutFile::Stdout,
-        Some(out) => OutFile::File(PathBuf::from(out)),
-    };
-    write_idl(&idl, out)
-}
+fn idl_metadata(cfg_override: &ConfigOverride, subcmd: IdlMetadataCommand) -> Result<()> {
+    match subcmd {
+        IdlMetadataCommand::Write {
+            program_id,
+            key,
+            filepath,
+            non_canonical,
+            priority_fee,
+        } => idl_metadata_write(
+            cfg_override,
+            program_id,
+            key,
+            filepath,
+            non_canonical,
+            priority_fee,
+        ),
+        IdlMetadataCommand::Fetch {
+            program_id,
+            key,
+            authority,
+            out,
+        } => idl_metadata_fetch(cfg_override, program_id, key, authority, out),
+        IdlMetadataCommand::SetAuthority {
+            program_id,
+            key,
+            non_canonical,
+            new_authority,
+            priority_fee,
+        } => idl_metadata_set_authority(
+            cfg_override,
+            program_id,
+            key,
+            non_canonical,
+            new_authority,
+            priority_fee,
+        ),
+        IdlMetadataCommand::Close {
+            program_id,
+            key,
+            non_canonical,
+            priority_fee,
+        } => idl_metadata_close(cfg_override, program_id, key, non_canonical, priority_fee),
+    }
+}
+
+fn idl_metadata_write(
+    cfg_override: &ConfigOverride,
+    program_id: Pubkey,
+    key: String,
+    filepath: String,
+    non_canonical: bool,
+    priority_fee: Option<u64>,
+) -> Result<()> {
+    with_workspace(cfg_override, |cfg| {
+        let keypair = get_keypair(&cfg.provider.wallet.to_string())?;
+        let url = cluster_url(cfg, &cfg.test_validator);
+        let client = create_client(url);
+
+        let data = fs::read(filepath)?;
+        let data = match key.as_str() {
+            metadata_key::IDL => serialize_idl(&convert_idl(&data)?)?,
+            _ => data,
+        };
+
+        let authority = non_canonical.then(|| keypair.pubkey());
+        let metadata_address = ProgramMetadata::address(&program_id, &key, authority.as_ref());
+
+        // Create the metadata account if it doesn't exist
+        let mut instructions = vec![];
+        if get_account_data_slice(&client, &metadata_address, 0, 0).is_err() {
+            let program_data = match non_canonical {
+                true => program_id,
+                false => {
+                    Pubkey::find_program_address(
+                        &[program_id.as_ref()],
+                        &bpf_loader_upgradeable::ID,
+                    )
+                    .0
+                }
+            };
+            instructions.push(Instruction {
+                program_id,
+                accounts: vec![
+                    AccountMeta::new(keypair.pubkey(), true),
+                    AccountMeta::new_readonly(keypair.pubkey(), true),
+                    AccountMeta::new(metadata_address, false),
+                    AccountMeta::new_readonly(program_id, false),
+                    AccountMeta::new_readonly(program_data, false),
+                    AccountMeta::new_readonly(solana_sdk::system_program::ID, false),
+                ],
+                data: serialize_idl_ix(IdlInstruction::CreateMetadata {
+                    key: key.clone(),
+                    canonical: !non_canonical,
+                })?,
+            });
+        }
+
+        println!("Metadata length: {} bytes", data.len());
+
+        // Writes start at the given offset, which makes retrying a write idempotent
+        const MAX_WRITE_SIZE: usize = 600;
+        let mut offset = 0;
+        loop {
+            let end = std::cmp::min(offset + MAX_WRITE_SIZE, data.len());
+            println!("Step {offset}/{} ", data.len());
+            instructions.push(Instruction {
+                program_id,
+                accounts: vec![
+                    AccountMeta::new(metadata_address, false),
+                    AccountMeta::new(keypair.pubkey(), true),
+                    AccountMeta::new_readonly(solana_sdk::system_program::ID, false),
+                ],
+                data: serialize_idl_ix(IdlInstruction::WriteMetadata {
+                    offset: offset as u32,
+                    data: data[offset..end].to_vec(),
+                })?,
+            });
+            let instructions =
+                prepend_compute_unit_ix(std::mem::take(&mut instructions), &client, priority_fee)?;
+            send_metadata_instructions(&client, &keypair, &instructions)?;
+
+            offset = end;
+            if offset == data.len() {
+                break;
+            }
+        }
+
+        println!("Metadata written: {metadata_address}");
+        Ok(())
+    })
+}
+
+fn idl_metadata_fetch(
+    cfg_override: &ConfigOverride,
+    program_id: Pubkey,
+    key: String,
+    authority: Option<Pubkey>,
+    out: Option<String>,
+) -> Result<()> {
+    let url = match Config::discover(cfg_override)? {
+        Some(cfg) => cluster_url(&cfg, &cfg.test_validator),
+        None => match cfg_override.cluster.as_ref() {
+            Some(cluster) => cluster.url().to_string(),
+            None => config::get_solana_cfg_url()?,
+        },
+    };
+    let client = create_client(url);
+
+    let metadata_address = ProgramMetadata::address(&program_id, &key, authority.as_ref());
+    let data = fetch_metadata(&client, &metadata_address)?;
+    let data = match key.as_str() {
+        metadata_key::IDL => {
+            let idl = decode_idl_data(&data[..])?;
+            serde_json::to_vec_pretty(&serde_json::from_slice::<serde_json::Value>(&idl)?)?
+        }
+        _ => data,
+    };
+
+    match out {
+        Some(out) => fs::write(out, data)?,
+        _ => println!("{}", String::from_utf8_lossy(&data)),
+    };
+
+    Ok(())
+}
+
+fn idl_metadata_set_authority(
+    cfg_override: &ConfigOverride,
+    program_id: Pubkey,
+    key: String,
+    non_canonical: bool,
+    new_authority: Pubkey,
+    priority_fee: Option<u64>,
+) -> Result<()> {
+    with_workspace(cfg_override, |cfg| {
+        let keypair = get_keypair(&cfg.provider.wallet.to_string())?;
+        let url = cluster_url(cfg, &cfg.test_validator);
+        let client = create_client(url);
+
+        let authority = non_canonical.then(|| keypair.pubkey());
+        let metadata_address = ProgramMetadata::address(&program_id, &key, authority.as_ref());
+        let ix = Instruction {
+            program_id,
+            accounts: vec![
+                AccountMeta::new(metadata_address, false),
+                AccountMeta::new_readonly(keypair.pubkey(), true),
+            ],
+            data: serialize_idl_ix(IdlInstruction::SetMetadataAuthority { new_authority })?,
+        };
+        let instructions = prepend_compute_unit_ix(vec![ix], &client, priority_fee)?;
+        send_metadata_instructions(&client, &keypair, &instructions)?;
+
+        println!("Authority update complete.");
+        Ok(())
+    })
+}
+
+fn idl_metadata_close(
+    cfg_override: &ConfigOverride,
+    program_id: Pubkey,
+    key: String,
+    non_canonical: bool,
+    priority_fee: Option<u64>,
+) -> Result<()> {
+    with_workspace(cfg_override, |cfg| {
+        let keypair = get_keypair(&cfg.provider.wallet.to_string())?;
+        let url = cluster_url(cfg, &cfg.test_validator);
+        let client = create_client(url);
+
+        let authority = non_canonical.then(|| keypair.pubkey());
+        let metadata_address = ProgramMetadata::address(&program_id, &key, authority.as_ref());
+        let ix = Instruction {
+            program_id,
+            accounts: vec![
+                AccountMeta::new(metadata_address, false),
+                AccountMeta::new_readonly(keypair.pubkey(), true),
+                AccountMeta::new(keypair.pubkey(), false),
+            ],
+            data: serialize_idl_ix(IdlInstruction::CloseMetadata)?,
+        };
+        let instructions = prepend_compute_unit_ix(vec![ix], &client, priority_fee)?;
+        send_metadata_instructions(&client, &keypair, &instructions)?;
+
+        println!("Metadata account closed: {metadata_address}");
+        Ok(())
+    })
+}
+
+fn send_metadata_instructions(
+    client: &RpcClient,
+    keypair: &Keypair,
+    instructions: &[Instruction],
+) -> Result<()> {
+    let latest_hash = client.get_latest_blockhash()?;
+    let tx = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&keypair.pubkey()),
+        &[keypair],
+        latest_hash,
+    );
+    client.send_and_confirm_transaction_with_spinner(&tx)?;
+    Ok(())
+}
+
+/// Fetch the data of a program metadata account, i.e. the part after
+/// [`ProgramMetadata::DATA_OFFSET`].
+fn fetch_metadata(client: &RpcClient, metadata_address: &Pubkey) -> Result<Vec<u8>> {
+    let account = client
+        .get_account(metadata_address)
+        .map_err(|_| anyhow!("Metadata account not found: {metadata_address}"))?;
+    let metadata = ProgramMetadata::try_deserialize(&mut &account.data[..])?;
+    account
+        .data
+        .get(ProgramMetadata::DATA_OFFSET..)
+        .and_then(|data| data.get(..metadata.data_len as usize))
+        .map(|data| data.to_vec())
+        .ok_or_else(|| anyhow!("Metadata account is smaller than its data length"))
+}
+
+fn idl_convert(
+    path: String,
+    out: Option<String>,
+    program_id: Option<Pubkey>,
+    to: IdlFormat,
+) -> Result<()> {
+    let idl = fs::read(path)?;
+
+    // Set the `metadata.address` field based on the given `program_id`
+    let idl = match program_id {
+        Some(program_id) => {
+            let mut idl = serde_json::from_slice::<serde_json::Value>(&idl)?;
+            idl.as_object_mut()
+                .ok_or_else(|| anyhow!("IDL must be an object"))?
+                .insert(
+                    "metadata".into(),
+                    serde_json::json!({ "address": program_id.to_string() }),
+                );
+            serde_json::to_vec(&idl)?
+        }
+        _ => idl,
+    };
+
+    let idl = convert_idl(&idl)?;
+    let out = match out {
+        None => OutFile::Stdout,
+        Some(out) => OutFile::File(PathBuf::from(out)),
+    };
+    let conversion = match to {
+        IdlFormat::New => return write_idl(&idl, out),
+        IdlFormat::Legacy => convert_idl_to_legacy(&idl)?,
+        IdlFormat::Codama => convert_idl_to_codama(&idl)?,
+    };
+    for warning in &conversion.warnings {
+        eprintln!("Warning: {warning}");
+    }
+
+    let idl_json = serde_json::to_string_pretty(&conversion.idl)?;
+    match out {
+        OutFile::Stdout => println!("{idl_json}"),
+        OutFile::File(out) => fs::write(out, idl_json)?,
+    };
+    Ok(())
+}
 
 fn idl_type(path: String, out: Option<String>) -> Result<()> {
     let idl = fs::read(path)?;
@@ -2875,28 +3709,170 @@ fn idl_type(path: String, out: Option<String>) -> Result<()> {
     Ok(())
 }
 
-fn idl_ts(idl: &Idl) -> Result<String> {
-    let idl_name = &idl.metadata.name;
-    let type_name = idl_name.to_pascal_case();
-    let idl = serde_json::to_string(idl)?;
+fn idl_diff(old: String, new: String, json: bool) -> Result<()> {
+    let old = convert_idl(&fs::read(old)?)?;
+    let new = convert_idl(&fs::read(new)?)?;
+    let diff = diff_idls(&old, &new);
+
+    if json {
+        let output = serde_json::json!({
+            "breaking": diff.is_breaking(),
+            "changes": diff.changes,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else if diff.is_empty() {
+        println!("No changes");
+    } else {
+        for (title, changes) in [
+            (
+                "Breaking changes",
+                diff.breaking_changes().collect::<Vec<_>>(),
+            ),
+            ("Compatible changes", diff.compatible_changes().collect()),
+        ] {
+            if !changes.is_empty() {
+                println!("{title}:");
+                changes.iter().for_each(|change| println!("  - {change}"));
+            }
+        }
+    }
+
+    if diff.is_breaking() {
+        return Err(anyhow!("Found breaking IDL changes"));
+    }
+    Ok(())
+}
+
+fn idl_schema(path: String, out: Option<String>) -> Result<()> {
+    let idl = convert_idl(&fs::read(path)?)?;
+    let schema = serde_json::to_string_pretty(&anchor_lang_idl::schema::idl_schema(&idl)?)?;
+    match out {
+        Some(out) => fs::write(out, schema)?,
+        _ => println!("{schema}"),
+    };
+    Ok(())
+}
+
+fn idl_bundle(cfg_override: &ConfigOverride, out: Option<String>) -> Result<()> {
+    let cfg = Config::discover(cfg_override)?.expect("Not in workspace");
+    set_workspace_dir_or_exit();
+
+    let bundle = generate_idl_bundle(&cfg)?;
+    bundle.verify()?;
+
+    let bundle = serde_json::to_string_pretty(&bundle)?;
+    match out {
+        Some(out) => fs::write(out, bundle)?,
+        _ => println!("{bundle}"),
+    };
+    Ok(())
+}
+
+/// Bundle the IDLs of the workspace programs that exist in `target/idl`.
+///
+/// The current directory must be the workspace root.
+fn generate_idl_bundle(cfg: &WithPath<Config>) -> Result<IdlBundle> {
+    let idls = cfg
+        .read_all_programs()?
+        .into_iter()
+        .filter_map(|program| program.idl)
+        .collect();
+    IdlBundle::new(idls)
+}
 
-    // Convert every field of the IDL to camelCase
-    let camel_idl = Regex::new(r#""\w+":"([\w\d]+)""#)?
-        .captures_iter(&idl)
-        .fold(idl.clone(), |acc, cur| {
-            let name = cur.get(1).unwrap().as_str();
+fn idl_client(path: String, lang: ClientLang, out: String) -> Result<()> {
+    let idl = convert_idl(&fs::read(path)?)?;
+    match lang {
+        ClientLang::Rust => idl_client_rust(&idl, Path::new(&out)),
+    }
+}
 
-            // Do not modify pubkeys
-            if Pubkey::from_str(name).is_ok() {
-                return acc;
+/// Generate a standalone Rust client crate from the IDL.
+///
+/// The crate is generated with the same code generator as `declare_program!`, and every top-level
+/// module of the generated code is written to its own file.
+fn idl_client_rust(idl: &Idl, out: &Path) -> Result<()> {
+    let tokens = anchor_syn::codegen::declare_program::gen_program_items(idl);
+    let mut file = syn::parse2::<syn::File>(tokens)
+        .map_err(|e| anyhow!("Failed to parse the generated client: {e}"))?;
+
+    let src = out.join("src");
+    fs::create_dir_all(&src)?;
+
+    for item in &mut file.items {
+        if let syn::Item::Mod(item_mod) = item {
+            if let Some((_, items)) = item_mod.content.take() {
+                let content = quote::quote! { #(#items)* };
+                fs::write(
+                    src.join(format!("{}.rs", item_mod.ident)),
+                    content.to_string(),
+                )?;
+                item_mod.semi = Some(Default::default());
             }
+        }
+    }
 
-            let camel_name = name.to_lower_camel_case();
-            acc.replace(&format!(r#""{name}""#), &format!(r#""{camel_name}""#))
-        });
+    let name = &idl.metadata.name;
+    let docs = [
+        format!("Generated client of program `{name}`."),
+        String::default(),
+    ]
+    .into_iter()
+    .chain(idl.docs.iter().cloned())
+    .map(|doc| format!("//! {doc}").trim_end().to_owned())
+    .collect::<Vec<_>>()
+    .join("\n");
+    let items = &file.items;
+    let lib = format!(
+        "{docs}\n\n#![allow(unused_imports, unused_mut)]\n\n{}",
+        quote::quote! { #(#items)* }
+    );
+    let lib_path = src.join("lib.rs");
+    fs::write(&lib_path, lib)?;
+
+    fs::write(
+        out.join("Cargo.toml"),
+        format!(
+            r#"[package]
+name = "{}-client"
+version = "{}"
+description = "Generated client of program `{name}`"
+edition = "2021"
+
+[dependencies]
+anchor-lang = "{VERSION}"
+"#,
+            name.to_kebab_case(),
+            idl.metadata.version,
+        ),
+    )?;
+
+    let exit = std::process::Command::new("rustfmt")
+        .args(["--edition", "2021"])
+        .arg(&lib_path)
+        .stderr(Stdio::inherit())
+        .output();
+    match exit {
+        Ok(exit) if exit.status.success() => {}
+        _ => eprintln!("Warning: Failed to format the generated client with `rustfmt`"),
+    }
+
+    println!("Generated client crate at {}", out.display());
+    Ok(())
+}
+
+fn idl_ts(idl: &Idl) -> Result<String> {
+    let idl_name = &idl.metadata.name;
+    let type_name = idl_name.to_pascal_case();
 
-    // Pretty format
-    let camel_idl = serde_json::to_string_pretty(&serde_json::from_str::<Idl>(&camel_idl)?)?;
+    let mut camel_idl = serde_json::to_value(idl)?;
+    convert_idl_names_to_camel_case(&mut camel_idl);
+
+    // Deserialize to keep the order of the fields, which `serde_json::Value` sorts
+    let camel_idl = serde_json::from_value::<Idl>(camel_idl)?;
+    let camel_idl = serde_json::from_str::<OrderedJson>(&serde_json::to_string(&camel_idl)?)?;
+    let mut camel_idl_ts = String::new();
+    camel_idl.write_ts(0, &mut camel_idl_ts);
 
     Ok(format!(
         r#"/**
@@ -2905,53 +3881,267 @@ fn idl_ts(idl: &Idl) -> Result<String> {
  * Note that this is only a type helper and is not the actual IDL. The original
  * IDL can be found at `target/idl/{idl_name}.json`.
  */
-export type {type_name} = {camel_idl};
+export type {type_name} = {camel_idl_ts};
 "#
     ))
 }
 
-fn write_idl(idl: &Idl, out: OutFile) -> Result<()> {
-    let idl_json = serde_json::to_string_pretty(idl)?;
-    match out {
-        OutFile::Stdout => println!("{idl_json}"),
-        OutFile::File(out) => fs::write(out, idl_json)?,
-    };
+/// Convert the names of the IDL to camelCase, like `convertIdlToCamelCase` of the TS package.
+///
+/// Only the values of the keys that hold names are converted, which keeps other values such as
+/// `"unit": "micro_lamports"` as they are.
+fn convert_idl_names_to_camel_case(value: &mut serde_json::Value) {
+    const KEYS: [&str; 5] = ["name", "path", "account", "relations", "generic"];
+
+    // `my_account.field` is converted to `myAccount.field`
+    fn to_camel_case(value: &mut serde_json::Value) {
+        if let serde_json::Value::String(name) = value {
+            *name = name
+                .split('.')
+                .map(|name| name.to_lower_camel_case())
+                .collect::<Vec<_>>()
+                .join(".");
+        }
+    }
 
-    Ok(())
+    match value {
+        serde_json::Value::Array(values) => {
+            values.iter_mut().for_each(convert_idl_names_to_camel_case)
+        }
+        serde_json::Value::Object(map) => {
+            for (key, value) in map {
+                match value {
+                    serde_json::Value::Array(names) if KEYS.contains(&key.as_str()) => {
+                        names.iter_mut().for_each(to_camel_case)
+                    }
+                    _ if KEYS.contains(&key.as_str()) => to_camel_case(value),
+                    _ => convert_idl_names_to_camel_case(value),
+                }
+            }
+        }
+        _ => {}
+    }
 }
 
-/// Print `base64+borsh` encoded IDL instruction.
-fn print_idl_instruction(ix_name: &str, ix: &Instruction, idl_address: &Pubkey) -> Result<()> {
-    use base64::engine::general_purpose::STANDARD;
-    use base64::Engine;
+/// JSON value that keeps the order of the keys of its objects, unlike [`serde_json::Value`].
+enum OrderedJson {
+    Value(serde_json::Value),
+    Array(Vec<OrderedJson>),
+    Object(Vec<(String, OrderedJson)>),
+}
 
-    println!("Print only mode. No execution!");
-    println!("Instruction: {ix_name}");
-    println!("IDL address: {idl_address}");
-    println!("Program: {}", ix.program_id);
+impl<'de> Deserialize<'de> for OrderedJson {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
 
-    // Serialize with `bincode` because `Instruction` does not implement `BorshSerialize`
-    let mut serialized_ix = bincode::serialize(ix)?;
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = OrderedJson;
 
-    // Remove extra bytes in order to make the serialized instruction `borsh` compatible
-    // `bincode` uses 8 bytes(LE) for length meanwhile `borsh` uses 4 bytes(LE)
-    let mut remove_extra_vec_bytes = |index: usize| {
-        serialized_ix.drain((index + 4)..(index + 8));
-    };
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a JSON value")
+            }
 
-    let accounts_index = std::mem::size_of_val(&ix.program_id);
-    remove_extra_vec_bytes(accounts_index);
-    let data_index = accounts_index + 4 + std::mem::size_of_val(&*ix.accounts);
-    remove_extra_vec_bytes(data_index);
+            fn visit_unit<E>(self) -> Result<Self::Value, E> {
+                Ok(OrderedJson::Value(serde_json::Value::Null))
+            }
 
-    println!(
-        "Base64 encoded instruction: {}",
-        STANDARD.encode(serialized_ix)
-    );
+            fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E> {
+                Ok(OrderedJson::Value(v.into()))
+            }
+
+            fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E> {
+                Ok(OrderedJson::Value(v.into()))
+            }
+
+            fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> {
+                Ok(OrderedJson::Value(v.into()))
+            }
+
+            fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E> {
+                Ok(OrderedJson::Value(v.into()))
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+                Ok(OrderedJson::Value(v.into()))
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> Result<Self::Value, A::Error> {
+                let mut values = vec![];
+                while let Some(value) = seq.next_element()? {
+                    values.push(value);
+                }
+                Ok(OrderedJson::Array(values))
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> Result<Self::Value, A::Error> {
+                let mut entries = vec![];
+                while let Some(entry) = map.next_entry()? {
+                    entries.push(entry);
+                }
+                Ok(OrderedJson::Object(entries))
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+impl OrderedJson {
+    fn get(&self, key: &str) -> Option<&Self> {
+        match self {
+            Self::Object(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::Value(value) => value.as_str(),
+            _ => None,
+        }
+    }
+
+    /// Write the value in the format of `serde_json::to_string_pretty`, with the deprecation,
+    /// unit and decimals of the objects of arrays, e.g. instructions and fields, as JSDoc.
+    fn write_ts(&self, indent: usize, out: &mut String) {
+        let pad = |indent: usize| "  ".repeat(indent);
+        match self {
+            Self::Array(values) if !values.is_empty() => {
+                out.push_str("[\n");
+                for (i, value) in values.iter().enumerate() {
+                    value.write_jsdoc(&pad(indent + 1), out);
+                    out.push_str(&pad(indent + 1));
+                    value.write_ts(indent + 1, out);
+                    out.push_str(if i + 1 < values.len() { ",\n" } else { "\n" });
+                }
+                out.push_str(&pad(indent));
+                out.push(']');
+            }
+            Self::Object(entries) if !entries.is_empty() => {
+                out.push_str("{\n");
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    out.push_str(&pad(indent + 1));
+                    out.push_str(&serde_json::Value::from(key.as_str()).to_string());
+                    out.push_str(": ");
+                    value.write_ts(indent + 1, out);
+                    out.push_str(if i + 1 < entries.len() { ",\n" } else { "\n" });
+                }
+                out.push_str(&pad(indent));
+                out.push('}');
+            }
+            Self::Array(_) => out.push_str("[]"),
+            Self::Object(_) => out.push_str("{}"),
+            Self::Value(value) => out.push_str(&value.to_string()),
+        }
+    }
+
+    fn write_jsdoc(&self, pad: &str, out: &mut String) {
+        let mut tags = vec![];
+        if let Some(deprecated) = self.get("deprecated") {
+            let since = deprecated.get("since").and_then(Self::as_str);
+            let note = deprecated.get("note").and_then(Self::as_str);
+            tags.push(match (since, note) {
+                (Some(since), Some(note)) => format!("@deprecated Since {since}: {note}"),
+                (Some(since), None) => format!("@deprecated Since {since}"),
+                (None, Some(note)) => format!("@deprecated {note}"),
+                (None, None) => "@deprecated".into(),
+            });
+        }
+        if let Some(unit) = self.get("unit").and_then(Self::as_str) {
+            tags.push(format!("@unit {unit}"));
+        }
+        if let Some(Self::Value(decimals)) = self.get("decimals") {
+            tags.push(format!("@decimals {decimals}"));
+        }
+        if tags.is_empty() {
+            return;
+        }
+
+        out.push_str(&format!("{pad}/**\n"));
+        for tag in tags {
+            // Don't end the comment early
+            out.push_str(&format!("{pad} * {}\n", tag.replace("*/", "*\\/")));
+        }
+        out.push_str(&format!("{pad} */\n"));
+    }
+}
+
+fn write_idl(idl: &Idl, out: OutFile) -> Result<()> {
+    let idl_json = serde_json::to_string_pretty(idl)?;
+    match out {
+        OutFile::Stdout => println!("{idl_json}"),
+        OutFile::File(out) => fs::write(out, idl_json)?,
+    };
+
+    Ok(())
+}
+
+/// Print `base64+borsh` encoded IDL instruction.
+fn print_idl_instruction(ix_name: &str, ix: &Instruction, idl_address: &Pubkey) -> Result<()> {
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+
+    println!("Print only mode. No execution!");
+    println!("Instruction: {ix_name}");
+    println!("IDL address: {idl_address}");
+    println!("Program: {}", ix.program_id);
+    println!(
+        "Base64 encoded instruction: {}",
+        STANDARD.encode(serialize_instruction(ix)?)
+    );
+
+    Ok(())
+}
+
+/// Print the instructions of an upgrade proposal, or its transaction message, for the authority
+/// to sign.
+fn print_proposal(ixs: &[Instruction], authority: &Pubkey, proposal: &ProposalArgs) -> Result<()> {
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+
+    let encode = |bytes: Vec<u8>| match proposal.encoding {
+        ProposalEncoding::Base58 => solana_sdk::bs58::encode(bytes).into_string(),
+        ProposalEncoding::Base64 => STANDARD.encode(bytes),
+    };
+
+    println!("Authority: {authority}");
+    if proposal.message {
+        let message = Message::new(ixs, Some(authority));
+        println!("Transaction message: {}", encode(message.serialize()));
+    } else {
+        for ix in ixs {
+            println!("Instruction: {}", encode(serialize_instruction(ix)?));
+        }
+    }
 
     Ok(())
 }
 
+/// Serialize the instruction in `borsh` format.
+fn serialize_instruction(ix: &Instruction) -> Result<Vec<u8>> {
+    // Serialize with `bincode` because `Instruction` does not implement `BorshSerialize`
+    let mut serialized_ix = bincode::serialize(ix)?;
+
+    // Remove extra bytes in order to make the serialized instruction `borsh` compatible
+    // `bincode` uses 8 bytes(LE) for length meanwhile `borsh` uses 4 bytes(LE)
+    let mut remove_extra_vec_bytes = |index: usize| {
+        serialized_ix.drain((index + 4)..(index + 8));
+    };
+
+    let accounts_index = std::mem::size_of_val(&ix.program_id);
+    remove_extra_vec_bytes(accounts_index);
+    let data_index = accounts_index + 4 + std::mem::size_of_val(&*ix.accounts);
+    remove_extra_vec_bytes(data_index);
+
+    Ok(serialized_ix)
+}
+
 fn account(
     cfg_override: &ConfigOverride,
     account_type: String,
@@ -3003,16 +4193,7 @@ fn account(
     };
 
     let data = create_client(cluster.url()).get_account_data(&address)?;
-    let disc_len = idl
-        .accounts
-        .iter()
-        .find(|acc| acc.name == account_type_name)
-        .map(|acc| acc.discriminator.len())
-        .ok_or_else(|| anyhow!("Account `{account_type_name}` not found in IDL"))?;
-    let mut data_view = &data[disc_len..];
-
-    let deserialized_json =
-        deserialize_idl_defined_type_to_json(&idl, account_type_name, &mut data_view)?;
+    let deserialized_json = decode_account(&idl, account_type_name, &data)?;
 
     println!(
         "{}",
@@ -3022,422 +4203,951 @@ fn account(
     Ok(())
 }
 
-// Deserializes user defined IDL types by munching the account data(recursively).
-fn deserialize_idl_defined_type_to_json(
-    idl: &Idl,
-    defined_type_name: &str,
-    data: &mut &[u8],
-) -> Result<JsonValue, anyhow::Error> {
-    let defined_type = &idl
-        .accounts
+enum OutFile {
+    Stdout,
+    File(PathBuf),
+}
+
+// Builds, deploys, and tests all workspace programs in a single command.
+#[allow(clippy::too_many_arguments)]
+fn test(
+    cfg_override: &ConfigOverride,
+    program_name: Option<String>,
+    skip_deploy: bool,
+    skip_local_validator: bool,
+    skip_build: bool,
+    skip_lint: bool,
+    no_idl: bool,
+    detach: bool,
+    jobs: Option<usize>,
+    tests_to_run: Vec<String>,
+    extra_args: Vec<String>,
+    env_vars: Vec<String>,
+    cargo_args: Vec<String>,
+    arch: ProgramArch,
+    runtime: TestRuntime,
+    coverage: bool,
+    include_generated: bool,
+) -> Result<()> {
+    let test_paths = tests_to_run
         .iter()
-        .find(|acc| acc.name == defined_type_name)
-        .and_then(|acc| idl.types.iter().find(|ty| ty.name == acc.name))
-        .or_else(|| idl.types.iter().find(|ty| ty.name == defined_type_name))
-        .ok_or_else(|| anyhow!("Type `{}` not found in IDL.", defined_type_name))?
-        .ty;
-
-    let mut deserialized_fields = Map::new();
-
-    match defined_type {
-        IdlTypeDefTy::Struct { fields } => {
-            if let Some(fields) = fields {
-                match fields {
-                    IdlDefinedFields::Named(fields) => {
-                        for field in fields {
-                            deserialized_fields.insert(
-                                field.name.clone(),
-                                deserialize_idl_type_to_json(&field.ty, data, idl)?,
-                            );
-                        }
+        .map(|path| {
+            PathBuf::from(path)
+                .canonicalize()
+                .map_err(|_| anyhow!("Wrong path {}", path))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    with_workspace(cfg_override, |cfg| {
+        // Build if needed.
+        if !skip_build {
+            build(
+                cfg_override,
+                no_idl,
+                None,
+                None,
+                false,
+                skip_lint,
+                program_name.clone(),
+                None,
+                None,
+                BootstrapMode::None,
+                None,
+                None,
+                env_vars,
+                cargo_args,
+                false,
+                arch,
+            )?;
+        }
+
+        let root = cfg.path().parent().unwrap().to_owned();
+        cfg.add_test_config(root, test_paths)?;
+
+        // Nothing to deploy when the tests run in-process. Coverage is only
+        // measured in-process, where the programs can run natively.
+        if runtime == TestRuntime::Svm || coverage {
+            return run_svm_test_suites(cfg, &extra_args, coverage, include_generated);
+        }
+
+        // Run the deploy against the cluster in two cases:
+        //
+        // 1. The cluster is not localnet.
+        // 2. The cluster is localnet, but we're not booting a local validator.
+        //
+        // In either case, skip the deploy if the user specifies.
+        let is_localnet = cfg.provider.cluster == Cluster::Localnet;
+        if (!is_localnet || skip_local_validator) && !skip_deploy {
+            deploy(
+                cfg_override,
+                None,
+                None,
+                false,
+                None,
+                5,
+                None,
+                false,
+                false,
+                false,
+            )?;
+        }
+        let mut is_first_suite = true;
+        if let Some(test_script) = cfg.scripts.get_mut("test") {
+            is_first_suite = false;
+
+            match program_name {
+                Some(program_name) => {
+                    if let Some((from, to)) = Regex::new("\\s(tests/\\S+\\.(js|ts))")
+                        .unwrap()
+                        .captures_iter(&test_script.clone())
+                        .last()
+                        .and_then(|c| c.get(1).and_then(|mtch| c.get(2).map(|ext| (mtch, ext))))
+                        .map(|(mtch, ext)| {
+                            (
+                                mtch.as_str(),
+                                format!("tests/{program_name}.{}", ext.as_str()),
+                            )
+                        })
+                    {
+                        println!("\nRunning tests of program `{program_name}`!");
+                        // Replace the last path to the program name's path
+                        *test_script = test_script.replace(from, &to);
                     }
-                    IdlDefinedFields::Tuple(fields) => {
-                        let mut values = Vec::new();
-                        for field in fields {
-                            values.push(deserialize_idl_type_to_json(field, data, idl)?);
+                }
+                _ => println!(
+                    "\nFound a 'test' script in the Anchor.toml. Running it as a test suite!"
+                ),
+            }
+
+            if jobs.is_none() {
+                run_test_suite(
+                    cfg,
+                    cfg.path(),
+                    is_localnet,
+                    skip_local_validator,
+                    skip_deploy,
+                    detach,
+                    &cfg.test_validator,
+                    &cfg.scripts,
+                    &extra_args,
+                )?;
+            }
+        }
+        if let Some(jobs) = jobs {
+            return run_parallel_test_suites(
+                cfg,
+                jobs,
+                is_localnet && !skip_local_validator,
+                skip_deploy,
+                &extra_args,
+            );
+        }
+        if let Some(test_config) = &cfg.test_config {
+            for test_suite in test_config.iter() {
+                if !is_first_suite {
+                    std::thread::sleep(std::time::Duration::from_millis(
+                        test_suite
+                            .1
+                            .test
+                            .as_ref()
+                            .map(|val| val.shutdown_wait)
+                            .unwrap_or(SHUTDOWN_WAIT) as u64,
+                    ));
+                } else {
+                    is_first_suite = false;
+                }
+
+                run_test_suite(
+                    cfg,
+                    test_suite.0,
+                    is_localnet,
+                    skip_local_validator,
+                    skip_deploy,
+                    detach,
+                    &test_suite.1.test,
+                    &test_suite.1.scripts,
+                    &extra_args,
+                )?;
+            }
+        }
+        Ok(())
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run_test_suite(
+    cfg: &WithPath<Config>,
+    test_suite_path: impl AsRef<Path>,
+    is_localnet: bool,
+    skip_local_validator: bool,
+    skip_deploy: bool,
+    detach: bool,
+    test_validator: &Option<TestValidator>,
+    scripts: &ScriptsConfig,
+    extra_args: &[String],
+) -> Result<()> {
+    println!("\nRunning test suite: {:#?}\n", test_suite_path.as_ref());
+    // Start local test validator, if needed.
+    let mut validator_handle = None;
+    if is_localnet && (!skip_local_validator) {
+        let flags = match skip_deploy {
+            true => None,
+            false => Some(validator_flags(cfg, test_validator)?),
+        };
+        validator_handle = Some(start_test_validator(cfg, test_validator, flags, true)?);
+    }
+
+    let url = cluster_url(cfg, test_validator);
+
+    let node_options = node_options()?;
+
+    // Setup log reader.
+    let log_streams = stream_logs(cfg, &url, &program_logs_dir());
+
+    // Run the tests.
+    let test_result = {
+        let cmd = scripts
+            .get("test")
+            .expect("Not able to find script for `test`")
+            .clone();
+        let script_args = format!("{cmd} {}", extra_args.join(" "));
+        std::process::Command::new("bash")
+            .arg("-c")
+            .arg(script_args)
+            .env("ANCHOR_PROVIDER_URL", url)
+            .env("ANCHOR_WALLET", cfg.provider.wallet.to_string())
+            .env("NODE_OPTIONS", node_options)
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .output()
+            .map_err(anyhow::Error::from)
+            .context(cmd)
+    };
+
+    // Keep validator running if needed.
+    if test_result.is_ok() && detach {
+        println!("Local validator still running. Press Ctrl + C quit.");
+        std::io::stdin().lock().lines().next().unwrap().unwrap();
+    }
+
+    // Check all errors and shut down.
+    if let Some(mut child) = validator_handle {
+        if let Err(err) = child.kill() {
+            println!("Failed to kill subprocess {}: {}", child.id(), err);
+        }
+    }
+    for mut child in log_streams? {
+        if let Err(err) = child.kill() {
+            println!("Failed to kill subprocess {}: {}", child.id(), err);
+        }
+    }
+
+    // Must exist *after* shutting down the validator and log streams.
+    match test_result {
+        Ok(exit) => {
+            if !exit.status.success() {
+                std::process::exit(exit.status.code().unwrap());
+            }
+        }
+        Err(err) => {
+            println!("Failed to run test: {err:#}");
+            return Err(err);
+        }
+    }
+
+    Ok(())
+}
+
+fn node_options() -> Result<String> {
+    Ok(format!(
+        "{} {}",
+        match std::env::var_os("NODE_OPTIONS") {
+            Some(value) => value
+                .into_string()
+                .map_err(std::env::VarError::NotUnicode)?,
+            None => "".to_owned(),
+        },
+        get_node_dns_option()?,
+    ))
+}
+
+/// Test suite that runs in parallel with the others, see [`run_parallel_test_suites`].
+struct IsolatedTestSuite<'a> {
+    /// Path of the suite's config, relative to the workspace root.
+    name: String,
+    /// Directory of the suite's ledger and logs.
+    dir: PathBuf,
+    test_validator: Option<TestValidator>,
+    /// Flags of the suite's local validator, if one is started.
+    validator_flags: Option<Vec<String>>,
+    scripts: &'a ScriptsConfig,
+}
+
+/// Run the test suites in parallel, with up to `jobs` suites at a time.
+///
+/// Every suite gets its own local validator, with dynamically allocated ports and a ledger in
+/// `.anchor/test-suites/<suite>`, next to the output of its test script and its program logs.
+/// The results of all suites are written to a JUnit report, see [`junit::REPORT_PATH`].
+fn run_parallel_test_suites(
+    cfg: &WithPath<Config>,
+    jobs: usize,
+    start_validators: bool,
+    skip_deploy: bool,
+    extra_args: &[String],
+) -> Result<()> {
+    let root = cfg.path().parent().unwrap();
+    let mut suites = vec![];
+    if cfg.scripts.contains_key("test") {
+        suites.push((cfg.path(), &cfg.test_validator, &cfg.scripts));
+    }
+    if let Some(test_config) = &cfg.test_config {
+        for (path, test_suite) in test_config.iter() {
+            suites.push((path, &test_suite.test, &test_suite.scripts));
+        }
+    }
+
+    let suites_dir = Path::new(".anchor").join("test-suites");
+    if suites_dir.exists() {
+        fs::remove_dir_all(&suites_dir)?;
+    }
+
+    // The validators are configured before running any suite, since the ports must not overlap
+    // and the validator flags are written to the IDLs.
+    let mut used_ports = HashSet::new();
+    let mut suites = suites
+        .into_iter()
+        .map(|(path, test_validator, scripts)| {
+            let name = path
+                .strip_prefix(root)
+                .unwrap_or(path)
+                .display()
+                .to_string();
+            let dir = suites_dir.join(name.replace(['/', '\\'], "_"));
+            fs::create_dir_all(&dir)?;
+            if !start_validators {
+                return Ok(IsolatedTestSuite {
+                    name,
+                    dir,
+                    test_validator: test_validator.clone(),
+                    validator_flags: None,
+                    scripts,
+                });
+            }
+
+            let mut test_validator = test_validator
+                .clone()
+                .unwrap_or_else(|| _TestValidator::default().into());
+            let mut validator = test_validator
+                .validator
+                .take()
+                .unwrap_or_else(|| _Validator::default().into());
+            validator.rpc_port = pick_free_ports(&mut used_ports, 2)?;
+            validator.faucet_port = Some(pick_free_ports(&mut used_ports, 1)?);
+            validator.gossip_port = Some(pick_free_ports(&mut used_ports, 1)?);
+            validator.ledger = dir.join("ledger").display().to_string();
+            let port_flags = vec![
+                "--rpc-port".to_string(),
+                validator.rpc_port.to_string(),
+                "--faucet-port".to_string(),
+                validator.faucet_port.unwrap().to_string(),
+                "--gossip-port".to_string(),
+                validator.gossip_port.unwrap().to_string(),
+            ];
+            test_validator.validator = Some(validator);
+
+            let test_validator = Some(test_validator);
+            let validator_flags = match skip_deploy {
+                true => port_flags,
+                false => validator_flags(cfg, &test_validator)?,
+            };
+            Ok(IsolatedTestSuite {
+                name,
+                dir,
+                test_validator,
+                validator_flags: Some(validator_flags),
+                scripts,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    suites.sort_by(|a, b| a.name.cmp(&b.name));
+
+    println!(
+        "\nRunning {} test suites with up to {jobs} in parallel\n",
+        suites.len()
+    );
+    let node_options = node_options()?;
+    let start = std::time::Instant::now();
+    let next_suite = std::sync::atomic::AtomicUsize::new(0);
+    let mut reports = std::thread::scope(|scope| {
+        let workers = (0..jobs.clamp(1, suites.len().max(1)))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut reports = vec![];
+                    loop {
+                        let i = next_suite.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                        let suite = match suites.get(i) {
+                            Some(suite) => suite,
+                            None => break reports,
+                        };
+
+                        let start = std::time::Instant::now();
+                        let failure =
+                            match run_isolated_test_suite(cfg, suite, extra_args, &node_options) {
+                                Ok(failure) => failure,
+                                Err(e) => Some(format!("{e:#}")),
+                            };
+                        let time = start.elapsed();
+                        match &failure {
+                            Some(failure) => println!(
+                                "Test suite `{}` failed in {:.1}s: {failure}",
+                                suite.name,
+                                time.as_secs_f64()
+                            ),
+                            None => println!(
+                                "Test suite `{}` passed in {:.1}s",
+                                suite.name,
+                                time.as_secs_f64()
+                            ),
                         }
-                        deserialized_fields
-                            .insert(defined_type_name.to_owned(), JsonValue::Array(values));
+                        let output =
+                            fs::read_to_string(suite.dir.join("test.log")).unwrap_or_default();
+                        reports.push((
+                            i,
+                            junit::SuiteReport {
+                                name: suite.name.clone(),
+                                time,
+                                failure,
+                                output,
+                            },
+                        ));
                     }
-                }
+                })
+            })
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("Test suite panicked"))
+            .collect::<Vec<_>>()
+    });
+    let time = start.elapsed();
+    reports.sort_by_key(|(i, _)| *i);
+    let reports = reports
+        .into_iter()
+        .map(|(_, report)| report)
+        .collect::<Vec<_>>();
+
+    junit::write(junit::REPORT_PATH, &reports, time)?;
+
+    let failed = reports
+        .iter()
+        .filter(|report| report.failure.is_some())
+        .collect::<Vec<_>>();
+    for report in failed.iter().filter(|report| !report.output.is_empty()) {
+        println!(
+            "\nOutput of test suite `{}`:\n\n{}",
+            report.name, report.output
+        );
+    }
+    println!(
+        "\n{} of {} test suites passed, see {} for the report and {} for the logs",
+        reports.len() - failed.len(),
+        reports.len(),
+        junit::REPORT_PATH,
+        suites_dir.display(),
+    );
+    if !failed.is_empty() {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+/// Run a test suite, with the output of its test script written to `test.log` in the suite's
+/// directory. Returns why the suite failed, if it did.
+fn run_isolated_test_suite(
+    cfg: &WithPath<Config>,
+    suite: &IsolatedTestSuite,
+    extra_args: &[String],
+    node_options: &str,
+) -> Result<Option<String>> {
+    let log_file = File::create(suite.dir.join("test.log"))?;
+    let validator_handle = match &suite.validator_flags {
+        Some(flags) => Some(start_test_validator(
+            cfg,
+            &suite.test_validator,
+            Some(flags.clone()),
+            true,
+        )?),
+        None => None,
+    };
+
+    let url = cluster_url(cfg, &suite.test_validator);
+    let log_streams = stream_logs(cfg, &url, &suite.dir.join("program-logs"));
+
+    let cmd = suite
+        .scripts
+        .get("test")
+        .expect("Not able to find script for `test`");
+    let test_result = log_file.try_clone().and_then(|stdout| {
+        std::process::Command::new("bash")
+            .arg("-c")
+            .arg(format!("{cmd} {}", extra_args.join(" ")))
+            .env("ANCHOR_PROVIDER_URL", &url)
+            .env("ANCHOR_WALLET", cfg.provider.wallet.to_string())
+            .env("NODE_OPTIONS", node_options)
+            .stdout(stdout)
+            .stderr(log_file)
+            .status()
+    });
+
+    if let Some(mut child) = validator_handle {
+        if let Err(err) = child.kill() {
+            println!("Failed to kill subprocess {}: {}", child.id(), err);
+        }
+    }
+    for mut child in log_streams? {
+        if let Err(err) = child.kill() {
+            println!("Failed to kill subprocess {}: {}", child.id(), err);
+        }
+    }
+
+    let status = test_result
+        .map_err(anyhow::Error::from)
+        .context(cmd.clone())?;
+    Ok(match status.code() {
+        Some(0) => None,
+        Some(code) => Some(format!("Test script exited with code {code}")),
+        None => Some("Test script was terminated".to_string()),
+    })
+}
+
+/// Pick a free port, along with the `count - 1` ports after it, that isn't in `used_ports`.
+fn pick_free_ports(used_ports: &mut HashSet<u16>, count: u16) -> Result<u16> {
+    for _ in 0..100 {
+        let port = match portpicker::pick_unused_port() {
+            Some(port) if port.checked_add(count).is_some() => port,
+            _ => continue,
+        };
+        let ports = port..port + count;
+        if ports
+            .clone()
+            .all(|port| !used_ports.contains(&port) && portpicker::is_free(port))
+        {
+            used_ports.extend(ports);
+            return Ok(port);
+        }
+    }
+
+    Err(anyhow!("Unable to find free ports for the test validator"))
+}
+
+/// Run the test suites in parallel against in-process SVMs, without a validator.
+///
+/// The genesis of every suite is written to `.anchor/test-genesis`, and passed to the tests in
+/// the [`TEST_GENESIS_ENV`] environment variable.
+fn run_svm_test_suites(
+    cfg: &WithPath<Config>,
+    extra_args: &[String],
+    coverage: bool,
+    include_generated: bool,
+) -> Result<()> {
+    let mut suites = vec![];
+    if cfg.scripts.contains_key("test") {
+        suites.push((cfg.path().to_path_buf(), &cfg.test_validator, &cfg.scripts));
+    }
+    if let Some(test_config) = &cfg.test_config {
+        for (path, test_suite) in test_config.iter() {
+            suites.push((path.clone(), &test_suite.test, &test_suite.scripts));
+        }
+    }
+
+    let genesis_dir = Path::new(".anchor").join("test-genesis");
+    fs::create_dir_all(&genesis_dir)?;
+    let genesis_paths = suites
+        .iter()
+        .enumerate()
+        .map(|(i, (_, test_validator, _))| {
+            let mut genesis = test_genesis(cfg, test_validator, &genesis_dir)?;
+            genesis.native = coverage;
+            let path = genesis_dir.join(format!("{i}.json"));
+            fs::write(&path, serde_json::to_string_pretty(&genesis)?)?;
+            path.canonicalize().map_err(Into::into)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let wallet = cfg.provider.wallet.to_string();
+    let coverage_env = match coverage {
+        true => coverage::env()?,
+        false => vec![],
+    };
+    let results = std::thread::scope(|scope| {
+        let handles = suites
+            .iter()
+            .zip(&genesis_paths)
+            .map(|((path, _, scripts), genesis_path)| {
+                let (wallet, coverage_env) = (&wallet, &coverage_env);
+                scope.spawn(move || {
+                    println!("\nRunning test suite: {path:#?}\n");
+                    let cmd = scripts
+                        .get("test")
+                        .expect("Not able to find script for `test`");
+                    std::process::Command::new("bash")
+                        .arg("-c")
+                        .arg(format!("{cmd} {}", extra_args.join(" ")))
+                        .env(TEST_GENESIS_ENV, genesis_path)
+                        .env("ANCHOR_WALLET", wallet)
+                        .envs(coverage_env.iter().cloned())
+                        .stdout(Stdio::inherit())
+                        .stderr(Stdio::inherit())
+                        .status()
+                        .map_err(anyhow::Error::from)
+                        .context(cmd.clone())
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("Test suite panicked"))
+            .collect::<Vec<_>>()
+    });
+
+    for result in results {
+        let status = result?;
+        if !status.success() {
+            std::process::exit(status.code().unwrap_or(1));
+        }
+    }
+
+    if coverage {
+        let programs = cfg
+            .read_all_programs()?
+            .into_iter()
+            .filter(|program| !program.solidity)
+            .collect::<Vec<_>>();
+        coverage::report(&programs, include_generated)?;
+    }
+
+    Ok(())
+}
+
+/// Returns the genesis of an in-process SVM, which is the same as the genesis that
+/// [`validator_flags`] configures for `solana-test-validator`.
+///
+/// Cloned accounts are fetched once and written to `genesis_dir`.
+fn test_genesis(
+    cfg: &WithPath<Config>,
+    test_validator: &Option<TestValidator>,
+    genesis_dir: &Path,
+) -> Result<TestGenesis> {
+    let wallet = cfg.wallet_kp()?.pubkey();
+    let upgradeable = test_validator
+        .as_ref()
+        .map(|test_validator| test_validator.upgradeable)
+        .unwrap_or(false);
+    let mut genesis = TestGenesis {
+        wallet: Some(wallet),
+        ..Default::default()
+    };
+
+    let programs = cfg.programs.get(&Cluster::Localnet);
+    for mut program in cfg.read_all_programs()? {
+        let address = match programs.and_then(|m| m.get(&program.lib_name)) {
+            Some(deployment) => deployment.address,
+            None => program.pubkey()?,
+        };
+        genesis.programs.push(TestGenesisProgram {
+            address,
+            path: program.binary_path(false).canonicalize()?,
+            upgrade_authority: upgradeable.then_some(wallet),
+        });
+
+        if let Some(idl) = program.idl.as_mut() {
+            // Add program address to the IDL.
+            idl.address = address.to_string();
+
+            // Persist it.
+            let idl_out = Path::new("target")
+                .join("idl")
+                .join(&idl.metadata.name)
+                .with_extension("json");
+            write_idl(idl, OutFile::File(idl_out))?;
+        }
+    }
+
+    let test = match test_validator {
+        Some(test) => test,
+        None => return Ok(genesis),
+    };
+    for entry in test.genesis.iter().flatten() {
+        let path = Path::new(&entry.program).canonicalize().map_err(|_| {
+            anyhow!(
+                "Program in genesis configuration does not exist at path: {}",
+                entry.program
+            )
+        })?;
+        genesis.programs.push(TestGenesisProgram {
+            address: Pubkey::from_str(&entry.address)?,
+            path,
+            upgrade_authority: entry.upgradeable.unwrap_or(false).then_some(wallet),
+        });
+    }
+
+    let validator = match &test.validator {
+        Some(validator) => validator,
+        None => return Ok(genesis),
+    };
+    for entry in validator.account.iter().flatten() {
+        genesis.accounts.push(TestGenesisAccount {
+            address: Pubkey::from_str(&entry.address)?,
+            path: Path::new(&entry.filename).canonicalize()?,
+        });
+    }
+    for entry in validator.account_dir.iter().flatten() {
+        for file in fs::read_dir(&entry.directory)? {
+            let path = file?.path();
+            if path.extension() != Some("json".as_ref()) {
+                continue;
             }
+            let account: serde_json::Value = serde_json::from_slice(&fs::read(&path)?)?;
+            let address = account["pubkey"]
+                .as_str()
+                .ok_or_else(|| anyhow!("Account file has no pubkey: {}", path.display()))?;
+            genesis.accounts.push(TestGenesisAccount {
+                address: Pubkey::from_str(address)?,
+                path: path.canonicalize()?,
+            });
         }
-        IdlTypeDefTy::Enum { variants } => {
-            let repr = <u8 as AnchorDeserialize>::deserialize(data)?;
-
-            let variant = variants
-                .get(repr as usize)
-                .ok_or_else(|| anyhow!("Error while deserializing enum variant {repr}"))?;
-
-            let mut value = json!({});
-
-            if let Some(enum_field) = &variant.fields {
-                match enum_field {
-                    IdlDefinedFields::Named(fields) => {
-                        let mut values = Map::new();
-                        for field in fields {
-                            values.insert(
-                                field.name.clone(),
-                                deserialize_idl_type_to_json(&field.ty, data, idl)?,
-                            );
-                        }
-                        value = JsonValue::Object(values);
-                    }
-                    IdlDefinedFields::Tuple(fields) => {
-                        let mut values = Vec::new();
-                        for field in fields {
-                            values.push(deserialize_idl_type_to_json(field, data, idl)?);
+    }
+    if let Some(clone) = &validator.clone {
+        // Load the cloned accounts from the fixtures if they were pulled
+        if let Some(fixtures) = load_fixtures(&test.fixtures)? {
+            for entry in clone {
+                for (address, fixture) in fixtures.resolve(&entry.address)? {
+                    let address = Pubkey::from_str(address)?;
+                    let path = Path::new(&test.fixtures.dir)
+                        .join(&fixture.path)
+                        .canonicalize()?;
+                    match fixture.kind {
+                        FixtureKind::Account => {
+                            genesis.accounts.push(TestGenesisAccount { address, path })
                         }
-                        value = JsonValue::Array(values);
+                        FixtureKind::Program => genesis.programs.push(TestGenesisProgram {
+                            address,
+                            path,
+                            upgrade_authority: None,
+                        }),
                     }
                 }
             }
+        } else {
+            let url = validator.url.as_ref().ok_or_else(|| {
+                anyhow!("Validator url for Solana's JSON RPC should be provided in order to clone accounts from it")
+            })?;
+            let clone_dir = genesis_dir.join("clones");
+            fs::create_dir_all(&clone_dir)?;
 
-            deserialized_fields.insert(variant.name.clone(), value);
-        }
-        IdlTypeDefTy::Type { alias } => {
-            return deserialize_idl_type_to_json(alias, data, idl);
+            let pubkeys = clone
+                .iter()
+                .map(|entry| Pubkey::from_str(&entry.address).map_err(Into::into))
+                .collect::<Result<Vec<_>>>()?;
+            let (accounts, _) = fetch_accounts(&create_client(url), &pubkeys)?;
+            for (pubkey, account) in accounts {
+                let path = clone_dir.join(format!("{pubkey}.json"));
+                write_account(&path, &pubkey, &account)?;
+                genesis.accounts.push(TestGenesisAccount {
+                    address: pubkey,
+                    path: path.canonicalize()?,
+                });
+            }
         }
     }
+    for feature in validator.deactivate_feature.iter().flatten() {
+        genesis
+            .deactivate_features
+            .push(Pubkey::from_str(feature).map_err(|_| anyhow!("Invalid pubkey {}", feature))?);
+    }
 
-    Ok(JsonValue::Object(deserialized_fields))
+    Ok(genesis)
 }
 
-// Deserializes a primitive type using AnchorDeserialize
-fn deserialize_idl_type_to_json(
-    idl_type: &IdlType,
-    data: &mut &[u8],
-    parent_idl: &Idl,
-) -> Result<JsonValue, anyhow::Error> {
-    if data.is_empty() {
-        return Err(anyhow::anyhow!("Unable to parse from empty bytes"));
+fn test_subcommand(cfg_override: &ConfigOverride, subcmd: TestCommand) -> Result<()> {
+    match subcmd {
+        TestCommand::Fixtures {
+            subcmd: FixturesCommand::Pull { url },
+        } => fixtures_pull(cfg_override, url),
     }
+}
 
-    Ok(match idl_type {
-        IdlType::Bool => json!(<bool as AnchorDeserialize>::deserialize(data)?),
-        IdlType::U8 => {
-            json!(<u8 as AnchorDeserialize>::deserialize(data)?)
-        }
-        IdlType::I8 => {
-            json!(<i8 as AnchorDeserialize>::deserialize(data)?)
-        }
-        IdlType::U16 => {
-            json!(<u16 as AnchorDeserialize>::deserialize(data)?)
-        }
-        IdlType::I16 => {
-            json!(<i16 as AnchorDeserialize>::deserialize(data)?)
-        }
-        IdlType::U32 => {
-            json!(<u32 as AnchorDeserialize>::deserialize(data)?)
-        }
-        IdlType::I32 => {
-            json!(<i32 as AnchorDeserialize>::deserialize(data)?)
-        }
-        IdlType::F32 => json!(<f32 as AnchorDeserialize>::deserialize(data)?),
-        IdlType::U64 => {
-            json!(<u64 as AnchorDeserialize>::deserialize(data)?)
-        }
-        IdlType::I64 => {
-            json!(<i64 as AnchorDeserialize>::deserialize(data)?)
-        }
-        IdlType::F64 => json!(<f64 as AnchorDeserialize>::deserialize(data)?),
-        IdlType::U128 => {
-            // TODO: Remove to_string once serde_json supports u128 deserialization
-            json!(<u128 as AnchorDeserialize>::deserialize(data)?.to_string())
-        }
-        IdlType::I128 => {
-            // TODO: Remove to_string once serde_json supports i128 deserialization
-            json!(<i128 as AnchorDeserialize>::deserialize(data)?.to_string())
-        }
-        IdlType::U256 => todo!("Upon completion of u256 IDL standard"),
-        IdlType::I256 => todo!("Upon completion of i256 IDL standard"),
-        IdlType::Bytes => JsonValue::Array(
-            <Vec<u8> as AnchorDeserialize>::deserialize(data)?
-                .iter()
-                .map(|i| json!(*i))
-                .collect(),
-        ),
-        IdlType::String => json!(<String as AnchorDeserialize>::deserialize(data)?),
-        IdlType::Pubkey => {
-            json!(<Pubkey as AnchorDeserialize>::deserialize(data)?.to_string())
-        }
-        IdlType::Array(ty, size) => match size {
-            IdlArrayLen::Value(size) => {
-                let mut array_data: Vec<JsonValue> = Vec::with_capacity(*size);
-
-                for _ in 0..*size {
-                    array_data.push(deserialize_idl_type_to_json(ty, data, parent_idl)?);
-                }
+fn fixtures_pull(cfg_override: &ConfigOverride, url: Option<String>) -> Result<()> {
+    with_workspace(cfg_override, |cfg| {
+        let root = cfg.path().parent().unwrap().to_owned();
+        cfg.add_test_config(root, vec![])?;
 
-                JsonValue::Array(array_data)
+        // Cloned accounts of the workspace and of the test suites, by fixtures directory
+        let mut pulls: BTreeMap<String, (Option<String>, Vec<Pubkey>)> = BTreeMap::new();
+        let suites = cfg
+            .test_config
+            .iter()
+            .flat_map(|test_config| test_config.values())
+            .filter_map(|test_suite| test_suite.test.as_ref());
+        for test in cfg.test_validator.iter().chain(suites) {
+            let validator = match &test.validator {
+                Some(validator) => validator,
+                None => continue,
+            };
+            let (validator_url, addresses) = pulls.entry(test.fixtures.dir.clone()).or_default();
+            if validator_url.is_none() {
+                validator_url.clone_from(&validator.url);
             }
-            // TODO:
-            IdlArrayLen::Generic(_) => unimplemented!("Generic array length is not yet supported"),
-        },
-        IdlType::Option(ty) => {
-            let is_present = <u8 as AnchorDeserialize>::deserialize(data)?;
-
-            if is_present == 0 {
-                JsonValue::String("None".to_string())
-            } else {
-                deserialize_idl_type_to_json(ty, data, parent_idl)?
+            for entry in validator.clone.iter().flatten() {
+                let address = Pubkey::from_str(&entry.address)
+                    .map_err(|_| anyhow!("Invalid pubkey {}", entry.address))?;
+                if !addresses.contains(&address) {
+                    addresses.push(address);
+                }
             }
         }
-        IdlType::Vec(ty) => {
-            let size: usize = <u32 as AnchorDeserialize>::deserialize(data)?
-                .try_into()
-                .unwrap();
-
-            let mut vec_data: Vec<JsonValue> = Vec::with_capacity(size);
-
-            for _ in 0..size {
-                vec_data.push(deserialize_idl_type_to_json(ty, data, parent_idl)?);
-            }
-
-            JsonValue::Array(vec_data)
+        pulls.retain(|_, (_, addresses)| !addresses.is_empty());
+        if pulls.is_empty() {
+            println!("No cloned accounts to pull.");
+            return Ok(());
         }
-        IdlType::Defined {
-            name,
-            generics: _generics,
-        } => {
-            // TODO: Generics
-            deserialize_idl_defined_type_to_json(parent_idl, name, data)?
+
+        for (dir, (validator_url, addresses)) in pulls {
+            let url = url.clone().or(validator_url).ok_or_else(|| {
+                anyhow!("Validator url for Solana's JSON RPC should be provided in order to clone accounts from it")
+            })?;
+            println!("Pulling {} cloned accounts from {url}...", addresses.len());
+            let manifest = fixtures::pull(&create_client(&url), &addresses, Path::new(&dir))?;
+            println!(
+                "Pulled {} accounts into {dir} at slot {}.",
+                manifest.accounts.len(),
+                manifest.slot
+            );
         }
-        IdlType::Generic(generic) => json!(generic),
-        _ => unimplemented!("{idl_type:?}"),
+
+        Ok(())
     })
 }
 
-enum OutFile {
-    Stdout,
-    File(PathBuf),
+// Loads the fixtures of the cloned accounts if they were pulled, and reports
+// them if they are stale.
+fn load_fixtures(config: &FixturesConfig) -> Result<Option<FixturesManifest>> {
+    let fixtures = FixturesManifest::load(&config.dir)?;
+    if let Some(report) = fixtures
+        .as_ref()
+        .and_then(|fixtures| fixtures.staleness(config.max_age_days))
+    {
+        eprintln!("Warning: {report}");
+    }
+    Ok(fixtures)
 }
 
-// Builds, deploys, and tests all workspace programs in a single command.
-#[allow(clippy::too_many_arguments)]
-fn test(
+fn fuzz(cfg_override: &ConfigOverride, subcmd: FuzzCommand) -> Result<()> {
+    match subcmd {
+        FuzzCommand::Init => {
+            with_workspace(cfg_override, |cfg| fuzz::init(&cfg.read_all_programs()?))
+        }
+        FuzzCommand::Run {
+            program_name,
+            iterations,
+            seed,
+            reproduce,
+            skip_build,
+        } => fuzz_run(
+            cfg_override,
+            program_name,
+            iterations,
+            seed,
+            reproduce,
+            skip_build,
+        ),
+    }
+}
+
+fn fuzz_run(
     cfg_override: &ConfigOverride,
-    program_name: Option<String>,
-    skip_deploy: bool,
-    skip_local_validator: bool,
+    program_name: String,
+    iterations: u64,
+    seed: Option<u64>,
+    reproduce: Option<String>,
     skip_build: bool,
-    skip_lint: bool,
-    no_idl: bool,
-    detach: bool,
-    tests_to_run: Vec<String>,
-    extra_args: Vec<String>,
-    env_vars: Vec<String>,
-    cargo_args: Vec<String>,
-    arch: ProgramArch,
 ) -> Result<()> {
-    let test_paths = tests_to_run
-        .iter()
+    let reproduce = reproduce
         .map(|path| {
-            PathBuf::from(path)
+            PathBuf::from(&path)
                 .canonicalize()
-                .map_err(|_| anyhow!("Wrong path {}", path))
+                .map_err(|_| anyhow!("Wrong path {path}"))
         })
-        .collect::<Result<Vec<_>, _>>()?;
+        .transpose()?;
 
     with_workspace(cfg_override, |cfg| {
-        // Build if needed.
+        let program = cfg.get_program(&program_name)?;
+        let fuzz_dir = Path::new(fuzz::FUZZ_DIR);
+        let harness = fuzz_dir
+            .join("src")
+            .join("bin")
+            .join(&program.lib_name)
+            .with_extension("rs");
+        if !harness.exists() {
+            return Err(anyhow!(
+                "No randomized testing harness found for program `{}`, run `anchor fuzz init`",
+                program.lib_name
+            ));
+        }
+
         if !skip_build {
             build(
                 cfg_override,
-                no_idl,
+                false,
                 None,
                 None,
                 false,
-                skip_lint,
-                program_name.clone(),
+                false,
+                None,
                 None,
                 None,
                 BootstrapMode::None,
                 None,
                 None,
-                env_vars,
-                cargo_args,
+                vec![],
+                vec![],
                 false,
-                arch,
-            )?;
-        }
-
-        let root = cfg.path().parent().unwrap().to_owned();
-        cfg.add_test_config(root, test_paths)?;
-
-        // Run the deploy against the cluster in two cases:
-        //
-        // 1. The cluster is not localnet.
-        // 2. The cluster is localnet, but we're not booting a local validator.
-        //
-        // In either case, skip the deploy if the user specifies.
-        let is_localnet = cfg.provider.cluster == Cluster::Localnet;
-        if (!is_localnet || skip_local_validator) && !skip_deploy {
-            deploy(cfg_override, None, None, false, vec![])?;
-        }
-        let mut is_first_suite = true;
-        if let Some(test_script) = cfg.scripts.get_mut("test") {
-            is_first_suite = false;
-
-            match program_name {
-                Some(program_name) => {
-                    if let Some((from, to)) = Regex::new("\\s(tests/\\S+\\.(js|ts))")
-                        .unwrap()
-                        .captures_iter(&test_script.clone())
-                        .last()
-                        .and_then(|c| c.get(1).and_then(|mtch| c.get(2).map(|ext| (mtch, ext))))
-                        .map(|(mtch, ext)| {
-                            (
-                                mtch.as_str(),
-                                format!("tests/{program_name}.{}", ext.as_str()),
-                            )
-                        })
-                    {
-                        println!("\nRunning tests of program `{program_name}`!");
-                        // Replace the last path to the program name's path
-                        *test_script = test_script.replace(from, &to);
-                    }
-                }
-                _ => println!(
-                    "\nFound a 'test' script in the Anchor.toml. Running it as a test suite!"
-                ),
-            }
-
-            run_test_suite(
-                cfg,
-                cfg.path(),
-                is_localnet,
-                skip_local_validator,
-                skip_deploy,
-                detach,
-                &cfg.test_validator,
-                &cfg.scripts,
-                &extra_args,
+                ProgramArch::Sbf,
             )?;
         }
-        if let Some(test_config) = &cfg.test_config {
-            for test_suite in test_config.iter() {
-                if !is_first_suite {
-                    std::thread::sleep(std::time::Duration::from_millis(
-                        test_suite
-                            .1
-                            .test
-                            .as_ref()
-                            .map(|val| val.shutdown_wait)
-                            .unwrap_or(SHUTDOWN_WAIT) as u64,
-                    ));
-                } else {
-                    is_first_suite = false;
-                }
-
-                run_test_suite(
-                    cfg,
-                    test_suite.0,
-                    is_localnet,
-                    skip_local_validator,
-                    skip_deploy,
-                    detach,
-                    &test_suite.1.test,
-                    &test_suite.1.scripts,
-                    &extra_args,
-                )?;
-            }
-        }
-        Ok(())
-    })
-}
-
-#[allow(clippy::too_many_arguments)]
-fn run_test_suite(
-    cfg: &WithPath<Config>,
-    test_suite_path: impl AsRef<Path>,
-    is_localnet: bool,
-    skip_local_validator: bool,
-    skip_deploy: bool,
-    detach: bool,
-    test_validator: &Option<TestValidator>,
-    scripts: &ScriptsConfig,
-    extra_args: &[String],
-) -> Result<()> {
-    println!("\nRunning test suite: {:#?}\n", test_suite_path.as_ref());
-    // Start local test validator, if needed.
-    let mut validator_handle = None;
-    if is_localnet && (!skip_local_validator) {
-        let flags = match skip_deploy {
-            true => None,
-            false => Some(validator_flags(cfg, test_validator)?),
-        };
-        validator_handle = Some(start_test_validator(cfg, test_validator, flags, true)?);
-    }
-
-    let url = cluster_url(cfg, test_validator);
-
-    let node_options = format!(
-        "{} {}",
-        match std::env::var_os("NODE_OPTIONS") {
-            Some(value) => value
-                .into_string()
-                .map_err(std::env::VarError::NotUnicode)?,
-            None => "".to_owned(),
-        },
-        get_node_dns_option()?,
-    );
-
-    // Setup log reader.
-    let log_streams = stream_logs(cfg, &url);
-
-    // Run the tests.
-    let test_result = {
-        let cmd = scripts
-            .get("test")
-            .expect("Not able to find script for `test`")
-            .clone();
-        let script_args = format!("{cmd} {}", extra_args.join(" "));
-        std::process::Command::new("bash")
-            .arg("-c")
-            .arg(script_args)
-            .env("ANCHOR_PROVIDER_URL", url)
-            .env("ANCHOR_WALLET", cfg.provider.wallet.to_string())
-            .env("NODE_OPTIONS", node_options)
-            .stdout(Stdio::inherit())
-            .stderr(Stdio::inherit())
-            .output()
-            .map_err(anyhow::Error::from)
-            .context(cmd)
-    };
-
-    // Keep validator running if needed.
-    if test_result.is_ok() && detach {
-        println!("Local validator still running. Press Ctrl + C quit.");
-        std::io::stdin().lock().lines().next().unwrap().unwrap();
-    }
-
-    // Check all errors and shut down.
-    if let Some(mut child) = validator_handle {
-        if let Err(err) = child.kill() {
-            println!("Failed to kill subprocess {}: {}", child.id(), err);
-        }
-    }
-    for mut child in log_streams? {
-        if let Err(err) = child.kill() {
-            println!("Failed to kill subprocess {}: {}", child.id(), err);
-        }
-    }
 
-    // Must exist *after* shutting down the validator and log streams.
-    match test_result {
-        Ok(exit) => {
-            if !exit.status.success() {
-                std::process::exit(exit.status.code().unwrap());
-            }
+        // The programs that the harness is given a processor for run natively
+        let genesis_dir = Path::new(".anchor").join("fuzz-genesis");
+        fs::create_dir_all(&genesis_dir)?;
+        let mut genesis = test_genesis(cfg, &cfg.test_validator, &genesis_dir)?;
+        genesis.native = true;
+        let genesis_path = genesis_dir.join("genesis.json");
+        fs::write(&genesis_path, serde_json::to_string_pretty(&genesis)?)?;
+
+        let mut args = vec!["--iterations".to_owned(), iterations.to_string()];
+        if let Some(seed) = seed {
+            args.extend(["--seed".to_owned(), seed.to_string()]);
         }
-        Err(err) => {
-            println!("Failed to run test: {err:#}");
-            return Err(err);
+        if let Some(reproduce) = reproduce {
+            args.extend(["--reproduce".to_owned(), reproduce.display().to_string()]);
+        }
+        let status = std::process::Command::new("cargo")
+            .args(["run", "--release", "--manifest-path"])
+            .arg(fuzz_dir.join("Cargo.toml"))
+            .args(["--bin", &program.lib_name, "--"])
+            .args(args)
+            .env(TEST_GENESIS_ENV, genesis_path.canonicalize()?)
+            .env("ANCHOR_WALLET", cfg.provider.wallet.to_string())
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .status()?;
+        if !status.success() {
+            std::process::exit(status.code().unwrap_or(1));
         }
-    }
 
-    Ok(())
+        Ok(())
+    })
 }
 
 // Returns the solana-test-validator flags. This will embed the workspace
@@ -3533,6 +5243,21 @@ fn validator_flags(
                         flags.push(entry["directory"].as_str().unwrap().to_string());
                     }
                 } else if key == "clone" {
+                    // Load the cloned accounts from the fixtures if they were pulled
+                    if let Some(fixtures) = load_fixtures(&test.fixtures)? {
+                        flags.extend(
+                            fixtures.validator_flags(
+                                &test.fixtures.dir,
+                                value
+                                    .as_array()
+                                    .unwrap()
+                                    .iter()
+                                    .map(|entry| entry["address"].as_str().unwrap()),
+                            )?,
+                        );
+                        continue;
+                    }
+
                     // Client for fetching accounts data
                     let client = if let Some(url) = entries["url"].as_str() {
                         create_client(url)
@@ -3612,12 +5337,19 @@ fn validator_flags(
     Ok(flags)
 }
 
-fn stream_logs(config: &WithPath<Config>, rpc_url: &str) -> Result<Vec<std::process::Child>> {
-    let program_logs_dir = Path::new(".anchor").join("program-logs");
+fn program_logs_dir() -> PathBuf {
+    Path::new(".anchor").join("program-logs")
+}
+
+fn stream_logs(
+    config: &WithPath<Config>,
+    rpc_url: &str,
+    program_logs_dir: &Path,
+) -> Result<Vec<std::process::Child>> {
     if program_logs_dir.exists() {
-        fs::remove_dir_all(&program_logs_dir)?;
+        fs::remove_dir_all(program_logs_dir)?;
     }
-    fs::create_dir_all(&program_logs_dir)?;
+    fs::create_dir_all(program_logs_dir)?;
 
     let mut handles = vec![];
     for program in config.read_all_programs()? {
@@ -3686,8 +5418,7 @@ fn start_test_validator(
 
     let rpc_url = test_validator_rpc_url(test_validator);
 
-    let rpc_port = cfg
-        .test_validator
+    let rpc_port = test_validator
         .as_ref()
         .and_then(|test| test.validator.as_ref().map(|v| v.rpc_port))
         .unwrap_or(solana_sdk::rpc_port::DEFAULT_RPC_PORT);
@@ -3696,8 +5427,7 @@ fn start_test_validator(
             "Your configured rpc port: {rpc_port} is already in use"
         ));
     }
-    let faucet_port = cfg
-        .test_validator
+    let faucet_port = test_validator
         .as_ref()
         .and_then(|test| test.validator.as_ref().and_then(|v| v.faucet_port))
         .unwrap_or(solana_faucet::faucet::FAUCET_PORT);
@@ -3734,12 +5464,11 @@ fn start_test_validator(
         count += 100;
     }
     if count >= ms_wait {
-        eprintln!(
+        validator_handle.kill()?;
+        return Err(anyhow!(
             "Unable to get latest blockhash. Test validator does not look started. \
             Check {test_ledger_log_filename:?} for errors. Consider increasing [test.startup_wait] in Anchor.toml."
-        );
-        validator_handle.kill()?;
-        std::process::exit(1);
+        ));
     }
     Ok(validator_handle)
 }
@@ -3835,73 +5564,129 @@ fn clean(cfg_override: &ConfigOverride) -> Result<()> {
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn deploy(
     cfg_override: &ConfigOverride,
     program_name: Option<String>,
     program_keypair: Option<String>,
     verifiable: bool,
-    solana_args: Vec<String>,
+    buffer: Option<String>,
+    max_retries: u32,
+    priority_fee: Option<u64>,
+    plan_only: bool,
+    force: bool,
+    with_idl: bool,
 ) -> Result<()> {
     // Execute the code within the workspace
     with_workspace(cfg_override, |cfg| {
         let url = cluster_url(cfg, &cfg.test_validator);
         let keypair = cfg.provider.wallet.to_string();
-
-        // Augment the given solana args with recommended defaults.
         let client = create_client(&url);
-        let solana_args = add_recommended_deployment_solana_args(&client, solana_args)?;
+        let cluster = cfg.provider.cluster.clone();
+        let mut state = DeploymentState::load(&cluster, &client.get_genesis_hash()?)?;
+
+        let programs = order_programs(cfg.get_programs(program_name)?)?;
+        let steps = plan_deployment(&client, programs, program_keypair.as_deref(), verifiable)?;
+
+        println!("Deployment plan for {cluster}:");
+        for (i, step) in steps.iter().enumerate() {
+            print!(
+                "  {}. {} ({}): {}",
+                i + 1,
+                step.program.lib_name,
+                step.program_keypair.pubkey(),
+                step.action
+            );
+            match step.idl_action {
+                Some(idl_action) if with_idl || plan_only => println!(", IDL: {idl_action}"),
+                _ => println!(),
+            }
+        }
+        if has_rust_migrations() {
+            println!("Migrations: {} applied", state.applied_migrations.len());
+        } else if let Some(migration_hash) = migration_hash()? {
+            if state.migration_hash == Some(migration_hash.to_string()) {
+                println!("Migrations: up to date");
+            } else {
+                println!("Migrations: pending");
+            }
+        }
+        if plan_only {
+            return Ok(());
+        }
 
         // Deploy the programs.
         println!("Deploying cluster: {}", url);
         println!("Upgrade authority: {}", keypair);
 
-        for mut program in cfg.get_programs(program_name)? {
-            let binary_path = program.binary_path(verifiable).display().to_string();
-
-            println!("Deploying program {:?}...", program.lib_name);
-            println!("Program path: {}...", binary_path);
-
-            let (program_keypair_filepath, program_id) = match &program_keypair {
-                Some(path) => (path.clone(), get_keypair(path)?.pubkey()),
-                None => (
-                    program.keypair_file()?.path().display().to_string(),
-                    program.pubkey()?,
-                ),
-            };
-
-            // Send deploy transactions using the Solana CLI
-            let exit = std::process::Command::new("solana")
-                .arg("program")
-                .arg("deploy")
-                .arg("--url")
-                .arg(&url)
-                .arg("--keypair")
-                .arg(&keypair)
-                .arg("--program-id")
-                .arg(strip_workspace_prefix(program_keypair_filepath))
-                .arg(strip_workspace_prefix(binary_path))
-                .args(&solana_args)
-                .stdout(Stdio::inherit())
-                .stderr(Stdio::inherit())
-                .output()
-                .expect("Must deploy");
-
-            // Check if deployment was successful
-            if !exit.status.success() {
-                println!("There was a problem deploying: {exit:?}.");
-                std::process::exit(exit.status.code().unwrap_or(1));
+        let payer = get_keypair(&keypair)?;
+        for mut step in steps {
+            let program_id = step.program_keypair.pubkey();
+            let name = step.program.lib_name.clone();
+            let deployed = step.action != DeployAction::Skip || force;
+            if deployed {
+                println!("Deploying program {:?}...", name);
+                println!("Program path: {}...", step.binary_path.display());
+
+                deploy_program(
+                    &client,
+                    &payer,
+                    &program_id,
+                    Some(&step.program_keypair),
+                    &step.binary_path,
+                    buffer.as_deref(),
+                    max_retries,
+                    priority_fee,
+                )?;
+            } else {
+                println!("Skipping program {:?}, it's unchanged", name);
             }
 
-            if let Some(idl) = program.idl.as_mut() {
+            let mut idl_hash = None;
+            if let Some(idl) = step.program.idl.as_mut() {
                 // Add program address to the IDL.
                 idl.address = program_id.to_string();
+                idl_hash = Some(hash(&serde_json::to_vec(idl)?).to_string());
 
                 // Persist it.
                 let idl_out = Path::new("target")
                     .join("idl")
                     .join(&idl.metadata.name)
                     .with_extension("json");
-                write_idl(idl, OutFile::File(idl_out))?;
+                write_idl(idl, OutFile::File(idl_out.clone()))?;
+
+                if with_idl {
+                    match step.idl_action {
+                        Some(IdlAction::Init) => {
+                            create_idl_account(cfg, &keypair, &program_id, idl, priority_fee)?;
+                        }
+                        Some(IdlAction::Upgrade) => idl_upgrade(
+                            cfg_override,
+                            program_id,
+                            idl_out.display().to_string(),
+                            priority_fee,
+                        )?,
+                        Some(IdlAction::Skip) | None => {}
+                    }
+                }
+            }
+
+            // Record the deployment, or the current state if it wasn't recorded before.
+            if deployed || !state.programs.contains_key(&name) {
+                let on_chain = fetch_program(&client, &program_id)?
+                    .ok_or_else(|| anyhow!("Program {program_id} is not deployed"))?;
+                state.programs.insert(
+                    name,
+                    DeploymentRecord {
+                        program_id: program_id.to_string(),
+                        binary_hash: step.binary_hash.to_string(),
+                        idl_hash,
+                        slot: on_chain.slot,
+                        upgrade_authority: on_chain.upgrade_authority.map(|a| a.to_string()),
+                        git_revision: git_revision(),
+                    },
+                );
+                state.save(&cluster)?;
             }
         }
 
@@ -3911,50 +5696,283 @@ fn deploy(
     })
 }
 
+/// Deployment of a single program.
+struct DeploymentStep {
+    program: Program,
+    program_keypair: Keypair,
+    binary_path: PathBuf,
+    binary_hash: Hash,
+    action: DeployAction,
+    /// `None` if the program has no IDL.
+    idl_action: Option<IdlAction>,
+}
+
+/// Plan the deployment of the programs by comparing the local artifacts against the on-chain
+/// programs and IDLs.
+fn plan_deployment(
+    client: &RpcClient,
+    programs: Vec<Program>,
+    program_keypair: Option<&str>,
+    verifiable: bool,
+) -> Result<Vec<DeploymentStep>> {
+    let mut steps = vec![];
+    for program in programs {
+        let binary_path = program.binary_path(verifiable);
+        let binary = fs::read(&binary_path)
+            .map_err(|e| anyhow!("Failed to read program {}: {e}", binary_path.display()))?;
+        let binary_hash = hash_program(&binary);
+
+        let program_keypair = match program_keypair {
+            Some(path) => get_keypair(path)?,
+            None => program.keypair()?,
+        };
+        let program_id = program_keypair.pubkey();
+
+        let action = match fetch_program(client, &program_id)? {
+            None => DeployAction::Deploy,
+            Some(on_chain) if on_chain.hash == binary_hash => DeployAction::Skip,
+            Some(_) => DeployAction::Upgrade,
+        };
+
+        let idl_action = match &program.idl {
+            Some(idl) => {
+                let idl_address = IdlAccount::address(&program_id);
+                if get_account_data_slice(client, &idl_address, 0, 0).is_err() {
+                    Some(IdlAction::Init)
+                } else {
+                    let on_chain_idl: serde_json::Value = serde_json::from_slice(
+                        &decode_idl_data(IdlDataReader::new(client, idl_address)?)?,
+                    )?;
+                    let mut idl = idl.clone();
+                    idl.address = program_id.to_string();
+                    if serde_json::to_value(&idl)? == on_chain_idl {
+                        Some(IdlAction::Skip)
+                    } else {
+                        Some(IdlAction::Upgrade)
+                    }
+                }
+            }
+            None => None,
+        };
+
+        steps.push(DeploymentStep {
+            program,
+            program_keypair,
+            binary_path,
+            binary_hash,
+            action,
+            idl_action,
+        });
+    }
+
+    Ok(steps)
+}
+
 fn upgrade(
     cfg_override: &ConfigOverride,
     program_id: Pubkey,
     program_filepath: String,
+    buffer: Option<String>,
     max_retries: u32,
-    solana_args: Vec<String>,
+    priority_fee: Option<u64>,
 ) -> Result<()> {
-    let path: PathBuf = program_filepath.parse().unwrap();
-    let program_filepath = path.canonicalize()?.display().to_string();
+    let program_filepath = PathBuf::from(program_filepath).canonicalize()?;
 
     with_workspace(cfg_override, |cfg| {
         let url = cluster_url(cfg, &cfg.test_validator);
         let client = create_client(&url);
-        let solana_args = add_recommended_deployment_solana_args(&client, solana_args)?;
-
-        for retry in 0..(1 + max_retries) {
-            let exit = std::process::Command::new("solana")
-                .arg("program")
-                .arg("deploy")
-                .arg("--url")
-                .arg(url.clone())
-                .arg("--keypair")
-                .arg(cfg.provider.wallet.to_string())
-                .arg("--program-id")
-                .arg(program_id.to_string())
-                .arg(strip_workspace_prefix(program_filepath.clone()))
-                .args(&solana_args)
-                .stdout(Stdio::inherit())
-                .stderr(Stdio::inherit())
-                .output()
-                .expect("Must deploy");
-            if exit.status.success() {
-                break;
+        let keypair = get_keypair(&cfg.provider.wallet.to_string())?;
+
+        deploy_program(
+            &client,
+            &keypair,
+            &program_id,
+            None,
+            &program_filepath,
+            buffer.as_deref(),
+            max_retries,
+            priority_fee,
+        )
+    })
+}
+
+fn upgrade_subcommand(cfg_override: &ConfigOverride, subcmd: UpgradeCommand) -> Result<()> {
+    match subcmd {
+        UpgradeCommand::Propose {
+            program_id,
+            program_filepath,
+            buffer,
+            max_retries,
+            priority_fee,
+            proposal,
+        } => upgrade_propose(
+            cfg_override,
+            program_id,
+            program_filepath,
+            buffer,
+            max_retries,
+            priority_fee,
+            &proposal,
+        ),
+    }
+}
+
+/// Write the program into a buffer, and optionally transfer the buffer's authority.
+///
+/// Unlike upgrades, a new buffer keypair is used by default because the authority of the buffer
+/// may be transferred.
+fn upgrade_write_buffer(
+    cfg_override: &ConfigOverride,
+    program_id: Pubkey,
+    program_filepath: String,
+    buffer: Option<String>,
+    buffer_authority: Option<Pubkey>,
+    max_retries: u32,
+    priority_fee: Option<u64>,
+) -> Result<Pubkey> {
+    let program_filepath = PathBuf::from(program_filepath).canonicalize()?;
+    let program_data = fs::read(&program_filepath)
+        .map_err(|e| anyhow!("Failed to read program {}: {e}", program_filepath.display()))?;
+
+    with_workspace(cfg_override, |cfg| {
+        let url = cluster_url(cfg, &cfg.test_validator);
+        let client = create_client(&url);
+        let keypair = get_keypair(&cfg.provider.wallet.to_string())?;
+        let buffer = match &buffer {
+            Some(path) => get_keypair(path)?,
+            None => Keypair::new(),
+        };
+
+        let deployer = create_deployer(&client, &keypair, max_retries, priority_fee)?;
+        deployer
+            .write_buffer(&buffer, &program_data)
+            .map_err(|e| anyhow!("There was a problem writing the buffer of {program_id}: {e}"))?;
+        println!();
+        println!("Buffer: {}", buffer.pubkey());
+
+        if let Some(buffer_authority) = buffer_authority {
+            deployer
+                .set_buffer_authority(&buffer.pubkey(), &buffer_authority)
+                .map_err(|e| anyhow!("There was a problem setting the buffer authority: {e}"))?;
+            println!("Buffer authority: {buffer_authority}");
+        }
+
+        Ok(buffer.pubkey())
+    })
+}
+
+/// Write the program into a buffer owned by the upgrade authority, and print the instructions to
+/// upgrade the program for the authority to sign.
+fn upgrade_propose(
+    cfg_override: &ConfigOverride,
+    program_id: Pubkey,
+    program_filepath: String,
+    buffer: Option<String>,
+    max_retries: u32,
+    priority_fee: Option<u64>,
+    proposal: &ProposalArgs,
+) -> Result<()> {
+    let program_len = fs::metadata(&program_filepath)
+        .map_err(|e| anyhow!("Failed to read program {program_filepath}: {e}"))?
+        .len() as usize;
+
+    let (state, authority) = with_workspace(cfg_override, |cfg| {
+        let url = cluster_url(cfg, &cfg.test_validator);
+        let client = create_client(url);
+        let state = get_program_state(&client, &program_id)?
+            .ok_or_else(|| anyhow!("Program {program_id} is not deployed"))?;
+        let authority = match (proposal.authority, state.upgrade_authority) {
+            (Some(authority), _) | (None, Some(authority)) => authority,
+            (None, None) => return Err(anyhow!("Program {program_id} is immutable")),
+        };
+        Ok((state, authority))
+    })?;
+
+    let buffer = upgrade_write_buffer(
+        cfg_override,
+        program_id,
+        program_filepath,
+        buffer,
+        Some(authority),
+        max_retries,
+        priority_fee,
+    )?;
+
+    let ixs = upgrade_instructions(
+        &program_id,
+        &buffer,
+        &authority,
+        &proposal.spill.unwrap_or(authority),
+        state.data_len,
+        program_len,
+    )?;
+    print_proposal(&ixs, &authority, proposal)
+}
+
+/// Create a [`Deployer`] that prints its progress.
+///
+/// If no priority fee is provided, a recommended fee is calculated based on recent transactions.
+fn create_deployer<'a>(
+    client: &'a RpcClient,
+    payer: &'a Keypair,
+    max_retries: u32,
+    priority_fee: Option<u64>,
+) -> Result<Deployer<'a>> {
+    let priority_fee = match priority_fee {
+        Some(priority_fee) => priority_fee,
+        None => get_recommended_micro_lamport_fee(client)?,
+    };
+
+    Ok(Deployer::new(client, payer)
+        .priority_fee(priority_fee)
+        .max_retries(max_retries)
+        .on_progress(|progress| match progress {
+            DeployProgress::Write { written, total } => {
+                print!("\rWriting buffer: {written}/{total} chunks");
+                std::io::stdout().flush().ok();
             }
+            DeployProgress::Deploy => println!(),
+        }))
+}
 
-            println!("There was a problem deploying: {exit:?}.");
-            if retry < max_retries {
-                println!("Retrying {} more time(s)...", max_retries - retry);
-            } else {
-                std::process::exit(exit.status.code().unwrap_or(1));
+/// Deploy or upgrade the program with the native [`Deployer`], printing its progress.
+///
+/// If no buffer keypair is given, a temporary one is reused across deployments, which allows
+/// resuming a deployment whose buffer writes failed.
+#[allow(clippy::too_many_arguments)]
+fn deploy_program(
+    client: &RpcClient,
+    payer: &Keypair,
+    program_id: &Pubkey,
+    program_keypair: Option<&Keypair>,
+    binary_path: &Path,
+    buffer: Option<&str>,
+    max_retries: u32,
+    priority_fee: Option<u64>,
+) -> Result<()> {
+    let program_data = fs::read(binary_path)
+        .map_err(|e| anyhow!("Failed to read program {}: {e}", binary_path.display()))?;
+
+    let buffer = match buffer {
+        Some(path) => get_keypair(path)?,
+        None => {
+            let tmp_keypair_path = std::env::temp_dir().join("anchor-upgrade-buffer.json");
+            if !tmp_keypair_path.exists() {
+                Keypair::new()
+                    .write_to_file(&tmp_keypair_path)
+                    .map_err(|e| anyhow!("Error creating keypair for buffer account, {:?}", e))?;
             }
+            get_keypair(&tmp_keypair_path.to_string_lossy())?
         }
-        Ok(())
-    })
+    };
+
+    let signature = create_deployer(client, payer, max_retries, priority_fee)?
+        .deploy(program_id, program_keypair, &program_data, &buffer)
+        .map_err(|e| anyhow!("There was a problem deploying: {e}"))?;
+
+    println!("Program Id: {program_id}");
+    println!("Signature: {signature}");
+    Ok(())
 }
 
 fn create_idl_account(
@@ -4128,10 +6146,7 @@ fn create_idl_buffer(
 
 // Serialize and compress the idl.
 fn serialize_idl(idl: &Idl) -> Result<Vec<u8>> {
-    let json_bytes = serde_json::to_vec(idl)?;
-    let mut e = ZlibEncoder::new(Vec::new(), Compression::default());
-    e.write_all(&json_bytes)?;
-    e.finish().map_err(Into::into)
+    encode_idl_data(idl).map_err(Into::into)
 }
 
 fn serialize_idl_ix(ix_inner: anchor_lang::idl::IdlInstruction) -> Result<Vec<u8>> {
@@ -4141,11 +6156,109 @@ fn serialize_idl_ix(ix_inner: anchor_lang::idl::IdlInstruction) -> Result<Vec<u8
     Ok(data)
 }
 
-fn migrate(cfg_override: &ConfigOverride) -> Result<()> {
+/// Whether the workspace has a `migrations` binary crate with Rust migrations.
+fn has_rust_migrations() -> bool {
+    Path::new("migrations").join("Cargo.toml").exists()
+}
+
+/// Run the Rust migrations of the `migrations` binary crate, recording the applied migrations.
+fn migrate_rust(
+    cfg: &WithPath<Config>,
+    url: &str,
+    mut state: DeploymentState,
+    force: bool,
+) -> Result<()> {
+    println!("Running Rust migrations");
+
+    let programs = cfg
+        .get_programs(None)?
+        .iter()
+        .map(|program| Ok(format!("{}={}", program.lib_name, program.pubkey()?)))
+        .collect::<Result<Vec<_>>>()?
+        .join(",");
+    if force {
+        state.applied_migrations.clear();
+    }
+
+    // The migrations binary appends the versions of the migrations it applies to this file
+    fs::create_dir_all(".anchor")?;
+    let applied_path = Path::new(".anchor").join("applied-migrations");
+    let applied = state
+        .applied_migrations
+        .iter()
+        .map(|version| format!("{version}\n"))
+        .collect::<String>();
+    fs::write(&applied_path, applied)?;
+
+    let exit = std::process::Command::new("cargo")
+        .args(["run", "--quiet", "--manifest-path", "migrations/Cargo.toml"])
+        .env(migrate::PROVIDER_URL_ENV, url)
+        .env(migrate::WALLET_ENV, cfg.provider.wallet.to_string())
+        .env(migrate::PROGRAMS_ENV, programs)
+        .env(migrate::APPLIED_ENV, fs::canonicalize(&applied_path)?)
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .status()
+        .map_err(|e| anyhow!("Failed to run the migrations: {e}"))?;
+
+    // Record the applied migrations even if one of them failed
+    let mut applied = fs::read_to_string(&applied_path)?
+        .lines()
+        .map(|version| {
+            version
+                .trim()
+                .parse()
+                .map_err(|_| anyhow!("Invalid migration version: {version}"))
+        })
+        .collect::<Result<Vec<u64>>>()?;
+    applied.sort_unstable();
+    applied.dedup();
+    state.applied_migrations = applied;
+    state.save(&cfg.provider.cluster)?;
+    fs::remove_file(&applied_path)?;
+
+    if !exit.success() {
+        eprintln!("Migrations failed.");
+        std::process::exit(exit.code().unwrap_or(1));
+    }
+
+    println!("Migrations complete.");
+    Ok(())
+}
+
+/// Hash of the migration deploy script, `None` if the workspace has no migrations.
+fn migration_hash() -> Result<Option<Hash>> {
+    let deploy_ts = Path::new("migrations").join("deploy.ts");
+    let deploy_js = deploy_ts.with_extension("js");
+    let script = if Path::new("tsconfig.json").exists() && deploy_ts.exists() {
+        deploy_ts
+    } else if deploy_js.exists() {
+        deploy_js
+    } else {
+        return Ok(None);
+    };
+
+    Ok(Some(hash(&fs::read(script)?)))
+}
+
+fn migrate(cfg_override: &ConfigOverride, force: bool) -> Result<()> {
     with_workspace(cfg_override, |cfg| {
+        let url = cluster_url(cfg, &cfg.test_validator);
+        let cluster = cfg.provider.cluster.clone();
+        let client = create_client(&url);
+        let mut state = DeploymentState::load(&cluster, &client.get_genesis_hash()?)?;
+        if has_rust_migrations() {
+            return migrate_rust(cfg, &url, state, force);
+        }
+
+        let migration_hash = migration_hash()?.map(|hash| hash.to_string());
+        if !force && migration_hash.is_some() && state.migration_hash == migration_hash {
+            println!("Migrations already ran on {cluster}, use --force to run them again");
+            return Ok(());
+        }
+
         println!("Running migration deploy script");
 
-        let url = cluster_url(cfg, &cfg.test_validator);
         let cur_dir = std::env::current_dir()?;
         let migrations_dir = cur_dir.join("migrations");
         let deploy_ts = Path::new("deploy.ts");
@@ -4198,6 +6311,10 @@ fn migrate(cfg_override: &ConfigOverride) -> Result<()> {
             std::process::exit(exit.status.code().unwrap());
         }
 
+        std::env::set_current_dir(cur_dir)?;
+        state.migration_hash = migration_hash;
+        state.save(&cluster)?;
+
         println!("Deploy complete.");
         Ok(())
     })
@@ -4699,7 +6816,7 @@ fn localnet(
 
         // Setup log reader.
         let url = test_validator_rpc_url(&cfg.test_validator);
-        let log_streams = stream_logs(cfg, &url);
+        let log_streams = stream_logs(cfg, &url, &program_logs_dir());
 
         std::io::stdin().lock().lines().next().unwrap().unwrap();
 
@@ -4766,46 +6883,6 @@ fn get_node_version() -> Result<Version> {
     Version::parse(output).map_err(Into::into)
 }
 
-fn add_recommended_deployment_solana_args(
-    client: &RpcClient,
-    args: Vec<String>,
-) -> Result<Vec<String>> {
-    let mut augmented_args = args.clone();
-
-    // If no priority fee is provided, calculate a recommended fee based on recent txs.
-    if !args.contains(&"--with-compute-unit-price".to_string()) {
-        let priority_fee = get_recommended_micro_lamport_fee(client)?;
-        augmented_args.push("--with-compute-unit-price".to_string());
-        augmented_args.push(priority_fee.to_string());
-    }
-
-    const DEFAULT_MAX_SIGN_ATTEMPTS: u8 = 30;
-    if !args.contains(&"--max-sign-attempts".to_string()) {
-        augmented_args.push("--max-sign-attempts".to_string());
-        augmented_args.push(DEFAULT_MAX_SIGN_ATTEMPTS.to_string());
-    }
-
-    // If no buffer keypair is provided, create a temporary one to reuse across deployments.
-    // This is particularly useful for upgrading larger programs, which suffer from an increased
-    // likelihood of some write transactions failing during any single deployment.
-    if !args.contains(&"--buffer".to_owned()) {
-        let tmp_keypair_path = std::env::temp_dir().join("anchor-upgrade-buffer.json");
-        if !tmp_keypair_path.exists() {
-            if let Err(err) = Keypair::new().write_to_file(&tmp_keypair_path) {
-                return Err(anyhow!(
-                    "Error creating keypair for buffer account, {:?}",
-                    err
-                ));
-            }
-        }
-
-        augmented_args.push("--buffer".to_owned());
-        augmented_args.push(tmp_keypair_path.to_string_lossy().to_string());
-    }
-
-    Ok(augmented_args)
-}
-
 fn get_recommended_micro_lamport_fee(client: &RpcClient) -> Result<u64> {
     let mut fees = client.get_recent_prioritization_fees(&[])?;
     if fees.is_empty() {
@@ -4860,21 +6937,6 @@ fn get_node_dns_option() -> Result<&'static str> {
     Ok(option)
 }
 
-// Remove the current workspace directory if it prefixes a string.
-// This is used as a workaround for the Solana CLI using the uriparse crate to
-// parse args but not handling percent encoding/decoding when using the path as
-// a local filesystem path. Removing the workspace prefix handles most/all cases
-// of spaces in keypair/binary paths, but this should be fixed in the Solana CLI
-// and removed here.
-fn strip_workspace_prefix(absolute_path: String) -> String {
-    let workspace_prefix =
-        std::env::current_dir().unwrap().display().to_string() + std::path::MAIN_SEPARATOR_STR;
-    absolute_path
-        .strip_prefix(&workspace_prefix)
-        .unwrap_or(&absolute_path)
-        .into()
-}
-
 /// Create a new [`RpcClient`] with `confirmed` commitment level instead of the default(finalized).
 fn create_client<U: ToString>(url: U) -> RpcClient {
     RpcClient::new_with_commitment(url, CommitmentConfig::confirmed())
@@ -4946,4 +7008,182 @@ mod tests {
         )
         .unwrap();
     }
+
+    #[test]
+    fn test_serialize_instruction() {
+        let ix = Instruction {
+            program_id: Pubkey::new_unique(),
+            accounts: vec![AccountMeta::new(Pubkey::new_unique(), true)],
+            data: vec![1, 2, 3],
+        };
+        let serialized = serialize_instruction(&ix).unwrap();
+
+        // Vector lengths are borsh encoded with 4 bytes
+        let mut expected = ix.program_id.to_bytes().to_vec();
+        expected.extend(1u32.to_le_bytes());
+        expected.extend(ix.accounts[0].pubkey.to_bytes());
+        expected.extend([1, 1]);
+        expected.extend(3u32.to_le_bytes());
+        expected.extend([1, 2, 3]);
+        assert_eq!(serialized, expected);
+    }
+
+    #[test]
+    fn test_idl_client_rust() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+        let idl = fs::read(root.join("tests/declare-program/idls/external.json")).unwrap();
+        let idl: Idl = serde_json::from_slice(&idl).unwrap();
+        let out = std::env::temp_dir().join(format!("anchor-idl-client-{}", std::process::id()));
+        idl_client_rust(&idl, &out).unwrap();
+
+        let cargo_toml = fs::read_to_string(out.join("Cargo.toml")).unwrap();
+        assert!(cargo_toml.contains(r#"name = "external-client""#));
+        assert!(cargo_toml.contains(&format!(r#"anchor-lang = "{VERSION}""#)));
+
+        let src = out.join("src");
+        let lib = syn::parse_file(&fs::read_to_string(src.join("lib.rs")).unwrap()).unwrap();
+        let mut mods = lib
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                syn::Item::Mod(item) => {
+                    assert!(item.content.is_none(), "`{}` is inline", item.ident);
+                    Some(item.ident.to_string())
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        mods.sort();
+        assert_eq!(
+            mods,
+            [
+                "accounts",
+                "client",
+                "constants",
+                "cpi",
+                "errors",
+                "events",
+                "instructions",
+                "internal",
+                "pda",
+                "program",
+                "types",
+                "utils",
+            ]
+        );
+
+        let mut files = fs::read_dir(&src)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        files.sort();
+        let mut expected = mods
+            .iter()
+            .map(|name| format!("{name}.rs"))
+            .chain(["lib.rs".to_owned()])
+            .collect::<Vec<_>>();
+        expected.sort();
+        assert_eq!(files, expected);
+        for name in &mods {
+            let path = src.join(format!("{name}.rs"));
+            syn::parse_file(&fs::read_to_string(&path).unwrap())
+                .unwrap_or_else(|e| panic!("Failed to parse {}: {e}", path.display()));
+        }
+
+        fs::remove_dir_all(&out).unwrap();
+    }
+
+    #[test]
+    fn test_idl_ts() {
+        let idl: Idl = serde_json::from_value(serde_json::json!({
+            "address": "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS",
+            "metadata": { "name": "my_program", "version": "0.1.0", "spec": "0.1.0" },
+            "instructions": [{
+                "name": "deposit_tokens",
+                "discriminator": [1, 2, 3, 4, 5, 6, 7, 8],
+                "accounts": [
+                    { "name": "vault_authority", "signer": true },
+                    {
+                        "name": "vault",
+                        "writable": true,
+                        "relations": ["vault_authority"],
+                        "pda": {
+                            "seeds": [{ "kind": "account", "path": "vault_authority.key" }]
+                        }
+                    }
+                ],
+                "args": [
+                    {
+                        "name": "token_amount",
+                        "type": "u64",
+                        "unit": "token_amount",
+                        "decimals": 6
+                    },
+                    { "name": "memo", "type": "string", "deprecated": {} }
+                ],
+                "deprecated": { "since": "0.2.0", "note": "Use `deposit` instead" }
+            }],
+            "errors": [{ "code": 6000, "name": "math_overflow", "msg": "Overflow" }]
+        }))
+        .unwrap();
+        let ts = idl_ts(&idl).unwrap();
+
+        assert!(ts.contains("export type MyProgram = {\n"));
+        assert!(ts.contains(
+            r#"    {
+      "name": "depositTokens","#
+        ));
+        assert!(ts.contains(
+            r#"  "instructions": [
+    /**
+     * @deprecated Since 0.2.0: Use `deposit` instead
+     */
+    {"#
+        ));
+        assert!(ts.contains(
+            r#"      "args": [
+        /**
+         * @unit token_amount
+         * @decimals 6
+         */
+        {
+          "name": "tokenAmount",
+          "type": "u64",
+          "unit": "token_amount",
+          "decimals": 6
+        },
+        /**
+         * @deprecated
+         */
+        {
+          "name": "memo","#
+        ));
+        assert!(ts.contains(
+            r#""relations": [
+            "vaultAuthority"
+          ]"#
+        ));
+        assert!(ts.contains(r#""path": "vaultAuthority.key""#));
+        assert!(ts.contains(r#""name": "mathOverflow""#));
+        assert!(ts.contains(r#""msg": "Overflow""#));
+
+        // Without the JSDoc, the type is the camelCase IDL
+        let json = ts
+            .lines()
+            .skip_while(|line| !line.starts_with("export type"))
+            .filter(|line| {
+                !line.trim_start().starts_with("/**") && !line.trim_start().starts_with('*')
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let json = json
+            .trim_start_matches("export type MyProgram = ")
+            .trim_end_matches(';');
+        let camel_idl: Idl = serde_json::from_str(json).unwrap();
+        assert_eq!(json, serde_json::to_string_pretty(&camel_idl).unwrap());
+        assert_eq!(
+            camel_idl.instructions[0].args[0].unit.as_deref(),
+            Some("token_amount")
+        );
+    }
 }
@@ -203,6 +203,7 @@ cpi = ["no-entrypoint"]
 no-entrypoint = []
 no-idl = []
 no-log-ix-name = []
+debug-invariants = []
 idl-build = ["anchor-lang/idl-build"]
 {2}
 
@@ -0,0 +1,511 @@
+//! Historical event backfill.
+//!
+//! Events are collected by walking `getSignaturesForAddress` of the program, fetching each
+//! transaction and decoding the events that were emitted either via logs ([`emit!`]) or via
+//! self-CPI ([`emit_cpi!`]).
+//!
+//! Progress can be persisted with a [`CheckpointStore`] so that an indexer can resume where it
+//! left off after a restart.
+//!
+//! [`emit!`]: anchor_lang::prelude::emit
+//! [`emit_cpi!`]: anchor_lang::prelude::emit_cpi
+
+use crate::{decode_event, decode_events, parse_logs_event_data, ClientError, RpcTransport};
+use anchor_lang::event::EVENT_IX_TAG_LE;
+use anchor_lang::solana_program::pubkey::Pubkey;
+use serde::{Deserialize, Serialize};
+use solana_client::{
+    rpc_client::GetConfirmedSignaturesForAddress2Config, rpc_config::RpcTransactionConfig,
+    rpc_response::RpcConfirmedTransactionStatusWithSignature,
+};
+use solana_sdk::{bs58, commitment_config::CommitmentConfig, signature::Signature};
+use solana_transaction_status_client_types::{
+    EncodedConfirmedTransactionWithStatusMeta, UiInstruction, UiTransactionEncoding,
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Maximum number of signatures a single `getSignaturesForAddress` request can return.
+pub const MAX_SIGNATURES_PER_PAGE: usize = 1000;
+
+/// Configuration of a backfill run.
+#[derive(Debug, Clone)]
+pub struct BackfillConfig {
+    /// Start searching backwards from this transaction signature (exclusive).
+    ///
+    /// Defaults to the most recent transaction.
+    pub before: Option<Signature>,
+    /// Stop searching at this transaction signature (exclusive).
+    ///
+    /// Defaults to the first transaction of the program, or to the saved checkpoint when a
+    /// [`CheckpointStore`] is used.
+    pub until: Option<Signature>,
+    /// Number of signatures to fetch per request, at most [`MAX_SIGNATURES_PER_PAGE`].
+    pub page_size: usize,
+    /// Commitment of the fetched transactions. `processed` is not supported by the RPC.
+    pub commitment: Option<CommitmentConfig>,
+}
+
+impl Default for BackfillConfig {
+    fn default() -> Self {
+        Self {
+            before: None,
+            until: None,
+            page_size: MAX_SIGNATURES_PER_PAGE,
+            commitment: None,
+        }
+    }
+}
+
+/// How an event was emitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventSource {
+    /// Via program logs, i.e. [`emit!`](anchor_lang::prelude::emit).
+    Log,
+    /// Via self-CPI, i.e. [`emit_cpi!`](anchor_lang::prelude::emit_cpi).
+    Cpi,
+}
+
+/// Event data that has not been deserialized yet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawEvent {
+    pub source: EventSource,
+    /// Discriminator followed by the serialized event.
+    pub data: Vec<u8>,
+}
+
+impl RawEvent {
+    /// Deserialize the event as `T`.
+    ///
+    /// Returns `None` if the discriminator doesn't match `T`.
+    pub fn decode<T: anchor_lang::Event + anchor_lang::AnchorDeserialize>(
+        &self,
+    ) -> Result<Option<T>, ClientError> {
+        decode_event(&self.data)
+    }
+
+    /// Deserialize the event as any of the events of `E`, e.g. the `utils::Event` enum generated
+    /// by `declare_program!`.
+    ///
+    /// Returns `None` if the discriminator doesn't match any of the events.
+    pub fn decode_any<E: anchor_lang::AnchorEvents>(&self) -> Result<Option<E>, ClientError> {
+        decode_events(&self.data)
+    }
+}
+
+/// Last transaction that was fully processed by a backfill run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    #[serde(with = "signature_string")]
+    pub signature: Signature,
+    pub slot: u64,
+}
+
+/// Persistent storage for backfill [`Checkpoint`]s.
+pub trait CheckpointStore: Send + Sync {
+    /// Load the latest checkpoint, if any.
+    fn load(&self) -> Result<Option<Checkpoint>, ClientError>;
+
+    /// Save the given checkpoint, replacing the previous one.
+    fn save(&self, checkpoint: &Checkpoint) -> Result<(), ClientError>;
+}
+
+/// [`CheckpointStore`] that keeps the checkpoint in a JSON file.
+#[derive(Debug, Clone)]
+pub struct FileCheckpointStore {
+    path: PathBuf,
+}
+
+impl FileCheckpointStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl CheckpointStore for FileCheckpointStore {
+    fn load(&self) -> Result<Option<Checkpoint>, ClientError> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&self.path)?;
+        let checkpoint = serde_json::from_str(&content).map_err(std::io::Error::from)?;
+        Ok(Some(checkpoint))
+    }
+
+    fn save(&self, checkpoint: &Checkpoint) -> Result<(), ClientError> {
+        let content = serde_json::to_string_pretty(checkpoint).map_err(std::io::Error::from)?;
+
+        // Write to a temporary file first so that a crash can't leave a partial checkpoint
+        let tmp_path = self.path.with_extension("tmp");
+        fs::write(&tmp_path, content)?;
+        fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+/// Pages of the signatures of the program's successful transactions within the configured
+/// range, oldest first.
+///
+/// `getSignaturesForAddress` returns the newest signatures first, so the range is first walked
+/// backwards to find where each page starts, and the pages are then fetched again from the oldest
+/// one. Only the start of each page is kept in memory, rather than every signature of the range.
+pub(crate) struct SignaturePages<'a> {
+    transport: &'a dyn RpcTransport,
+    program_id: &'a Pubkey,
+    config: &'a BackfillConfig,
+    /// Start of the pages that were not returned yet, newest first.
+    starts: Vec<Option<Signature>>,
+    /// The oldest page, which was already fetched while walking the range.
+    oldest: Option<Vec<RpcConfirmedTransactionStatusWithSignature>>,
+}
+
+impl<'a> SignaturePages<'a> {
+    pub(crate) async fn new(
+        transport: &'a dyn RpcTransport,
+        program_id: &'a Pubkey,
+        config: &'a BackfillConfig,
+    ) -> Result<Self, ClientError> {
+        let mut pages = Self {
+            transport,
+            program_id,
+            config,
+            starts: vec![],
+            oldest: None,
+        };
+        let mut before = config.before;
+        loop {
+            let page = pages.fetch(before).await?;
+            match page.last() {
+                Some(last) if page.len() == pages.limit() => {
+                    pages.starts.push(before);
+                    before = Some(parse_signature(&last.signature)?);
+                }
+                _ => {
+                    pages.oldest = Some(page);
+                    return Ok(pages);
+                }
+            }
+        }
+    }
+
+    /// Fetch the next page, with the signatures of the page oldest first.
+    pub(crate) async fn next(
+        &mut self,
+    ) -> Result<Option<Vec<RpcConfirmedTransactionStatusWithSignature>>, ClientError> {
+        let mut page = match self.oldest.take() {
+            Some(page) => page,
+            None => match self.starts.pop() {
+                Some(before) => self.fetch(before).await?,
+                None => return Ok(None),
+            },
+        };
+
+        // Events of failed transactions were never committed
+        page.retain(|status| status.err.is_none());
+        page.reverse();
+        Ok(Some(page))
+    }
+
+    async fn fetch(
+        &self,
+        before: Option<Signature>,
+    ) -> Result<Vec<RpcConfirmedTransactionStatusWithSignature>, ClientError> {
+        self.transport
+            .get_signatures_for_address_with_config(
+                self.program_id,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    until: self.config.until,
+                    limit: Some(self.limit()),
+                    commitment: self.config.commitment,
+                },
+            )
+            .await
+    }
+
+    fn limit(&self) -> usize {
+        self.config.page_size.clamp(1, MAX_SIGNATURES_PER_PAGE)
+    }
+}
+
+/// Fetch the transaction with the given signature and decode the program's events from it.
+pub(crate) async fn fetch_transaction_events(
+    transport: &dyn RpcTransport,
+    program_id: &Pubkey,
+    signature: &Signature,
+    commitment: Option<CommitmentConfig>,
+) -> Result<Vec<RawEvent>, ClientError> {
+    let tx = transport
+        .get_transaction_with_config(
+            signature,
+            RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::Base64),
+                commitment,
+                max_supported_transaction_version: Some(0),
+            },
+        )
+        .await?;
+
+    parse_transaction_events(&tx, program_id)
+}
+
+/// Decode the events the given program emitted in the transaction, in the order they were
+/// emitted.
+pub(crate) fn parse_transaction_events(
+    tx: &EncodedConfirmedTransactionWithStatusMeta,
+    program_id: &Pubkey,
+) -> Result<Vec<RawEvent>, ClientError> {
+    let meta = tx
+        .transaction
+        .meta
+        .as_ref()
+        .ok_or_else(|| ClientError::TransactionParseError("Missing meta".into()))?;
+
+    // Events are ordered by the index of their log. The log of a CPI event is the `invoke` log of
+    // its inner instruction.
+    let logs: Option<&Vec<String>> = meta.log_messages.as_ref().into();
+    let logs = logs.map(Vec::as_slice).unwrap_or_default();
+    let mut events = parse_logs_event_data(logs, &program_id.to_string())
+        .into_iter()
+        .map(|(index, data)| {
+            (
+                index,
+                RawEvent {
+                    source: EventSource::Log,
+                    data,
+                },
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let inner_instructions: Option<&Vec<_>> = meta.inner_instructions.as_ref().into();
+    let Some(inner_instructions) = inner_instructions else {
+        return Ok(events.into_iter().map(|(_, event)| event).collect());
+    };
+
+    // Inner instructions are in the order of their `invoke` logs, unless the logs were truncated,
+    // in which case the events of the remaining inner instructions come last
+    let mut invoke_logs = logs
+        .iter()
+        .enumerate()
+        .filter(|(_, log)| is_cpi_invoke_log(log))
+        .map(|(index, _)| index);
+    let account_keys = transaction_account_keys(tx)?;
+    for ix in inner_instructions.iter().flat_map(|ixs| &ixs.instructions) {
+        let index = invoke_logs.next().unwrap_or(usize::MAX);
+        let UiInstruction::Compiled(ix) = ix else {
+            return Err(ClientError::TransactionParseError(
+                "Parsed inner instructions are not supported".into(),
+            ));
+        };
+        if account_keys.get(ix.program_id_index as usize) != Some(program_id) {
+            continue;
+        }
+
+        let data = bs58::decode(&ix.data)
+            .into_vec()
+            .map_err(|e| ClientError::TransactionParseError(e.to_string()))?;
+        if let Some(data) = data.strip_prefix(EVENT_IX_TAG_LE) {
+            events.push((
+                index,
+                RawEvent {
+                    source: EventSource::Cpi,
+                    data: data.to_vec(),
+                },
+            ));
+        }
+    }
+
+    // Stable, so events of the same log keep their order
+    events.sort_by_key(|(index, _)| *index);
+    Ok(events.into_iter().map(|(_, event)| event).collect())
+}
+
+/// Whether the log is the `invoke` log of an inner instruction, i.e. of a depth above 1.
+fn is_cpi_invoke_log(log: &str) -> bool {
+    let invoke = log
+        .strip_prefix("Program ")
+        .and_then(|log| log.strip_suffix(']'))
+        .and_then(|log| log.split_once(" invoke ["));
+    match invoke {
+        Some((program, depth)) => {
+            !program.contains([' ', ':']) && depth.parse::<u32>().is_ok_and(|depth| depth > 1)
+        }
+        None => false,
+    }
+}
+
+/// Static account keys of the transaction followed by the addresses loaded from lookup tables.
+fn transaction_account_keys(
+    tx: &EncodedConfirmedTransactionWithStatusMeta,
+) -> Result<Vec<Pubkey>, ClientError> {
+    let versioned_tx = tx.transaction.transaction.decode().ok_or_else(|| {
+        ClientError::TransactionParseError("Unsupported transaction encoding".into())
+    })?;
+    let mut keys = versioned_tx.message.static_account_keys().to_vec();
+
+    let loaded_addresses: Option<&_> = tx
+        .transaction
+        .meta
+        .as_ref()
+        .and_then(|meta| meta.loaded_addresses.as_ref().into());
+    if let Some(loaded_addresses) = loaded_addresses {
+        for key in loaded_addresses
+            .writable
+            .iter()
+            .chain(&loaded_addresses.readonly)
+        {
+            let key = key
+                .parse()
+                .map_err(|_| ClientError::TransactionParseError(format!("Invalid key: {key}")))?;
+            keys.push(key);
+        }
+    }
+
+    Ok(keys)
+}
+
+pub(crate) fn parse_signature(signature: &str) -> Result<Signature, ClientError> {
+    signature
+        .parse()
+        .map_err(|_| ClientError::TransactionParseError(format!("Invalid signature: {signature}")))
+}
+
+mod signature_string {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use solana_sdk::signature::Signature;
+
+    pub fn serialize<S: Serializer>(
+        signature: &Signature,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(signature)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Signature, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::__private::base64::{prelude::BASE64_STANDARD, Engine};
+    use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+    use solana_sdk::{message::Message, transaction::Transaction};
+
+    #[test]
+    fn parse_log_and_cpi_events() {
+        let program_id = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+        let event_authority = Pubkey::new_unique();
+        let message = Message::new(
+            &[Instruction::new_with_bytes(
+                program_id,
+                &[],
+                vec![AccountMeta::new_readonly(event_authority, false)],
+            )],
+            Some(&payer),
+        );
+        let program_id_index = message
+            .account_keys
+            .iter()
+            .position(|key| *key == program_id)
+            .unwrap();
+        let tx = Transaction::new_unsigned(message);
+
+        let log_event = [1u8, 2, 3];
+        let cpi_event = [4u8, 5, 6];
+        let last_log_event = [7u8, 8, 9];
+        let cpi_data = [EVENT_IX_TAG_LE, &cpi_event].concat();
+        let tx: EncodedConfirmedTransactionWithStatusMeta =
+            serde_json::from_value(serde_json::json!({
+                "slot": 1,
+                "blockTime": null,
+                "transaction": [BASE64_STANDARD.encode(bincode::serialize(&tx).unwrap()), "base64"],
+                "meta": {
+                    "err": null,
+                    "status": { "Ok": null },
+                    "fee": 5000,
+                    "preBalances": [],
+                    "postBalances": [],
+                    "innerInstructions": [{
+                        "index": 0,
+                        "instructions": [
+                            {
+                                "programIdIndex": program_id_index,
+                                "accounts": [],
+                                "data": bs58::encode(&cpi_data).into_string(),
+                                "stackHeight": 2
+                            },
+                            {
+                                "programIdIndex": program_id_index,
+                                "accounts": [],
+                                "data": bs58::encode(&cpi_event).into_string(),
+                                "stackHeight": 2
+                            }
+                        ]
+                    }],
+                    "logMessages": [
+                        format!("Program {program_id} invoke [1]"),
+                        format!("Program data: {}", BASE64_STANDARD.encode(log_event)),
+                        "Program log: Not an invoke [2]",
+                        format!("Program {program_id} invoke [2]"),
+                        format!("Program {program_id} success"),
+                        format!("Program {program_id} invoke [2]"),
+                        format!("Program {program_id} success"),
+                        format!("Program data: {}", BASE64_STANDARD.encode(last_log_event)),
+                        format!("Program {program_id} success"),
+                    ]
+                }
+            }))
+            .unwrap();
+
+        let events = parse_transaction_events(&tx, &program_id).unwrap();
+        assert_eq!(
+            events,
+            vec![
+                RawEvent {
+                    source: EventSource::Log,
+                    data: log_event.to_vec()
+                },
+                RawEvent {
+                    source: EventSource::Cpi,
+                    data: cpi_event.to_vec()
+                },
+                RawEvent {
+                    source: EventSource::Log,
+                    data: last_log_event.to_vec()
+                },
+            ]
+        );
+        assert!(parse_transaction_events(&tx, &Pubkey::new_unique())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn file_checkpoint_store() {
+        let path = std::env::temp_dir().join(format!("checkpoint-{}.json", Pubkey::new_unique()));
+        let store = FileCheckpointStore::new(&path);
+        assert_eq!(store.load().unwrap(), None);
+
+        let checkpoint = Checkpoint {
+            signature: Signature::new_unique(),
+            slot: 42,
+        };
+        store.save(&checkpoint).unwrap();
+        assert_eq!(store.load().unwrap(), Some(checkpoint));
+
+        fs::remove_file(path).unwrap();
+    }
+}
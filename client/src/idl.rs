@@ -0,0 +1,238 @@
+//! Reading on-chain IDLs and decoding events based on an [`Idl`].
+
+use crate::{ClientError, RpcTransport};
+use anchor_lang::idl::{IdlAccount, IdlDataHeader, ProgramMetadata, IDL_DATA_MAGIC};
+use anchor_lang::solana_program::pubkey::Pubkey;
+use anchor_lang::{AccountDeserialize, AnchorDeserialize};
+use anchor_lang_idl::types::Idl;
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use solana_account_decoder::{UiAccountEncoding, UiDataSliceConfig};
+use solana_client::rpc_config::RpcAccountInfoConfig;
+use std::io::{Read, Write};
+
+/// Size of the chunks the data of IDL accounts is read in.
+pub const IDL_READ_CHUNK_SIZE: usize = 16 * 1024;
+
+/// Serialize the IDL into the data that is stored in its IDL account, i.e. an [`IdlDataHeader`]
+/// followed by the compressed IDL.
+pub fn encode_idl_data(idl: &Idl) -> Result<Vec<u8>, ClientError> {
+    let json = serde_json::to_vec(idl).map_err(std::io::Error::from)?;
+    let mut data = IdlDataHeader::new(&idl.metadata.spec, &json).to_bytes();
+
+    let mut encoder = ZlibEncoder::new(&mut data, Compression::default());
+    encoder.write_all(&json)?;
+    encoder.finish()?;
+    Ok(data)
+}
+
+/// Read the data of an IDL account, i.e. the part after [`IdlAccount::DATA_OFFSET`], decompress
+/// it and verify its hash.
+///
+/// Returns the IDL as JSON. Legacy IDL data without an [`IdlDataHeader`] is returned without
+/// verification.
+///
+/// [`IdlAccount::DATA_OFFSET`]: anchor_lang::idl::IdlAccount::DATA_OFFSET
+pub fn decode_idl_data(mut reader: impl Read) -> Result<Vec<u8>, ClientError> {
+    let mut magic = [0; IDL_DATA_MAGIC.len()];
+    reader.read_exact(&mut magic)?;
+
+    let header = match magic == IDL_DATA_MAGIC {
+        true => {
+            let header = IdlDataHeader::deserialize_reader(&mut reader)?;
+            if header.version != anchor_lang::idl::IDL_DATA_VERSION {
+                return Err(ClientError::IdlParseError(format!(
+                    "Unsupported IDL data version: {}",
+                    header.version
+                )));
+            }
+            Some(header)
+        }
+        false => None,
+    };
+
+    let compressed = match header {
+        Some(_) => Box::new(reader) as Box<dyn Read>,
+        None => Box::new(magic.chain(reader)),
+    };
+    let mut idl = vec![];
+    ZlibDecoder::new(compressed).read_to_end(&mut idl)?;
+
+    if let Some(header) = header {
+        if !header.verify(&idl) {
+            return Err(ClientError::IdlParseError("IDL hash mismatch".into()));
+        }
+    }
+    Ok(idl)
+}
+
+/// Fetch the data of the given IDL account in chunks of [`IDL_READ_CHUNK_SIZE`] bytes.
+pub(crate) async fn fetch_idl_account_data(
+    transport: &dyn RpcTransport,
+    idl_address: &Pubkey,
+) -> Result<Vec<u8>, ClientError> {
+    let header =
+        fetch_account_data_slice(transport, idl_address, 0, IdlAccount::DATA_OFFSET).await?;
+    let idl_account = IdlAccount::try_deserialize(&mut &header[..])?;
+    fetch_account_data_chunks(
+        transport,
+        idl_address,
+        IdlAccount::DATA_OFFSET,
+        idl_account.data_len as usize,
+    )
+    .await
+}
+
+/// Fetch the data of the given program metadata account in chunks of [`IDL_READ_CHUNK_SIZE`]
+/// bytes.
+pub(crate) async fn fetch_metadata_account_data(
+    transport: &dyn RpcTransport,
+    metadata_address: &Pubkey,
+) -> Result<Vec<u8>, ClientError> {
+    let header =
+        fetch_account_data_slice(transport, metadata_address, 0, ProgramMetadata::DATA_OFFSET)
+            .await?;
+    let metadata = ProgramMetadata::try_deserialize(&mut &header[..])?;
+    fetch_account_data_chunks(
+        transport,
+        metadata_address,
+        ProgramMetadata::DATA_OFFSET,
+        metadata.data_len as usize,
+    )
+    .await
+}
+
+async fn fetch_account_data_chunks(
+    transport: &dyn RpcTransport,
+    address: &Pubkey,
+    start: usize,
+    len: usize,
+) -> Result<Vec<u8>, ClientError> {
+    let end = start + len;
+    let mut data = Vec::with_capacity(len);
+    for offset in (start..end).step_by(IDL_READ_CHUNK_SIZE) {
+        let length = IDL_READ_CHUNK_SIZE.min(end - offset);
+        data.extend(fetch_account_data_slice(transport, address, offset, length).await?);
+    }
+    Ok(data)
+}
+
+async fn fetch_account_data_slice(
+    transport: &dyn RpcTransport,
+    address: &Pubkey,
+    offset: usize,
+    length: usize,
+) -> Result<Vec<u8>, ClientError> {
+    let config = RpcAccountInfoConfig {
+        encoding: Some(UiAccountEncoding::Base64),
+        data_slice: Some(UiDataSliceConfig { offset, length }),
+        commitment: Some(transport.commitment()),
+        min_context_slot: None,
+    };
+    let account = transport
+        .get_account_with_config(address, config)
+        .await?
+        .ok_or(ClientError::AccountNotFound)?;
+    if account.data.len() != length {
+        return Err(ClientError::IdlParseError(
+            "IDL account is smaller than its data length".into(),
+        ));
+    }
+    Ok(account.data)
+}
+
+/// Event of a program decoded based on its IDL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdlEvent {
+    /// Name of the event as defined in the IDL.
+    pub name: String,
+    /// Serialized event without the discriminator.
+    ///
+    /// The layout of the data is described by the type of the same name in the IDL.
+    pub data: Vec<u8>,
+}
+
+/// Matches event data against the discriminators of the events of an IDL.
+pub(crate) struct IdlEventDecoder {
+    events: Vec<(Vec<u8>, String)>,
+}
+
+impl IdlEventDecoder {
+    pub fn new(idl: &Idl) -> Self {
+        let events = idl
+            .events
+            .iter()
+            .map(|ev| (ev.discriminator.clone(), ev.name.clone()))
+            .collect();
+        Self { events }
+    }
+
+    /// Returns `None` if the discriminator doesn't match any of the events.
+    pub fn decode(&self, data: &[u8]) -> Option<IdlEvent> {
+        self.events.iter().find_map(|(discriminator, name)| {
+            data.strip_prefix(discriminator.as_slice())
+                .map(|data| IdlEvent {
+                    name: name.clone(),
+                    data: data.to_vec(),
+                })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn idl() -> Idl {
+        serde_json::from_value(serde_json::json!({
+            "address": "11111111111111111111111111111111",
+            "metadata": { "name": "test", "version": "0.1.0", "spec": "0.1.0" },
+            "instructions": [],
+            "events": [
+                { "name": "Deposit", "discriminator": [1, 2] },
+                { "name": "Withdraw", "discriminator": [3, 4] }
+            ]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn encode_decode_idl_data() {
+        let idl = idl();
+        let data = encode_idl_data(&idl).unwrap();
+        assert!(data.starts_with(IDL_DATA_MAGIC));
+        assert_eq!(
+            decode_idl_data(&data[..]).unwrap(),
+            serde_json::to_vec(&idl).unwrap()
+        );
+
+        // Legacy data without a header
+        let (_, compressed) = IdlDataHeader::split(&data).unwrap();
+        assert_eq!(
+            decode_idl_data(compressed).unwrap(),
+            serde_json::to_vec(&idl).unwrap()
+        );
+
+        // Corrupted data
+        let mut header = IdlDataHeader::split(&data).unwrap().0.unwrap();
+        header.hash[0] ^= 1;
+        let corrupted = [header.to_bytes(), compressed.to_vec()].concat();
+        assert!(matches!(
+            decode_idl_data(&corrupted[..]),
+            Err(ClientError::IdlParseError(_))
+        ));
+    }
+
+    #[test]
+    fn decode_event() {
+        let decoder = IdlEventDecoder::new(&idl());
+
+        assert_eq!(
+            decoder.decode(&[3, 4, 5]),
+            Some(IdlEvent {
+                name: "Withdraw".into(),
+                data: vec![5],
+            })
+        );
+        assert_eq!(decoder.decode(&[5, 6, 7]), None);
+    }
+}
@@ -68,6 +68,17 @@
 //! useful for mocking RPC responses, e.g. via [`RpcClient::new_mock`].
 //!
 //! [`RpcClient::new_mock`]: https://docs.rs/solana-client/2.1.0/solana_client/rpc_client/struct.RpcClient.html#method.new_mock
+//!
+//! ## `idl`
+//!
+//! Allows fetching the on-chain IDL of a program via `Program::fetch_idl`, and subscribing to the
+//! events of a program based on its IDL, without generated types, via `Program::on_idl_events`.
+//!
+//! ## `program-test`
+//!
+//! Adds [`ProgramTestTransport`], an in-memory [`RpcTransport`] that processes transactions in a
+//! local bank via [`solana_program_test`]. Pass it to [`Client::program_with_transport`] to test
+//! client code without running a validator.
 
 use anchor_lang::solana_program::program_error::ProgramError;
 use anchor_lang::solana_program::pubkey::Pubkey;
@@ -83,7 +94,7 @@ use solana_client::rpc_config::{
 use solana_client::rpc_filter::{Memcmp, RpcFilterType};
 use solana_client::{
     client_error::ClientError as SolanaClientError,
-    nonblocking::pubsub_client::{PubsubClient, PubsubClientError},
+    nonblocking::pubsub_client::PubsubClientError,
     rpc_response::{Response as RpcResponse, RpcLogsResponse},
 };
 use solana_sdk::account::Account;
@@ -101,22 +112,40 @@ use std::vec::IntoIter;
 use thiserror::Error;
 use tokio::{
     runtime::Handle,
-    sync::{
-        mpsc::{unbounded_channel, UnboundedReceiver},
-        RwLock,
-    },
+    sync::mpsc::{unbounded_channel, UnboundedReceiver},
     task::JoinHandle,
 };
 
 pub use anchor_lang;
+#[cfg(feature = "idl")]
+pub use anchor_lang_idl;
+pub use backfill::{
+    BackfillConfig, Checkpoint, CheckpointStore, EventSource, FileCheckpointStore, RawEvent,
+};
 pub use cluster::Cluster;
+#[cfg(feature = "idl")]
+pub use idl::{decode_idl_data, encode_idl_data, IdlEvent, IDL_READ_CHUNK_SIZE};
 #[cfg(feature = "async")]
 pub use nonblocking::ThreadSafeSigner;
+#[cfg(feature = "program-test")]
+pub use program_test::{NativeProcessor, ProgramTestTransport};
 pub use solana_account_decoder;
 pub use solana_client;
+#[cfg(feature = "program-test")]
+pub use solana_program_test;
 pub use solana_sdk;
+pub use test_genesis::{TestGenesis, TestGenesisAccount, TestGenesisProgram, TEST_GENESIS_ENV};
+pub use transport::{LogsStream, RpcClientTransport, RpcTransport};
 
+mod backfill;
 mod cluster;
+#[cfg(feature = "idl")]
+mod idl;
+pub mod migrate;
+#[cfg(feature = "program-test")]
+mod program_test;
+mod test_genesis;
+mod transport;
 
 #[cfg(not(feature = "async"))]
 mod blocking;
@@ -126,7 +155,7 @@ mod nonblocking;
 const PROGRAM_LOG: &str = "Program log: ";
 const PROGRAM_DATA: &str = "Program data: ";
 
-type UnsubscribeFn = Box<dyn FnOnce() -> Pin<Box<dyn Future<Output = ()> + Send>> + Send>;
+pub type UnsubscribeFn = Box<dyn FnOnce() -> Pin<Box<dyn Future<Output = ()> + Send>> + Send>;
 /// Client defines the base configuration for building RPC clients to
 /// communicate with Anchor programs running on a Solana cluster. It's
 /// primary use is to build a `Program` client via the `program` method.
@@ -173,6 +202,22 @@ impl<C: Clone + Deref<Target = impl Signer>> Client<C> {
             rpc_client,
         )
     }
+
+    /// Create a program client that sends all of its requests through the given transport
+    /// instead of the cluster's RPC endpoints.
+    pub fn program_with_transport(
+        &self,
+        program_id: Pubkey,
+        transport: Arc<dyn RpcTransport>,
+    ) -> Result<Program<C>, ClientError> {
+        let cfg = Config {
+            cluster: self.cfg.cluster.clone(),
+            options: self.cfg.options,
+            payer: self.cfg.payer.clone(),
+        };
+
+        Program::new_with_transport(program_id, cfg, transport)
+    }
 }
 
 /// Auxiliary data structure to align the types of the Solana CLI utils with Anchor client.
@@ -235,10 +280,9 @@ impl EventUnsubscriber<'_> {
 pub struct Program<C> {
     program_id: Pubkey,
     cfg: Config<C>,
-    sub_client: Arc<RwLock<Option<PubsubClient>>>,
     #[cfg(not(feature = "async"))]
     rt: tokio::runtime::Runtime,
-    internal_rpc_client: AsyncRpcClient,
+    transport: Arc<dyn RpcTransport>,
 }
 
 impl<C: Deref<Target = impl Signer> + Clone> Program<C> {
@@ -250,9 +294,22 @@ impl<C: Deref<Target = impl Signer> + Clone> Program<C> {
         self.program_id
     }
 
-    #[cfg(feature = "mock")]
-    pub fn internal_rpc(&self) -> &AsyncRpcClient {
-        &self.internal_rpc_client
+    /// Returns the transport used to send requests to the cluster.
+    pub fn transport(&self) -> &dyn RpcTransport {
+        self.transport.as_ref()
+    }
+
+    fn default_transport(
+        cfg: &Config<C>,
+        #[cfg(feature = "mock")] rpc_client: AsyncRpcClient,
+    ) -> RpcClientTransport {
+        #[cfg(not(feature = "mock"))]
+        let rpc_client = AsyncRpcClient::new_with_commitment(
+            cfg.cluster.url().to_string(),
+            cfg.options.unwrap_or_default(),
+        );
+
+        RpcClientTransport::new(rpc_client, cfg.cluster.ws_url())
     }
 
     async fn account_internal<T: AccountDeserialize>(
@@ -260,10 +317,9 @@ impl<C: Deref<Target = impl Signer> + Clone> Program<C> {
         address: Pubkey,
     ) -> Result<T, ClientError> {
         let account = self
-            .internal_rpc_client
+            .transport
             .get_account_with_commitment(&address, CommitmentConfig::processed())
             .await?
-            .value
             .ok_or(ClientError::AccountNotFound)?;
         let mut data: &[u8] = &account.data;
         T::try_deserialize(&mut data).map_err(Into::into)
@@ -286,7 +342,7 @@ impl<C: Deref<Target = impl Signer> + Clone> Program<C> {
 
         Ok(ProgramAccountsIterator {
             inner: self
-                .internal_rpc_client
+                .transport
                 .get_program_accounts_with_config(&self.id(), config)
                 .await?
                 .into_iter()
@@ -296,21 +352,77 @@ impl<C: Deref<Target = impl Signer> + Clone> Program<C> {
         })
     }
 
-    async fn init_sub_client_if_needed(&self) -> Result<(), ClientError> {
-        let lock = &self.sub_client;
-        let mut client = lock.write().await;
+    async fn on_internal<T: anchor_lang::Event + anchor_lang::AnchorDeserialize>(
+        &self,
+        f: impl Fn(&EventContext, T) + Send + 'static,
+    ) -> Result<
+        (
+            JoinHandle<Result<(), ClientError>>,
+            UnboundedReceiver<UnsubscribeFn>,
+        ),
+        ClientError,
+    > {
+        // Closures, unlike function items, don't require `T: 'static`
+        self.subscribe_internal(|data| decode_event::<T>(data), f)
+            .await
+    }
 
-        if client.is_none() {
-            let sub_client = PubsubClient::new(self.cfg.cluster.ws_url()).await?;
-            *client = Some(sub_client);
-        }
+    async fn on_events_internal<E: anchor_lang::AnchorEvents>(
+        &self,
+        f: impl Fn(&EventContext, E) + Send + 'static,
+    ) -> Result<
+        (
+            JoinHandle<Result<(), ClientError>>,
+            UnboundedReceiver<UnsubscribeFn>,
+        ),
+        ClientError,
+    > {
+        self.subscribe_internal(|data| decode_events::<E>(data), f)
+            .await
+    }
 
-        Ok(())
+    #[cfg(feature = "idl")]
+    async fn on_idl_events_internal(
+        &self,
+        idl: &anchor_lang_idl::types::Idl,
+        f: impl Fn(&EventContext, IdlEvent) + Send + 'static,
+    ) -> Result<
+        (
+            JoinHandle<Result<(), ClientError>>,
+            UnboundedReceiver<UnsubscribeFn>,
+        ),
+        ClientError,
+    > {
+        let decoder = idl::IdlEventDecoder::new(idl);
+        self.subscribe_internal(move |data| Ok(decoder.decode(data)), f)
+            .await
     }
 
-    async fn on_internal<T: anchor_lang::Event + anchor_lang::AnchorDeserialize>(
+    #[cfg(feature = "idl")]
+    async fn fetch_idl_internal(&self) -> Result<anchor_lang_idl::types::Idl, ClientError> {
+        let idl_address = anchor_lang::idl::IdlAccount::address(&self.program_id);
+        let data = match idl::fetch_idl_account_data(self.transport.as_ref(), &idl_address).await {
+            // Fall back to the canonical IDL metadata of the program
+            Err(ClientError::AccountNotFound) => {
+                let metadata_address = anchor_lang::idl::ProgramMetadata::address(
+                    &self.program_id,
+                    anchor_lang::idl::metadata_key::IDL,
+                    None,
+                );
+                idl::fetch_metadata_account_data(self.transport.as_ref(), &metadata_address).await?
+            }
+            data => data?,
+        };
+        let idl = decode_idl_data(&data[..])?;
+        serde_json::from_slice(&idl).map_err(|e| ClientError::IdlParseError(e.to_string()))
+    }
+
+    /// Subscribe to the logs of the program with a single subscription, and call `f` with every
+    /// event that `decode` recognizes.
+    async fn subscribe_internal<E>(
         &self,
-        f: impl Fn(&EventContext, T) + Send + 'static,
+        decode: impl Fn(&[u8]) -> Result<Option<E>, ClientError> + Send + 'static,
+        f: impl Fn(&EventContext, E) + Send + 'static,
     ) -> Result<
         (
             JoinHandle<Result<(), ClientError>>,
@@ -318,7 +430,6 @@ impl<C: Deref<Target = impl Signer> + Clone> Program<C> {
         ),
         ClientError,
     > {
-        self.init_sub_client_if_needed().await?;
         let (tx, rx) = unbounded_channel::<_>();
         let config = RpcTransactionLogsConfig {
             commitment: self.cfg.options,
@@ -326,29 +437,26 @@ impl<C: Deref<Target = impl Signer> + Clone> Program<C> {
         let program_id_str = self.program_id.to_string();
         let filter = RpcTransactionLogsFilter::Mentions(vec![program_id_str.clone()]);
 
-        let lock = Arc::clone(&self.sub_client);
+        let transport = Arc::clone(&self.transport);
 
         let handle = tokio::spawn(async move {
-            if let Some(ref client) = *lock.read().await {
-                let (mut notifications, unsubscribe) =
-                    client.logs_subscribe(filter, config).await?;
-
-                tx.send(unsubscribe).map_err(|e| {
-                    ClientError::SolanaClientPubsubError(PubsubClientError::RequestFailed {
-                        message: "Unsubscribe failed".to_string(),
-                        reason: e.to_string(),
-                    })
-                })?;
-
-                while let Some(logs) = notifications.next().await {
-                    let ctx = EventContext {
-                        signature: logs.value.signature.parse().unwrap(),
-                        slot: logs.context.slot,
-                    };
-                    let events = parse_logs_response(logs, &program_id_str)?;
-                    for e in events {
-                        f(&ctx, e);
-                    }
+            let (mut notifications, unsubscribe) = transport.logs_subscribe(filter, config).await?;
+
+            tx.send(unsubscribe).map_err(|e| {
+                ClientError::SolanaClientPubsubError(PubsubClientError::RequestFailed {
+                    message: "Unsubscribe failed".to_string(),
+                    reason: e.to_string(),
+                })
+            })?;
+
+            while let Some(logs) = notifications.next().await {
+                let ctx = EventContext {
+                    signature: logs.value.signature.parse().unwrap(),
+                    slot: logs.context.slot,
+                };
+                let events = parse_logs_response(logs, &program_id_str, &decode)?;
+                for e in events {
+                    f(&ctx, e);
                 }
             }
             Ok::<(), ClientError>(())
@@ -356,6 +464,56 @@ impl<C: Deref<Target = impl Signer> + Clone> Program<C> {
 
         Ok((handle, rx))
     }
+
+    async fn backfill_events_internal(
+        &self,
+        mut config: BackfillConfig,
+        checkpoint_store: Option<&dyn CheckpointStore>,
+        mut f: impl FnMut(&EventContext, RawEvent),
+    ) -> Result<Option<Checkpoint>, ClientError> {
+        if let Some(store) = checkpoint_store {
+            if config.until.is_none() {
+                config.until = store.load()?.map(|checkpoint| checkpoint.signature);
+            }
+        }
+
+        let transport = self.transport.as_ref();
+        let mut pages = backfill::SignaturePages::new(transport, &self.program_id, &config).await?;
+
+        let mut checkpoint = None;
+        while let Some(page) = pages.next().await? {
+            for status in page {
+                let signature = backfill::parse_signature(&status.signature)?;
+                let events = backfill::fetch_transaction_events(
+                    transport,
+                    &self.program_id,
+                    &signature,
+                    config.commitment,
+                )
+                .await?;
+
+                let ctx = EventContext {
+                    signature,
+                    slot: status.slot,
+                };
+                for event in events {
+                    f(&ctx, event);
+                }
+
+                // Only save the checkpoint after all events of the transaction have been handled
+                let new_checkpoint = Checkpoint {
+                    signature,
+                    slot: status.slot,
+                };
+                if let Some(store) = checkpoint_store {
+                    store.save(&new_checkpoint)?;
+                }
+                checkpoint = Some(new_checkpoint);
+            }
+        }
+
+        Ok(checkpoint)
+    }
 }
 
 /// Iterator with items of type (Pubkey, T). Used to lazily deserialize account structs.
@@ -379,6 +537,21 @@ pub fn handle_program_log<T: anchor_lang::Event + anchor_lang::AnchorDeserialize
     self_program_str: &str,
     l: &str,
 ) -> Result<(Option<T>, Option<String>, bool), ClientError> {
+    let (log_bytes, program, did_pop) = handle_program_log_data(self_program_str, l);
+    let event = log_bytes
+        .map(|log_bytes| decode_event::<T>(&log_bytes))
+        .transpose()?
+        .flatten();
+
+    Ok((event, program, did_pop))
+}
+
+/// Same as [`handle_program_log`], but returns the raw (base64 decoded) event data instead of a
+/// deserialized event.
+pub fn handle_program_log_data(
+    self_program_str: &str,
+    l: &str,
+) -> (Option<Vec<u8>>, Option<String>, bool) {
     use anchor_lang::__private::base64;
     use base64::engine::general_purpose::STANDARD;
     use base64::Engine;
@@ -388,32 +561,43 @@ pub fn handle_program_log<T: anchor_lang::Event + anchor_lang::AnchorDeserialize
         .strip_prefix(PROGRAM_LOG)
         .or_else(|| l.strip_prefix(PROGRAM_DATA))
     {
-        let log_bytes = match STANDARD.decode(log) {
-            Ok(log_bytes) => log_bytes,
+        match STANDARD.decode(log) {
+            Ok(log_bytes) => (Some(log_bytes), None, false),
             _ => {
                 #[cfg(feature = "debug")]
                 println!("Could not base64 decode log: {}", log);
-                return Ok((None, None, false));
+                (None, None, false)
             }
-        };
-
-        let event = log_bytes
-            .starts_with(T::DISCRIMINATOR)
-            .then(|| {
-                let mut data = &log_bytes[T::DISCRIMINATOR.len()..];
-                T::deserialize(&mut data).map_err(|e| ClientError::LogParseError(e.to_string()))
-            })
-            .transpose()?;
-
-        Ok((event, None, false))
+        }
     }
     // System log.
     else {
         let (program, did_pop) = handle_system_log(self_program_str, l);
-        Ok((None, program, did_pop))
+        (None, program, did_pop)
     }
 }
 
+/// Deserialize the event data (discriminator followed by the serialized event).
+///
+/// Returns `None` if the discriminator doesn't match `T`.
+pub fn decode_event<T: anchor_lang::Event + anchor_lang::AnchorDeserialize>(
+    data: &[u8],
+) -> Result<Option<T>, ClientError> {
+    data.starts_with(T::DISCRIMINATOR)
+        .then(|| {
+            let mut data = &data[T::DISCRIMINATOR.len()..];
+            T::deserialize(&mut data).map_err(|e| ClientError::LogParseError(e.to_string()))
+        })
+        .transpose()
+}
+
+/// Deserialize the event data as any of the events of `E`.
+///
+/// Returns `None` if the discriminator doesn't match any of the events.
+pub fn decode_events<E: anchor_lang::AnchorEvents>(data: &[u8]) -> Result<Option<E>, ClientError> {
+    E::try_from_event_data(data).map_err(|e| ClientError::LogParseError(e.to_string()))
+}
+
 pub fn handle_system_log(this_program_str: &str, log: &str) -> (Option<String>, bool) {
     if log.starts_with(&format!("Program {this_program_str} log:")) {
         (Some(this_program_str.to_string()), false)
@@ -490,6 +674,10 @@ pub enum ClientError {
     SolanaClientPubsubError(#[from] PubsubClientError),
     #[error("Unable to parse log: {0}")]
     LogParseError(String),
+    #[error("Unable to parse transaction: {0}")]
+    TransactionParseError(String),
+    #[error("Unable to parse IDL: {0}")]
+    IdlParseError(String),
     #[error(transparent)]
     IOError(#[from] std::io::Error),
 }
@@ -517,7 +705,7 @@ pub struct RequestBuilder<'a, C, S: 'a> {
     signers: Vec<S>,
     #[cfg(not(feature = "async"))]
     handle: &'a Handle,
-    internal_rpc_client: &'a AsyncRpcClient,
+    transport: &'a dyn RpcTransport,
     _phantom: PhantomData<&'a ()>,
 }
 
@@ -635,57 +823,67 @@ impl<C: Deref<Target = impl Signer> + Clone, S: AsSigner> RequestBuilder<'_, C,
     }
 
     async fn signed_transaction_internal(&self) -> Result<Transaction, ClientError> {
-        let latest_hash = self.internal_rpc_client.get_latest_blockhash().await?;
+        let latest_hash = self.transport.get_latest_blockhash().await?;
 
         let tx = self.signed_transaction_with_blockhash(latest_hash)?;
         Ok(tx)
     }
 
     async fn send_internal(&self) -> Result<Signature, ClientError> {
-        let latest_hash = self.internal_rpc_client.get_latest_blockhash().await?;
+        let latest_hash = self.transport.get_latest_blockhash().await?;
         let tx = self.signed_transaction_with_blockhash(latest_hash)?;
 
-        self.internal_rpc_client
-            .send_and_confirm_transaction(&tx)
-            .await
-            .map_err(Into::into)
+        self.transport.send_and_confirm_transaction(&tx).await
     }
 
     async fn send_with_spinner_and_config_internal(
         &self,
         config: RpcSendTransactionConfig,
     ) -> Result<Signature, ClientError> {
-        let latest_hash = self.internal_rpc_client.get_latest_blockhash().await?;
+        let latest_hash = self.transport.get_latest_blockhash().await?;
         let tx = self.signed_transaction_with_blockhash(latest_hash)?;
 
-        self.internal_rpc_client
+        self.transport
             .send_and_confirm_transaction_with_spinner_and_config(
                 &tx,
-                self.internal_rpc_client.commitment(),
+                self.transport.commitment(),
                 config,
             )
             .await
-            .map_err(Into::into)
     }
 }
 
-fn parse_logs_response<T: anchor_lang::Event + anchor_lang::AnchorDeserialize>(
+fn parse_logs_response<T>(
     logs: RpcResponse<RpcLogsResponse>,
     program_id_str: &str,
+    decode: impl Fn(&[u8]) -> Result<Option<T>, ClientError>,
 ) -> Result<Vec<T>, ClientError> {
-    let mut logs = &logs.value.logs[..];
-    let mut events: Vec<T> = Vec::new();
+    parse_logs_event_data(&logs.value.logs, program_id_str)
+        .iter()
+        .filter_map(|(_, data)| decode(data).transpose())
+        .collect()
+}
+
+/// Parse the raw data of all events emitted by the given program via logs, along with the index
+/// of the log of each event.
+pub(crate) fn parse_logs_event_data(
+    logs: &[String],
+    program_id_str: &str,
+) -> Vec<(usize, Vec<u8>)> {
+    let mut logs = logs;
+    let mut events = Vec::new();
     if !logs.is_empty() {
         if let Ok(mut execution) = Execution::new(&mut logs) {
-            // Create a new peekable iterator so that we can peek at the next log whilst iterating
-            let mut logs_iter = logs.iter().peekable();
+            // Create a new peekable iterator so that we can peek at the next log whilst iterating.
+            // The first log was consumed by `Execution::new`.
+            let mut logs_iter = logs.iter().enumerate().map(|(i, l)| (i + 1, l)).peekable();
             let regex = Regex::new(r"^Program (.*) invoke.*$").unwrap();
 
-            while let Some(l) = logs_iter.next() {
+            while let Some((i, l)) = logs_iter.next() {
                 // Parse the log.
                 let (event, new_program, did_pop) = {
                     if program_id_str == execution.program() {
-                        handle_program_log(program_id_str, l)?
+                        handle_program_log_data(program_id_str, l)
                     } else {
                         let (program, did_pop) = handle_system_log(program_id_str, l);
                         (None, program, did_pop)
@@ -693,7 +891,7 @@ fn parse_logs_response<T: anchor_lang::Event + anchor_lang::AnchorDeserialize>(
                 };
                 // Emit the event.
                 if let Some(e) = event {
-                    events.push(e);
+                    events.push((i, e));
                 }
                 // Switch program context on CPI.
                 if let Some(new_program) = new_program {
@@ -710,7 +908,7 @@ fn parse_logs_response<T: anchor_lang::Event + anchor_lang::AnchorDeserialize>(
                     // We need to ensure that the `Execution` instance is updated with
                     // the next program ID, or else `execution.program()` will cause
                     // a panic during the next iteration.
-                    if let Some(&next_log) = logs_iter.peek() {
+                    if let Some(&(_, next_log)) = logs_iter.peek() {
                         if next_log.ends_with("invoke [1]") {
                             let next_instruction =
                                 regex.captures(next_log).unwrap().get(1).unwrap().as_str();
@@ -724,7 +922,7 @@ fn parse_logs_response<T: anchor_lang::Event + anchor_lang::AnchorDeserialize>(
             }
         }
     }
-    Ok(events)
+    events
 }
 
 #[cfg(test)]
@@ -734,7 +932,7 @@ mod tests {
     // Creating a mock struct that implements `anchor_lang::events`
     // for type inference in `test_logs`
     use anchor_lang::prelude::*;
-    #[derive(Debug, Clone, Copy)]
+    #[derive(Debug, Clone, Copy, PartialEq)]
     #[event]
     pub struct MockEvent {}
 
@@ -863,9 +1061,60 @@ mod tests {
                 },
             },
             program_id_str,
+            decode_event::<MockEvent>,
         )
         .unwrap();
 
         Ok(())
     }
+
+    #[test]
+    fn test_parse_logs_response_events() {
+        #[event]
+        #[derive(Debug, PartialEq)]
+        pub struct OtherMockEvent {
+            value: u8,
+        }
+
+        #[derive(Debug, PartialEq, AnchorEvents)]
+        pub enum MockEvents {
+            MockEvent(MockEvent),
+            OtherMockEvent(OtherMockEvent),
+        }
+
+        let program_id_str = "VeryCoolProgram";
+        let data = |data: Vec<u8>| {
+            use anchor_lang::__private::base64::{engine::general_purpose::STANDARD, Engine};
+            format!("Program data: {}", STANDARD.encode(data))
+        };
+        let logs = vec![
+            format!("Program {program_id_str} invoke [1]"),
+            data(anchor_lang::Event::data(&MockEvent {})),
+            // Unknown events are skipped
+            data(vec![0; 8]),
+            data(anchor_lang::Event::data(&OtherMockEvent { value: 1 })),
+            format!("Program {program_id_str} success"),
+        ];
+
+        let events = parse_logs_response(
+            RpcResponse {
+                context: RpcResponseContext::new(0),
+                value: RpcLogsResponse {
+                    signature: "".to_string(),
+                    err: None,
+                    logs,
+                },
+            },
+            program_id_str,
+            decode_events::<MockEvents>,
+        )
+        .unwrap();
+        assert_eq!(
+            events,
+            vec![
+                MockEvents::MockEvent(MockEvent {}),
+                MockEvents::OtherMockEvent(OtherMockEvent { value: 1 }),
+            ]
+        );
+    }
 }
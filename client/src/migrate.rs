@@ -0,0 +1,224 @@
+//! Rust migrations run by `anchor migrate`.
+//!
+//! A workspace with a `migrations/Cargo.toml` binary crate has it run by `anchor migrate` instead
+//! of the `migrations/deploy.ts` script. The binary declares its migrations and passes them to
+//! [`run`]:
+//!
+//! ```ignore
+//! use anchor_client::migrate::{run, Migration, MigrationContext};
+//!
+//! fn initialize(ctx: &MigrationContext) -> anyhow::Result<()> {
+//!     let program = ctx.client.program(ctx.program_id("my_program")?)?;
+//!     // Send the transactions of the migration
+//!     Ok(())
+//! }
+//!
+//! fn main() -> anyhow::Result<()> {
+//!     run(&[Migration::new(1, "initialize", initialize)])
+//! }
+//! ```
+//!
+//! Migrations are applied in the order of their versions, and `anchor migrate` records the
+//! migrations that were applied on each cluster so that every migration only runs once.
+
+use crate::{Client, Cluster};
+use anyhow::{anyhow, Context, Result};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{read_keypair_file, Keypair};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Environment variable of the cluster url.
+pub const PROVIDER_URL_ENV: &str = "ANCHOR_PROVIDER_URL";
+/// Environment variable of the wallet keypair path.
+pub const WALLET_ENV: &str = "ANCHOR_WALLET";
+/// Environment variable of the workspace programs, as comma-separated `name=program_id` pairs.
+pub const PROGRAMS_ENV: &str = "ANCHOR_MIGRATION_PROGRAMS";
+/// Environment variable of the path of the file with the applied migration versions, one per
+/// line. The versions of newly applied migrations are appended to it.
+pub const APPLIED_ENV: &str = "ANCHOR_MIGRATIONS_APPLIED";
+
+/// Function that applies a migration.
+pub type MigrationFn = fn(&MigrationContext) -> Result<()>;
+
+/// A versioned migration.
+#[derive(Clone, Copy)]
+pub struct Migration {
+    /// Version of the migration, which determines the order migrations are applied in.
+    pub version: u64,
+    pub name: &'static str,
+    pub run: MigrationFn,
+}
+
+impl Migration {
+    pub const fn new(version: u64, name: &'static str, run: MigrationFn) -> Self {
+        Self { version, name, run }
+    }
+}
+
+/// Context that the migrations are applied with.
+pub struct MigrationContext {
+    pub cluster: Cluster,
+    /// Wallet of the workspace, which pays for the migrations.
+    pub payer: Arc<Keypair>,
+    /// Client of the cluster, with the wallet as the payer.
+    pub client: Client<Arc<Keypair>>,
+    /// Ids of the workspace programs by name.
+    pub programs: BTreeMap<String, Pubkey>,
+}
+
+impl MigrationContext {
+    /// Create the context from the environment that `anchor migrate` sets.
+    pub fn from_env() -> Result<Self> {
+        let url = env(PROVIDER_URL_ENV)?;
+        let cluster = Cluster::from_str(&url)?;
+        let wallet = env(WALLET_ENV)?;
+        let payer = read_keypair_file(&wallet)
+            .map_err(|e| anyhow!("Failed to read the wallet {wallet}: {e}"))?;
+        let payer = Arc::new(payer);
+        let programs = parse_programs(&std::env::var(PROGRAMS_ENV).unwrap_or_default())?;
+
+        Ok(Self {
+            client: Client::new_with_options(
+                cluster.clone(),
+                payer.clone(),
+                CommitmentConfig::confirmed(),
+            ),
+            cluster,
+            payer,
+            programs,
+        })
+    }
+
+    /// Id of the workspace program.
+    pub fn program_id(&self, name: &str) -> Result<Pubkey> {
+        self.programs
+            .get(name)
+            .copied()
+            .ok_or_else(|| anyhow!("Program {name} not found in the workspace"))
+    }
+}
+
+/// Apply the migrations that weren't applied yet, in the order of their versions.
+///
+/// This is the entrypoint of the `migrations` binary, which is run by `anchor migrate`.
+pub fn run(migrations: &[Migration]) -> Result<()> {
+    let ctx = MigrationContext::from_env()?;
+    let applied_path = PathBuf::from(env(APPLIED_ENV)?);
+    let applied = read_applied(&applied_path)?;
+
+    for migration in pending(migrations, &applied)? {
+        println!(
+            "Applying migration {} {}...",
+            migration.version, migration.name
+        );
+        (migration.run)(&ctx).with_context(|| {
+            format!("Migration {} {} failed", migration.version, migration.name)
+        })?;
+
+        // Record the migration right away, so it doesn't run again if a later one fails
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&applied_path)?;
+        writeln!(file, "{}", migration.version)?;
+    }
+
+    Ok(())
+}
+
+/// The migrations that weren't applied yet, in the order of their versions.
+fn pending<'a>(migrations: &'a [Migration], applied: &BTreeSet<u64>) -> Result<Vec<&'a Migration>> {
+    let mut pending = migrations
+        .iter()
+        .filter(|migration| !applied.contains(&migration.version))
+        .collect::<Vec<_>>();
+    pending.sort_by_key(|migration| migration.version);
+
+    let mut versions = migrations
+        .iter()
+        .map(|migration| migration.version)
+        .collect::<Vec<_>>();
+    versions.sort_unstable();
+    if let Some(version) = versions.windows(2).find(|w| w[0] == w[1]).map(|w| w[0]) {
+        return Err(anyhow!("Duplicate migration version: {version}"));
+    }
+
+    Ok(pending)
+}
+
+fn read_applied(path: &Path) -> Result<BTreeSet<u64>> {
+    let applied = match fs::read_to_string(path) {
+        Ok(applied) => applied,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeSet::new()),
+        Err(e) => return Err(e.into()),
+    };
+    applied
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            line.trim()
+                .parse()
+                .map_err(|_| anyhow!("Invalid migration version: {line}"))
+        })
+        .collect()
+}
+
+fn parse_programs(programs: &str) -> Result<BTreeMap<String, Pubkey>> {
+    programs
+        .split(',')
+        .filter(|program| !program.is_empty())
+        .map(|program| {
+            let (name, id) = program
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Invalid program: {program}"))?;
+            Ok((name.to_owned(), Pubkey::from_str(id)?))
+        })
+        .collect()
+}
+
+fn env(key: &str) -> Result<String> {
+    std::env::var(key)
+        .map_err(|_| anyhow!("{key} is not set, run the migrations with `anchor migrate`"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn noop(_: &MigrationContext) -> Result<()> {
+        Ok(())
+    }
+
+    #[test]
+    fn test_pending() {
+        let migrations = [
+            Migration::new(3, "c", noop),
+            Migration::new(1, "a", noop),
+            Migration::new(2, "b", noop),
+        ];
+        let pending = pending(&migrations, &BTreeSet::from([2])).unwrap();
+        assert_eq!(
+            pending.iter().map(|m| m.name).collect::<Vec<_>>(),
+            ["a", "c"]
+        );
+
+        let migrations = [Migration::new(1, "a", noop), Migration::new(1, "b", noop)];
+        assert!(super::pending(&migrations, &BTreeSet::new()).is_err());
+    }
+
+    #[test]
+    fn test_parse_programs() {
+        let id = Pubkey::new_unique();
+        let programs = parse_programs(&format!("foo={id},bar={id}")).unwrap();
+        assert_eq!(programs.len(), 2);
+        assert_eq!(programs["foo"], id);
+        assert!(parse_programs("").unwrap().is_empty());
+        assert!(parse_programs("foo").is_err());
+    }
+}
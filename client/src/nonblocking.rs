@@ -1,6 +1,8 @@
+#[cfg(feature = "idl")]
+use crate::IdlEvent;
 use crate::{
-    AsSigner, ClientError, Config, EventContext, EventUnsubscriber, Program,
-    ProgramAccountsIterator, RequestBuilder,
+    AsSigner, BackfillConfig, Checkpoint, CheckpointStore, ClientError, Config, EventContext,
+    EventUnsubscriber, Program, ProgramAccountsIterator, RawEvent, RequestBuilder, RpcTransport,
 };
 use anchor_lang::{prelude::Pubkey, AccountDeserialize, Discriminator};
 use solana_client::nonblocking::rpc_client::RpcClient as AsyncRpcClient;
@@ -10,7 +12,6 @@ use solana_sdk::{
     transaction::Transaction,
 };
 use std::{marker::PhantomData, ops::Deref, sync::Arc};
-use tokio::sync::RwLock;
 
 impl<'a> EventUnsubscriber<'a> {
     /// Unsubscribe gracefully.
@@ -41,18 +42,25 @@ impl<C: Deref<Target = impl Signer> + Clone> Program<C> {
         cfg: Config<C>,
         #[cfg(feature = "mock")] rpc_client: AsyncRpcClient,
     ) -> Result<Self, ClientError> {
-        #[cfg(not(feature = "mock"))]
-        let rpc_client = {
-            let comm_config = cfg.options.unwrap_or_default();
-            let cluster_url = cfg.cluster.url().to_string();
-            AsyncRpcClient::new_with_commitment(cluster_url.clone(), comm_config)
-        };
+        let transport = Self::default_transport(
+            &cfg,
+            #[cfg(feature = "mock")]
+            rpc_client,
+        );
 
+        Self::new_with_transport(program_id, cfg, Arc::new(transport))
+    }
+
+    /// Create a program client that uses the given transport for all requests.
+    pub fn new_with_transport(
+        program_id: Pubkey,
+        cfg: Config<C>,
+        transport: Arc<dyn RpcTransport>,
+    ) -> Result<Self, ClientError> {
         Ok(Self {
             program_id,
             cfg,
-            sub_client: Arc::new(RwLock::new(None)),
-            internal_rpc_client: rpc_client,
+            transport,
         })
     }
 
@@ -76,7 +84,7 @@ impl<C: Deref<Target = impl Signer> + Clone> Program<C> {
             self.cfg.cluster.url(),
             self.cfg.payer.clone(),
             self.cfg.options,
-            &self.internal_rpc_client,
+            self.transport.as_ref(),
         )
     }
 
@@ -117,6 +125,79 @@ impl<C: Deref<Target = impl Signer> + Clone> Program<C> {
             _lifetime_marker: PhantomData,
         })
     }
+
+    /// Subscribe to all events of the program with a single log subscription.
+    ///
+    /// `E` is usually the `utils::Event` enum generated by `declare_program!`, or an enum that
+    /// derives [`AnchorEvents`](anchor_lang::AnchorEvents). Events that are not part of `E` are
+    /// skipped.
+    ///
+    /// Returns an [`EventUnsubscriber`] to unsubscribe and close connection gracefully.
+    pub async fn on_events<E: anchor_lang::AnchorEvents>(
+        &self,
+        f: impl Fn(&EventContext, E) + Send + 'static,
+    ) -> Result<EventUnsubscriber<'_>, ClientError> {
+        let (handle, rx) = self.on_events_internal(f).await?;
+
+        Ok(EventUnsubscriber {
+            handle,
+            rx,
+            _lifetime_marker: PhantomData,
+        })
+    }
+
+    /// Fetch the IDL of the program from its IDL account, or from its canonical IDL metadata if
+    /// it has no IDL account.
+    ///
+    /// The IDL account is read in chunks, and the integrity of the IDL is verified based on the
+    /// hash stored in the account.
+    #[cfg(feature = "idl")]
+    pub async fn fetch_idl(&self) -> Result<anchor_lang_idl::types::Idl, ClientError> {
+        self.fetch_idl_internal().await
+    }
+
+    /// Subscribe to all events of the program that are defined in its IDL with a single log
+    /// subscription.
+    ///
+    /// Returns an [`EventUnsubscriber`] to unsubscribe and close connection gracefully.
+    #[cfg(feature = "idl")]
+    pub async fn on_idl_events(
+        &self,
+        idl: &anchor_lang_idl::types::Idl,
+        f: impl Fn(&EventContext, IdlEvent) + Send + 'static,
+    ) -> Result<EventUnsubscriber<'_>, ClientError> {
+        let (handle, rx) = self.on_idl_events_internal(idl, f).await?;
+
+        Ok(EventUnsubscriber {
+            handle,
+            rx,
+            _lifetime_marker: PhantomData,
+        })
+    }
+
+    /// Backfill the events of the program from its transaction history.
+    ///
+    /// Transactions are processed oldest first, and `f` is called for every event that was
+    /// emitted in them, in the order the events were emitted. Returns the checkpoint of the last
+    /// processed transaction, or `None` if there were no transactions in the given range.
+    pub async fn backfill_events(
+        &self,
+        config: BackfillConfig,
+        f: impl FnMut(&EventContext, RawEvent),
+    ) -> Result<Option<Checkpoint>, ClientError> {
+        self.backfill_events_internal(config, None, f).await
+    }
+
+    /// Same as [`Self::backfill_events`], but resumes from the checkpoint saved in `store` (unless
+    /// [`BackfillConfig::until`] is set) and saves a new checkpoint after each transaction.
+    pub async fn backfill_events_with_checkpoint(
+        &self,
+        config: BackfillConfig,
+        store: &dyn CheckpointStore,
+        f: impl FnMut(&EventContext, RawEvent),
+    ) -> Result<Option<Checkpoint>, ClientError> {
+        self.backfill_events_internal(config, Some(store), f).await
+    }
 }
 
 impl<'a, C: Deref<Target = impl Signer> + Clone> RequestBuilder<'a, C, Arc<dyn ThreadSafeSigner>> {
@@ -125,7 +206,7 @@ impl<'a, C: Deref<Target = impl Signer> + Clone> RequestBuilder<'a, C, Arc<dyn T
         cluster: &str,
         payer: C,
         options: Option<CommitmentConfig>,
-        rpc_client: &'a AsyncRpcClient,
+        transport: &'a dyn RpcTransport,
     ) -> Self {
         Self {
             program_id,
@@ -136,7 +217,7 @@ impl<'a, C: Deref<Target = impl Signer> + Clone> RequestBuilder<'a, C, Arc<dyn T
             instructions: Vec::new(),
             instruction_data: None,
             signers: Vec::new(),
-            internal_rpc_client: rpc_client,
+            transport,
             _phantom: PhantomData,
         }
     }
@@ -0,0 +1,1229 @@
+//! In-memory [`RpcTransport`] backed by [`solana_program_test`].
+//!
+//! [`ProgramTestTransport`] processes transactions in a local bank, which makes it possible to
+//! test client code without running a validator:
+//!
+//! ```ignore
+//! let mut program_test = ProgramTest::default();
+//! program_test.add_program("my_program", my_program::ID, None);
+//! let transport = Arc::new(ProgramTestTransport::start(program_test)?);
+//!
+//! let client = Client::new(Cluster::Localnet, Rc::new(transport.payer()));
+//! let program = client.program_with_transport(my_program::ID, transport.clone())?;
+//! ```
+//!
+//! [`ProgramTestTransport::start`] starts the bank on a runtime owned by the transport, so it
+//! can't be called from within a Tokio runtime. Async tests, e.g. `#[tokio::test]`, use
+//! [`ProgramTestTransport::start_async`] instead.
+//!
+//! When the tests are run with `anchor test --runtime svm`, the bank can instead be started from
+//! the [`TestGenesis`] of the workspace, i.e. its programs and the `[test.validator]` accounts:
+//!
+//! ```ignore
+//! let transport = Arc::new(TestGenesis::from_env()?.start()?);
+//! ```
+//!
+//! To measure the coverage of the programs with `anchor test --coverage`, the programs need to be
+//! given a [`NativeProcessor`], which runs them natively instead of from their binaries:
+//!
+//! ```ignore
+//! let transport = Arc::new(
+//!     TestGenesis::from_env()?
+//!         .start_with_processors(&[(my_program::ID, native_processor!(my_program::entry))])?,
+//! );
+//! ```
+
+use crate::{
+    test_genesis::invalid_data,
+    transport::{LogsStream, RpcTransport},
+    ClientError, TestGenesis, UnsubscribeFn,
+};
+use anchor_lang::__private::base64::{engine::general_purpose::STANDARD, Engine};
+use anchor_lang::solana_program::pubkey::Pubkey;
+use async_trait::async_trait;
+use futures::channel::mpsc::{unbounded, UnboundedSender};
+use solana_client::{
+    client_error::{ClientError as SolanaClientError, ClientErrorKind},
+    rpc_client::GetConfirmedSignaturesForAddress2Config,
+    rpc_config::{
+        RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcSendTransactionConfig,
+        RpcTransactionConfig, RpcTransactionLogsConfig, RpcTransactionLogsFilter,
+    },
+    rpc_custom_error::JSON_RPC_SERVER_ERROR_SEND_TRANSACTION_PREFLIGHT_FAILURE,
+    rpc_filter::RpcFilterType,
+    rpc_request::{RpcError, RpcRequest, RpcResponseErrorData},
+    rpc_response::{
+        Response as RpcResponse, RpcConfirmedTransactionStatusWithSignature, RpcKeyedAccount,
+        RpcLogsResponse, RpcResponseContext, RpcSimulateTransactionResult,
+    },
+};
+use solana_program_test::{
+    BanksClient, BanksClientError, EbpfVm, InvokeContext, ProgramTest, ProgramTestContext,
+};
+use solana_sdk::{
+    account::{Account, AccountSharedData},
+    bpf_loader,
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    clock::Clock,
+    commitment_config::{CommitmentConfig, CommitmentLevel},
+    hash::Hash,
+    inner_instruction::InnerInstructions,
+    native_token::LAMPORTS_PER_SOL,
+    rent::Rent,
+    signature::{Keypair, Signature},
+    transaction::{Transaction, TransactionError},
+};
+use solana_transaction_status_client_types::{
+    option_serializer::OptionSerializer, EncodedConfirmedTransactionWithStatusMeta,
+    EncodedTransaction, EncodedTransactionWithStatusMeta, TransactionBinaryEncoding,
+    TransactionConfirmationStatus, UiCompiledInstruction, UiInnerInstructions, UiInstruction,
+    UiTransactionStatusMeta,
+};
+use std::{
+    collections::BTreeSet,
+    fs,
+    sync::{Arc, Mutex, MutexGuard},
+};
+use tokio::runtime::{Builder, Runtime};
+
+/// [`RpcTransport`] implementation that executes transactions in an in-memory bank.
+///
+/// `getProgramAccounts` only considers accounts the transport knows about, i.e. accounts that
+/// were set via [`ProgramTestTransport::set_account`] or referenced by a processed transaction.
+pub struct ProgramTestTransport {
+    banks_client: BanksClient,
+    context: Mutex<ProgramTestContext>,
+    state: Arc<Mutex<State>>,
+    runtime: Option<Runtime>,
+}
+
+#[derive(Default)]
+struct State {
+    known_accounts: BTreeSet<Pubkey>,
+    subscribers: Vec<LogsSubscriber>,
+    next_subscriber_id: u64,
+    transactions: Vec<ProcessedTransaction>,
+}
+
+struct ProcessedTransaction {
+    signature: Signature,
+    slot: u64,
+    transaction: Transaction,
+    logs: Vec<String>,
+    inner_instructions: Vec<InnerInstructions>,
+    compute_units_consumed: u64,
+}
+
+struct LogsSubscriber {
+    id: u64,
+    filter: RpcTransactionLogsFilter,
+    sender: UnboundedSender<RpcResponse<RpcLogsResponse>>,
+}
+
+impl ProgramTestTransport {
+    /// Start the given [`ProgramTest`] on a background runtime owned by the transport.
+    ///
+    /// This works with both the blocking and the `async` client, but it must be called outside
+    /// of any Tokio runtime, e.g. not from a `#[tokio::test]`. Use
+    /// [`ProgramTestTransport::start_async`] there instead.
+    pub fn start(program_test: ProgramTest) -> Result<Self, ClientError> {
+        let runtime = Builder::new_multi_thread().enable_all().build()?;
+        let context = runtime.block_on(program_test.start_with_context());
+
+        let mut transport = Self::from_context(context);
+        transport.runtime = Some(runtime);
+        Ok(transport)
+    }
+
+    /// Start the given [`ProgramTest`] on the current Tokio runtime, which must outlive the
+    /// transport.
+    pub async fn start_async(program_test: ProgramTest) -> Self {
+        Self::from_context(program_test.start_with_context().await)
+    }
+
+    /// Create a transport from an already started [`ProgramTestContext`].
+    ///
+    /// The runtime the context was started on must outlive the transport.
+    pub fn from_context(context: ProgramTestContext) -> Self {
+        Self {
+            banks_client: context.banks_client.clone(),
+            context: Mutex::new(context),
+            state: Arc::new(Mutex::new(State::default())),
+            runtime: None,
+        }
+    }
+
+    /// Returns a copy of the payer that was funded at genesis.
+    pub fn payer(&self) -> Keypair {
+        self.lock_context().payer.insecure_clone()
+    }
+
+    /// Store the given account at `pubkey`, overwriting any existing account.
+    pub fn set_account(&self, pubkey: Pubkey, account: Account) {
+        self.lock_context()
+            .set_account(&pubkey, &AccountSharedData::from(account));
+        lock(&self.state).known_accounts.insert(pubkey);
+    }
+
+    /// Run `f` with mutable access to the underlying [`ProgramTestContext`], e.g. to warp to a
+    /// slot or to set a sysvar.
+    ///
+    /// Accounts created this way are not tracked for `getProgramAccounts`; use
+    /// [`ProgramTestTransport::set_account`] instead.
+    pub fn with_context<R>(&self, f: impl FnOnce(&mut ProgramTestContext) -> R) -> R {
+        f(&mut self.lock_context())
+    }
+
+    fn lock_context(&self) -> MutexGuard<'_, ProgramTestContext> {
+        lock(&self.context)
+    }
+
+    async fn process_transaction(
+        &self,
+        transaction: &Transaction,
+    ) -> Result<Signature, ClientError> {
+        // Simulate first like the RPC's preflight check does, because only simulations record
+        // inner instructions
+        let simulation = self
+            .banks_client
+            .simulate_transaction_with_commitment(transaction.clone(), CommitmentLevel::Processed)
+            .await
+            .map_err(banks_client_error)?;
+        let (logs, units_consumed, inner_instructions) = simulation
+            .simulation_details
+            .map(|details| {
+                (
+                    details.logs,
+                    Some(details.units_consumed),
+                    details.inner_instructions.unwrap_or_default(),
+                )
+            })
+            .unwrap_or_default();
+        if let Some(Err(err)) = simulation.result {
+            return Err(preflight_failure(err, logs, units_consumed));
+        }
+
+        let result = self
+            .banks_client
+            .process_transaction_with_metadata(transaction.clone())
+            .await
+            .map_err(banks_client_error)?;
+        let (logs, compute_units_consumed) = result
+            .metadata
+            .map(|metadata| (metadata.log_messages, metadata.compute_units_consumed))
+            .unwrap_or((logs, units_consumed.unwrap_or_default()));
+        if let Err(err) = result.result {
+            return Err(preflight_failure(err, logs, Some(compute_units_consumed)));
+        }
+
+        let slot = self
+            .banks_client
+            .get_sysvar::<Clock>()
+            .await
+            .map_err(banks_client_error)?
+            .slot;
+        let signature = transaction.signatures[0];
+
+        let mut state = lock(&self.state);
+        state
+            .known_accounts
+            .extend(transaction.message.account_keys.iter().copied());
+        state.notify_subscribers(transaction, signature, slot, &logs);
+        state.transactions.push(ProcessedTransaction {
+            signature,
+            slot,
+            transaction: transaction.clone(),
+            logs,
+            inner_instructions,
+            compute_units_consumed,
+        });
+
+        Ok(signature)
+    }
+}
+
+impl Drop for ProgramTestTransport {
+    fn drop(&mut self) {
+        // Dropping a runtime blocks, which panics when the transport is dropped from within an
+        // async context.
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+/// Lamports of the wallet at genesis, the same as the faucet of `solana-test-validator`.
+const WALLET_LAMPORTS: u64 = 500_000_000 * LAMPORTS_PER_SOL;
+
+/// Processor that runs a program natively, as created by [`native_processor!`] or
+/// [`solana_program_test::processor!`].
+pub type NativeProcessor = fn(*mut EbpfVm<InvokeContext<'static>>, u64, u64, u64, u64, u64);
+
+/// Create the [`NativeProcessor`] of an Anchor program from its `entry` function.
+///
+/// ```ignore
+/// let processor = native_processor!(my_program::entry);
+/// ```
+#[macro_export]
+macro_rules! native_processor {
+    ($entry:path) => {{
+        fn process_instruction(
+            program_id: &$crate::anchor_lang::solana_program::pubkey::Pubkey,
+            accounts: &[$crate::anchor_lang::solana_program::account_info::AccountInfo],
+            data: &[u8],
+        ) -> $crate::anchor_lang::solana_program::entrypoint::ProgramResult {
+            // SAFETY: `entry` requires the slice of the accounts to live as long as the accounts,
+            // while the runtime only lends the slice for the duration of the call. The accounts
+            // outlive the slice, and `entry` can't keep a reference past the call, so shortening
+            // their lifetime to the one of the slice is sound.
+            let accounts = unsafe {
+                std::mem::transmute::<
+                    &[$crate::anchor_lang::solana_program::account_info::AccountInfo],
+                    &[$crate::anchor_lang::solana_program::account_info::AccountInfo],
+                >(accounts)
+            };
+            $entry(program_id, accounts, data)
+        }
+
+        let processor: Option<$crate::NativeProcessor> =
+            $crate::solana_program_test::processor!(process_instruction);
+        processor
+    }};
+}
+
+impl TestGenesis {
+    /// Create a [`ProgramTest`] with the programs and the accounts of the genesis.
+    pub fn program_test(&self) -> Result<ProgramTest, ClientError> {
+        self.program_test_with_processors(&[])
+    }
+
+    /// Create a [`ProgramTest`] with the programs and the accounts of the genesis, where the
+    /// programs with a processor run natively if [`TestGenesis::native`] is set.
+    ///
+    /// Native programs are not upgradeable, regardless of their upgrade authority.
+    pub fn program_test_with_processors(
+        &self,
+        processors: &[(Pubkey, Option<NativeProcessor>)],
+    ) -> Result<ProgramTest, ClientError> {
+        let processors = self.native_processors(processors);
+        let mut program_test = ProgramTest::default();
+        for (address, account) in self.genesis_accounts(&processors)? {
+            program_test.add_account(address, account);
+        }
+        for (address, processor) in processors {
+            program_test.add_builtin_program("native_program", address, processor);
+        }
+        for feature in &self.deactivate_features {
+            program_test.deactivate_feature(*feature);
+        }
+
+        Ok(program_test)
+    }
+
+    /// Start a [`ProgramTestTransport`] from the genesis.
+    ///
+    /// Unlike with [`ProgramTestTransport::start`], the genesis accounts are considered by
+    /// `getProgramAccounts`.
+    pub fn start(&self) -> Result<ProgramTestTransport, ClientError> {
+        self.start_with_processors(&[])
+    }
+
+    /// Start a [`ProgramTestTransport`] from the genesis, where the programs with a processor run
+    /// natively if [`TestGenesis::native`] is set, see
+    /// [`TestGenesis::program_test_with_processors`].
+    pub fn start_with_processors(
+        &self,
+        processors: &[(Pubkey, Option<NativeProcessor>)],
+    ) -> Result<ProgramTestTransport, ClientError> {
+        let transport =
+            ProgramTestTransport::start(self.program_test_with_processors(processors)?)?;
+        self.track_genesis_accounts(&transport, processors)?;
+        Ok(transport)
+    }
+
+    /// Start a [`ProgramTestTransport`] from the genesis on the current Tokio runtime, see
+    /// [`ProgramTestTransport::start_async`].
+    pub async fn start_async(&self) -> Result<ProgramTestTransport, ClientError> {
+        self.start_with_processors_async(&[]).await
+    }
+
+    /// Start a [`ProgramTestTransport`] from the genesis on the current Tokio runtime, where the
+    /// programs with a processor run natively if [`TestGenesis::native`] is set.
+    pub async fn start_with_processors_async(
+        &self,
+        processors: &[(Pubkey, Option<NativeProcessor>)],
+    ) -> Result<ProgramTestTransport, ClientError> {
+        let transport =
+            ProgramTestTransport::start_async(self.program_test_with_processors(processors)?).await;
+        self.track_genesis_accounts(&transport, processors)?;
+        Ok(transport)
+    }
+
+    fn track_genesis_accounts(
+        &self,
+        transport: &ProgramTestTransport,
+        processors: &[(Pubkey, Option<NativeProcessor>)],
+    ) -> Result<(), ClientError> {
+        lock(&transport.state).known_accounts.extend(
+            self.genesis_accounts(&self.native_processors(processors))?
+                .into_iter()
+                .map(|(address, _)| address),
+        );
+        Ok(())
+    }
+
+    fn native_processors(
+        &self,
+        processors: &[(Pubkey, Option<NativeProcessor>)],
+    ) -> Vec<(Pubkey, NativeProcessor)> {
+        if !self.native {
+            return vec![];
+        }
+        processors
+            .iter()
+            .filter_map(|(address, processor)| Some((*address, (*processor)?)))
+            .collect()
+    }
+
+    /// Accounts of the genesis, without the programs that run natively.
+    fn genesis_accounts(
+        &self,
+        native: &[(Pubkey, NativeProcessor)],
+    ) -> Result<Vec<(Pubkey, Account)>, ClientError> {
+        let rent = Rent::default();
+        let mut accounts = vec![];
+        if let Some(wallet) = self.wallet {
+            accounts.push((
+                wallet,
+                Account::new(WALLET_LAMPORTS, 0, &solana_sdk::system_program::ID),
+            ));
+        }
+
+        for program in &self.programs {
+            if native
+                .iter()
+                .any(|(address, _)| *address == program.address)
+            {
+                continue;
+            }
+            let elf = fs::read(&program.path)?;
+            match program.upgrade_authority {
+                Some(authority) => {
+                    let programdata_address = Pubkey::find_program_address(
+                        &[program.address.as_ref()],
+                        &bpf_loader_upgradeable::ID,
+                    )
+                    .0;
+                    let program_data = UpgradeableLoaderState::ProgramData {
+                        slot: 0,
+                        upgrade_authority_address: Some(authority),
+                    };
+                    let mut data = bincode::serialize(&program_data).map_err(invalid_data)?;
+                    data.resize(UpgradeableLoaderState::size_of_programdata_metadata(), 0);
+                    data.extend(elf);
+                    accounts.push((
+                        programdata_address,
+                        Account {
+                            lamports: rent.minimum_balance(data.len()),
+                            data,
+                            owner: bpf_loader_upgradeable::ID,
+                            executable: false,
+                            rent_epoch: 0,
+                        },
+                    ));
+
+                    let data = bincode::serialize(&UpgradeableLoaderState::Program {
+                        programdata_address,
+                    })
+                    .map_err(invalid_data)?;
+                    accounts.push((
+                        program.address,
+                        Account {
+                            lamports: rent.minimum_balance(data.len()),
+                            data,
+                            owner: bpf_loader_upgradeable::ID,
+                            executable: true,
+                            rent_epoch: 0,
+                        },
+                    ));
+                }
+                None => accounts.push((
+                    program.address,
+                    Account {
+                        lamports: rent.minimum_balance(elf.len()),
+                        data: elf,
+                        owner: bpf_loader::ID,
+                        executable: true,
+                        rent_epoch: 0,
+                    },
+                )),
+            }
+        }
+
+        for account in &self.accounts {
+            let keyed_account: RpcKeyedAccount =
+                serde_json::from_slice(&fs::read(&account.path)?).map_err(invalid_data)?;
+            let decoded = keyed_account.account.decode::<Account>().ok_or_else(|| {
+                invalid_data(format!("Invalid account file: {}", account.path.display()))
+            })?;
+            accounts.push((account.address, decoded));
+        }
+
+        Ok(accounts)
+    }
+}
+
+impl State {
+    fn notify_subscribers(
+        &mut self,
+        transaction: &Transaction,
+        signature: Signature,
+        slot: u64,
+        logs: &[String],
+    ) {
+        let account_keys = transaction
+            .message
+            .account_keys
+            .iter()
+            .map(|key| key.to_string())
+            .collect::<Vec<_>>();
+
+        self.subscribers.retain(|subscriber| {
+            let is_match = match &subscriber.filter {
+                RpcTransactionLogsFilter::All | RpcTransactionLogsFilter::AllWithVotes => true,
+                RpcTransactionLogsFilter::Mentions(addresses) => addresses
+                    .iter()
+                    .any(|address| account_keys.contains(address)),
+            };
+            if !is_match {
+                return !subscriber.sender.is_closed();
+            }
+
+            let notification = RpcResponse {
+                context: RpcResponseContext::new(slot),
+                value: RpcLogsResponse {
+                    signature: signature.to_string(),
+                    err: None,
+                    logs: logs.to_vec(),
+                },
+            };
+            subscriber.sender.unbounded_send(notification).is_ok()
+        });
+    }
+}
+
+#[async_trait]
+impl RpcTransport for ProgramTestTransport {
+    fn commitment(&self) -> CommitmentConfig {
+        CommitmentConfig::processed()
+    }
+
+    async fn get_account_with_commitment(
+        &self,
+        pubkey: &Pubkey,
+        commitment: CommitmentConfig,
+    ) -> Result<Option<Account>, ClientError> {
+        self.banks_client
+            .get_account_with_commitment(*pubkey, commitment.commitment)
+            .await
+            .map_err(banks_client_error)
+    }
+
+    async fn get_account_with_config(
+        &self,
+        pubkey: &Pubkey,
+        config: RpcAccountInfoConfig,
+    ) -> Result<Option<Account>, ClientError> {
+        let commitment = config.commitment.unwrap_or_else(|| self.commitment());
+        let account = self.get_account_with_commitment(pubkey, commitment).await?;
+        Ok(account.map(|mut account| {
+            if let Some(data_slice) = config.data_slice {
+                let len = account.data.len();
+                let start = data_slice.offset.min(len);
+                let end = data_slice.offset.saturating_add(data_slice.length).min(len);
+                account.data = account.data[start..end].to_vec();
+            }
+            account
+        }))
+    }
+
+    async fn get_program_accounts_with_config(
+        &self,
+        program_id: &Pubkey,
+        config: RpcProgramAccountsConfig,
+    ) -> Result<Vec<(Pubkey, Account)>, ClientError> {
+        let keys = lock(&self.state).known_accounts.clone();
+        let filters = config.filters.unwrap_or_default();
+
+        let mut accounts = vec![];
+        for key in keys {
+            let Some(account) = self
+                .get_account_with_commitment(&key, self.commitment())
+                .await?
+            else {
+                continue;
+            };
+            if account.owner != *program_id {
+                continue;
+            }
+
+            if filters.iter().all(|filter| filter_allows(filter, &account)) {
+                accounts.push((key, account));
+            }
+        }
+
+        Ok(accounts)
+    }
+
+    async fn get_latest_blockhash(&self) -> Result<Hash, ClientError> {
+        self.banks_client
+            .get_latest_blockhash()
+            .await
+            .map_err(banks_client_error)
+    }
+
+    async fn send_and_confirm_transaction(
+        &self,
+        transaction: &Transaction,
+    ) -> Result<Signature, ClientError> {
+        self.process_transaction(transaction).await
+    }
+
+    async fn send_and_confirm_transaction_with_spinner_and_config(
+        &self,
+        transaction: &Transaction,
+        _commitment: CommitmentConfig,
+        _config: RpcSendTransactionConfig,
+    ) -> Result<Signature, ClientError> {
+        self.process_transaction(transaction).await
+    }
+
+    async fn get_signatures_for_address_with_config(
+        &self,
+        address: &Pubkey,
+        config: GetConfirmedSignaturesForAddress2Config,
+    ) -> Result<Vec<RpcConfirmedTransactionStatusWithSignature>, ClientError> {
+        let state = lock(&self.state);
+        let mut transactions = state
+            .transactions
+            .iter()
+            .rev()
+            .filter(|tx| tx.transaction.message.account_keys.contains(address))
+            .peekable();
+        if let Some(before) = config.before {
+            while transactions.next_if(|tx| tx.signature != before).is_some() {}
+            transactions.next();
+        }
+
+        let signatures = transactions
+            .take_while(|tx| Some(tx.signature) != config.until)
+            .take(config.limit.unwrap_or(1000))
+            .map(|tx| RpcConfirmedTransactionStatusWithSignature {
+                signature: tx.signature.to_string(),
+                slot: tx.slot,
+                err: None,
+                memo: None,
+                block_time: None,
+                confirmation_status: Some(TransactionConfirmationStatus::Confirmed),
+            })
+            .collect();
+
+        Ok(signatures)
+    }
+
+    async fn get_transaction_with_config(
+        &self,
+        signature: &Signature,
+        _config: RpcTransactionConfig,
+    ) -> Result<EncodedConfirmedTransactionWithStatusMeta, ClientError> {
+        let state = lock(&self.state);
+        let tx = state
+            .transactions
+            .iter()
+            .find(|tx| tx.signature == *signature)
+            .ok_or_else(|| {
+                SolanaClientError::from(ClientErrorKind::Custom(format!(
+                    "Transaction not found: {signature}"
+                )))
+            })?;
+
+        let serialized_tx = bincode::serialize(&tx.transaction)
+            .map_err(|e| SolanaClientError::from(ClientErrorKind::Custom(e.to_string())))?;
+        let meta = UiTransactionStatusMeta {
+            err: None,
+            status: Ok(()),
+            fee: 0,
+            pre_balances: vec![],
+            post_balances: vec![],
+            inner_instructions: OptionSerializer::Some(
+                tx.inner_instructions
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, ixs)| !ixs.is_empty())
+                    .map(|(index, ixs)| UiInnerInstructions {
+                        index: index as u8,
+                        instructions: ixs
+                            .iter()
+                            .map(|ix| {
+                                UiInstruction::Compiled(UiCompiledInstruction::from(
+                                    &ix.instruction,
+                                    Some(ix.stack_height.into()),
+                                ))
+                            })
+                            .collect(),
+                    })
+                    .collect(),
+            ),
+            log_messages: OptionSerializer::Some(tx.logs.clone()),
+            pre_token_balances: OptionSerializer::None,
+            post_token_balances: OptionSerializer::None,
+            rewards: OptionSerializer::None,
+            loaded_addresses: OptionSerializer::Skip,
+            return_data: OptionSerializer::Skip,
+            compute_units_consumed: OptionSerializer::Some(tx.compute_units_consumed),
+        };
+
+        Ok(EncodedConfirmedTransactionWithStatusMeta {
+            slot: tx.slot,
+            transaction: EncodedTransactionWithStatusMeta {
+                transaction: EncodedTransaction::Binary(
+                    STANDARD.encode(serialized_tx),
+                    TransactionBinaryEncoding::Base64,
+                ),
+                meta: Some(meta),
+                version: None,
+            },
+            block_time: None,
+        })
+    }
+
+    async fn logs_subscribe(
+        &self,
+        filter: RpcTransactionLogsFilter,
+        _config: RpcTransactionLogsConfig,
+    ) -> Result<(LogsStream<'_>, UnsubscribeFn), ClientError> {
+        let (sender, receiver) = unbounded();
+        let id = {
+            let mut state = lock(&self.state);
+            let id = state.next_subscriber_id;
+            state.next_subscriber_id += 1;
+            state
+                .subscribers
+                .push(LogsSubscriber { id, filter, sender });
+            id
+        };
+
+        let state = Arc::clone(&self.state);
+        let unsubscribe: UnsubscribeFn = Box::new(move || {
+            Box::pin(async move {
+                lock(&state)
+                    .subscribers
+                    .retain(|subscriber| subscriber.id != id);
+            })
+        });
+
+        Ok((Box::pin(receiver), unsubscribe))
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn filter_allows(filter: &RpcFilterType, account: &Account) -> bool {
+    match filter {
+        RpcFilterType::DataSize(size) => account.data.len() as u64 == *size,
+        RpcFilterType::Memcmp(compare) => compare.bytes_match(&account.data),
+        RpcFilterType::TokenAccountState => {
+            // Same check as SPL Token's `GenericTokenAccount::valid_account_data`
+            const ACCOUNT_LEN: usize = 165;
+            const MULTISIG_LEN: usize = 355;
+            const STATE_OFFSET: usize = 108;
+            const ACCOUNT_TYPE_ACCOUNT: u8 = 2;
+
+            let data = &account.data;
+            (data.len() == ACCOUNT_LEN && data[STATE_OFFSET] != 0)
+                || (data.len() > ACCOUNT_LEN
+                    && data.len() != MULTISIG_LEN
+                    && data[ACCOUNT_LEN] == ACCOUNT_TYPE_ACCOUNT)
+        }
+    }
+}
+
+fn banks_client_error(err: BanksClientError) -> ClientError {
+    match err {
+        BanksClientError::TransactionError(err) => preflight_failure(err, vec![], None),
+        BanksClientError::SimulationError {
+            err,
+            logs,
+            units_consumed,
+            ..
+        } => preflight_failure(err, logs, Some(units_consumed)),
+        err => ClientError::IOError(err.into()),
+    }
+}
+
+/// Convert a failed transaction into the same error an RPC node returns when preflight fails, so
+/// that callers can handle both transports the same way.
+fn preflight_failure(
+    err: TransactionError,
+    logs: Vec<String>,
+    units_consumed: Option<u64>,
+) -> ClientError {
+    let rpc_error = RpcError::RpcResponseError {
+        code: JSON_RPC_SERVER_ERROR_SEND_TRANSACTION_PREFLIGHT_FAILURE,
+        message: format!("Transaction simulation failed: {err}"),
+        data: RpcResponseErrorData::SendTransactionPreflightFailure(RpcSimulateTransactionResult {
+            err: Some(err),
+            logs: Some(logs),
+            accounts: None,
+            units_consumed,
+            return_data: None,
+            inner_instructions: None,
+            replacement_blockhash: None,
+        }),
+    };
+
+    SolanaClientError::new_with_request(rpc_error.into(), RpcRequest::SendTransaction).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{backfill::SignaturePages, BackfillConfig, TestGenesisAccount, TestGenesisProgram};
+    use anchor_lang::solana_program::{
+        account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    };
+    use futures::StreamExt;
+    use solana_sdk::{
+        instruction::{AccountMeta, Instruction, InstructionError},
+        signer::Signer,
+        system_instruction, system_program,
+    };
+
+    #[test]
+    fn process_transaction() {
+        let transport = ProgramTestTransport::start(ProgramTest::default()).unwrap();
+        let payer = transport.payer();
+        let recipient = Pubkey::new_unique();
+
+        let rt = Builder::new_current_thread().enable_all().build().unwrap();
+        rt.block_on(async {
+            let (transport, payer) = (&transport, &payer);
+            let filter = RpcTransactionLogsFilter::Mentions(vec![system_program::ID.to_string()]);
+            let (mut notifications, unsubscribe) = transport
+                .logs_subscribe(filter, RpcTransactionLogsConfig { commitment: None })
+                .await
+                .unwrap();
+
+            let transfer = |lamports| async move {
+                let blockhash = transport.get_latest_blockhash().await?;
+                let tx = Transaction::new_signed_with_payer(
+                    &[system_instruction::transfer(
+                        &payer.pubkey(),
+                        &recipient,
+                        lamports,
+                    )],
+                    Some(&payer.pubkey()),
+                    &[payer],
+                    blockhash,
+                );
+                transport.send_and_confirm_transaction(&tx).await
+            };
+
+            let signature = transfer(1_000_000).await.unwrap();
+            let account = transport
+                .get_account_with_commitment(&recipient, CommitmentConfig::processed())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(account.lamports, 1_000_000);
+
+            let notification = notifications.next().await.unwrap();
+            assert_eq!(notification.value.signature, signature.to_string());
+
+            let accounts = transport
+                .get_program_accounts_with_config(&system_program::ID, Default::default())
+                .await
+                .unwrap();
+            assert!(accounts.iter().any(|(key, _)| *key == recipient));
+
+            // Failed transactions are reported as preflight failures with logs
+            let err = transfer(u64::MAX).await.unwrap_err();
+            let ClientError::SolanaClientError(err) = err else {
+                panic!("Unexpected error: {err}");
+            };
+            assert!(err.get_transaction_error().is_some());
+
+            unsubscribe().await;
+        });
+    }
+
+    #[test]
+    fn signature_pages() {
+        let transport = ProgramTestTransport::start(ProgramTest::default()).unwrap();
+        let payer = transport.payer();
+        let recipient = Pubkey::new_unique();
+
+        let rt = Builder::new_current_thread().enable_all().build().unwrap();
+        rt.block_on(async {
+            let mut signatures = vec![];
+            for lamports in 1..=5 {
+                let blockhash = transport.get_latest_blockhash().await.unwrap();
+                let tx = Transaction::new_signed_with_payer(
+                    &[system_instruction::transfer(
+                        &payer.pubkey(),
+                        &recipient,
+                        lamports * 1_000_000,
+                    )],
+                    Some(&payer.pubkey()),
+                    &[&payer],
+                    blockhash,
+                );
+                let signature = transport.send_and_confirm_transaction(&tx).await.unwrap();
+                signatures.push(signature.to_string());
+            }
+
+            for (until, expected_pages) in [
+                (None, vec![1, 2, 2]),
+                (Some(0), vec![0, 2, 2]),
+                (Some(2), vec![0, 2]),
+            ] {
+                let config = BackfillConfig {
+                    until: until.map(|i: usize| signatures[i].parse().unwrap()),
+                    page_size: 2,
+                    ..Default::default()
+                };
+                let mut pages = SignaturePages::new(&transport, &recipient, &config)
+                    .await
+                    .unwrap();
+                let mut page_lens = vec![];
+                let mut fetched = vec![];
+                while let Some(page) = pages.next().await.unwrap() {
+                    page_lens.push(page.len());
+                    fetched.extend(page.into_iter().map(|status| status.signature));
+                }
+                assert_eq!(page_lens, expected_pages);
+                assert_eq!(fetched, signatures[until.map_or(0, |i| i + 1)..]);
+            }
+        });
+    }
+
+    #[test]
+    fn start_async() {
+        let rt = Builder::new_multi_thread().enable_all().build().unwrap();
+        rt.block_on(async {
+            let transport = ProgramTestTransport::start_async(ProgramTest::default()).await;
+            let payer = transport.payer();
+            let account = transport
+                .get_account_with_commitment(&payer.pubkey(), CommitmentConfig::processed())
+                .await
+                .unwrap()
+                .unwrap();
+            assert!(account.lamports > 0);
+        });
+    }
+
+    #[cfg(feature = "idl")]
+    #[test]
+    fn fetch_idl_account_data() {
+        use crate::{decode_idl_data, encode_idl_data};
+        use anchor_lang::{idl::IdlAccount, AccountSerialize};
+
+        // Large enough to be read in multiple chunks
+        let events = (0..2000)
+            .map(|_| {
+                let key = Pubkey::new_unique();
+                serde_json::json!({ "name": key.to_string(), "discriminator": &key.to_bytes()[..8] })
+            })
+            .collect::<Vec<_>>();
+        let idl = serde_json::from_value(serde_json::json!({
+            "address": "11111111111111111111111111111111",
+            "metadata": { "name": "test", "version": "0.1.0", "spec": "0.1.0" },
+            "instructions": [],
+            "events": events,
+        }))
+        .unwrap();
+        let idl_data = encode_idl_data(&idl).unwrap();
+        assert!(idl_data.len() > crate::IDL_READ_CHUNK_SIZE);
+
+        let program_id = Pubkey::new_unique();
+        let idl_address = IdlAccount::address(&program_id);
+        let mut data = vec![];
+        IdlAccount {
+            authority: Pubkey::new_unique(),
+            data_len: idl_data.len() as u32,
+        }
+        .try_serialize(&mut data)
+        .unwrap();
+        data.extend(&idl_data);
+        // Trailing space for future growth
+        data.extend([0; 100]);
+
+        let transport = ProgramTestTransport::start(ProgramTest::default()).unwrap();
+        transport.set_account(
+            idl_address,
+            Account {
+                lamports: 1_000_000_000,
+                data,
+                owner: program_id,
+                ..Default::default()
+            },
+        );
+
+        let rt = Builder::new_current_thread().enable_all().build().unwrap();
+        let data = rt
+            .block_on(crate::idl::fetch_idl_account_data(&transport, &idl_address))
+            .unwrap();
+        assert_eq!(data, idl_data);
+        assert_eq!(
+            decode_idl_data(&data[..]).unwrap(),
+            serde_json::to_vec(&idl).unwrap()
+        );
+    }
+
+    #[cfg(feature = "idl")]
+    #[test]
+    fn fetch_metadata_account_data() {
+        use anchor_lang::{idl::ProgramMetadata, AccountSerialize};
+
+        let program_id = Pubkey::new_unique();
+        let metadata_address = ProgramMetadata::address(&program_id, "source-url", None);
+        let metadata = b"https://github.com/coral-xyz/anchor";
+        let mut data = vec![];
+        ProgramMetadata {
+            authority: Pubkey::new_unique(),
+            canonical: true,
+            key: ProgramMetadata::pad_key("source-url").unwrap(),
+            data_len: metadata.len() as u32,
+        }
+        .try_serialize(&mut data)
+        .unwrap();
+        assert_eq!(data.len(), ProgramMetadata::DATA_OFFSET);
+        data.extend(metadata);
+
+        let transport = ProgramTestTransport::start(ProgramTest::default()).unwrap();
+        transport.set_account(
+            metadata_address,
+            Account {
+                lamports: 1_000_000_000,
+                data,
+                owner: program_id,
+                ..Default::default()
+            },
+        );
+
+        let rt = Builder::new_current_thread().enable_all().build().unwrap();
+        let data = rt
+            .block_on(crate::idl::fetch_metadata_account_data(
+                &transport,
+                &metadata_address,
+            ))
+            .unwrap();
+        assert_eq!(data, metadata);
+    }
+
+    #[test]
+    fn test_genesis() {
+        let dir =
+            std::env::temp_dir().join(format!("anchor-test-genesis-{}", Pubkey::new_unique()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let account_address = Pubkey::new_unique();
+        let account_path = dir.join("account.json");
+        fs::write(
+            &account_path,
+            serde_json::json!({
+                "pubkey": account_address.to_string(),
+                "account": {
+                    "lamports": 1_000_000,
+                    "data": [STANDARD.encode([1, 2, 3]), "base64"],
+                    "owner": system_program::ID.to_string(),
+                    "executable": false,
+                    "rentEpoch": 0,
+                    "space": 3,
+                },
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let program_path = dir.join("program.so");
+        fs::write(&program_path, [0x7f, b'E', b'L', b'F']).unwrap();
+
+        let wallet = Pubkey::new_unique();
+        let program = Pubkey::new_unique();
+        let genesis = TestGenesis {
+            wallet: Some(wallet),
+            programs: vec![TestGenesisProgram {
+                address: program,
+                path: program_path,
+                upgrade_authority: Some(wallet),
+            }],
+            accounts: vec![TestGenesisAccount {
+                address: account_address,
+                path: account_path,
+            }],
+            deactivate_features: vec![],
+            native: false,
+        };
+        let accounts = genesis.genesis_accounts(&[]).unwrap();
+        let transport = genesis.start().unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        let rt = Builder::new_current_thread().enable_all().build().unwrap();
+        let account = rt
+            .block_on(
+                transport
+                    .get_account_with_commitment(&account_address, CommitmentConfig::processed()),
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(account.data, [1, 2, 3]);
+
+        assert_eq!(accounts.len(), 4);
+        assert_eq!(
+            accounts[0],
+            (
+                wallet,
+                Account::new(WALLET_LAMPORTS, 0, &system_program::ID)
+            )
+        );
+
+        // Upgradeable programs are split into the program data and program accounts
+        let (programdata_address, programdata) = &accounts[1];
+        assert_eq!(programdata.owner, bpf_loader_upgradeable::ID);
+        assert!(programdata.data.ends_with(b"\x7fELF"));
+        assert_eq!(
+            accounts[2]
+                .1
+                .deserialize_data::<UpgradeableLoaderState>()
+                .unwrap(),
+            UpgradeableLoaderState::Program {
+                programdata_address: *programdata_address
+            }
+        );
+
+        assert_eq!(accounts[3].0, account_address);
+        assert_eq!(accounts[3].1.data, [1, 2, 3]);
+        assert_eq!(accounts[3].1.lamports, 1_000_000);
+    }
+
+    // Same signature as the `entry` function of Anchor programs
+    fn entry<'info>(
+        _program_id: &Pubkey,
+        _accounts: &'info [AccountInfo<'info>],
+        data: &[u8],
+    ) -> ProgramResult {
+        match data.first() {
+            Some(code) => Err(ProgramError::Custom(*code as u32)),
+            None => Ok(()),
+        }
+    }
+
+    #[test]
+    fn test_genesis_native() {
+        let program = Pubkey::new_unique();
+        let genesis = TestGenesis {
+            programs: vec![TestGenesisProgram {
+                address: program,
+                // Native programs are not read from their binaries
+                path: "missing.so".into(),
+                upgrade_authority: None,
+            }],
+            native: true,
+            ..Default::default()
+        };
+        let transport = genesis
+            .start_with_processors(&[(program, native_processor!(entry))])
+            .unwrap();
+        let payer = transport.payer();
+
+        let rt = Builder::new_current_thread().enable_all().build().unwrap();
+        rt.block_on(async {
+            let (transport, payer) = (&transport, &payer);
+            let invoke = |data: Vec<u8>| async move {
+                let blockhash = transport.get_latest_blockhash().await?;
+                let tx = Transaction::new_signed_with_payer(
+                    &[Instruction::new_with_bytes(program, &data, vec![])],
+                    Some(&payer.pubkey()),
+                    &[payer],
+                    blockhash,
+                );
+                transport.send_and_confirm_transaction(&tx).await
+            };
+
+            invoke(vec![]).await.unwrap();
+            let ClientError::SolanaClientError(err) = invoke(vec![7]).await.unwrap_err() else {
+                panic!("Unexpected error");
+            };
+            assert_eq!(
+                err.get_transaction_error(),
+                Some(TransactionError::InstructionError(
+                    0,
+                    InstructionError::Custom(7)
+                ))
+            );
+        });
+    }
+
+    fn counter_entry<'info>(
+        _program_id: &Pubkey,
+        accounts: &'info [AccountInfo<'info>],
+        _data: &[u8],
+    ) -> ProgramResult {
+        let mut data = accounts[0].try_borrow_mut_data()?;
+        let count = u64::from_le_bytes(data[..8].try_into().unwrap());
+        data[..8].copy_from_slice(&(count + 1).to_le_bytes());
+        Ok(())
+    }
+
+    #[test]
+    fn native_processor_many_instructions() {
+        let program = Pubkey::new_unique();
+        let genesis = TestGenesis {
+            programs: vec![TestGenesisProgram {
+                address: program,
+                path: "missing.so".into(),
+                upgrade_authority: None,
+            }],
+            native: true,
+            ..Default::default()
+        };
+        let transport = genesis
+            .start_with_processors(&[(program, native_processor!(counter_entry))])
+            .unwrap();
+        let payer = transport.payer();
+        let counter = Pubkey::new_unique();
+        transport.set_account(counter, Account::new(LAMPORTS_PER_SOL, 8, &program));
+
+        let rt = Builder::new_current_thread().enable_all().build().unwrap();
+        rt.block_on(async {
+            for i in 0..50u8 {
+                // The data makes the transactions distinct
+                let instructions = (0..10)
+                    .map(|_| {
+                        Instruction::new_with_bytes(
+                            program,
+                            &[i],
+                            vec![AccountMeta::new(counter, false)],
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                let blockhash = transport.get_latest_blockhash().await.unwrap();
+                let tx = Transaction::new_signed_with_payer(
+                    &instructions,
+                    Some(&payer.pubkey()),
+                    &[&payer],
+                    blockhash,
+                );
+                transport.send_and_confirm_transaction(&tx).await.unwrap();
+            }
+
+            let account = transport
+                .get_account_with_commitment(&counter, CommitmentConfig::processed())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(account.data, 500u64.to_le_bytes());
+        });
+    }
+}
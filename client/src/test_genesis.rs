@@ -0,0 +1,81 @@
+//! Genesis of the in-memory bank that tests run against with `anchor test --runtime svm`.
+//!
+//! `anchor test --runtime svm` writes a [`TestGenesis`] for every test suite, from the workspace
+//! programs and the `[test.validator]` section of `Anchor.toml`, and passes its path to the tests
+//! in the [`TEST_GENESIS_ENV`] environment variable. With the `program-test` feature, the tests
+//! can start a [`ProgramTestTransport`](crate::ProgramTestTransport) from it.
+
+use anchor_lang::solana_program::pubkey::Pubkey;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::ClientError;
+
+/// Environment variable of the path of the [`TestGenesis`] that `anchor test --runtime svm`
+/// writes for every test suite.
+pub const TEST_GENESIS_ENV: &str = "ANCHOR_TEST_GENESIS";
+
+/// Genesis of an in-memory bank, as configured by the `[test.validator]` section of `Anchor.toml`.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestGenesis {
+    /// Wallet that is funded at genesis.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wallet: Option<Pubkey>,
+    #[serde(default)]
+    pub programs: Vec<TestGenesisProgram>,
+    /// Accounts in the JSON format of `solana account --output json`.
+    #[serde(default)]
+    pub accounts: Vec<TestGenesisAccount>,
+    #[serde(default)]
+    pub deactivate_features: Vec<Pubkey>,
+    /// Whether the programs that are given a native processor run natively instead of from their
+    /// binaries, which `anchor test --coverage` sets to measure their coverage.
+    #[serde(default)]
+    pub native: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestGenesisProgram {
+    pub address: Pubkey,
+    /// Path of the program binary.
+    pub path: PathBuf,
+    /// Upgrade authority of the program, which is deployed with the non-upgradeable loader if
+    /// there is none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upgrade_authority: Option<Pubkey>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestGenesisAccount {
+    pub address: Pubkey,
+    /// Path of the account JSON file.
+    pub path: PathBuf,
+}
+
+impl TestGenesis {
+    /// Read the genesis of the test suite that is run by `anchor test --runtime svm`.
+    pub fn from_env() -> Result<Self, ClientError> {
+        let path = std::env::var(TEST_GENESIS_ENV).map_err(|_| {
+            invalid_data(format!(
+                "{TEST_GENESIS_ENV} is not set, run the tests with `anchor test --runtime svm`"
+            ))
+        })?;
+        Self::read(path)
+    }
+
+    /// Read the genesis from a JSON file.
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, ClientError> {
+        let genesis = fs::read(path)?;
+        serde_json::from_slice(&genesis).map_err(|e| invalid_data(e).into())
+    }
+}
+
+pub(crate) fn invalid_data(err: impl ToString) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string())
+}
@@ -0,0 +1,236 @@
+//! Pluggable RPC transport used by [`Program`](crate::Program).
+//!
+//! By default, programs talk to a cluster through [`RpcClientTransport`], which wraps the
+//! non-blocking [`RpcClient`] and [`PubsubClient`]. Any other implementation of
+//! [`RpcTransport`] can be passed in via [`Client::program_with_transport`], e.g. the in-memory
+//! [`ProgramTestTransport`](crate::ProgramTestTransport) that is available with the
+//! `program-test` feature.
+//!
+//! [`Client::program_with_transport`]: crate::Client::program_with_transport
+
+use crate::{ClientError, UnsubscribeFn};
+use anchor_lang::solana_program::pubkey::Pubkey;
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+use solana_client::{
+    nonblocking::{pubsub_client::PubsubClient, rpc_client::RpcClient},
+    rpc_client::GetConfirmedSignaturesForAddress2Config,
+    rpc_config::{
+        RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcSendTransactionConfig,
+        RpcTransactionConfig, RpcTransactionLogsConfig, RpcTransactionLogsFilter,
+    },
+    rpc_response::{
+        Response as RpcResponse, RpcConfirmedTransactionStatusWithSignature, RpcLogsResponse,
+    },
+};
+use solana_sdk::{
+    account::Account, commitment_config::CommitmentConfig, hash::Hash, signature::Signature,
+    transaction::Transaction,
+};
+use solana_transaction_status_client_types::EncodedConfirmedTransactionWithStatusMeta;
+use tokio::sync::OnceCell;
+
+/// Stream of log notifications returned by [`RpcTransport::logs_subscribe`].
+pub type LogsStream<'a> = BoxStream<'a, RpcResponse<RpcLogsResponse>>;
+
+/// The set of RPC and pubsub methods the client needs in order to interact with a cluster.
+///
+/// Method names and semantics mirror the ones of the same name on [`RpcClient`] and
+/// [`PubsubClient`].
+#[async_trait]
+pub trait RpcTransport: Send + Sync {
+    /// Default commitment used by the transport.
+    fn commitment(&self) -> CommitmentConfig;
+
+    async fn get_account_with_commitment(
+        &self,
+        pubkey: &Pubkey,
+        commitment: CommitmentConfig,
+    ) -> Result<Option<Account>, ClientError>;
+
+    /// Same as [`Self::get_account_with_commitment`], but also supports fetching only a slice of
+    /// the account data via [`RpcAccountInfoConfig::data_slice`].
+    async fn get_account_with_config(
+        &self,
+        pubkey: &Pubkey,
+        config: RpcAccountInfoConfig,
+    ) -> Result<Option<Account>, ClientError>;
+
+    async fn get_program_accounts_with_config(
+        &self,
+        program_id: &Pubkey,
+        config: RpcProgramAccountsConfig,
+    ) -> Result<Vec<(Pubkey, Account)>, ClientError>;
+
+    async fn get_latest_blockhash(&self) -> Result<Hash, ClientError>;
+
+    async fn send_and_confirm_transaction(
+        &self,
+        transaction: &Transaction,
+    ) -> Result<Signature, ClientError>;
+
+    async fn send_and_confirm_transaction_with_spinner_and_config(
+        &self,
+        transaction: &Transaction,
+        commitment: CommitmentConfig,
+        config: RpcSendTransactionConfig,
+    ) -> Result<Signature, ClientError>;
+
+    /// Returns confirmed signatures of transactions that include the given address, newest first.
+    async fn get_signatures_for_address_with_config(
+        &self,
+        address: &Pubkey,
+        config: GetConfirmedSignaturesForAddress2Config,
+    ) -> Result<Vec<RpcConfirmedTransactionStatusWithSignature>, ClientError>;
+
+    async fn get_transaction_with_config(
+        &self,
+        signature: &Signature,
+        config: RpcTransactionConfig,
+    ) -> Result<EncodedConfirmedTransactionWithStatusMeta, ClientError>;
+
+    /// Subscribe to transaction logs matching the given filter.
+    async fn logs_subscribe(
+        &self,
+        filter: RpcTransactionLogsFilter,
+        config: RpcTransactionLogsConfig,
+    ) -> Result<(LogsStream<'_>, UnsubscribeFn), ClientError>;
+}
+
+/// [`RpcTransport`] implementation that talks to a cluster over JSON RPC and websockets.
+///
+/// The websocket connection is lazily created on the first subscription.
+pub struct RpcClientTransport {
+    rpc_client: RpcClient,
+    ws_url: String,
+    sub_client: OnceCell<PubsubClient>,
+}
+
+impl RpcClientTransport {
+    pub fn new(rpc_client: RpcClient, ws_url: impl Into<String>) -> Self {
+        Self {
+            rpc_client,
+            ws_url: ws_url.into(),
+            sub_client: OnceCell::new(),
+        }
+    }
+
+    /// Returns the underlying RPC client.
+    pub fn rpc_client(&self) -> &RpcClient {
+        &self.rpc_client
+    }
+
+    /// Returns the websocket URL used for subscriptions.
+    pub fn ws_url(&self) -> &str {
+        &self.ws_url
+    }
+}
+
+#[async_trait]
+impl RpcTransport for RpcClientTransport {
+    fn commitment(&self) -> CommitmentConfig {
+        self.rpc_client.commitment()
+    }
+
+    async fn get_account_with_commitment(
+        &self,
+        pubkey: &Pubkey,
+        commitment: CommitmentConfig,
+    ) -> Result<Option<Account>, ClientError> {
+        self.rpc_client
+            .get_account_with_commitment(pubkey, commitment)
+            .await
+            .map(|response| response.value)
+            .map_err(Into::into)
+    }
+
+    async fn get_account_with_config(
+        &self,
+        pubkey: &Pubkey,
+        config: RpcAccountInfoConfig,
+    ) -> Result<Option<Account>, ClientError> {
+        self.rpc_client
+            .get_account_with_config(pubkey, config)
+            .await
+            .map(|response| response.value)
+            .map_err(Into::into)
+    }
+
+    async fn get_program_accounts_with_config(
+        &self,
+        program_id: &Pubkey,
+        config: RpcProgramAccountsConfig,
+    ) -> Result<Vec<(Pubkey, Account)>, ClientError> {
+        self.rpc_client
+            .get_program_accounts_with_config(program_id, config)
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn get_latest_blockhash(&self) -> Result<Hash, ClientError> {
+        self.rpc_client
+            .get_latest_blockhash()
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn send_and_confirm_transaction(
+        &self,
+        transaction: &Transaction,
+    ) -> Result<Signature, ClientError> {
+        self.rpc_client
+            .send_and_confirm_transaction(transaction)
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn send_and_confirm_transaction_with_spinner_and_config(
+        &self,
+        transaction: &Transaction,
+        commitment: CommitmentConfig,
+        config: RpcSendTransactionConfig,
+    ) -> Result<Signature, ClientError> {
+        self.rpc_client
+            .send_and_confirm_transaction_with_spinner_and_config(transaction, commitment, config)
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn get_signatures_for_address_with_config(
+        &self,
+        address: &Pubkey,
+        config: GetConfirmedSignaturesForAddress2Config,
+    ) -> Result<Vec<RpcConfirmedTransactionStatusWithSignature>, ClientError> {
+        self.rpc_client
+            .get_signatures_for_address_with_config(address, config)
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn get_transaction_with_config(
+        &self,
+        signature: &Signature,
+        config: RpcTransactionConfig,
+    ) -> Result<EncodedConfirmedTransactionWithStatusMeta, ClientError> {
+        self.rpc_client
+            .get_transaction_with_config(signature, config)
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn logs_subscribe(
+        &self,
+        filter: RpcTransactionLogsFilter,
+        config: RpcTransactionLogsConfig,
+    ) -> Result<(LogsStream<'_>, UnsubscribeFn), ClientError> {
+        let sub_client = self
+            .sub_client
+            .get_or_try_init(|| PubsubClient::new(&self.ws_url))
+            .await?;
+
+        sub_client
+            .logs_subscribe(filter, config)
+            .await
+            .map_err(Into::into)
+    }
+}
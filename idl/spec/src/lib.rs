@@ -66,6 +66,16 @@ pub struct IdlInstruction {
     pub args: Vec<IdlField>,
     #[serde(skip_serializing_if = "is_default")]
     pub returns: Option<IdlType>,
+    #[serde(skip_serializing_if = "is_default")]
+    pub deprecated: Option<IdlDeprecation>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct IdlDeprecation {
+    #[serde(skip_serializing_if = "is_default")]
+    pub since: Option<String>,
+    #[serde(skip_serializing_if = "is_default")]
+    pub note: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -158,6 +168,8 @@ pub struct IdlConst {
 pub struct IdlErrorCode {
     pub code: u32,
     pub name: String,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub docs: Vec<String>,
     #[serde(skip_serializing_if = "is_default")]
     pub msg: Option<String>,
 }
@@ -169,6 +181,12 @@ pub struct IdlField {
     pub docs: Vec<String>,
     #[serde(rename = "type")]
     pub ty: IdlType,
+    #[serde(skip_serializing_if = "is_default")]
+    pub deprecated: Option<IdlDeprecation>,
+    #[serde(skip_serializing_if = "is_default")]
+    pub unit: Option<String>,
+    #[serde(skip_serializing_if = "is_default")]
+    pub decimals: Option<u8>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -184,6 +202,14 @@ pub struct IdlTypeDef {
     pub generics: Vec<IdlTypeDefGeneric>,
     #[serde(rename = "type")]
     pub ty: IdlTypeDefTy,
+    #[serde(skip_serializing_if = "is_default")]
+    pub origin: Option<IdlTypeDefOrigin>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct IdlTypeDefOrigin {
+    pub program: String,
+    pub name: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
@@ -247,6 +273,8 @@ pub struct IdlEnumVariant {
     pub name: String,
     #[serde(skip_serializing_if = "is_default")]
     pub fields: Option<IdlDefinedFields>,
+    #[serde(skip_serializing_if = "is_default")]
+    pub deprecated: Option<IdlDeprecation>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -301,6 +329,12 @@ pub enum IdlType {
     Option(Box<IdlType>),
     Vec(Box<IdlType>),
     Array(Box<IdlType>, IdlArrayLen),
+    Tuple(Vec<IdlType>),
+    Map {
+        key: Box<IdlType>,
+        value: Box<IdlType>,
+    },
+    Set(Box<IdlType>),
     Defined {
         name: String,
         #[serde(default, skip_serializing_if = "is_default")]
@@ -355,6 +389,45 @@ impl FromStr for IdlType {
                     return Ok(IdlType::Vec(Box::new(inner_ty)));
                 }
 
+                for prefix in ["HashMap<", "BTreeMap<"] {
+                    if let Some(inner) = s.strip_prefix(prefix) {
+                        let inner = inner
+                            .strip_suffix('>')
+                            .ok_or_else(|| anyhow!("Invalid map"))?;
+                        let (key, value) = match split_top_level(inner).as_slice() {
+                            [key, value] => (Self::from_str(key)?, Self::from_str(value)?),
+                            _ => return Err(anyhow!("Invalid map")),
+                        };
+                        return Ok(IdlType::Map {
+                            key: Box::new(key),
+                            value: Box::new(value),
+                        });
+                    }
+                }
+
+                for prefix in ["HashSet<", "BTreeSet<"] {
+                    if let Some(inner) = s.strip_prefix(prefix) {
+                        let inner_ty = Self::from_str(
+                            inner
+                                .strip_suffix('>')
+                                .ok_or_else(|| anyhow!("Invalid set"))?,
+                        )?;
+                        return Ok(IdlType::Set(Box::new(inner_ty)));
+                    }
+                }
+
+                if let Some(inner) = s.strip_prefix('(') {
+                    let inner = inner
+                        .strip_suffix(')')
+                        .ok_or_else(|| anyhow!("Invalid tuple"))?;
+                    let elems = split_top_level(inner)
+                        .into_iter()
+                        .filter(|elem| !elem.is_empty())
+                        .map(Self::from_str)
+                        .collect::<Result<Vec<_>, _>>()?;
+                    return Ok(IdlType::Tuple(elems));
+                }
+
                 if s.starts_with('[') {
                     fn array_from_str(inner: &str) -> IdlType {
                         match inner.strip_suffix(']') {
@@ -377,11 +450,8 @@ impl FromStr for IdlType {
                 let (name, generics) = if let Some(i) = s.find('<') {
                     (
                         s.get(..i).unwrap().to_owned(),
-                        s.get(i + 1..)
-                            .unwrap()
-                            .strip_suffix('>')
-                            .unwrap()
-                            .split(',')
+                        split_top_level(s.get(i + 1..).unwrap().strip_suffix('>').unwrap())
+                            .into_iter()
                             .map(|g| g.trim().to_owned())
                             .map(|g| {
                                 if g.parse::<bool>().is_ok()
@@ -407,6 +477,27 @@ impl FromStr for IdlType {
     }
 }
 
+/// Split the given comma separated list of types without splitting nested types, e.g. `u8,(u16,u32)`
+/// is split into `u8` and `(u16,u32)`.
+fn split_top_level(s: &str) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            '>' | ')' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
 pub type IdlDiscriminator = Vec<u8>;
 
 /// Get whether the given data is the default of its type.
@@ -434,6 +525,39 @@ mod tests {
         )
     }
 
+    #[test]
+    fn tuple() {
+        assert_eq!(
+            IdlType::from_str("(u8, Vec<(u16, u32)>)").unwrap(),
+            IdlType::Tuple(vec![
+                IdlType::U8,
+                IdlType::Vec(Box::new(IdlType::Tuple(vec![IdlType::U16, IdlType::U32])))
+            ])
+        )
+    }
+
+    #[test]
+    fn map() {
+        assert_eq!(
+            IdlType::from_str("BTreeMap<Pubkey, HashMap<u8, String>>").unwrap(),
+            IdlType::Map {
+                key: Box::new(IdlType::Pubkey),
+                value: Box::new(IdlType::Map {
+                    key: Box::new(IdlType::U8),
+                    value: Box::new(IdlType::String),
+                }),
+            }
+        )
+    }
+
+    #[test]
+    fn set() {
+        assert_eq!(
+            IdlType::from_str("HashSet<u64>").unwrap(),
+            IdlType::Set(Box::new(IdlType::U64))
+        )
+    }
+
     #[test]
     fn array() {
         assert_eq!(
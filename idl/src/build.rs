@@ -1,15 +1,19 @@
 use std::{
     collections::BTreeMap,
-    env, mem,
+    env, fs, mem,
     path::{Path, PathBuf},
-    process::{Command, Stdio},
+    process::{self, Command, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
 };
 
 use anyhow::{anyhow, Result};
 use regex::Regex;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::types::{Idl, IdlEvent, IdlTypeDef};
+use crate::{
+    lint::{lint_idl, IdlLintDiagnostic, IdlLintLevel, IdlLintLevels},
+    types::{Idl, IdlConst, IdlErrorCode, IdlEvent, IdlTypeDef, IdlTypeDefOrigin},
+};
 
 /// A trait that types must implement in order to include the type in the IDL definition.
 ///
@@ -55,6 +59,8 @@ pub struct IdlBuilder {
     program_path: Option<PathBuf>,
     resolution: Option<bool>,
     skip_lint: Option<bool>,
+    lints: Option<IdlLintLevels>,
+    programs: Option<BTreeMap<String, String>>,
     no_docs: Option<bool>,
     cargo_args: Option<Vec<String>>,
 }
@@ -82,6 +88,21 @@ impl IdlBuilder {
         self
     }
 
+    /// Set the lint levels (default: every lint is [`IdlLintLevel::Warn`]).
+    pub fn lints(mut self, lints: IdlLintLevels) -> Self {
+        self.lints.replace(lints);
+        self
+    }
+
+    /// Set the other programs whose types can be used in the program, as a map of crate names to
+    /// program addresses (default: empty).
+    ///
+    /// Types that are defined by these programs are marked with their origin in the IDL.
+    pub fn programs(mut self, programs: BTreeMap<String, String>) -> Self {
+        self.programs.replace(programs);
+        self
+    }
+
     /// Set whether to skip generating docs in the IDL (default: false).
     pub fn no_docs(mut self, no_docs: bool) -> Self {
         self.no_docs.replace(no_docs);
@@ -97,19 +118,25 @@ impl IdlBuilder {
 
     /// Build the IDL with the current configuration.
     pub fn build(self) -> Result<Idl> {
-        let idl = build(
+        let skip_lint = self.skip_lint.unwrap_or_default();
+        let (idl, mut diagnostics) = build(
             &self
                 .program_path
                 .unwrap_or_else(|| std::env::current_dir().expect("Failed to get program path")),
             self.resolution.unwrap_or(true),
-            self.skip_lint.unwrap_or_default(),
+            skip_lint,
             self.no_docs.unwrap_or_default(),
             &self.cargo_args.unwrap_or_default(),
-        )
-        .map(convert_module_paths)
-        .map(sort)?;
+        )?;
+        let idl = set_type_origins(idl, &self.programs.unwrap_or_default());
+        let idl = sort(convert_module_paths(idl));
         verify(&idl)?;
 
+        if !skip_lint {
+            diagnostics.extend(lint_idl(&idl));
+            report_lints(&diagnostics, &self.lints.unwrap_or_default())?;
+        }
+
         Ok(idl)
     }
 }
@@ -130,6 +157,56 @@ pub fn build_idl(
         .build()
 }
 
+/// Environment variable that contains the directory to write the IDL build fragments to.
+pub const IDL_BUILD_OUT_DIR_ENV: &str = "ANCHOR_IDL_BUILD_OUT_DIR";
+
+/// A part of the IDL that gets written by the generated IDL build functions.
+///
+/// Each fragment is written to a separate JSON file in the directory specified by the
+/// [`IDL_BUILD_OUT_DIR_ENV`] environment variable, and the fragments are merged into a single IDL
+/// once all of them have been written.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum IdlBuildFragment {
+    Address {
+        address: String,
+    },
+    Const {
+        constant: IdlConst,
+    },
+    Event {
+        event: IdlEvent,
+        types: Vec<IdlTypeDef>,
+    },
+    Errors {
+        errors: Vec<IdlErrorCode>,
+    },
+    Lints {
+        lints: Vec<IdlLintDiagnostic>,
+    },
+    Program {
+        idl: Idl,
+    },
+}
+
+impl IdlBuildFragment {
+    /// Write the fragment to the IDL build output directory.
+    ///
+    /// The `id` must be unique for each fragment. Nothing is written if the output directory is
+    /// not specified.
+    pub fn write(&self, id: &str) -> Result<()> {
+        let out_dir = match env::var_os(IDL_BUILD_OUT_DIR_ENV) {
+            Some(out_dir) => out_dir,
+            None => return Ok(()),
+        };
+
+        let path = Path::new(&out_dir).join(format!("{}.json", id.replace("::", ".")));
+        fs::write(path, serde_json::to_vec(self)?)?;
+        Ok(())
+    }
+}
+
 /// Build IDL.
 fn build(
     program_path: &Path,
@@ -137,23 +214,33 @@ fn build(
     skip_lint: bool,
     no_docs: bool,
     cargo_args: &[String],
-) -> Result<Idl> {
-    // `nightly` toolchain is currently required for building the IDL.
-    let toolchain = std::env::var("RUSTUP_TOOLCHAIN")
-        .map(|toolchain| format!("+{}", toolchain))
-        .unwrap_or_else(|_| "+nightly".to_string());
-
-    install_toolchain_if_needed(&toolchain)?;
+) -> Result<(Idl, Vec<IdlLintDiagnostic>)> {
+    // Getting the source file of the types (required for resolving type aliases and external
+    // types from non-root modules) is only possible with the `nightly` toolchain.
+    let is_nightly = env::var("RUSTUP_TOOLCHAIN")
+        .map(|toolchain| toolchain.starts_with("nightly"))
+        .unwrap_or_default();
+    let rustflags = if is_nightly {
+        "--cfg procmacro2_semver_exempt -A warnings"
+    } else {
+        "-A warnings"
+    };
+
+    // The IDL build functions are generated by separate macros across the program crate, so they
+    // are compiled as tests of the library, since the test harness is the only way to collect them
+    // on stable without adding a dependency to the program. Only the test binary is compiled
+    // here, and only the generated functions are run below.
     let output = Command::new("cargo")
         .args([
-            &toolchain,
             "test",
-            "__anchor_private_print_idl",
+            "--lib",
+            "--no-run",
             "--features",
             "idl-build",
+            "--message-format",
+            "json-render-diagnostics",
         ])
         .args(cargo_args)
-        .args(["--", "--show-output", "--quiet"])
         .env(
             "ANCHOR_IDL_BUILD_NO_DOCS",
             if no_docs { "TRUE" } else { "FALSE" },
@@ -167,14 +254,68 @@ fn build(
             if skip_lint { "TRUE" } else { "FALSE" },
         )
         .env("ANCHOR_IDL_BUILD_PROGRAM_PATH", program_path)
-        .env("RUSTFLAGS", "--cfg procmacro2_semver_exempt -A warnings")
+        .env("RUSTFLAGS", rustflags)
+        .current_dir(program_path)
+        .stderr(Stdio::inherit())
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow!("Building IDL failed"));
+    }
+
+    #[derive(Deserialize)]
+    struct CompilerArtifact {
+        reason: String,
+        executable: Option<PathBuf>,
+    }
+
+    let executables = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| serde_json::from_str::<CompilerArtifact>(line).ok())
+        .filter(|artifact| artifact.reason == "compiler-artifact")
+        .filter_map(|artifact| artifact.executable)
+        .collect::<Vec<_>>();
+    if executables.is_empty() {
+        return Err(anyhow!("IDL build test binary not found"));
+    }
+
+    let out_dir = create_out_dir()?;
+    let result = executables
+        .iter()
+        .try_for_each(|executable| run_idl_build_fns(executable, program_path, &out_dir))
+        .and_then(|_| merge_fragments(&out_dir));
+    fs::remove_dir_all(&out_dir)?;
+
+    result
+}
+
+/// Create an empty directory for the IDL build fragments.
+fn create_out_dir() -> Result<PathBuf> {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    let out_dir = env::temp_dir().join(format!(
+        "anchor-idl-build-{}-{}",
+        process::id(),
+        COUNT.fetch_add(1, Ordering::Relaxed)
+    ));
+    if out_dir.exists() {
+        fs::remove_dir_all(&out_dir)?;
+    }
+    fs::create_dir_all(&out_dir)?;
+
+    Ok(out_dir)
+}
+
+/// Run the generated IDL build functions of the given test binary.
+fn run_idl_build_fns(executable: &Path, program_path: &Path, out_dir: &Path) -> Result<()> {
+    let output = Command::new(executable)
+        .args(["__anchor_private_print_idl", "--quiet"])
+        .env(IDL_BUILD_OUT_DIR_ENV, out_dir)
         .current_dir(program_path)
         .stderr(Stdio::inherit())
         .output()?;
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
     if env::var("ANCHOR_LOG").is_ok() {
-        eprintln!("{}", stdout);
+        eprintln!("{}", String::from_utf8_lossy(&output.stdout));
     }
 
     if !output.status.success() {
@@ -183,121 +324,91 @@ fn build(
         ));
     }
 
-    enum State {
-        Pass,
-        Address,
-        Constants(Vec<String>),
-        Events(Vec<String>),
-        Errors(Vec<String>),
-        Program(Vec<String>),
-    }
+    Ok(())
+}
 
+/// Merge the IDL build fragments in the given directory into a single IDL, and return it together
+/// with the lint diagnostics of the program source.
+fn merge_fragments(out_dir: &Path) -> Result<(Idl, Vec<IdlLintDiagnostic>)> {
     let mut address = String::new();
     let mut events = vec![];
     let mut error_codes = vec![];
     let mut constants = vec![];
     let mut types = BTreeMap::new();
+    let mut diagnostics = vec![];
     let mut idl: Option<Idl> = None;
 
-    let mut state = State::Pass;
-    for line in stdout.lines() {
-        match &mut state {
-            State::Pass => match line {
-                "--- IDL begin address ---" => state = State::Address,
-                "--- IDL begin const ---" => state = State::Constants(vec![]),
-                "--- IDL begin event ---" => state = State::Events(vec![]),
-                "--- IDL begin errors ---" => state = State::Errors(vec![]),
-                "--- IDL begin program ---" => state = State::Program(vec![]),
-                _ => {
-                    if line.starts_with("test result: ok")
-                        && !line.starts_with("test result: ok. 0 passed; 0 failed; 0")
-                    {
-                        if let Some(idl) = idl.as_mut() {
-                            idl.address = mem::take(&mut address);
-                            idl.constants = mem::take(&mut constants);
-                            idl.events = mem::take(&mut events);
-                            idl.errors = mem::take(&mut error_codes);
-                            idl.types = {
-                                let prog_ty = mem::take(&mut idl.types);
-                                let mut types = mem::take(&mut types);
-                                types.extend(prog_ty.into_iter().map(|ty| (ty.name.clone(), ty)));
-                                types.into_values().collect()
-                            };
-                        }
-                    }
-                }
-            },
-            State::Address => {
-                address = line.replace(|c: char| !c.is_alphanumeric(), "");
-                state = State::Pass;
-                continue;
-            }
-            State::Constants(lines) => {
-                if line == "--- IDL end const ---" {
-                    let constant = serde_json::from_str(&lines.join("\n"))?;
-                    constants.push(constant);
-                    state = State::Pass;
-                    continue;
-                }
-
-                lines.push(line.to_owned());
-            }
-            State::Events(lines) => {
-                if line == "--- IDL end event ---" {
-                    #[derive(Deserialize)]
-                    struct IdlBuildEventPrint {
-                        event: IdlEvent,
-                        types: Vec<IdlTypeDef>,
-                    }
-
-                    let event = serde_json::from_str::<IdlBuildEventPrint>(&lines.join("\n"))?;
-                    events.push(event.event);
-                    types.extend(event.types.into_iter().map(|ty| (ty.name.clone(), ty)));
-                    state = State::Pass;
-                    continue;
-                }
-
-                lines.push(line.to_owned());
-            }
-            State::Errors(lines) => {
-                if line == "--- IDL end errors ---" {
-                    error_codes = serde_json::from_str(&lines.join("\n"))?;
-                    state = State::Pass;
-                    continue;
-                }
-
-                lines.push(line.to_owned());
-            }
-            State::Program(lines) => {
-                if line == "--- IDL end program ---" {
-                    idl = Some(serde_json::from_str(&lines.join("\n"))?);
-                    state = State::Pass;
-                    continue;
-                }
-
-                lines.push(line.to_owned());
+    for entry in fs::read_dir(out_dir)? {
+        let fragment = serde_json::from_slice(&fs::read(entry?.path())?)?;
+        match fragment {
+            IdlBuildFragment::Address { address: addr } => address = addr,
+            IdlBuildFragment::Const { constant } => constants.push(constant),
+            IdlBuildFragment::Event {
+                event,
+                types: event_types,
+            } => {
+                events.push(event);
+                types.extend(event_types.into_iter().map(|ty| (ty.name.clone(), ty)));
             }
+            IdlBuildFragment::Errors { errors } => error_codes.extend(errors),
+            IdlBuildFragment::Lints { lints } => diagnostics.extend(lints),
+            IdlBuildFragment::Program { idl: program_idl } => idl = Some(program_idl),
         }
     }
 
-    idl.ok_or_else(|| anyhow!("IDL doesn't exist"))
+    let mut idl = idl.ok_or_else(|| anyhow!("IDL doesn't exist"))?;
+    error_codes.sort_by_key(|error_code: &IdlErrorCode| error_code.code);
+    idl.address = address;
+    idl.constants = constants;
+    idl.events = events;
+    idl.errors = error_codes;
+    idl.types = {
+        let prog_ty = mem::take(&mut idl.types);
+        types.extend(prog_ty.into_iter().map(|ty| (ty.name.clone(), ty)));
+        types.into_values().collect()
+    };
+    diagnostics.sort_by(|a: &IdlLintDiagnostic, b| a.path.cmp(&b.path));
+
+    Ok((idl, diagnostics))
 }
 
-/// Install the given toolchain if it's not already installed.
-fn install_toolchain_if_needed(toolchain: &str) -> Result<()> {
-    let is_installed = Command::new("cargo")
-        .arg(toolchain)
-        .output()?
-        .status
-        .success();
-    if !is_installed {
-        Command::new("rustup")
-            .args(["toolchain", "install", toolchain.trim_start_matches('+')])
-            .spawn()?
-            .wait()?;
+/// Print the lint diagnostics based on their level, and error if any of them is denied.
+fn report_lints(diagnostics: &[IdlLintDiagnostic], levels: &IdlLintLevels) -> Result<()> {
+    let mut denied = vec![];
+    for diagnostic in diagnostics {
+        match levels.get(&diagnostic.lint).copied().unwrap_or_default() {
+            IdlLintLevel::Allow => {}
+            IdlLintLevel::Warn => eprintln!("Warning: {diagnostic}"),
+            IdlLintLevel::Deny => denied.push(diagnostic.to_string()),
+        }
     }
 
-    Ok(())
+    if denied.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!("IDL lints failed:\n{}", denied.join("\n")))
+    }
+}
+
+/// Set the origin of the types that are defined by the given programs.
+///
+/// This must be called before converting the module paths, as the crate name is only available
+/// in the full path of the type.
+fn set_type_origins(mut idl: Idl, programs: &BTreeMap<String, String>) -> Idl {
+    for ty_def in &mut idl.types {
+        let Some((crate_name, _)) = ty_def.name.split_once("::") else {
+            continue;
+        };
+        if let Some(address) = programs.get(crate_name) {
+            let name = ty_def.name.rsplit("::").next().unwrap();
+            ty_def.origin = Some(IdlTypeDefOrigin {
+                program: address.to_owned(),
+                name: name.to_owned(),
+            });
+        }
+    }
+
+    idl
 }
 
 /// Convert paths to name if there are no conflicts.
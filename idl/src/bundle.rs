@@ -0,0 +1,225 @@
+//! IDL bundles, i.e. the IDLs of multiple programs, e.g. all programs of a workspace.
+//!
+//! Types that are defined by another program are marked with their origin in the IDL, and the
+//! bundle can resolve them to a single definition, which is the definition of the program that
+//! defines them.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::types::{Idl, IdlTypeDef};
+
+/// IDLs of multiple programs.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IdlBundle {
+    pub idls: Vec<Idl>,
+}
+
+impl IdlBundle {
+    /// Create a new bundle, the program addresses must be unique.
+    pub fn new(idls: Vec<Idl>) -> Result<Self> {
+        for (i, idl) in idls.iter().enumerate() {
+            if let Some(other) = idls.iter().skip(i + 1).find(|o| o.address == idl.address) {
+                return Err(anyhow!(
+                    "Programs `{}` and `{}` have the same address: {}",
+                    idl.metadata.name,
+                    other.metadata.name,
+                    idl.address
+                ));
+            }
+        }
+
+        Ok(Self { idls })
+    }
+
+    /// Get the IDL of the program with the given address.
+    pub fn get(&self, address: &str) -> Option<&Idl> {
+        self.idls.iter().find(|idl| idl.address == address)
+    }
+
+    /// Resolve the type definition to a single definition of the type.
+    ///
+    /// Type definitions with the same origin resolve to the definition of the program that defines
+    /// the type. If that program is not in the bundle, or the type is not in its IDL (e.g. the
+    /// type is not used in the program's interface), they resolve to the first definition with
+    /// the same origin instead.
+    ///
+    /// Type definitions without an origin are returned as is.
+    pub fn resolve_type<'a>(&'a self, ty_def: &'a IdlTypeDef) -> &'a IdlTypeDef {
+        let origin = match &ty_def.origin {
+            Some(origin) => origin,
+            None => return ty_def,
+        };
+
+        self.get(&origin.program)
+            .and_then(|idl| {
+                idl.types
+                    .iter()
+                    .find(|ty_def| ty_def.name == origin.name && ty_def.origin.is_none())
+            })
+            .or_else(|| {
+                self.idls
+                    .iter()
+                    .flat_map(|idl| &idl.types)
+                    .find(|other| other.origin.as_ref() == Some(origin))
+            })
+            .unwrap_or(ty_def)
+    }
+
+    /// Verify that the type definitions with an origin are compatible with their resolved
+    /// definition.
+    pub fn verify(&self) -> Result<()> {
+        let errors = self
+            .idls
+            .iter()
+            .flat_map(|idl| {
+                idl.types
+                    .iter()
+                    .filter(|ty_def| ty_def.origin.is_some())
+                    .filter(|ty_def| !is_compatible(ty_def, self.resolve_type(ty_def)))
+                    .map(|ty_def| {
+                        format!(
+                            "{}.types.{}: Definition is not compatible with the definition of \
+                            its origin",
+                            idl.metadata.name, ty_def.name
+                        )
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!("Invalid IDL bundle:\n{}", errors.join("\n")))
+        }
+    }
+}
+
+/// Whether the type definitions have the same layout.
+///
+/// Docs and field metadata are ignored, and so are the paths of the defined types, as they
+/// depend on the other types of the program.
+fn is_compatible(a: &IdlTypeDef, b: &IdlTypeDef) -> bool {
+    fn normalize(value: &mut Value) {
+        match value {
+            Value::Object(map) => {
+                for key in ["docs", "deprecated", "unit", "decimals"] {
+                    map.remove(key);
+                }
+                if let Some(Value::String(name)) = map
+                    .get_mut("defined")
+                    .and_then(|defined| defined.get_mut("name"))
+                {
+                    *name = name.rsplit("::").next().unwrap().to_owned();
+                }
+                map.values_mut().for_each(normalize);
+            }
+            Value::Array(values) => values.iter_mut().for_each(normalize),
+            _ => {}
+        }
+    }
+
+    let layout = |ty_def: &IdlTypeDef| {
+        let mut value = serde_json::json!({
+            "serialization": ty_def.serialization,
+            "repr": ty_def.repr,
+            "generics": ty_def.generics,
+            "type": ty_def.ty,
+        });
+        normalize(&mut value);
+        value
+    };
+
+    layout(a) == layout(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn idl(address: &str, name: &str, types: Value) -> Idl {
+        serde_json::from_value(serde_json::json!({
+            "address": address,
+            "metadata": { "name": name, "version": "0.1.0", "spec": "0.1.0" },
+            "instructions": [],
+            "types": types
+        }))
+        .unwrap()
+    }
+
+    fn my_struct(field_ty: &str, origin: Option<&str>) -> Value {
+        let mut ty_def = serde_json::json!({
+            "name": "MyStruct",
+            "type": {
+                "kind": "struct",
+                "fields": [{ "name": "value", "type": field_ty }]
+            }
+        });
+        if let Some(program) = origin {
+            ty_def["origin"] = serde_json::json!({ "program": program, "name": "MyStruct" });
+        }
+        ty_def
+    }
+
+    #[test]
+    fn resolve_type() {
+        let bundle = IdlBundle::new(vec![
+            idl(
+                "A11111",
+                "a",
+                serde_json::json!([my_struct("u8", Some("B11111"))]),
+            ),
+            idl("B11111", "b", serde_json::json!([my_struct("u8", None)])),
+        ])
+        .unwrap();
+        let resolved = bundle.resolve_type(&bundle.idls[0].types[0]);
+        assert!(std::ptr::eq(resolved, &bundle.idls[1].types[0]));
+        assert!(bundle.verify().is_ok());
+    }
+
+    #[test]
+    fn resolve_type_without_origin_program() {
+        let bundle = IdlBundle::new(vec![
+            idl(
+                "A11111",
+                "a",
+                serde_json::json!([my_struct("u8", Some("B11111"))]),
+            ),
+            idl(
+                "C11111",
+                "c",
+                serde_json::json!([my_struct("u8", Some("B11111"))]),
+            ),
+        ])
+        .unwrap();
+        let resolved = bundle.resolve_type(&bundle.idls[1].types[0]);
+        assert!(std::ptr::eq(resolved, &bundle.idls[0].types[0]));
+        assert!(bundle.verify().is_ok());
+
+        let duplicate = IdlBundle::new(vec![
+            idl("A11111", "a", serde_json::json!([])),
+            idl("A11111", "b", serde_json::json!([])),
+        ]);
+        assert!(duplicate.is_err());
+    }
+
+    #[test]
+    fn verify_incompatible() {
+        let bundle = IdlBundle::new(vec![
+            idl(
+                "A11111",
+                "a",
+                serde_json::json!([my_struct("u8", Some("B11111"))]),
+            ),
+            idl(
+                "C11111",
+                "c",
+                serde_json::json!([my_struct("u16", Some("B11111"))]),
+            ),
+        ])
+        .unwrap();
+        let err = bundle.verify().unwrap_err().to_string();
+        assert!(err.contains("c.types.MyStruct: Definition is not compatible"));
+    }
+}
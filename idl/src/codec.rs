@@ -0,0 +1,1540 @@
+//! Encode and decode values of IDL types between their serialized bytes and JSON.
+//!
+//! The JSON representation is the same as the one described by the [`schema`](crate::schema)
+//! module.
+//!
+//! Types are serialized with Borsh, unless they are defined with `bytemuck` serialization (e.g.
+//! zero-copy accounts), in which case their in-memory layout is used based on their `repr`.
+
+use std::cmp::Ordering;
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Map, Number, Value};
+
+use crate::types::{
+    Idl, IdlArrayLen, IdlDefinedFields, IdlGenericArg, IdlRepr, IdlSerialization, IdlType,
+    IdlTypeDef, IdlTypeDefGeneric, IdlTypeDefTy,
+};
+
+/// Encode the given JSON value of type `ty`.
+pub fn encode(idl: &Idl, ty: &IdlType, value: &Value) -> Result<Vec<u8>> {
+    let mut buf = vec![];
+    Codec { idl }.encode(ty, &[], value, &mut buf)?;
+    Ok(buf)
+}
+
+/// Decode a value of type `ty` from the given data, advancing it by the number of bytes read.
+pub fn decode(idl: &Idl, ty: &IdlType, data: &mut &[u8]) -> Result<Value> {
+    Codec { idl }.decode(ty, &[], data)
+}
+
+/// Encode the account with the given name, including its discriminator.
+pub fn encode_account(idl: &Idl, name: &str, value: &Value) -> Result<Vec<u8>> {
+    let discriminator = find_account_discriminator(idl, name)?;
+    let data = encode(idl, &defined(name), value)?;
+    Ok([discriminator, &data].concat())
+}
+
+/// Decode the account with the given name from its data, including its discriminator.
+pub fn decode_account(idl: &Idl, name: &str, data: &[u8]) -> Result<Value> {
+    let discriminator = find_account_discriminator(idl, name)?;
+    let mut data = data
+        .strip_prefix(discriminator)
+        .ok_or_else(|| anyhow!("Invalid discriminator for account `{name}`"))?;
+    decode(idl, &defined(name), &mut data)
+}
+
+fn find_account_discriminator<'a>(idl: &'a Idl, name: &str) -> Result<&'a [u8]> {
+    idl.accounts
+        .iter()
+        .find(|acc| acc.name == name)
+        .map(|acc| acc.discriminator.as_slice())
+        .ok_or_else(|| anyhow!("Account `{name}` not found"))
+}
+
+fn defined(name: &str) -> IdlType {
+    IdlType::Defined {
+        name: name.to_owned(),
+        generics: vec![],
+    }
+}
+
+/// Generic arguments of a type definition, resolved to concrete types and values.
+pub(crate) type Generics = [(String, IdlGenericArg)];
+
+/// Size and alignment of a type in its in-memory (`bytemuck`) representation.
+#[derive(Clone, Copy)]
+struct Layout {
+    size: usize,
+    align: usize,
+}
+
+struct Codec<'a> {
+    idl: &'a Idl,
+}
+
+impl<'a> Codec<'a> {
+    fn find_type_def(&self, name: &str) -> Result<&'a IdlTypeDef> {
+        self.idl
+            .types
+            .iter()
+            .find(|ty| ty.name == name)
+            .ok_or_else(|| anyhow!("Type `{name}` not found"))
+    }
+
+    /// Resolve the type definition of a defined type and its generic arguments.
+    fn resolve_defined(
+        &self,
+        name: &str,
+        args: &[IdlGenericArg],
+        generics: &Generics,
+    ) -> Result<(&'a IdlTypeDef, Vec<(String, IdlGenericArg)>)> {
+        let ty_def = self.find_type_def(name)?;
+        if ty_def.generics.len() != args.len() {
+            return Err(anyhow!(
+                "Type `{name}` expects {} generic arguments but got {}",
+                ty_def.generics.len(),
+                args.len()
+            ));
+        }
+
+        let generics = ty_def
+            .generics
+            .iter()
+            .zip(args)
+            .map(|(generic, arg)| {
+                let name = match generic {
+                    IdlTypeDefGeneric::Type { name } => name,
+                    IdlTypeDefGeneric::Const { name, .. } => name,
+                };
+                Ok((name.to_owned(), substitute_arg(arg, generics)?))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok((ty_def, generics))
+    }
+
+    fn decode(&self, ty: &IdlType, generics: &Generics, data: &mut &[u8]) -> Result<Value> {
+        if let Some(value) = decode_primitive(ty, data)? {
+            return Ok(value);
+        }
+
+        let value = match ty {
+            IdlType::Bytes => {
+                let len = decode_len(data)?;
+                Value::Array(take(data, len)?.iter().map(|b| json!(b)).collect())
+            }
+            IdlType::String => {
+                let len = decode_len(data)?;
+                let s = std::str::from_utf8(take(data, len)?).context("Invalid UTF-8 string")?;
+                Value::String(s.to_owned())
+            }
+            IdlType::Option(ty) => match take(data, 1)?[0] {
+                0 => Value::Null,
+                1 => self.decode(ty, generics, data)?,
+                tag => return Err(anyhow!("Invalid option tag: {tag}")),
+            },
+            IdlType::Vec(ty) | IdlType::Set(ty) => {
+                let len = decode_len(data)?;
+                Value::Array(
+                    (0..len)
+                        .map(|_| self.decode(ty, generics, data))
+                        .collect::<Result<_>>()?,
+                )
+            }
+            IdlType::Array(ty, len) => {
+                let len = resolve_array_len(len, generics)?;
+                Value::Array(
+                    (0..len)
+                        .map(|_| self.decode(ty, generics, data))
+                        .collect::<Result<_>>()?,
+                )
+            }
+            IdlType::Tuple(tys) => Value::Array(
+                tys.iter()
+                    .map(|ty| self.decode(ty, generics, data))
+                    .collect::<Result<_>>()?,
+            ),
+            IdlType::Map { key, value } => {
+                let len = decode_len(data)?;
+                let mut map = Map::new();
+                for _ in 0..len {
+                    let key = match self.decode(key, generics, data)? {
+                        Value::String(key) => key,
+                        key => key.to_string(),
+                    };
+                    map.insert(key, self.decode(value, generics, data)?);
+                }
+                Value::Object(map)
+            }
+            IdlType::Defined {
+                name,
+                generics: args,
+            } => {
+                let (ty_def, generics) = self.resolve_defined(name, args, generics)?;
+                match &ty_def.serialization {
+                    IdlSerialization::Borsh => self.decode_type_def(ty_def, &generics, data)?,
+                    IdlSerialization::Bytemuck | IdlSerialization::BytemuckUnsafe => {
+                        self.decode_pod_type_def(ty_def, &generics, data)?
+                    }
+                    serialization => {
+                        return Err(anyhow!(
+                            "Unsupported serialization of type `{name}`: {serialization:?}"
+                        ))
+                    }
+                }
+            }
+            IdlType::Generic(name) => match find_generic(name, generics) {
+                Some(IdlGenericArg::Type { ty }) => self.decode(ty, &[], data)?,
+                _ => return Err(anyhow!("Generic type `{name}` not found")),
+            },
+            _ => return Err(anyhow!("Unsupported IDL type: {ty:?}")),
+        };
+
+        Ok(value)
+    }
+
+    fn decode_type_def(
+        &self,
+        ty_def: &IdlTypeDef,
+        generics: &Generics,
+        data: &mut &[u8],
+    ) -> Result<Value> {
+        match &ty_def.ty {
+            IdlTypeDefTy::Struct { fields } => self.decode_fields(fields.as_ref(), generics, data),
+            IdlTypeDefTy::Enum { variants } => {
+                let index = take(data, 1)?[0];
+                let variant = variants.get(index as usize).ok_or_else(|| {
+                    anyhow!("Invalid variant index {index} of enum `{}`", ty_def.name)
+                })?;
+                let fields = self
+                    .decode_fields(variant.fields.as_ref(), generics, data)
+                    .with_context(|| format!("Failed to decode variant `{}`", variant.name))?;
+                Ok(json!({ &variant.name: fields }))
+            }
+            IdlTypeDefTy::Type { alias } => self.decode(alias, generics, data),
+        }
+    }
+
+    fn decode_fields(
+        &self,
+        fields: Option<&IdlDefinedFields>,
+        generics: &Generics,
+        data: &mut &[u8],
+    ) -> Result<Value> {
+        match fields {
+            Some(IdlDefinedFields::Named(fields)) => {
+                let mut map = Map::new();
+                for field in fields {
+                    let value = self
+                        .decode(&field.ty, generics, data)
+                        .with_context(|| format!("Failed to decode field `{}`", field.name))?;
+                    map.insert(field.name.to_owned(), value);
+                }
+                Ok(Value::Object(map))
+            }
+            Some(IdlDefinedFields::Tuple(tys)) => Ok(Value::Array(
+                tys.iter()
+                    .map(|ty| self.decode(ty, generics, data))
+                    .collect::<Result<_>>()?,
+            )),
+            None => Ok(json!({})),
+        }
+    }
+
+    fn encode(
+        &self,
+        ty: &IdlType,
+        generics: &Generics,
+        value: &Value,
+        buf: &mut Vec<u8>,
+    ) -> Result<()> {
+        if encode_primitive(ty, value, buf)? {
+            return Ok(());
+        }
+
+        match ty {
+            IdlType::Bytes => {
+                let bytes = as_array(value)?
+                    .iter()
+                    .map(|b| {
+                        b.as_u64()
+                            .and_then(|b| u8::try_from(b).ok())
+                            .ok_or_else(|| anyhow!("Expected a byte, found `{b}`"))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                encode_len(bytes.len(), buf)?;
+                buf.extend(bytes);
+            }
+            IdlType::String => {
+                let s = value
+                    .as_str()
+                    .ok_or_else(|| anyhow!("Expected a string, found `{value}`"))?;
+                encode_len(s.len(), buf)?;
+                buf.extend(s.as_bytes());
+            }
+            IdlType::Option(ty) => match value {
+                Value::Null => buf.push(0),
+                _ => {
+                    buf.push(1);
+                    self.encode(ty, generics, value, buf)?;
+                }
+            },
+            IdlType::Vec(ty) => {
+                let values = as_array(value)?;
+                encode_len(values.len(), buf)?;
+                for value in values {
+                    self.encode(ty, generics, value, buf)?;
+                }
+            }
+            IdlType::Set(ty) => {
+                let entries = as_array(value)?
+                    .iter()
+                    .map(|value| {
+                        let mut element = vec![];
+                        self.encode(ty, generics, value, &mut element)?;
+                        Ok((element, vec![]))
+                    })
+                    .collect::<Result<_>>()?;
+                self.encode_sorted(ty, generics, entries, buf)?;
+            }
+            IdlType::Array(ty, len) => {
+                let len = resolve_array_len(len, generics)?;
+                let values = as_array(value)?;
+                if values.len() != len {
+                    return Err(anyhow!(
+                        "Expected an array of length {len}, found {}",
+                        values.len()
+                    ));
+                }
+                for value in values {
+                    self.encode(ty, generics, value, buf)?;
+                }
+            }
+            IdlType::Tuple(tys) => self.encode_tuple(tys, generics, value, buf)?,
+            IdlType::Map { key, value: ty } => {
+                let map = value
+                    .as_object()
+                    .ok_or_else(|| anyhow!("Expected an object, found `{value}`"))?;
+                let mut entries = vec![];
+                for (k, v) in map {
+                    let k = match key.as_ref() {
+                        IdlType::String
+                        | IdlType::Pubkey
+                        | IdlType::U64
+                        | IdlType::I64
+                        | IdlType::U128
+                        | IdlType::I128
+                        | IdlType::U256
+                        | IdlType::I256 => Value::String(k.to_owned()),
+                        _ => serde_json::from_str(k)
+                            .with_context(|| format!("Invalid map key `{k}`"))?,
+                    };
+                    let (mut k_buf, mut v_buf) = (vec![], vec![]);
+                    self.encode(key, generics, &k, &mut k_buf)?;
+                    self.encode(ty, generics, v, &mut v_buf)?;
+                    entries.push((k_buf, v_buf));
+                }
+                self.encode_sorted(key, generics, entries, buf)?;
+            }
+            IdlType::Defined {
+                name,
+                generics: args,
+            } => {
+                let (ty_def, generics) = self.resolve_defined(name, args, generics)?;
+                match &ty_def.serialization {
+                    IdlSerialization::Borsh => {
+                        self.encode_type_def(ty_def, &generics, value, buf)?
+                    }
+                    IdlSerialization::Bytemuck | IdlSerialization::BytemuckUnsafe => {
+                        self.encode_pod_type_def(ty_def, &generics, value, buf)?
+                    }
+                    serialization => {
+                        return Err(anyhow!(
+                            "Unsupported serialization of type `{name}`: {serialization:?}"
+                        ))
+                    }
+                }
+            }
+            IdlType::Generic(name) => match find_generic(name, generics) {
+                Some(IdlGenericArg::Type { ty }) => self.encode(ty, &[], value, buf)?,
+                _ => return Err(anyhow!("Generic type `{name}` not found")),
+            },
+            _ => return Err(anyhow!("Unsupported IDL type: {ty:?}")),
+        }
+
+        Ok(())
+    }
+
+    fn encode_tuple(
+        &self,
+        tys: &[IdlType],
+        generics: &Generics,
+        value: &Value,
+        buf: &mut Vec<u8>,
+    ) -> Result<()> {
+        let values = as_array(value)?;
+        if values.len() != tys.len() {
+            return Err(anyhow!(
+                "Expected {} elements, found {}",
+                tys.len(),
+                values.len()
+            ));
+        }
+        for (ty, value) in tys.iter().zip(values) {
+            self.encode(ty, generics, value, buf)?;
+        }
+
+        Ok(())
+    }
+
+    /// Encode the entries of a map or set, which are sorted by their keys like Borsh does, since
+    /// the entries of a JSON object are sorted by their string keys and arrays are not sorted.
+    fn encode_sorted(
+        &self,
+        key: &IdlType,
+        generics: &Generics,
+        mut entries: Vec<(Vec<u8>, Vec<u8>)>,
+        buf: &mut Vec<u8>,
+    ) -> Result<()> {
+        let mut error = None;
+        entries.sort_by(|(a, _), (b, _)| {
+            self.compare(key, generics, &mut a.as_slice(), &mut b.as_slice())
+                .unwrap_or_else(|e| {
+                    error.get_or_insert(e);
+                    Ordering::Equal
+                })
+        });
+        if let Some(e) = error {
+            return Err(e);
+        }
+        if let Some(duplicate) = entries.windows(2).find(|w| w[0].0 == w[1].0) {
+            let key = self.decode(key, generics, &mut duplicate[0].0.as_slice())?;
+            return Err(anyhow!("Duplicate key `{key}`"));
+        }
+
+        encode_len(entries.len(), buf)?;
+        for (key, value) in entries {
+            buf.extend(key);
+            buf.extend(value);
+        }
+
+        Ok(())
+    }
+
+    /// Compare two serialized values of type `ty` in the order of their Rust type.
+    fn compare(
+        &self,
+        ty: &IdlType,
+        generics: &Generics,
+        a: &mut &[u8],
+        b: &mut &[u8],
+    ) -> Result<Ordering> {
+        let ordering = match ty {
+            IdlType::Bool | IdlType::U8 => compare_le(take(a, 1)?, take(b, 1)?, false),
+            IdlType::I8 => compare_le(take(a, 1)?, take(b, 1)?, true),
+            IdlType::U16 => compare_le(take(a, 2)?, take(b, 2)?, false),
+            IdlType::I16 => compare_le(take(a, 2)?, take(b, 2)?, true),
+            IdlType::U32 => compare_le(take(a, 4)?, take(b, 4)?, false),
+            IdlType::I32 => compare_le(take(a, 4)?, take(b, 4)?, true),
+            IdlType::U64 => compare_le(take(a, 8)?, take(b, 8)?, false),
+            IdlType::I64 => compare_le(take(a, 8)?, take(b, 8)?, true),
+            IdlType::U128 => compare_le(take(a, 16)?, take(b, 16)?, false),
+            IdlType::I128 => compare_le(take(a, 16)?, take(b, 16)?, true),
+            IdlType::U256 => compare_le(take(a, 32)?, take(b, 32)?, false),
+            IdlType::I256 => compare_le(take(a, 32)?, take(b, 32)?, true),
+            IdlType::F32 => f32::from_le_bytes(take_array(a)?)
+                .partial_cmp(&f32::from_le_bytes(take_array(b)?))
+                .unwrap_or(Ordering::Equal),
+            IdlType::F64 => f64::from_le_bytes(take_array(a)?)
+                .partial_cmp(&f64::from_le_bytes(take_array(b)?))
+                .unwrap_or(Ordering::Equal),
+            IdlType::Pubkey => take(a, 32)?.cmp(take(b, 32)?),
+            IdlType::Bytes | IdlType::String => {
+                let (a_len, b_len) = (decode_len(a)?, decode_len(b)?);
+                take(a, a_len)?.cmp(take(b, b_len)?)
+            }
+            IdlType::Option(ty) => match (take(a, 1)?[0], take(b, 1)?[0]) {
+                (1, 1) => self.compare(ty, generics, a, b)?,
+                (a, b) => a.cmp(&b),
+            },
+            IdlType::Vec(ty) | IdlType::Set(ty) => {
+                let (a_len, b_len) = (decode_len(a)?, decode_len(b)?);
+                let tys = std::iter::repeat_n(ty.as_ref(), a_len.min(b_len));
+                self.compare_seq(tys, generics, a, b)?
+                    .then(a_len.cmp(&b_len))
+            }
+            IdlType::Map { key, value } => {
+                let (a_len, b_len) = (decode_len(a)?, decode_len(b)?);
+                let tys =
+                    std::iter::repeat_n([key.as_ref(), value.as_ref()], a_len.min(b_len)).flatten();
+                self.compare_seq(tys, generics, a, b)?
+                    .then(a_len.cmp(&b_len))
+            }
+            IdlType::Array(ty, len) => {
+                let len = resolve_array_len(len, generics)?;
+                self.compare_seq(std::iter::repeat_n(ty.as_ref(), len), generics, a, b)?
+            }
+            IdlType::Tuple(tys) => self.compare_seq(tys.iter(), generics, a, b)?,
+            IdlType::Defined {
+                name,
+                generics: args,
+            } => {
+                let (ty_def, generics) = self.resolve_defined(name, args, generics)?;
+                if !matches!(ty_def.serialization, IdlSerialization::Borsh) {
+                    return Err(anyhow!("Unsupported key type `{name}`"));
+                }
+                match &ty_def.ty {
+                    IdlTypeDefTy::Struct { fields } => {
+                        let tys = field_types(fields.as_ref());
+                        self.compare_seq(tys.into_iter(), &generics, a, b)?
+                    }
+                    IdlTypeDefTy::Enum { variants } => {
+                        let (a_index, b_index) = (take(a, 1)?[0], take(b, 1)?[0]);
+                        match variants.get(a_index as usize) {
+                            Some(variant) if a_index == b_index => {
+                                let tys = field_types(variant.fields.as_ref());
+                                self.compare_seq(tys.into_iter(), &generics, a, b)?
+                            }
+                            _ => a_index.cmp(&b_index),
+                        }
+                    }
+                    IdlTypeDefTy::Type { alias } => self.compare(alias, &generics, a, b)?,
+                }
+            }
+            IdlType::Generic(name) => match find_generic(name, generics) {
+                Some(IdlGenericArg::Type { ty }) => self.compare(ty, &[], a, b)?,
+                _ => return Err(anyhow!("Generic type `{name}` not found")),
+            },
+            _ => return Err(anyhow!("Unsupported key type: {ty:?}")),
+        };
+
+        Ok(ordering)
+    }
+
+    /// Compare two serialized sequences of values of the given types lexicographically.
+    fn compare_seq<'t>(
+        &self,
+        tys: impl Iterator<Item = &'t IdlType>,
+        generics: &Generics,
+        a: &mut &[u8],
+        b: &mut &[u8],
+    ) -> Result<Ordering> {
+        for ty in tys {
+            let ordering = self.compare(ty, generics, a, b)?;
+            if ordering.is_ne() {
+                return Ok(ordering);
+            }
+        }
+
+        Ok(Ordering::Equal)
+    }
+
+    fn encode_type_def(
+        &self,
+        ty_def: &IdlTypeDef,
+        generics: &Generics,
+        value: &Value,
+        buf: &mut Vec<u8>,
+    ) -> Result<()> {
+        match &ty_def.ty {
+            IdlTypeDefTy::Struct { fields } => {
+                self.encode_fields(fields.as_ref(), generics, value, buf)
+            }
+            IdlTypeDefTy::Enum { variants } => {
+                let (name, fields) = match value.as_object() {
+                    Some(map) if map.len() == 1 => map.iter().next().unwrap(),
+                    _ => {
+                        return Err(anyhow!(
+                        "Expected an object with a single variant of enum `{}`, found `{value}`",
+                        ty_def.name
+                    ))
+                    }
+                };
+                let (index, variant) = variants
+                    .iter()
+                    .enumerate()
+                    .find(|(_, variant)| &variant.name == name)
+                    .ok_or_else(|| {
+                        anyhow!("Variant `{name}` not found in enum `{}`", ty_def.name)
+                    })?;
+                buf.push(
+                    u8::try_from(index)
+                        .map_err(|_| anyhow!("Too many variants in enum `{}`", ty_def.name))?,
+                );
+                self.encode_fields(variant.fields.as_ref(), generics, fields, buf)
+                    .with_context(|| format!("Failed to encode variant `{name}`"))
+            }
+            IdlTypeDefTy::Type { alias } => self.encode(alias, generics, value, buf),
+        }
+    }
+
+    fn encode_fields(
+        &self,
+        fields: Option<&IdlDefinedFields>,
+        generics: &Generics,
+        value: &Value,
+        buf: &mut Vec<u8>,
+    ) -> Result<()> {
+        match fields {
+            Some(IdlDefinedFields::Named(fields)) => {
+                for field in fields {
+                    let value = value
+                        .get(&field.name)
+                        .ok_or_else(|| anyhow!("Missing field `{}`", field.name))?;
+                    self.encode(&field.ty, generics, value, buf)
+                        .with_context(|| format!("Failed to encode field `{}`", field.name))?;
+                }
+                Ok(())
+            }
+            Some(IdlDefinedFields::Tuple(tys)) => self.encode_tuple(tys, generics, value, buf),
+            None => Ok(()),
+        }
+    }
+
+    /// Get the in-memory layout of the type.
+    fn layout(&self, ty: &IdlType, generics: &Generics) -> Result<Layout> {
+        let layout = |size, align| Ok(Layout { size, align });
+        match ty {
+            IdlType::Bool | IdlType::U8 | IdlType::I8 => layout(1, 1),
+            IdlType::U16 | IdlType::I16 => layout(2, 2),
+            IdlType::U32 | IdlType::I32 | IdlType::F32 => layout(4, 4),
+            IdlType::U64 | IdlType::I64 | IdlType::F64 => layout(8, 8),
+            // `u128` and `i128` are 8 byte aligned on SBF
+            IdlType::U128 | IdlType::I128 => layout(16, 8),
+            IdlType::Pubkey => layout(32, 1),
+            IdlType::Array(ty, len) => {
+                let elem = self.layout(ty, generics)?;
+                layout(elem.size * resolve_array_len(len, generics)?, elem.align)
+            }
+            IdlType::Defined {
+                name,
+                generics: args,
+            } => {
+                let (ty_def, generics) = self.resolve_defined(name, args, generics)?;
+                self.pod_type_def_layout(ty_def, &generics)
+                    .map(|(layout, _)| layout)
+            }
+            IdlType::Generic(name) => match find_generic(name, generics) {
+                Some(IdlGenericArg::Type { ty }) => self.layout(ty, &[]),
+                _ => Err(anyhow!("Generic type `{name}` not found")),
+            },
+            _ => Err(anyhow!(
+                "Type is not supported with `bytemuck` serialization: {ty:?}"
+            )),
+        }
+    }
+
+    /// Get the layout of the type definition, and the offsets of its fields.
+    fn pod_type_def_layout(
+        &self,
+        ty_def: &IdlTypeDef,
+        generics: &Generics,
+    ) -> Result<(Layout, Vec<usize>)> {
+        let tys = match &ty_def.ty {
+            IdlTypeDefTy::Struct { fields } => field_types(fields.as_ref()),
+            IdlTypeDefTy::Type { alias } => vec![alias],
+            IdlTypeDefTy::Enum { .. } => {
+                return Err(anyhow!(
+                    "Enum `{}` is not supported with `bytemuck` serialization",
+                    ty_def.name
+                ))
+            }
+        };
+        let (packed, min_align) = match &ty_def.repr {
+            Some(IdlRepr::Rust(modifier) | IdlRepr::C(modifier)) => {
+                (modifier.packed, modifier.align.unwrap_or(1))
+            }
+            _ => (false, 1),
+        };
+
+        let mut offsets = Vec::with_capacity(tys.len());
+        let mut offset = 0usize;
+        let mut align = min_align;
+        for ty in tys {
+            let layout = self.layout(ty, generics)?;
+            let field_align = if packed { 1 } else { layout.align };
+            offset = offset.next_multiple_of(field_align);
+            offsets.push(offset);
+            offset += layout.size;
+            align = align.max(field_align);
+        }
+
+        let layout = Layout {
+            size: offset.next_multiple_of(align),
+            align,
+        };
+        Ok((layout, offsets))
+    }
+
+    fn decode_pod(&self, ty: &IdlType, generics: &Generics, data: &mut &[u8]) -> Result<Value> {
+        match ty {
+            IdlType::Array(ty, len) => Ok(Value::Array(
+                (0..resolve_array_len(len, generics)?)
+                    .map(|_| self.decode_pod(ty, generics, data))
+                    .collect::<Result<_>>()?,
+            )),
+            IdlType::Defined {
+                name,
+                generics: args,
+            } => {
+                let (ty_def, generics) = self.resolve_defined(name, args, generics)?;
+                self.decode_pod_type_def(ty_def, &generics, data)
+            }
+            IdlType::Generic(name) => match find_generic(name, generics) {
+                Some(IdlGenericArg::Type { ty }) => self.decode_pod(ty, &[], data),
+                _ => Err(anyhow!("Generic type `{name}` not found")),
+            },
+            _ => decode_primitive(ty, data)?.ok_or_else(|| {
+                anyhow!("Type is not supported with `bytemuck` serialization: {ty:?}")
+            }),
+        }
+    }
+
+    fn decode_pod_type_def(
+        &self,
+        ty_def: &IdlTypeDef,
+        generics: &Generics,
+        data: &mut &[u8],
+    ) -> Result<Value> {
+        let (layout, offsets) = self.pod_type_def_layout(ty_def, generics)?;
+        let start = data.len();
+        let skip_to = |data: &mut &[u8], offset: usize| {
+            let read = start - data.len();
+            take(data, offset - read).map(|_| ())
+        };
+
+        let value = match &ty_def.ty {
+            IdlTypeDefTy::Struct { fields } => {
+                let tys = field_types(fields.as_ref());
+                let mut values = Vec::with_capacity(tys.len());
+                for (ty, offset) in tys.into_iter().zip(offsets) {
+                    skip_to(data, offset)?;
+                    values.push(self.decode_pod(ty, generics, data)?);
+                }
+
+                match fields {
+                    Some(IdlDefinedFields::Named(fields)) => Value::Object(
+                        fields
+                            .iter()
+                            .map(|field| field.name.to_owned())
+                            .zip(values)
+                            .collect(),
+                    ),
+                    Some(IdlDefinedFields::Tuple(_)) => Value::Array(values),
+                    None => json!({}),
+                }
+            }
+            IdlTypeDefTy::Type { alias } => self.decode_pod(alias, generics, data)?,
+            IdlTypeDefTy::Enum { .. } => unreachable!("Checked in layout"),
+        };
+        skip_to(data, layout.size)?;
+
+        Ok(value)
+    }
+
+    fn encode_pod(
+        &self,
+        ty: &IdlType,
+        generics: &Generics,
+        value: &Value,
+        buf: &mut Vec<u8>,
+    ) -> Result<()> {
+        match ty {
+            IdlType::Array(ty, len) => {
+                let len = resolve_array_len(len, generics)?;
+                let values = as_array(value)?;
+                if values.len() != len {
+                    return Err(anyhow!(
+                        "Expected an array of length {len}, found {}",
+                        values.len()
+                    ));
+                }
+                values
+                    .iter()
+                    .try_for_each(|value| self.encode_pod(ty, generics, value, buf))
+            }
+            IdlType::Defined {
+                name,
+                generics: args,
+            } => {
+                let (ty_def, generics) = self.resolve_defined(name, args, generics)?;
+                self.encode_pod_type_def(ty_def, &generics, value, buf)
+            }
+            IdlType::Generic(name) => match find_generic(name, generics) {
+                Some(IdlGenericArg::Type { ty }) => self.encode_pod(ty, &[], value, buf),
+                _ => Err(anyhow!("Generic type `{name}` not found")),
+            },
+            _ => match encode_primitive(ty, value, buf)? {
+                true => Ok(()),
+                false => Err(anyhow!(
+                    "Type is not supported with `bytemuck` serialization: {ty:?}"
+                )),
+            },
+        }
+    }
+
+    fn encode_pod_type_def(
+        &self,
+        ty_def: &IdlTypeDef,
+        generics: &Generics,
+        value: &Value,
+        buf: &mut Vec<u8>,
+    ) -> Result<()> {
+        let (layout, offsets) = self.pod_type_def_layout(ty_def, generics)?;
+        let start = buf.len();
+        let pad_to = |buf: &mut Vec<u8>, offset: usize| buf.resize(start + offset, 0);
+
+        match &ty_def.ty {
+            IdlTypeDefTy::Struct { fields } => {
+                let values = match fields {
+                    Some(IdlDefinedFields::Named(fields)) => fields
+                        .iter()
+                        .map(|field| {
+                            value
+                                .get(&field.name)
+                                .ok_or_else(|| anyhow!("Missing field `{}`", field.name))
+                        })
+                        .collect::<Result<Vec<_>>>()?,
+                    Some(IdlDefinedFields::Tuple(_)) => as_array(value)?.iter().collect(),
+                    None => vec![],
+                };
+                let tys = field_types(fields.as_ref());
+                if values.len() != tys.len() {
+                    return Err(anyhow!(
+                        "Expected {} fields, found {}",
+                        tys.len(),
+                        values.len()
+                    ));
+                }
+                for ((ty, value), offset) in tys.into_iter().zip(values).zip(offsets) {
+                    pad_to(buf, offset);
+                    self.encode_pod(ty, generics, value, buf)?;
+                }
+            }
+            IdlTypeDefTy::Type { alias } => self.encode_pod(alias, generics, value, buf)?,
+            IdlTypeDefTy::Enum { .. } => unreachable!("Checked in layout"),
+        }
+        pad_to(buf, layout.size);
+
+        Ok(())
+    }
+}
+
+fn field_types(fields: Option<&IdlDefinedFields>) -> Vec<&IdlType> {
+    match fields {
+        Some(IdlDefinedFields::Named(fields)) => fields.iter().map(|field| &field.ty).collect(),
+        Some(IdlDefinedFields::Tuple(tys)) => tys.iter().collect(),
+        None => vec![],
+    }
+}
+
+fn find_generic<'a>(name: &str, generics: &'a Generics) -> Option<&'a IdlGenericArg> {
+    generics
+        .iter()
+        .find(|(generic, _)| generic == name)
+        .map(|(_, arg)| arg)
+}
+
+fn resolve_array_len(len: &IdlArrayLen, generics: &Generics) -> Result<usize> {
+    match len {
+        IdlArrayLen::Value(len) => Ok(*len),
+        IdlArrayLen::Generic(name) => match find_generic(name, generics) {
+            Some(IdlGenericArg::Const { value }) => value
+                .parse()
+                .map_err(|_| anyhow!("Invalid array length: `{value}`")),
+            _ => Err(anyhow!("Generic array length `{name}` not found")),
+        },
+    }
+}
+
+/// Replace the generic parameters of the type with the given arguments.
+pub(crate) fn substitute(ty: &IdlType, generics: &Generics) -> Result<IdlType> {
+    let ty = match ty {
+        IdlType::Option(ty) => IdlType::Option(Box::new(substitute(ty, generics)?)),
+        IdlType::Vec(ty) => IdlType::Vec(Box::new(substitute(ty, generics)?)),
+        IdlType::Set(ty) => IdlType::Set(Box::new(substitute(ty, generics)?)),
+        IdlType::Array(ty, len) => IdlType::Array(
+            Box::new(substitute(ty, generics)?),
+            match len {
+                IdlArrayLen::Generic(_) => IdlArrayLen::Value(resolve_array_len(len, generics)?),
+                IdlArrayLen::Value(len) => IdlArrayLen::Value(*len),
+            },
+        ),
+        IdlType::Tuple(tys) => IdlType::Tuple(
+            tys.iter()
+                .map(|ty| substitute(ty, generics))
+                .collect::<Result<_>>()?,
+        ),
+        IdlType::Map { key, value } => IdlType::Map {
+            key: Box::new(substitute(key, generics)?),
+            value: Box::new(substitute(value, generics)?),
+        },
+        IdlType::Defined {
+            name,
+            generics: args,
+        } => IdlType::Defined {
+            name: name.to_owned(),
+            generics: args
+                .iter()
+                .map(|arg| substitute_arg(arg, generics))
+                .collect::<Result<_>>()?,
+        },
+        IdlType::Generic(name) => match find_generic(name, generics) {
+            Some(IdlGenericArg::Type { ty }) => ty.to_owned(),
+            _ => return Err(anyhow!("Generic type `{name}` not found")),
+        },
+        _ => ty.to_owned(),
+    };
+
+    Ok(ty)
+}
+
+/// Replace the generic parameters of the generic argument with the given arguments.
+///
+/// Const arguments can refer to an outer const generic parameter by its name, either directly or
+/// as a generic type argument.
+fn substitute_arg(arg: &IdlGenericArg, generics: &Generics) -> Result<IdlGenericArg> {
+    let arg = match arg {
+        IdlGenericArg::Type {
+            ty: IdlType::Generic(name),
+        } if matches!(
+            find_generic(name, generics),
+            Some(IdlGenericArg::Const { .. })
+        ) =>
+        {
+            find_generic(name, generics).unwrap().to_owned()
+        }
+        IdlGenericArg::Type { ty } => IdlGenericArg::Type {
+            ty: substitute(ty, generics)?,
+        },
+        IdlGenericArg::Const { value } => IdlGenericArg::Const {
+            value: match find_generic(value, generics) {
+                Some(IdlGenericArg::Const { value }) => value.to_owned(),
+                _ => value.to_owned(),
+            },
+        },
+    };
+
+    Ok(arg)
+}
+
+fn take<'d>(data: &mut &'d [u8], len: usize) -> Result<&'d [u8]> {
+    if data.len() < len {
+        return Err(anyhow!(
+            "Unexpected end of data: expected {len} bytes, found {}",
+            data.len()
+        ));
+    }
+
+    let (bytes, rest) = data.split_at(len);
+    *data = rest;
+    Ok(bytes)
+}
+
+fn take_array<const N: usize>(data: &mut &[u8]) -> Result<[u8; N]> {
+    Ok(take(data, N)?.try_into().unwrap())
+}
+
+fn decode_len(data: &mut &[u8]) -> Result<usize> {
+    Ok(u32::from_le_bytes(take_array(data)?) as usize)
+}
+
+fn encode_len(len: usize, buf: &mut Vec<u8>) -> Result<()> {
+    let len = u32::try_from(len).map_err(|_| anyhow!("Length {len} is too large"))?;
+    buf.extend(len.to_le_bytes());
+    Ok(())
+}
+
+/// Compare two little-endian integers.
+fn compare_le(a: &[u8], b: &[u8], signed: bool) -> Ordering {
+    let sign = |n: &[u8]| signed && n.last().is_some_and(|b| b & 0x80 != 0);
+    sign(b)
+        .cmp(&sign(a))
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn as_array(value: &Value) -> Result<&Vec<Value>> {
+    value
+        .as_array()
+        .ok_or_else(|| anyhow!("Expected an array, found `{value}`"))
+}
+
+/// Decode a fixed size primitive, returning `None` if the type is not one.
+fn decode_primitive(ty: &IdlType, data: &mut &[u8]) -> Result<Option<Value>> {
+    let value = match ty {
+        IdlType::Bool => match take(data, 1)?[0] {
+            0 => json!(false),
+            1 => json!(true),
+            b => return Err(anyhow!("Invalid bool: {b}")),
+        },
+        IdlType::U8 => json!(u8::from_le_bytes(take_array(data)?)),
+        IdlType::I8 => json!(i8::from_le_bytes(take_array(data)?)),
+        IdlType::U16 => json!(u16::from_le_bytes(take_array(data)?)),
+        IdlType::I16 => json!(i16::from_le_bytes(take_array(data)?)),
+        IdlType::U32 => json!(u32::from_le_bytes(take_array(data)?)),
+        IdlType::I32 => json!(i32::from_le_bytes(take_array(data)?)),
+        IdlType::F32 => float(f32::from_le_bytes(take_array(data)?).into())?,
+        IdlType::F64 => float(f64::from_le_bytes(take_array(data)?))?,
+        IdlType::U64 => json!(u64::from_le_bytes(take_array(data)?).to_string()),
+        IdlType::I64 => json!(i64::from_le_bytes(take_array(data)?).to_string()),
+        IdlType::U128 => json!(u128::from_le_bytes(take_array(data)?).to_string()),
+        IdlType::I128 => json!(i128::from_le_bytes(take_array(data)?).to_string()),
+        IdlType::U256 => json!(u256_to_string(take_array(data)?)),
+        IdlType::I256 => json!(i256_to_string(take_array(data)?)),
+        IdlType::Pubkey => json!(bs58::encode(take(data, 32)?).into_string()),
+        _ => return Ok(None),
+    };
+
+    Ok(Some(value))
+}
+
+/// Encode a fixed size primitive, returning `false` if the type is not one.
+fn encode_primitive(ty: &IdlType, value: &Value, buf: &mut Vec<u8>) -> Result<bool> {
+    match ty {
+        IdlType::Bool => buf.push(
+            value
+                .as_bool()
+                .ok_or_else(|| anyhow!("Expected a bool, found `{value}`"))? as u8,
+        ),
+        IdlType::U8 => buf.extend(u8::try_from(unsigned(value)?)?.to_le_bytes()),
+        IdlType::I8 => buf.extend(i8::try_from(signed(value)?)?.to_le_bytes()),
+        IdlType::U16 => buf.extend(u16::try_from(unsigned(value)?)?.to_le_bytes()),
+        IdlType::I16 => buf.extend(i16::try_from(signed(value)?)?.to_le_bytes()),
+        IdlType::U32 => buf.extend(u32::try_from(unsigned(value)?)?.to_le_bytes()),
+        IdlType::I32 => buf.extend(i32::try_from(signed(value)?)?.to_le_bytes()),
+        IdlType::U64 => buf.extend(u64::try_from(unsigned(value)?)?.to_le_bytes()),
+        IdlType::I64 => buf.extend(i64::try_from(signed(value)?)?.to_le_bytes()),
+        IdlType::U128 => buf.extend(unsigned(value)?.to_le_bytes()),
+        IdlType::I128 => buf.extend(signed(value)?.to_le_bytes()),
+        IdlType::F32 => buf.extend((as_f64(value)? as f32).to_le_bytes()),
+        IdlType::F64 => buf.extend(as_f64(value)?.to_le_bytes()),
+        IdlType::U256 => buf.extend(parse_u256(&integer_string(value)?)?),
+        IdlType::I256 => buf.extend(parse_i256(&integer_string(value)?)?),
+        IdlType::Pubkey => {
+            let pubkey = value
+                .as_str()
+                .ok_or_else(|| anyhow!("Expected a base58 string, found `{value}`"))?;
+            let mut bytes = [0; 32];
+            match bs58::decode(pubkey).onto(&mut bytes) {
+                Ok(32) => buf.extend(bytes),
+                _ => return Err(anyhow!("Invalid public key: `{pubkey}`")),
+            }
+        }
+        _ => return Ok(false),
+    }
+
+    Ok(true)
+}
+
+fn float(f: f64) -> Result<Value> {
+    Number::from_f64(f)
+        .map(Value::Number)
+        .ok_or_else(|| anyhow!("Float `{f}` can't be represented in JSON"))
+}
+
+fn as_f64(value: &Value) -> Result<f64> {
+    value
+        .as_f64()
+        .ok_or_else(|| anyhow!("Expected a number, found `{value}`"))
+}
+
+/// Get the string representation of an integer that is either a JSON number or a string.
+fn integer_string(value: &Value) -> Result<String> {
+    match value {
+        Value::Number(n) if n.is_u64() || n.is_i64() => Ok(n.to_string()),
+        Value::String(s) => Ok(s.to_owned()),
+        _ => Err(anyhow!("Expected an integer, found `{value}`")),
+    }
+}
+
+fn unsigned(value: &Value) -> Result<u128> {
+    let s = integer_string(value)?;
+    s.parse()
+        .map_err(|_| anyhow!("Invalid unsigned integer: `{s}`"))
+}
+
+fn signed(value: &Value) -> Result<i128> {
+    let s = integer_string(value)?;
+    s.parse()
+        .map_err(|_| anyhow!("Invalid signed integer: `{s}`"))
+}
+
+/// Convert a little-endian 256-bit unsigned integer to its decimal representation.
+fn u256_to_string(mut le: [u8; 32]) -> String {
+    let mut digits = vec![];
+    while le.iter().any(|b| *b != 0) {
+        // Divide by 10, starting from the most significant byte
+        let mut rem = 0u16;
+        for b in le.iter_mut().rev() {
+            let cur = (rem << 8) | *b as u16;
+            *b = (cur / 10) as u8;
+            rem = cur % 10;
+        }
+        digits.push(b'0' + rem as u8);
+    }
+
+    if digits.is_empty() {
+        return "0".into();
+    }
+    digits.reverse();
+    String::from_utf8(digits).unwrap()
+}
+
+fn i256_to_string(le: [u8; 32]) -> String {
+    match le[31] >> 7 {
+        1 => format!("-{}", u256_to_string(negate(le))),
+        _ => u256_to_string(le),
+    }
+}
+
+/// Parse the decimal representation of a 256-bit unsigned integer as little-endian bytes.
+fn parse_u256(s: &str) -> Result<[u8; 32]> {
+    let invalid = || anyhow!("Invalid unsigned 256-bit integer: `{s}`");
+    if s.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut le = [0u8; 32];
+    for c in s.chars() {
+        let digit = c.to_digit(10).ok_or_else(invalid)?;
+
+        // Multiply by 10 and add the digit
+        let mut carry = digit;
+        for b in le.iter_mut() {
+            let cur = *b as u32 * 10 + carry;
+            *b = cur as u8;
+            carry = cur >> 8;
+        }
+        if carry != 0 {
+            return Err(invalid());
+        }
+    }
+
+    Ok(le)
+}
+
+fn parse_i256(s: &str) -> Result<[u8; 32]> {
+    let invalid = || anyhow!("Invalid signed 256-bit integer: `{s}`");
+    let (negative, magnitude) = match s.strip_prefix('-') {
+        Some(magnitude) => (true, magnitude),
+        None => (false, s),
+    };
+    let le = parse_u256(magnitude).map_err(|_| invalid())?;
+
+    let is_zero = le.iter().all(|b| *b == 0);
+    match (negative && !is_zero, le[31] >> 7) {
+        (false, 0) => Ok(le),
+        (true, _) => {
+            let le = negate(le);
+            // The negation must be negative, which only fails if the magnitude is too large
+            match le[31] >> 7 {
+                1 => Ok(le),
+                _ => Err(invalid()),
+            }
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// Two's complement negation of a little-endian 256-bit integer.
+fn negate(mut le: [u8; 32]) -> [u8; 32] {
+    let mut carry = true;
+    for b in le.iter_mut() {
+        let (sum, overflow) = (!*b).overflowing_add(carry as u8);
+        *b = sum;
+        carry = overflow;
+    }
+    le
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn idl(value: Value) -> Idl {
+        let mut idl = json!({
+            "address": "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS",
+            "metadata": { "name": "test", "version": "0.1.0", "spec": "0.1.0" },
+            "instructions": []
+        });
+        idl.as_object_mut()
+            .unwrap()
+            .extend(value.as_object().unwrap().clone());
+        serde_json::from_value(idl).unwrap()
+    }
+
+    fn ty(value: Value) -> IdlType {
+        serde_json::from_value(value).unwrap()
+    }
+
+    /// Assert that `value` encodes to `bytes`, and `bytes` decodes to `value`.
+    fn assert_roundtrip(idl: &Idl, ty: &IdlType, value: Value, bytes: &[u8]) {
+        assert_eq!(encode(idl, ty, &value).unwrap(), bytes);
+
+        let mut data = bytes;
+        assert_eq!(decode(idl, ty, &mut data).unwrap(), value);
+        assert!(data.is_empty());
+    }
+
+    #[test]
+    fn primitives() {
+        let idl = idl(json!({}));
+        assert_roundtrip(&idl, &IdlType::Bool, json!(true), &[1]);
+        assert_roundtrip(&idl, &IdlType::U8, json!(255), &[255]);
+        assert_roundtrip(&idl, &IdlType::I8, json!(-1), &[255]);
+        assert_roundtrip(&idl, &IdlType::U16, json!(258), &[2, 1]);
+        assert_roundtrip(&idl, &IdlType::I32, json!(-2), &[254, 255, 255, 255]);
+        assert_roundtrip(&idl, &IdlType::F32, json!(1.5), &1.5f32.to_le_bytes());
+        assert_roundtrip(&idl, &IdlType::F64, json!(-0.25), &(-0.25f64).to_le_bytes());
+        assert_roundtrip(
+            &idl,
+            &IdlType::U64,
+            json!(u64::MAX.to_string()),
+            &u64::MAX.to_le_bytes(),
+        );
+        assert_roundtrip(
+            &idl,
+            &IdlType::I128,
+            json!(i128::MIN.to_string()),
+            &i128::MIN.to_le_bytes(),
+        );
+        assert_roundtrip(&idl, &IdlType::String, json!("ab"), &[2, 0, 0, 0, 97, 98]);
+        assert_roundtrip(&idl, &IdlType::Bytes, json!([1, 2]), &[2, 0, 0, 0, 1, 2]);
+        assert_roundtrip(
+            &idl,
+            &IdlType::Pubkey,
+            json!("11111111111111111111111111111111"),
+            &[0; 32],
+        );
+
+        // Integers can also be encoded from numbers
+        assert_eq!(
+            encode(&idl, &IdlType::U64, &json!(5)).unwrap(),
+            5u64.to_le_bytes()
+        );
+        assert_eq!(
+            encode(&idl, &IdlType::U8, &json!("5")).unwrap(),
+            5u8.to_le_bytes()
+        );
+        assert!(encode(&idl, &IdlType::U8, &json!(256)).is_err());
+        assert!(encode(&idl, &IdlType::Bool, &json!(1)).is_err());
+    }
+
+    #[test]
+    fn u256() {
+        let idl = idl(json!({}));
+        let max = "115792089237316195423570985008687907853269984665640564039457584007913129639935";
+        assert_roundtrip(&idl, &IdlType::U256, json!(max), &[255; 32]);
+        assert_roundtrip(&idl, &IdlType::U256, json!("0"), &[0; 32]);
+
+        let mut bytes = [0; 32];
+        bytes[..16].copy_from_slice(&u128::MAX.to_le_bytes());
+        assert_roundtrip(&idl, &IdlType::U256, json!(u128::MAX.to_string()), &bytes);
+        assert!(encode(&idl, &IdlType::U256, &json!(format!("{max}0"))).is_err());
+
+        assert_roundtrip(&idl, &IdlType::I256, json!("-1"), &[255; 32]);
+        let mut bytes = [0; 32];
+        bytes[31] = 0x80;
+        let min = "-57896044618658097711785492504343953926634992332820282019728792003956564819968";
+        assert_roundtrip(&idl, &IdlType::I256, json!(min), &bytes);
+        assert!(encode(&idl, &IdlType::I256, &json!(&min[1..])).is_err());
+    }
+
+    #[test]
+    fn collections() {
+        let idl = idl(json!({}));
+        assert_roundtrip(&idl, &ty(json!({ "option": "u8" })), json!(null), &[0]);
+        assert_roundtrip(&idl, &ty(json!({ "option": "u8" })), json!(7), &[1, 7]);
+        assert_roundtrip(
+            &idl,
+            &ty(json!({ "vec": "u16" })),
+            json!([1, 2]),
+            &[2, 0, 0, 0, 1, 0, 2, 0],
+        );
+        assert_roundtrip(
+            &idl,
+            &ty(json!({ "array": ["u8", 3] })),
+            json!([1, 2, 3]),
+            &[1, 2, 3],
+        );
+        assert_roundtrip(
+            &idl,
+            &ty(json!({ "tuple": ["u8", "bool"] })),
+            json!([1, true]),
+            &[1, 1],
+        );
+        assert_roundtrip(
+            &idl,
+            &ty(json!({ "set": "u8" })),
+            json!([1, 2]),
+            &[2, 0, 0, 0, 1, 2],
+        );
+        assert_roundtrip(
+            &idl,
+            &ty(json!({ "map": { "key": "u8", "value": "string" } })),
+            json!({ "1": "a" }),
+            &[1, 0, 0, 0, 1, 1, 0, 0, 0, 97],
+        );
+        assert_roundtrip(
+            &idl,
+            &ty(json!({ "map": { "key": "string", "value": "u64" } })),
+            json!({ "a": "1" }),
+            &[1, 0, 0, 0, 1, 0, 0, 0, 97, 1, 0, 0, 0, 0, 0, 0, 0],
+        );
+
+        assert!(encode(&idl, &ty(json!({ "array": ["u8", 3] })), &json!([1])).is_err());
+        assert!(decode(
+            &idl,
+            &ty(json!({ "vec": "u8" })),
+            &mut [1, 0, 0, 0].as_slice()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn sorted_collections() {
+        let idl = idl(json!({
+            "types": [{
+                "name": "Key",
+                "type": {
+                    "kind": "enum",
+                    "variants": [{ "name": "A", "fields": ["i16"] }, { "name": "B" }]
+                }
+            }]
+        }));
+
+        // Keys are sorted by their value, not by their JSON string
+        assert_roundtrip(
+            &idl,
+            &ty(json!({ "map": { "key": "u8", "value": "bool" } })),
+            json!({ "9": true, "10": false }),
+            &[2, 0, 0, 0, 9, 1, 10, 0],
+        );
+        let map = ty(json!({ "map": { "key": "u64", "value": "u8" } }));
+        let bytes = encode(&idl, &map, &json!({ "256": 2, "1": 1, "65536": 3 })).unwrap();
+        let mut expected = vec![3, 0, 0, 0];
+        for (key, value) in [(1u64, 1), (256, 2), (65536, 3)] {
+            expected.extend(key.to_le_bytes());
+            expected.push(value);
+        }
+        assert_eq!(bytes, expected);
+        assert_eq!(
+            encode(
+                &idl,
+                &map,
+                &decode(&idl, &map, &mut bytes.as_slice()).unwrap()
+            )
+            .unwrap(),
+            bytes
+        );
+        assert_eq!(
+            encode(&idl, &ty(json!({ "set": "i8" })), &json!([1, -1, 0])).unwrap(),
+            [3, 0, 0, 0, 255, 0, 1]
+        );
+        assert_eq!(
+            encode(&idl, &ty(json!({ "set": "string" })), &json!(["b", "ab"])).unwrap(),
+            [2, 0, 0, 0, 2, 0, 0, 0, 97, 98, 1, 0, 0, 0, 98]
+        );
+        assert_eq!(
+            encode(
+                &idl,
+                &ty(json!({ "set": { "defined": { "name": "Key" } } })),
+                &json!([{ "B": {} }, { "A": [1] }, { "A": [-2] }])
+            )
+            .unwrap(),
+            [3, 0, 0, 0, 0, 254, 255, 0, 1, 0, 1]
+        );
+
+        assert!(encode(&idl, &ty(json!({ "set": "u8" })), &json!([1, 1])).is_err());
+    }
+
+    #[test]
+    fn defined_types() {
+        let idl = idl(json!({
+            "types": [
+                {
+                    "name": "Named",
+                    "type": {
+                        "kind": "struct",
+                        "fields": [
+                            { "name": "a", "type": "u8" },
+                            { "name": "b", "type": { "defined": { "name": "Tuple" } } }
+                        ]
+                    }
+                },
+                { "name": "Tuple", "type": { "kind": "struct", "fields": ["u8", "bool"] } },
+                { "name": "Unit", "type": { "kind": "struct" } },
+                { "name": "Alias", "type": { "kind": "type", "alias": "u16" } },
+                {
+                    "name": "MyEnum",
+                    "type": {
+                        "kind": "enum",
+                        "variants": [
+                            { "name": "Unit" },
+                            { "name": "Named", "fields": [{ "name": "a", "type": "u8" }] },
+                            { "name": "Tuple", "fields": ["u8", "string"] }
+                        ]
+                    }
+                }
+            ]
+        }));
+
+        assert_roundtrip(
+            &idl,
+            &defined("Named"),
+            json!({ "a": 1, "b": [2, false] }),
+            &[1, 2, 0],
+        );
+        assert_roundtrip(&idl, &defined("Unit"), json!({}), &[]);
+        assert_roundtrip(&idl, &defined("Alias"), json!(3), &[3, 0]);
+        assert_roundtrip(&idl, &defined("MyEnum"), json!({ "Unit": {} }), &[0]);
+        assert_roundtrip(
+            &idl,
+            &defined("MyEnum"),
+            json!({ "Named": { "a": 1 } }),
+            &[1, 1],
+        );
+        assert_roundtrip(
+            &idl,
+            &defined("MyEnum"),
+            json!({ "Tuple": [1, "a"] }),
+            &[2, 1, 1, 0, 0, 0, 97],
+        );
+
+        assert!(encode(&idl, &defined("Named"), &json!({ "a": 1 })).is_err());
+        assert!(encode(&idl, &defined("MyEnum"), &json!({ "Other": {} })).is_err());
+        assert!(decode(&idl, &defined("MyEnum"), &mut [3].as_slice()).is_err());
+        assert!(decode(&idl, &defined("Missing"), &mut [0].as_slice()).is_err());
+    }
+
+    #[test]
+    fn generics() {
+        let idl = idl(json!({
+            "types": [
+                {
+                    "name": "Wrapper",
+                    "generics": [
+                        { "kind": "type", "name": "T" },
+                        { "kind": "const", "name": "N", "type": "usize" }
+                    ],
+                    "type": {
+                        "kind": "struct",
+                        "fields": [
+                            { "name": "value", "type": { "generic": "T" } },
+                            { "name": "values", "type": { "array": [{ "generic": "T" }, { "generic": "N" }] } },
+                            {
+                                "name": "inner",
+                                "type": {
+                                    "defined": {
+                                        "name": "Inner",
+                                        "generics": [
+                                            { "kind": "type", "type": { "option": { "generic": "T" } } },
+                                            { "kind": "const", "value": "N" }
+                                        ]
+                                    }
+                                }
+                            }
+                        ]
+                    }
+                },
+                {
+                    "name": "Inner",
+                    "generics": [
+                        { "kind": "type", "name": "U" },
+                        { "kind": "const", "name": "M", "type": "usize" }
+                    ],
+                    "type": {
+                        "kind": "struct",
+                        "fields": [{ "name": "items", "type": { "array": [{ "generic": "U" }, { "generic": "M" }] } }]
+                    }
+                }
+            ]
+        }));
+
+        let ty = ty(json!({
+            "defined": {
+                "name": "Wrapper",
+                "generics": [
+                    { "kind": "type", "type": "u16" },
+                    { "kind": "const", "value": "2" }
+                ]
+            }
+        }));
+        assert_roundtrip(
+            &idl,
+            &ty,
+            json!({
+                "value": 1,
+                "values": [2, 3],
+                "inner": { "items": [null, 4] }
+            }),
+            &[1, 0, 2, 0, 3, 0, 0, 1, 4, 0],
+        );
+        assert!(decode(&idl, &defined("Wrapper"), &mut [0; 16].as_slice()).is_err());
+    }
+
+    #[test]
+    fn bytemuck() {
+        let idl = idl(json!({
+            "accounts": [{ "name": "ZcAccount", "discriminator": [1, 2] }],
+            "types": [
+                {
+                    "name": "ZcAccount",
+                    "serialization": "bytemuck",
+                    "repr": { "kind": "c" },
+                    "type": {
+                        "kind": "struct",
+                        "fields": [
+                            { "name": "a", "type": "u8" },
+                            { "name": "b", "type": "u64" },
+                            { "name": "c", "type": "u16" },
+                            { "name": "inner", "type": { "defined": { "name": "Packed" } } },
+                            { "name": "aligned", "type": { "defined": { "name": "Aligned" } } }
+                        ]
+                    }
+                },
+                {
+                    "name": "Packed",
+                    "serialization": "bytemuckunsafe",
+                    "repr": { "kind": "c", "packed": true },
+                    "type": {
+                        "kind": "struct",
+                        "fields": [
+                            { "name": "x", "type": "u8" },
+                            { "name": "y", "type": "u32" }
+                        ]
+                    }
+                },
+                {
+                    "name": "Aligned",
+                    "serialization": "bytemuck",
+                    "repr": { "kind": "c", "align": 4 },
+                    "type": { "kind": "struct", "fields": ["u8"] }
+                }
+            ]
+        }));
+
+        let value = json!({
+            "a": 1,
+            "b": "2",
+            "c": 3,
+            "inner": { "x": 4, "y": 5 },
+            "aligned": [6]
+        });
+        #[rustfmt::skip]
+        let bytes = [
+            1, 2, // discriminator
+            1, 0, 0, 0, 0, 0, 0, 0, // a + padding
+            2, 0, 0, 0, 0, 0, 0, 0, // b
+            3, 0, // c
+            4, 5, 0, 0, 0, // inner (packed)
+            0, // padding
+            6, 0, 0, 0, // aligned
+            0, 0, 0, 0, // struct padding
+        ];
+        assert_eq!(encode_account(&idl, "ZcAccount", &value).unwrap(), bytes);
+        assert_eq!(decode_account(&idl, "ZcAccount", &bytes).unwrap(), value);
+        assert!(decode_account(&idl, "ZcAccount", &bytes[1..]).is_err());
+    }
+}
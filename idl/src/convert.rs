@@ -27,11 +27,40 @@ pub fn convert_idl(idl: &[u8]) -> Result<Idl> {
     }
 }
 
+/// Result of converting an [`Idl`] to another format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IdlConversion {
+    /// Converted IDL
+    pub idl: serde_json::Value,
+    /// Information that could not be represented in the target format
+    pub warnings: Vec<String>,
+}
+
+/// Convert the given [`Idl`] to the legacy IDL spec (pre Anchor v0.30).
+///
+/// Information that doesn't exist in the legacy spec (e.g. custom discriminators or PDAs) is
+/// dropped with a warning, and types that can't be represented (e.g. tuples and maps) result in an
+/// error.
+pub fn convert_idl_to_legacy(idl: &Idl) -> Result<IdlConversion> {
+    let (idl, warnings) = legacy::from_idl(idl)?;
+    Ok(IdlConversion {
+        idl: serde_json::to_value(idl)?,
+        warnings,
+    })
+}
+
+/// Convert the given [`Idl`] to a [Codama](https://github.com/codama-idl/codama) root node.
+///
+/// Generic types are expanded in place, as Codama doesn't support generics.
+pub fn convert_idl_to_codama(idl: &Idl) -> Result<IdlConversion> {
+    codama::from_idl(idl)
+}
+
 /// Legacy IDL spec (pre Anchor v0.30)
 mod legacy {
     use crate::types as t;
     use anyhow::{anyhow, Result};
-    use heck::SnakeCase;
+    use heck::{MixedCase, SnakeCase};
     use serde::{Deserialize, Serialize};
 
     #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -334,6 +363,7 @@ mod legacy {
                 accounts: value.accounts.into_iter().map(Into::into).collect(),
                 args: value.args.into_iter().map(Into::into).collect(),
                 returns: value.returns.map(|r| r.into()),
+                deprecated: Default::default(),
             }
         }
     }
@@ -361,6 +391,7 @@ mod legacy {
             Self {
                 name: value.name,
                 code: value.code,
+                docs: Default::default(),
                 msg: value.msg,
             }
         }
@@ -398,6 +429,7 @@ mod legacy {
                 repr: Default::default(),
                 generics: Default::default(),
                 ty: value.ty.into(),
+                origin: Default::default(),
             }
         }
     }
@@ -419,10 +451,14 @@ mod legacy {
                                 name: f.name.to_snake_case(),
                                 docs: Default::default(),
                                 ty: f.ty.into(),
+                                deprecated: Default::default(),
+                                unit: Default::default(),
+                                decimals: Default::default(),
                             })
                             .collect(),
                     )),
                 },
+                origin: Default::default(),
             }
         }
     }
@@ -450,6 +486,7 @@ mod legacy {
                                     tys.into_iter().map(Into::into).collect(),
                                 ),
                             }),
+                            deprecated: Default::default(),
                         })
                         .collect(),
                 },
@@ -466,6 +503,9 @@ mod legacy {
                 name: value.name.to_snake_case(),
                 docs: value.docs.unwrap_or_default(),
                 ty: value.ty.into(),
+                deprecated: Default::default(),
+                unit: Default::default(),
+                decimals: Default::default(),
             }
         }
     }
@@ -555,4 +595,1016 @@ mod legacy {
             Ok(seed)
         }
     }
+
+    /// Convert the given [`t::Idl`] to the legacy spec, returning the lossy conversion warnings.
+    pub fn from_idl(idl: &t::Idl) -> Result<(Idl, Vec<String>)> {
+        let mut exporter = Exporter::default();
+        let idl = exporter.idl(idl)?;
+        Ok((idl, exporter.warnings))
+    }
+
+    #[derive(Default)]
+    struct Exporter {
+        warnings: Vec<String>,
+    }
+
+    impl Exporter {
+        fn warn(&mut self, path: &str, message: impl std::fmt::Display) {
+            self.warnings.push(format!("{path}: {message}"));
+        }
+
+        fn check_disc(&mut self, path: &str, disc: &[u8], prefix: &str, name: &str) {
+            if disc != get_disc(prefix, name) {
+                self.warn(
+                    path,
+                    "Custom discriminator is not supported and has been dropped",
+                );
+            }
+        }
+
+        fn idl(&mut self, idl: &t::Idl) -> Result<Idl> {
+            let is_account = |name: &str| idl.accounts.iter().any(|acc| acc.name == name);
+            let is_event = |name: &str| idl.events.iter().any(|ev| ev.name == name);
+            let find_type = |name: &str| {
+                idl.types
+                    .iter()
+                    .find(|ty| ty.name == name)
+                    .ok_or_else(|| anyhow!("Type `{name}` not found"))
+            };
+
+            let instructions = idl
+                .instructions
+                .iter()
+                .map(|ix| self.instruction(ix))
+                .collect::<Result<_>>()?;
+            let accounts = idl
+                .accounts
+                .iter()
+                .map(|acc| {
+                    let path = format!("accounts.{}", acc.name);
+                    self.check_disc(&path, &acc.discriminator, "account", &acc.name);
+                    self.type_def(&path, find_type(&acc.name)?)
+                })
+                .collect::<Result<_>>()?;
+            let events = idl
+                .events
+                .iter()
+                .map(|ev| {
+                    let path = format!("events.{}", ev.name);
+                    self.check_disc(&path, &ev.discriminator, "event", &ev.name);
+                    let fields = match &find_type(&ev.name)?.ty {
+                        t::IdlTypeDefTy::Struct {
+                            fields: Some(t::IdlDefinedFields::Named(fields)),
+                        } => fields
+                            .iter()
+                            .map(|field| {
+                                Ok(IdlEventField {
+                                    name: field.name.to_mixed_case(),
+                                    ty: self.ty(&format!("{path}.{}", field.name), &field.ty)?,
+                                    index: false,
+                                })
+                            })
+                            .collect::<Result<_>>()?,
+                        t::IdlTypeDefTy::Struct { fields: None } => vec![],
+                        _ => return Err(anyhow!("{path}: Events must have named fields")),
+                    };
+                    Ok(IdlEvent {
+                        name: ev.name.to_owned(),
+                        fields,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let types = idl
+                .types
+                .iter()
+                .filter(|ty| !is_account(&ty.name) && !is_event(&ty.name))
+                .map(|ty| self.type_def(&format!("types.{}", ty.name), ty))
+                .collect::<Result<_>>()?;
+            let constants = idl
+                .constants
+                .iter()
+                .map(|c| {
+                    Ok(IdlConst {
+                        name: c.name.to_owned(),
+                        ty: self.ty(&format!("constants.{}", c.name), &c.ty)?,
+                        value: c.value.to_owned(),
+                    })
+                })
+                .collect::<Result<_>>()?;
+
+            Ok(Idl {
+                version: idl.metadata.version.to_owned(),
+                name: idl.metadata.name.to_owned(),
+                docs: (!idl.docs.is_empty()).then(|| idl.docs.to_owned()),
+                constants,
+                instructions,
+                accounts,
+                types,
+                events: (!events.is_empty()).then_some(events),
+                errors: (!idl.errors.is_empty()).then(|| {
+                    idl.errors
+                        .iter()
+                        .map(|err| IdlErrorCode {
+                            code: err.code,
+                            name: err.name.to_owned(),
+                            msg: err.msg.to_owned(),
+                        })
+                        .collect()
+                }),
+                metadata: Some(serde_json::json!({ "address": idl.address })),
+            })
+        }
+
+        fn instruction(&mut self, ix: &t::IdlInstruction) -> Result<IdlInstruction> {
+            let path = format!("instructions.{}", ix.name);
+            self.check_disc(&path, &ix.discriminator, "global", &ix.name);
+            if ix.deprecated.is_some() {
+                self.warn(&path, "Deprecation is not supported and has been dropped");
+            }
+
+            Ok(IdlInstruction {
+                name: ix.name.to_mixed_case(),
+                docs: (!ix.docs.is_empty()).then(|| ix.docs.to_owned()),
+                accounts: ix
+                    .accounts
+                    .iter()
+                    .map(|acc| self.account_item(&path, acc))
+                    .collect(),
+                args: ix
+                    .args
+                    .iter()
+                    .map(|arg| self.field(&path, arg))
+                    .collect::<Result<_>>()?,
+                returns: ix
+                    .returns
+                    .as_ref()
+                    .map(|ty| self.ty(&format!("{path}.returns"), ty))
+                    .transpose()?,
+            })
+        }
+
+        fn account_item(
+            &mut self,
+            path: &str,
+            item: &t::IdlInstructionAccountItem,
+        ) -> IdlAccountItem {
+            match item {
+                t::IdlInstructionAccountItem::Single(acc) => {
+                    let path = format!("{path}.{}", acc.name);
+                    if acc.pda.is_some() {
+                        self.warn(&path, "PDA is not supported and has been dropped");
+                    }
+                    if acc.address.is_some() {
+                        self.warn(&path, "Address is not supported and has been dropped");
+                    }
+
+                    IdlAccountItem::IdlAccount(IdlAccount {
+                        name: acc.name.to_mixed_case(),
+                        is_mut: acc.writable,
+                        is_signer: acc.signer,
+                        is_optional: acc.optional.then_some(true),
+                        docs: (!acc.docs.is_empty()).then(|| acc.docs.to_owned()),
+                        pda: None,
+                        relations: acc.relations.to_owned(),
+                    })
+                }
+                t::IdlInstructionAccountItem::Composite(accs) => {
+                    let path = format!("{path}.{}", accs.name);
+                    IdlAccountItem::IdlAccounts(IdlAccounts {
+                        name: accs.name.to_mixed_case(),
+                        accounts: accs
+                            .accounts
+                            .iter()
+                            .map(|acc| self.account_item(&path, acc))
+                            .collect(),
+                    })
+                }
+            }
+        }
+
+        fn type_def(&mut self, path: &str, ty_def: &t::IdlTypeDef) -> Result<IdlTypeDefinition> {
+            if ty_def.serialization != t::IdlSerialization::Borsh {
+                self.warn(
+                    path,
+                    format!(
+                        "Serialization `{:?}` is not supported and has been dropped",
+                        ty_def.serialization
+                    ),
+                );
+            }
+            if ty_def.repr.is_some() {
+                self.warn(path, "Representation is not supported and has been dropped");
+            }
+            if ty_def.origin.is_some() {
+                self.warn(path, "Origin is not supported and has been dropped");
+            }
+
+            let ty = match &ty_def.ty {
+                t::IdlTypeDefTy::Struct { fields } => IdlTypeDefinitionTy::Struct {
+                    fields: match fields {
+                        Some(t::IdlDefinedFields::Named(fields)) => fields
+                            .iter()
+                            .map(|field| self.field(path, field))
+                            .collect::<Result<_>>()?,
+                        Some(t::IdlDefinedFields::Tuple(tys)) => {
+                            self.warn(path, "Tuple fields have been converted to named fields");
+                            tys.iter()
+                                .enumerate()
+                                .map(|(i, ty)| {
+                                    Ok(IdlField {
+                                        name: i.to_string(),
+                                        docs: None,
+                                        ty: self.ty(&format!("{path}.{i}"), ty)?,
+                                    })
+                                })
+                                .collect::<Result<_>>()?
+                        }
+                        None => vec![],
+                    },
+                },
+                t::IdlTypeDefTy::Enum { variants } => IdlTypeDefinitionTy::Enum {
+                    variants: variants
+                        .iter()
+                        .map(|variant| {
+                            let path = format!("{path}.{}", variant.name);
+                            if variant.deprecated.is_some() {
+                                self.warn(
+                                    &path,
+                                    "Deprecation is not supported and has been dropped",
+                                );
+                            }
+                            Ok(IdlEnumVariant {
+                                name: variant.name.to_owned(),
+                                fields: match &variant.fields {
+                                    Some(t::IdlDefinedFields::Named(fields)) => {
+                                        Some(EnumFields::Named(
+                                            fields
+                                                .iter()
+                                                .map(|field| self.field(&path, field))
+                                                .collect::<Result<_>>()?,
+                                        ))
+                                    }
+                                    Some(t::IdlDefinedFields::Tuple(tys)) => {
+                                        Some(EnumFields::Tuple(
+                                            tys.iter()
+                                                .map(|ty| self.ty(&path, ty))
+                                                .collect::<Result<_>>()?,
+                                        ))
+                                    }
+                                    None => None,
+                                },
+                            })
+                        })
+                        .collect::<Result<_>>()?,
+                },
+                t::IdlTypeDefTy::Type { alias } => IdlTypeDefinitionTy::Alias {
+                    value: self.ty(path, alias)?,
+                },
+            };
+
+            Ok(IdlTypeDefinition {
+                name: ty_def.name.to_owned(),
+                docs: (!ty_def.docs.is_empty()).then(|| ty_def.docs.to_owned()),
+                generics: (!ty_def.generics.is_empty()).then(|| {
+                    ty_def
+                        .generics
+                        .iter()
+                        .map(|generic| match generic {
+                            t::IdlTypeDefGeneric::Type { name } => name.to_owned(),
+                            t::IdlTypeDefGeneric::Const { name, .. } => name.to_owned(),
+                        })
+                        .collect()
+                }),
+                ty,
+            })
+        }
+
+        fn field(&mut self, path: &str, field: &t::IdlField) -> Result<IdlField> {
+            let path = format!("{path}.{}", field.name);
+            if field.deprecated.is_some() || field.unit.is_some() || field.decimals.is_some() {
+                self.warn(
+                    &path,
+                    "Field metadata is not supported and has been dropped",
+                );
+            }
+
+            Ok(IdlField {
+                name: field.name.to_mixed_case(),
+                docs: (!field.docs.is_empty()).then(|| field.docs.to_owned()),
+                ty: self.ty(&path, &field.ty)?,
+            })
+        }
+
+        fn ty(&mut self, path: &str, ty: &t::IdlType) -> Result<IdlType> {
+            let ty = match ty {
+                t::IdlType::Pubkey => IdlType::PublicKey,
+                t::IdlType::Option(ty) => IdlType::Option(Box::new(self.ty(path, ty)?)),
+                t::IdlType::Vec(ty) => IdlType::Vec(Box::new(self.ty(path, ty)?)),
+                t::IdlType::Set(ty) => {
+                    self.warn(path, "Set type has been converted to a vector");
+                    IdlType::Vec(Box::new(self.ty(path, ty)?))
+                }
+                t::IdlType::Array(ty, t::IdlArrayLen::Value(len)) => {
+                    IdlType::Array(Box::new(self.ty(path, ty)?), *len)
+                }
+                t::IdlType::Array(ty, t::IdlArrayLen::Generic(len)) => {
+                    IdlType::GenericLenArray(Box::new(self.ty(path, ty)?), len.to_owned())
+                }
+                t::IdlType::Defined { name, generics } if generics.is_empty() => {
+                    IdlType::Defined(name.to_owned())
+                }
+                t::IdlType::Defined { name, generics } => IdlType::DefinedWithTypeArgs {
+                    name: name.to_owned(),
+                    args: generics
+                        .iter()
+                        .map(|arg| {
+                            Ok(match arg {
+                                t::IdlGenericArg::Type {
+                                    ty: t::IdlType::Generic(generic),
+                                } => IdlDefinedTypeArg::Generic(generic.to_owned()),
+                                t::IdlGenericArg::Type { ty } => {
+                                    IdlDefinedTypeArg::Type(self.ty(path, ty)?)
+                                }
+                                t::IdlGenericArg::Const { value } => {
+                                    IdlDefinedTypeArg::Value(value.to_owned())
+                                }
+                            })
+                        })
+                        .collect::<Result<_>>()?,
+                },
+                t::IdlType::Generic(generic) => IdlType::Generic(generic.to_owned()),
+                t::IdlType::Tuple(_) | t::IdlType::Map { .. } => {
+                    return Err(anyhow!(
+                        "{path}: Type is not supported by the legacy IDL spec: {ty:?}"
+                    ))
+                }
+                _ => serde_json::to_value(ty)
+                    .and_then(serde_json::from_value)
+                    .map_err(|_| anyhow!("{path}: Unsupported type: {ty:?}"))?,
+            };
+
+            Ok(ty)
+        }
+    }
+}
+
+/// [Codama](https://github.com/codama-idl/codama) node format
+mod codama {
+    use crate::{
+        codec::{substitute, Generics},
+        types as t,
+    };
+    use anyhow::{anyhow, Result};
+    use heck::MixedCase;
+    use serde_json::{json, Value};
+
+    use super::IdlConversion;
+
+    const CODAMA_VERSION: &str = "1.0.0";
+
+    pub fn from_idl(idl: &t::Idl) -> Result<IdlConversion> {
+        let mut exporter = Exporter {
+            idl,
+            warnings: vec![],
+        };
+        let program = exporter.program()?;
+        Ok(IdlConversion {
+            idl: json!({
+                "kind": "rootNode",
+                "standard": "codama",
+                "version": CODAMA_VERSION,
+                "program": program,
+                "additionalPrograms": [],
+            }),
+            warnings: exporter.warnings,
+        })
+    }
+
+    struct Exporter<'a> {
+        idl: &'a t::Idl,
+        warnings: Vec<String>,
+    }
+
+    impl<'a> Exporter<'a> {
+        fn warn(&mut self, path: &str, message: impl std::fmt::Display) {
+            self.warnings.push(format!("{path}: {message}"));
+        }
+
+        fn find_type_def(&self, name: &str) -> Result<&'a t::IdlTypeDef> {
+            self.idl
+                .types
+                .iter()
+                .find(|ty| ty.name == name)
+                .ok_or_else(|| anyhow!("Type `{name}` not found"))
+        }
+
+        fn program(&mut self) -> Result<Value> {
+            let idl = self.idl;
+            let accounts = idl
+                .accounts
+                .iter()
+                .map(|acc| {
+                    let path = format!("accounts.{}", acc.name);
+                    let ty_def = self.find_type_def(&acc.name)?;
+                    let data = match self.type_def(&path, ty_def, &[])? {
+                        Value::Object(mut data) if data["kind"] == "structTypeNode" => {
+                            let fields = data["fields"].as_array_mut().unwrap();
+                            fields.insert(
+                                0,
+                                discriminator_field("structFieldTypeNode", &acc.discriminator),
+                            );
+                            Value::Object(data)
+                        }
+                        _ => {
+                            return Err(anyhow!(
+                                "{path}: Accounts must be structs with named fields"
+                            ))
+                        }
+                    };
+                    Ok(json!({
+                        "kind": "accountNode",
+                        "name": acc.name.to_mixed_case(),
+                        "docs": ty_def.docs,
+                        "data": data,
+                        "discriminators": [discriminator_node()],
+                    }))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let instructions = idl
+                .instructions
+                .iter()
+                .map(|ix| self.instruction(ix))
+                .collect::<Result<Vec<_>>>()?;
+            let defined_types = idl
+                .types
+                .iter()
+                .filter_map(|ty_def| {
+                    let path = format!("types.{}", ty_def.name);
+                    if !ty_def.generics.is_empty() {
+                        self.warn(&path, "Generic type has been expanded where it's used");
+                        return None;
+                    }
+
+                    Some(self.type_def(&path, ty_def, &[]).map(|ty| {
+                        json!({
+                            "kind": "definedTypeNode",
+                            "name": ty_def.name.to_mixed_case(),
+                            "docs": ty_def.docs,
+                            "type": ty,
+                        })
+                    }))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let errors = idl
+                .errors
+                .iter()
+                .map(|err| {
+                    json!({
+                        "kind": "errorNode",
+                        "name": err.name.to_mixed_case(),
+                        "code": err.code,
+                        "message": err.msg.as_deref().unwrap_or_default(),
+                        "docs": [],
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            Ok(json!({
+                "kind": "programNode",
+                "name": idl.metadata.name.to_mixed_case(),
+                "publicKey": idl.address,
+                "version": idl.metadata.version,
+                "origin": "anchor",
+                "docs": idl.docs,
+                "accounts": accounts,
+                "instructions": instructions,
+                "definedTypes": defined_types,
+                "pdas": [],
+                "errors": errors,
+            }))
+        }
+
+        fn instruction(&mut self, ix: &t::IdlInstruction) -> Result<Value> {
+            let path = format!("instructions.{}", ix.name);
+
+            let mut accounts = vec![];
+            self.flatten_accounts(&path, ix, &ix.accounts, &mut accounts)?;
+
+            let mut arguments = vec![discriminator_field(
+                "instructionArgumentNode",
+                &ix.discriminator,
+            )];
+            for arg in &ix.args {
+                arguments.push(json!({
+                    "kind": "instructionArgumentNode",
+                    "name": arg.name.to_mixed_case(),
+                    "docs": arg.docs,
+                    "type": self.ty(&format!("{path}.{}", arg.name), &arg.ty)?,
+                }));
+            }
+
+            Ok(json!({
+                "kind": "instructionNode",
+                "name": ix.name.to_mixed_case(),
+                "docs": ix.docs,
+                "optionalAccountStrategy": "programId",
+                "accounts": accounts,
+                "arguments": arguments,
+                "discriminators": [discriminator_node()],
+            }))
+        }
+
+        /// Codama doesn't support composite accounts, so nested accounts are flattened.
+        fn flatten_accounts(
+            &mut self,
+            path: &str,
+            ix: &t::IdlInstruction,
+            items: &[t::IdlInstructionAccountItem],
+            accounts: &mut Vec<Value>,
+        ) -> Result<()> {
+            for item in items {
+                match item {
+                    t::IdlInstructionAccountItem::Single(acc) => {
+                        let path = format!("{path}.{}", acc.name);
+                        let mut node = json!({
+                            "kind": "instructionAccountNode",
+                            "name": acc.name.to_mixed_case(),
+                            "isWritable": acc.writable,
+                            "isSigner": acc.signer,
+                            "isOptional": acc.optional,
+                            "docs": acc.docs,
+                        });
+                        let default_value = match (&acc.address, &acc.pda) {
+                            (Some(address), _) => Some(json!({
+                                "kind": "publicKeyValueNode",
+                                "publicKey": address,
+                            })),
+                            (_, Some(pda)) => self.pda(&path, ix, &acc.name, pda)?,
+                            _ => None,
+                        };
+                        if let Some(default_value) = default_value {
+                            node["defaultValue"] = default_value;
+                        }
+                        accounts.push(node);
+                    }
+                    t::IdlInstructionAccountItem::Composite(accs) => {
+                        let path = format!("{path}.{}", accs.name);
+                        self.flatten_accounts(&path, ix, &accs.accounts, accounts)?;
+                    }
+                }
+            }
+
+            Ok(())
+        }
+
+        /// Convert the PDA to a `pdaValueNode`, or `None` with a warning if it's not possible.
+        fn pda(
+            &mut self,
+            path: &str,
+            ix: &t::IdlInstruction,
+            name: &str,
+            pda: &t::IdlPda,
+        ) -> Result<Option<Value>> {
+            let mut seeds = vec![];
+            let mut values = vec![];
+            for seed in &pda.seeds {
+                match seed {
+                    t::IdlSeed::Const(seed) => seeds.push(json!({
+                        "kind": "constantPdaSeedNode",
+                        "type": { "kind": "bytesTypeNode" },
+                        "value": bytes_value(&seed.value),
+                    })),
+                    t::IdlSeed::Arg(seed) if !seed.path.contains('.') => {
+                        let arg = ix
+                            .args
+                            .iter()
+                            .find(|arg| arg.name == seed.path)
+                            .ok_or_else(|| {
+                                anyhow!("{path}: Seed argument `{}` not found", seed.path)
+                            })?;
+                        let ty = match &arg.ty {
+                            t::IdlType::String => {
+                                json!({ "kind": "stringTypeNode", "encoding": "utf8" })
+                            }
+                            t::IdlType::Bytes => json!({ "kind": "bytesTypeNode" }),
+                            ty => self.ty(path, ty)?,
+                        };
+                        let name = seed.path.to_mixed_case();
+                        seeds.push(json!({
+                            "kind": "variablePdaSeedNode",
+                            "name": name,
+                            "docs": [],
+                            "type": ty,
+                        }));
+                        values.push(json!({
+                            "kind": "pdaSeedValueNode",
+                            "name": name,
+                            "value": { "kind": "argumentValueNode", "name": name },
+                        }));
+                    }
+                    t::IdlSeed::Account(seed) if !seed.path.contains('.') => {
+                        let name = seed.path.to_mixed_case();
+                        seeds.push(json!({
+                            "kind": "variablePdaSeedNode",
+                            "name": name,
+                            "docs": [],
+                            "type": { "kind": "publicKeyTypeNode" },
+                        }));
+                        values.push(json!({
+                            "kind": "pdaSeedValueNode",
+                            "name": name,
+                            "value": { "kind": "accountValueNode", "name": name },
+                        }));
+                    }
+                    _ => {
+                        self.warn(
+                            path,
+                            "PDA seeds with nested paths are not supported, PDA has been dropped",
+                        );
+                        return Ok(None);
+                    }
+                }
+            }
+
+            let mut pda_node = json!({
+                "kind": "pdaNode",
+                "name": name.to_mixed_case(),
+                "docs": [],
+                "seeds": seeds,
+            });
+            match &pda.program {
+                None => {}
+                Some(t::IdlSeed::Const(seed)) if seed.value.len() == 32 => {
+                    pda_node["programId"] = bs58::encode(&seed.value).into_string().into();
+                }
+                Some(_) => {
+                    self.warn(
+                        path,
+                        "PDA with a non-constant program is not supported, PDA has been dropped",
+                    );
+                    return Ok(None);
+                }
+            }
+
+            Ok(Some(json!({
+                "kind": "pdaValueNode",
+                "pda": pda_node,
+                "seeds": values,
+            })))
+        }
+
+        fn type_def(
+            &mut self,
+            path: &str,
+            ty_def: &t::IdlTypeDef,
+            generics: &Generics,
+        ) -> Result<Value> {
+            if matches!(
+                ty_def.serialization,
+                t::IdlSerialization::Bytemuck | t::IdlSerialization::BytemuckUnsafe
+            ) {
+                self.warn(path, "Padding of `bytemuck` types is not represented");
+            }
+
+            match &ty_def.ty {
+                t::IdlTypeDefTy::Struct { fields } => self.fields(path, fields.as_ref(), generics),
+                t::IdlTypeDefTy::Enum { variants } => {
+                    let variants = variants
+                        .iter()
+                        .map(|variant| {
+                            let path = format!("{path}.{}", variant.name);
+                            let name = variant.name.to_mixed_case();
+                            Ok(match &variant.fields {
+                                None => json!({ "kind": "enumEmptyVariantTypeNode", "name": name }),
+                                Some(fields @ t::IdlDefinedFields::Named(_)) => json!({
+                                    "kind": "enumStructVariantTypeNode",
+                                    "name": name,
+                                    "struct": self.fields(&path, Some(fields), generics)?,
+                                }),
+                                Some(fields @ t::IdlDefinedFields::Tuple(_)) => json!({
+                                    "kind": "enumTupleVariantTypeNode",
+                                    "name": name,
+                                    "tuple": self.fields(&path, Some(fields), generics)?,
+                                }),
+                            })
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+                    Ok(json!({
+                        "kind": "enumTypeNode",
+                        "variants": variants,
+                        "size": number("u8"),
+                    }))
+                }
+                t::IdlTypeDefTy::Type { alias } => self.ty(path, &substitute(alias, generics)?),
+            }
+        }
+
+        fn fields(
+            &mut self,
+            path: &str,
+            fields: Option<&t::IdlDefinedFields>,
+            generics: &Generics,
+        ) -> Result<Value> {
+            match fields {
+                Some(t::IdlDefinedFields::Named(fields)) => {
+                    let fields = fields
+                        .iter()
+                        .map(|field| {
+                            Ok(json!({
+                                "kind": "structFieldTypeNode",
+                                "name": field.name.to_mixed_case(),
+                                "docs": field.docs,
+                                "type": self.ty(
+                                    &format!("{path}.{}", field.name),
+                                    &substitute(&field.ty, generics)?,
+                                )?,
+                            }))
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+                    Ok(json!({ "kind": "structTypeNode", "fields": fields }))
+                }
+                Some(t::IdlDefinedFields::Tuple(tys)) => {
+                    let items = tys
+                        .iter()
+                        .map(|ty| self.ty(path, &substitute(ty, generics)?))
+                        .collect::<Result<Vec<_>>>()?;
+                    Ok(json!({ "kind": "tupleTypeNode", "items": items }))
+                }
+                None => Ok(json!({ "kind": "structTypeNode", "fields": [] })),
+            }
+        }
+
+        /// Convert a type that doesn't have generic parameters.
+        fn ty(&mut self, path: &str, ty: &t::IdlType) -> Result<Value> {
+            let u32_prefix = || json!({ "kind": "prefixedCountNode", "prefix": number("u32") });
+            let node = match ty {
+                t::IdlType::Bool => json!({ "kind": "booleanTypeNode", "size": number("u8") }),
+                t::IdlType::U8 => number("u8"),
+                t::IdlType::I8 => number("i8"),
+                t::IdlType::U16 => number("u16"),
+                t::IdlType::I16 => number("i16"),
+                t::IdlType::U32 => number("u32"),
+                t::IdlType::I32 => number("i32"),
+                t::IdlType::F32 => number("f32"),
+                t::IdlType::U64 => number("u64"),
+                t::IdlType::I64 => number("i64"),
+                t::IdlType::F64 => number("f64"),
+                t::IdlType::U128 => number("u128"),
+                t::IdlType::I128 => number("i128"),
+                t::IdlType::U256 | t::IdlType::I256 => {
+                    self.warn(path, "256-bit integers have been converted to 32 bytes");
+                    json!({
+                        "kind": "fixedSizeTypeNode",
+                        "size": 32,
+                        "type": { "kind": "bytesTypeNode" },
+                    })
+                }
+                t::IdlType::Bytes => json!({
+                    "kind": "sizePrefixTypeNode",
+                    "type": { "kind": "bytesTypeNode" },
+                    "prefix": number("u32"),
+                }),
+                t::IdlType::String => json!({
+                    "kind": "sizePrefixTypeNode",
+                    "type": { "kind": "stringTypeNode", "encoding": "utf8" },
+                    "prefix": number("u32"),
+                }),
+                t::IdlType::Pubkey => json!({ "kind": "publicKeyTypeNode" }),
+                t::IdlType::Option(ty) => json!({
+                    "kind": "optionTypeNode",
+                    "fixed": false,
+                    "item": self.ty(path, ty)?,
+                    "prefix": number("u8"),
+                }),
+                t::IdlType::Vec(ty) => json!({
+                    "kind": "arrayTypeNode",
+                    "item": self.ty(path, ty)?,
+                    "count": u32_prefix(),
+                }),
+                t::IdlType::Array(ty, t::IdlArrayLen::Value(len)) => json!({
+                    "kind": "arrayTypeNode",
+                    "item": self.ty(path, ty)?,
+                    "count": { "kind": "fixedCountNode", "value": len },
+                }),
+                t::IdlType::Tuple(tys) => json!({
+                    "kind": "tupleTypeNode",
+                    "items": tys.iter().map(|ty| self.ty(path, ty)).collect::<Result<Vec<_>>>()?,
+                }),
+                t::IdlType::Map { key, value } => json!({
+                    "kind": "mapTypeNode",
+                    "key": self.ty(path, key)?,
+                    "value": self.ty(path, value)?,
+                    "count": u32_prefix(),
+                }),
+                t::IdlType::Set(ty) => json!({
+                    "kind": "setTypeNode",
+                    "item": self.ty(path, ty)?,
+                    "count": u32_prefix(),
+                }),
+                t::IdlType::Defined { name, generics } if generics.is_empty() => json!({
+                    "kind": "definedTypeLinkNode",
+                    "name": name.to_mixed_case(),
+                }),
+                t::IdlType::Defined {
+                    name,
+                    generics: args,
+                } => {
+                    let ty_def = self.find_type_def(name)?;
+                    let generics = ty_def
+                        .generics
+                        .iter()
+                        .map(|generic| match generic {
+                            t::IdlTypeDefGeneric::Type { name } => name.to_owned(),
+                            t::IdlTypeDefGeneric::Const { name, .. } => name.to_owned(),
+                        })
+                        .zip(args.iter().cloned())
+                        .collect::<Vec<_>>();
+                    self.type_def(path, ty_def, &generics)?
+                }
+                _ => return Err(anyhow!("{path}: Unsupported type: {ty:?}")),
+            };
+
+            Ok(node)
+        }
+    }
+
+    fn number(format: &str) -> Value {
+        json!({ "kind": "numberTypeNode", "format": format, "endian": "le" })
+    }
+
+    fn bytes_value(bytes: &[u8]) -> Value {
+        let data = bytes.iter().map(|b| format!("{b:02x}")).collect::<String>();
+        json!({ "kind": "bytesValueNode", "data": data, "encoding": "base16" })
+    }
+
+    fn discriminator_node() -> Value {
+        json!({ "kind": "fieldDiscriminatorNode", "name": "discriminator", "offset": 0 })
+    }
+
+    /// Discriminator field of the given kind, which is omitted from the generated client inputs.
+    fn discriminator_field(kind: &str, discriminator: &[u8]) -> Value {
+        json!({
+            "kind": kind,
+            "name": "discriminator",
+            "docs": [],
+            "type": {
+                "kind": "fixedSizeTypeNode",
+                "size": discriminator.len(),
+                "type": { "kind": "bytesTypeNode" },
+            },
+            "defaultValue": bytes_value(discriminator),
+            "defaultValueStrategy": "omitted",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::types::{IdlInstructionAccountItem, IdlPda, IdlSeed, IdlSeedConst, IdlType};
+
+    fn legacy_idl() -> serde_json::Value {
+        json!({
+            "version": "0.1.0",
+            "name": "test",
+            "instructions": [
+                {
+                    "name": "initialize",
+                    "accounts": [
+                        { "name": "myAccount", "isMut": true, "isSigner": false },
+                        { "name": "authority", "isMut": false, "isSigner": true }
+                    ],
+                    "args": [{ "name": "amount", "type": "u64" }]
+                }
+            ],
+            "accounts": [
+                {
+                    "name": "MyAccount",
+                    "type": {
+                        "kind": "struct",
+                        "fields": [
+                            { "name": "authority", "type": "publicKey" },
+                            { "name": "state", "type": { "defined": "State" } }
+                        ]
+                    }
+                }
+            ],
+            "types": [
+                {
+                    "name": "State",
+                    "type": {
+                        "kind": "enum",
+                        "variants": [
+                            { "name": "Active" },
+                            { "name": "Closed", "fields": [{ "name": "closedAt", "type": "i64" }] }
+                        ]
+                    }
+                }
+            ],
+            "errors": [{ "code": 6000, "name": "Invalid", "msg": "Invalid amount" }],
+            "metadata": { "address": "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS" }
+        })
+    }
+
+    fn idl() -> Idl {
+        convert_idl(&serde_json::to_vec(&legacy_idl()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn legacy_round_trip() {
+        let conversion = convert_idl_to_legacy(&idl()).unwrap();
+        assert_eq!(conversion.idl, legacy_idl());
+        assert!(conversion.warnings.is_empty());
+    }
+
+    #[test]
+    fn legacy_lossy() {
+        let mut idl = idl();
+        idl.instructions[0].discriminator = vec![1];
+        match &mut idl.instructions[0].accounts[0] {
+            IdlInstructionAccountItem::Single(acc) => {
+                acc.pda = Some(IdlPda {
+                    seeds: vec![IdlSeed::Const(IdlSeedConst {
+                        value: b"seed".to_vec(),
+                    })],
+                    program: None,
+                })
+            }
+            _ => unreachable!(),
+        }
+
+        let conversion = convert_idl_to_legacy(&idl).unwrap();
+        assert_eq!(conversion.warnings.len(), 2);
+        assert!(conversion.warnings[0].starts_with("instructions.initialize: "));
+        assert!(conversion.warnings[1].starts_with("instructions.initialize.my_account: "));
+
+        idl.instructions[0].args[0].ty = IdlType::Tuple(vec![IdlType::U8, IdlType::U8]);
+        assert!(convert_idl_to_legacy(&idl).is_err());
+    }
+
+    #[test]
+    fn codama() {
+        let idl = idl();
+        let conversion = convert_idl_to_codama(&idl).unwrap();
+        assert!(conversion.warnings.is_empty());
+
+        let root = conversion.idl;
+        assert_eq!(root["kind"], "rootNode");
+        let program = &root["program"];
+        assert_eq!(program["name"], "test");
+        assert_eq!(program["publicKey"], idl.address);
+
+        let account = &program["accounts"][0];
+        assert_eq!(account["name"], "myAccount");
+        let fields = account["data"]["fields"].as_array().unwrap();
+        assert_eq!(fields[0]["name"], "discriminator");
+        assert_eq!(fields[0]["type"]["size"], 8);
+        assert_eq!(fields[1]["type"]["kind"], "publicKeyTypeNode");
+        assert_eq!(
+            fields[2]["type"],
+            json!({ "kind": "definedTypeLinkNode", "name": "state" })
+        );
+
+        let ix = &program["instructions"][0];
+        assert_eq!(ix["accounts"][1]["isSigner"], true);
+        assert_eq!(ix["arguments"][0]["defaultValueStrategy"], "omitted");
+        assert_eq!(ix["arguments"][1]["type"]["format"], "u64");
+
+        let variants = program["definedTypes"][0]["type"]["variants"]
+            .as_array()
+            .unwrap();
+        assert_eq!(variants[0]["kind"], "enumEmptyVariantTypeNode");
+        assert_eq!(variants[1]["kind"], "enumStructVariantTypeNode");
+        assert_eq!(program["errors"][0]["code"], 6000);
+    }
+
+    #[test]
+    fn codama_generics() {
+        let mut idl = idl();
+        idl.types.push(serde_json::from_value(json!({
+            "name": "Wrapper",
+            "generics": [{ "kind": "type", "name": "T" }, { "kind": "const", "name": "N", "type": "usize" }],
+            "type": {
+                "kind": "struct",
+                "fields": [{ "name": "inner", "type": { "array": [{ "generic": "T" }, { "generic": "N" }] } }]
+            }
+        })).unwrap());
+        idl.instructions[0].args[0].ty = serde_json::from_value(json!({
+            "defined": {
+                "name": "Wrapper",
+                "generics": [{ "kind": "type", "type": "u16" }, { "kind": "const", "value": "4" }]
+            }
+        }))
+        .unwrap();
+
+        let conversion = convert_idl_to_codama(&idl).unwrap();
+        assert_eq!(conversion.warnings.len(), 1);
+        let program = &conversion.idl["program"];
+        assert_eq!(program["definedTypes"].as_array().unwrap().len(), 2);
+        let inner = &program["instructions"][0]["arguments"][1]["type"]["fields"][0]["type"];
+        assert_eq!(inner["item"]["format"], "u16");
+        assert_eq!(inner["count"]["value"], 4);
+    }
 }
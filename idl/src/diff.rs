@@ -0,0 +1,669 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::types::{
+    Idl, IdlArrayLen, IdlDefinedFields, IdlField, IdlGenericArg, IdlInstruction,
+    IdlInstructionAccount, IdlInstructionAccountItem, IdlType, IdlTypeDef, IdlTypeDefTy,
+};
+
+/// Whether a change can break existing clients or on-chain data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compatibility {
+    Compatible,
+    Breaking,
+}
+
+/// A single change between two IDLs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IdlChange {
+    /// Dot separated path of the changed item, e.g. `instructions.initialize.args.amount`.
+    pub path: String,
+    pub compatibility: Compatibility,
+    /// Human-readable description of the change.
+    pub message: String,
+}
+
+impl fmt::Display for IdlChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Changes between two IDLs, in the order they were found.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct IdlDiff {
+    pub changes: Vec<IdlChange>,
+}
+
+impl IdlDiff {
+    /// Whether there are no changes.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Whether any of the changes is breaking.
+    pub fn is_breaking(&self) -> bool {
+        self.breaking_changes().next().is_some()
+    }
+
+    pub fn breaking_changes(&self) -> impl Iterator<Item = &IdlChange> {
+        self.changes_with(Compatibility::Breaking)
+    }
+
+    pub fn compatible_changes(&self) -> impl Iterator<Item = &IdlChange> {
+        self.changes_with(Compatibility::Compatible)
+    }
+
+    fn changes_with(&self, compatibility: Compatibility) -> impl Iterator<Item = &IdlChange> {
+        self.changes
+            .iter()
+            .filter(move |change| change.compatibility == compatibility)
+    }
+
+    fn push(&mut self, path: &str, compatibility: Compatibility, message: impl Into<String>) {
+        self.changes.push(IdlChange {
+            path: path.to_owned(),
+            compatibility,
+            message: message.into(),
+        });
+    }
+
+    fn compatible(&mut self, path: &str, message: impl Into<String>) {
+        self.push(path, Compatibility::Compatible, message)
+    }
+
+    fn breaking(&mut self, path: &str, message: impl Into<String>) {
+        self.push(path, Compatibility::Breaking, message)
+    }
+}
+
+/// Compare two IDLs and classify the changes from `old` to `new` as compatible or breaking.
+///
+/// Serialized data is compared based on its Borsh layout, meaning the order of fields and enum
+/// variants matters. Appending an optional (`Option<T>`) field is considered compatible, while
+/// appending a required field is not.
+///
+/// Docs and metadata (other than the program address) are ignored.
+pub fn diff_idls(old: &Idl, new: &Idl) -> IdlDiff {
+    let mut diff = IdlDiff::default();
+
+    if old.address != new.address {
+        diff.breaking(
+            "address",
+            format!("Changed from `{}` to `{}`", old.address, new.address),
+        );
+    }
+
+    diff_items(
+        &mut diff,
+        "instructions",
+        &old.instructions,
+        &new.instructions,
+        |ix| &ix.name,
+        diff_instruction,
+    );
+    diff_items(
+        &mut diff,
+        "accounts",
+        &old.accounts,
+        &new.accounts,
+        |acc| &acc.name,
+        |diff, path, old, new| {
+            diff_discriminator(diff, path, &old.discriminator, &new.discriminator)
+        },
+    );
+    diff_items(
+        &mut diff,
+        "events",
+        &old.events,
+        &new.events,
+        |ev| &ev.name,
+        |diff, path, old, new| {
+            diff_discriminator(diff, path, &old.discriminator, &new.discriminator)
+        },
+    );
+    diff_items(
+        &mut diff,
+        "errors",
+        &old.errors,
+        &new.errors,
+        |err| &err.name,
+        |diff, path, old, new| {
+            if old.code != new.code {
+                diff.breaking(
+                    path,
+                    format!("Error code changed from {} to {}", old.code, new.code),
+                );
+            }
+            if old.msg != new.msg {
+                diff.compatible(path, "Error message changed");
+            }
+        },
+    );
+    diff_items(
+        &mut diff,
+        "types",
+        &old.types,
+        &new.types,
+        |ty| &ty.name,
+        diff_type_def,
+    );
+    diff_items(
+        &mut diff,
+        "constants",
+        &old.constants,
+        &new.constants,
+        |c| &c.name,
+        |diff, path, old, new| {
+            if old.ty != new.ty {
+                diff.breaking(path, type_changed(&old.ty, &new.ty));
+            }
+            if old.value != new.value {
+                diff.compatible(
+                    path,
+                    format!("Value changed from `{}` to `{}`", old.value, new.value),
+                );
+            }
+        },
+    );
+
+    diff
+}
+
+/// Diff items that are identified by their names. Added items are compatible, removed items are
+/// breaking.
+fn diff_items<T>(
+    diff: &mut IdlDiff,
+    path: &str,
+    old: &[T],
+    new: &[T],
+    name: impl Fn(&T) -> &String,
+    diff_item: impl Fn(&mut IdlDiff, &str, &T, &T),
+) {
+    for old_item in old {
+        let item_path = format!("{path}.{}", name(old_item));
+        match new.iter().find(|new_item| name(new_item) == name(old_item)) {
+            Some(new_item) => diff_item(diff, &item_path, old_item, new_item),
+            None => diff.breaking(&item_path, "Removed"),
+        }
+    }
+
+    for new_item in new {
+        if !old.iter().any(|old_item| name(old_item) == name(new_item)) {
+            diff.compatible(&format!("{path}.{}", name(new_item)), "Added");
+        }
+    }
+}
+
+fn diff_discriminator(diff: &mut IdlDiff, path: &str, old: &[u8], new: &[u8]) {
+    if old != new {
+        diff.breaking(
+            path,
+            format!("Discriminator changed from {old:?} to {new:?}"),
+        );
+    }
+}
+
+fn diff_instruction(diff: &mut IdlDiff, path: &str, old: &IdlInstruction, new: &IdlInstruction) {
+    diff_discriminator(diff, path, &old.discriminator, &new.discriminator);
+    diff_instruction_accounts(
+        diff,
+        &format!("{path}.accounts"),
+        &flatten_accounts(&old.accounts, None),
+        &flatten_accounts(&new.accounts, None),
+    );
+    diff_fields(diff, &format!("{path}.args"), &old.args, &new.args);
+
+    if old.returns != new.returns {
+        let fmt_returns = |ty: &Option<IdlType>| match ty {
+            Some(ty) => format!("`{}`", fmt_type(ty)),
+            None => "nothing".into(),
+        };
+        diff.breaking(
+            &format!("{path}.returns"),
+            format!(
+                "Return type changed from {} to {}",
+                fmt_returns(&old.returns),
+                fmt_returns(&new.returns)
+            ),
+        );
+    }
+}
+
+/// Flatten composite accounts into a list of accounts in the order they're passed in, named by
+/// their path, e.g. `composite.account`.
+fn flatten_accounts<'a>(
+    items: &'a [IdlInstructionAccountItem],
+    prefix: Option<&str>,
+) -> Vec<(String, &'a IdlInstructionAccount)> {
+    let path = |name: &str| match prefix {
+        Some(prefix) => format!("{prefix}.{name}"),
+        None => name.to_owned(),
+    };
+
+    let mut accounts = vec![];
+    for item in items {
+        match item {
+            IdlInstructionAccountItem::Single(acc) => accounts.push((path(&acc.name), acc)),
+            IdlInstructionAccountItem::Composite(accs) => {
+                accounts.extend(flatten_accounts(&accs.accounts, Some(&path(&accs.name))))
+            }
+        }
+    }
+    accounts
+}
+
+fn diff_instruction_accounts(
+    diff: &mut IdlDiff,
+    path: &str,
+    old: &[(String, &IdlInstructionAccount)],
+    new: &[(String, &IdlInstructionAccount)],
+) {
+    for (i, (old_name, old_acc)) in old.iter().enumerate() {
+        let Some((new_name, new_acc)) = new.get(i) else {
+            diff.breaking(&format!("{path}.{old_name}"), "Removed");
+            continue;
+        };
+
+        let acc_path = format!("{path}.{old_name}");
+        if old_name != new_name {
+            diff.breaking(
+                &acc_path,
+                format!("Account at position {i} changed from `{old_name}` to `{new_name}`"),
+            );
+            continue;
+        }
+
+        if old_acc.writable != new_acc.writable {
+            match new_acc.writable {
+                true => diff.breaking(&acc_path, "Account is now writable"),
+                false => diff.compatible(&acc_path, "Account is no longer writable"),
+            }
+        }
+        if old_acc.signer != new_acc.signer {
+            match new_acc.signer {
+                true => diff.breaking(&acc_path, "Account is now a signer"),
+                false => diff.compatible(&acc_path, "Account is no longer a signer"),
+            }
+        }
+        if old_acc.optional != new_acc.optional {
+            match new_acc.optional {
+                true => diff.compatible(&acc_path, "Account is now optional"),
+                false => diff.breaking(&acc_path, "Account is no longer optional"),
+            }
+        }
+        if old_acc.address != new_acc.address {
+            diff.breaking(&acc_path, "Address changed");
+        }
+        if old_acc.pda != new_acc.pda {
+            diff.breaking(&acc_path, "Seeds changed");
+        }
+        if old_acc.relations != new_acc.relations {
+            diff.breaking(&acc_path, "Relations changed");
+        }
+    }
+
+    for (name, acc) in new.iter().skip(old.len()) {
+        let acc_path = format!("{path}.{name}");
+        match acc.optional {
+            true => diff.compatible(&acc_path, "Appended optional account"),
+            false => diff.breaking(&acc_path, "Appended required account"),
+        }
+    }
+}
+
+/// Diff fields based on their Borsh layout.
+fn diff_fields(diff: &mut IdlDiff, path: &str, old: &[IdlField], new: &[IdlField]) {
+    for (i, old_field) in old.iter().enumerate() {
+        let field_path = format!("{path}.{}", old_field.name);
+        let Some(new_field) = new.get(i) else {
+            diff.breaking(&field_path, "Removed");
+            continue;
+        };
+
+        if old_field.name != new_field.name {
+            let message = match new.iter().position(|f| f.name == old_field.name) {
+                Some(new_i) => format!("Moved from position {i} to {new_i}"),
+                None => format!("Renamed to `{}`", new_field.name),
+            };
+            diff.breaking(&field_path, message);
+        } else if old_field.ty != new_field.ty {
+            diff.breaking(&field_path, type_changed(&old_field.ty, &new_field.ty));
+        }
+    }
+
+    for field in new.iter().skip(old.len()) {
+        diff_appended_field(diff, &format!("{path}.{}", field.name), &field.ty);
+    }
+}
+
+/// Diff unnamed (tuple) fields based on their Borsh layout.
+fn diff_tuple_fields(diff: &mut IdlDiff, path: &str, old: &[IdlType], new: &[IdlType]) {
+    for (i, old_ty) in old.iter().enumerate() {
+        let field_path = format!("{path}.{i}");
+        match new.get(i) {
+            Some(new_ty) if old_ty != new_ty => {
+                diff.breaking(&field_path, type_changed(old_ty, new_ty))
+            }
+            Some(_) => {}
+            None => diff.breaking(&field_path, "Removed"),
+        }
+    }
+
+    for (i, ty) in new.iter().enumerate().skip(old.len()) {
+        diff_appended_field(diff, &format!("{path}.{i}"), ty);
+    }
+}
+
+fn diff_appended_field(diff: &mut IdlDiff, path: &str, ty: &IdlType) {
+    match ty {
+        IdlType::Option(_) => diff.compatible(path, "Appended optional field"),
+        _ => diff.breaking(
+            path,
+            format!("Appended required field of type `{}`", fmt_type(ty)),
+        ),
+    }
+}
+
+fn diff_defined_fields(
+    diff: &mut IdlDiff,
+    path: &str,
+    old: &Option<IdlDefinedFields>,
+    new: &Option<IdlDefinedFields>,
+) {
+    match (old, new) {
+        (None, None) => {}
+        (Some(IdlDefinedFields::Named(old)), Some(IdlDefinedFields::Named(new))) => {
+            diff_fields(diff, path, old, new)
+        }
+        (Some(IdlDefinedFields::Tuple(old)), Some(IdlDefinedFields::Tuple(new))) => {
+            diff_tuple_fields(diff, path, old, new)
+        }
+        (None, Some(IdlDefinedFields::Named(new))) => diff_fields(diff, path, &[], new),
+        (None, Some(IdlDefinedFields::Tuple(new))) => diff_tuple_fields(diff, path, &[], new),
+        _ => diff.breaking(path, "Fields changed between named, unnamed or unit"),
+    }
+}
+
+fn diff_type_def(diff: &mut IdlDiff, path: &str, old: &IdlTypeDef, new: &IdlTypeDef) {
+    if old.serialization != new.serialization {
+        diff.breaking(path, "Serialization changed");
+    }
+    if old.repr != new.repr {
+        diff.breaking(path, "Representation changed");
+    }
+    if old.generics != new.generics {
+        diff.breaking(path, "Generics changed");
+    }
+
+    match (&old.ty, &new.ty) {
+        (IdlTypeDefTy::Struct { fields: old }, IdlTypeDefTy::Struct { fields: new }) => {
+            diff_defined_fields(diff, path, old, new)
+        }
+        (IdlTypeDefTy::Enum { variants: old }, IdlTypeDefTy::Enum { variants: new }) => {
+            for (i, old_variant) in old.iter().enumerate() {
+                let variant_path = format!("{path}.{}", old_variant.name);
+                match new.get(i) {
+                    Some(new_variant) if new_variant.name == old_variant.name => {
+                        diff_defined_fields(
+                            diff,
+                            &variant_path,
+                            &old_variant.fields,
+                            &new_variant.fields,
+                        )
+                    }
+                    Some(_) => {
+                        let message = match new.iter().position(|v| v.name == old_variant.name) {
+                            Some(new_i) => format!("Moved from position {i} to {new_i}"),
+                            None => "Removed".into(),
+                        };
+                        diff.breaking(&variant_path, message);
+                    }
+                    None => diff.breaking(&variant_path, "Removed"),
+                }
+            }
+
+            for (i, variant) in new.iter().enumerate() {
+                if old.iter().any(|v| v.name == variant.name) {
+                    continue;
+                }
+
+                let variant_path = format!("{path}.{}", variant.name);
+                if i < old.len() {
+                    diff.breaking(&variant_path, format!("Inserted variant at position {i}"));
+                } else {
+                    diff.compatible(&variant_path, "Appended variant");
+                }
+            }
+        }
+        (IdlTypeDefTy::Type { alias: old }, IdlTypeDefTy::Type { alias: new }) => {
+            if old != new {
+                diff.breaking(path, type_changed(old, new));
+            }
+        }
+        _ => diff.breaking(path, "Kind changed"),
+    }
+}
+
+fn type_changed(old: &IdlType, new: &IdlType) -> String {
+    format!(
+        "Type changed from `{}` to `{}`",
+        fmt_type(old),
+        fmt_type(new)
+    )
+}
+
+/// Format the type in Rust syntax.
+fn fmt_type(ty: &IdlType) -> String {
+    match ty {
+        IdlType::Bool => "bool".into(),
+        IdlType::U8 => "u8".into(),
+        IdlType::I8 => "i8".into(),
+        IdlType::U16 => "u16".into(),
+        IdlType::I16 => "i16".into(),
+        IdlType::U32 => "u32".into(),
+        IdlType::I32 => "i32".into(),
+        IdlType::F32 => "f32".into(),
+        IdlType::U64 => "u64".into(),
+        IdlType::I64 => "i64".into(),
+        IdlType::F64 => "f64".into(),
+        IdlType::U128 => "u128".into(),
+        IdlType::I128 => "i128".into(),
+        IdlType::U256 => "u256".into(),
+        IdlType::I256 => "i256".into(),
+        IdlType::Bytes => "Vec<u8>".into(),
+        IdlType::String => "String".into(),
+        IdlType::Pubkey => "Pubkey".into(),
+        IdlType::Option(ty) => format!("Option<{}>", fmt_type(ty)),
+        IdlType::Vec(ty) => format!("Vec<{}>", fmt_type(ty)),
+        IdlType::Tuple(tys) => format!(
+            "({})",
+            tys.iter().map(fmt_type).collect::<Vec<_>>().join(", ")
+        ),
+        IdlType::Map { key, value } => format!("Map<{}, {}>", fmt_type(key), fmt_type(value)),
+        IdlType::Set(ty) => format!("Set<{}>", fmt_type(ty)),
+        IdlType::Array(ty, len) => {
+            let len = match len {
+                IdlArrayLen::Generic(len) => len.to_owned(),
+                IdlArrayLen::Value(len) => len.to_string(),
+            };
+            format!("[{}; {len}]", fmt_type(ty))
+        }
+        IdlType::Defined { name, generics } if generics.is_empty() => name.to_owned(),
+        IdlType::Defined { name, generics } => {
+            let generics = generics
+                .iter()
+                .map(|generic| match generic {
+                    IdlGenericArg::Type { ty } => fmt_type(ty),
+                    IdlGenericArg::Const { value } => value.to_owned(),
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("{name}<{generics}>")
+        }
+        IdlType::Generic(name) => name.to_owned(),
+        _ => serde_json::to_string(ty).unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn idl(value: serde_json::Value) -> Idl {
+        let mut idl = serde_json::json!({
+            "address": "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS",
+            "metadata": { "name": "test", "version": "0.1.0", "spec": "0.1.0" },
+            "instructions": []
+        });
+        idl.as_object_mut()
+            .unwrap()
+            .extend(value.as_object().unwrap().clone());
+        serde_json::from_value(idl).unwrap()
+    }
+
+    fn changes(diff: &IdlDiff) -> Vec<(&str, Compatibility)> {
+        diff.changes
+            .iter()
+            .map(|change| (change.path.as_str(), change.compatibility))
+            .collect()
+    }
+
+    #[test]
+    fn no_changes() {
+        let idl = idl(serde_json::json!({}));
+        assert!(diff_idls(&idl, &idl).is_empty());
+    }
+
+    #[test]
+    fn instructions() {
+        let old = idl(serde_json::json!({
+            "instructions": [
+                {
+                    "name": "initialize",
+                    "discriminator": [1],
+                    "accounts": [
+                        { "name": "authority", "signer": true },
+                        {
+                            "name": "config",
+                            "writable": true,
+                            "pda": { "seeds": [{ "kind": "const", "value": [1] }] }
+                        }
+                    ],
+                    "args": [{ "name": "amount", "type": "u64" }]
+                },
+                { "name": "close", "discriminator": [2], "accounts": [], "args": [] }
+            ]
+        }));
+        let new = idl(serde_json::json!({
+            "instructions": [
+                {
+                    "name": "initialize",
+                    "discriminator": [1],
+                    "accounts": [
+                        { "name": "authority" },
+                        {
+                            "name": "config",
+                            "writable": true,
+                            "pda": { "seeds": [{ "kind": "const", "value": [2] }] }
+                        },
+                        { "name": "extra", "optional": true }
+                    ],
+                    "args": [
+                        { "name": "amount", "type": "u64" },
+                        { "name": "memo", "type": { "option": "string" } }
+                    ]
+                },
+                { "name": "update", "discriminator": [3], "accounts": [], "args": [] }
+            ]
+        }));
+
+        let diff = diff_idls(&old, &new);
+        assert_eq!(
+            changes(&diff),
+            vec![
+                (
+                    "instructions.initialize.accounts.authority",
+                    Compatibility::Compatible
+                ),
+                (
+                    "instructions.initialize.accounts.config",
+                    Compatibility::Breaking
+                ),
+                (
+                    "instructions.initialize.accounts.extra",
+                    Compatibility::Compatible
+                ),
+                (
+                    "instructions.initialize.args.memo",
+                    Compatibility::Compatible
+                ),
+                ("instructions.close", Compatibility::Breaking),
+                ("instructions.update", Compatibility::Compatible),
+            ]
+        );
+        assert!(diff.is_breaking());
+    }
+
+    #[test]
+    fn types() {
+        let old = idl(serde_json::json!({
+            "accounts": [{ "name": "State", "discriminator": [1] }],
+            "types": [
+                {
+                    "name": "State",
+                    "type": {
+                        "kind": "struct",
+                        "fields": [
+                            { "name": "a", "type": "u8" },
+                            { "name": "b", "type": "u16" },
+                            { "name": "c", "type": "u32" }
+                        ]
+                    }
+                },
+                {
+                    "name": "Kind",
+                    "type": { "kind": "enum", "variants": [{ "name": "A" }, { "name": "B" }] }
+                }
+            ]
+        }));
+        let new = idl(serde_json::json!({
+            "accounts": [{ "name": "State", "discriminator": [2] }],
+            "types": [
+                {
+                    "name": "State",
+                    "type": {
+                        "kind": "struct",
+                        "fields": [
+                            { "name": "b", "type": "u16" },
+                            { "name": "a", "type": "u8" },
+                            { "name": "c", "type": "u64" },
+                            { "name": "d", "type": "bool" }
+                        ]
+                    }
+                },
+                {
+                    "name": "Kind",
+                    "type": { "kind": "enum", "variants": [{ "name": "A" }, { "name": "C" }] }
+                }
+            ]
+        }));
+
+        let diff = diff_idls(&old, &new);
+        assert_eq!(
+            changes(&diff),
+            vec![
+                ("accounts.State", Compatibility::Breaking),
+                ("types.State.a", Compatibility::Breaking),
+                ("types.State.b", Compatibility::Breaking),
+                ("types.State.c", Compatibility::Breaking),
+                ("types.State.d", Compatibility::Breaking),
+                ("types.Kind.B", Compatibility::Breaking),
+                ("types.Kind.C", Compatibility::Breaking),
+            ]
+        );
+        assert_eq!(diff.changes[3].message, "Type changed from `u32` to `u64`");
+    }
+}
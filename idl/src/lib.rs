@@ -6,6 +6,12 @@ pub mod build;
 #[cfg(feature = "convert")]
 pub mod convert;
 
+pub mod bundle;
+pub mod codec;
+pub mod diff;
+pub mod lint;
+pub mod schema;
+
 pub use anchor_lang_idl_spec as types;
 
 #[cfg(feature = "build")]
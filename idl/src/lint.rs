@@ -0,0 +1,223 @@
+//! Lints for unsafe or ambiguous program interfaces.
+//!
+//! Lints that require the program source (e.g. account constraints) are checked during the IDL
+//! build, and lints that only require the IDL are checked with [`lint_idl`].
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::{Idl, IdlInstructionAccount, IdlInstructionAccountItem};
+
+/// IDL lint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IdlLint {
+    /// Writable unchecked account without an owner, `has_one`, `seeds` or `address` validation
+    UncheckedWritable,
+    /// `init_if_needed` account without a `constraint` that guards against re-initialization
+    InitIfNeeded,
+    /// Mutable accounts of the same type that could be the same account
+    DuplicateMutable,
+    /// Signer account that is a PDA in other instructions, but doesn't have seeds
+    SignerPdaWithoutSeeds,
+    /// Instruction argument that isn't used in the instruction handler
+    UnusedInstructionArg,
+    /// Type name that is defined in multiple modules
+    TypeNameCollision,
+}
+
+impl fmt::Display for IdlLint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::UncheckedWritable => "unchecked-writable",
+            Self::InitIfNeeded => "init-if-needed",
+            Self::DuplicateMutable => "duplicate-mutable",
+            Self::SignerPdaWithoutSeeds => "signer-pda-without-seeds",
+            Self::UnusedInstructionArg => "unused-instruction-arg",
+            Self::TypeNameCollision => "type-name-collision",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Level of an [`IdlLint`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IdlLintLevel {
+    /// Ignore the lint
+    Allow,
+    /// Print a warning
+    #[default]
+    Warn,
+    /// Fail the IDL build
+    Deny,
+}
+
+/// Lint levels, lints that are not specified default to [`IdlLintLevel::Warn`].
+pub type IdlLintLevels = BTreeMap<IdlLint, IdlLintLevel>;
+
+/// A lint violation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdlLintDiagnostic {
+    pub lint: IdlLint,
+    /// Path of the item, e.g. `instructions.initialize.authority`
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for IdlLintDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}: {}", self.lint, self.path, self.message)
+    }
+}
+
+/// Check the lints that only require the IDL.
+pub fn lint_idl(idl: &Idl) -> Vec<IdlLintDiagnostic> {
+    let mut diagnostics = vec![];
+
+    // Signer PDAs without seeds
+    let mut pdas = BTreeSet::new();
+    for ix in &idl.instructions {
+        visit_accounts(&ix.accounts, "", &mut |_, acc| {
+            if acc.pda.is_some() {
+                pdas.insert(acc.name.clone());
+            }
+        });
+    }
+    for ix in &idl.instructions {
+        visit_accounts(&ix.accounts, "", &mut |path, acc| {
+            if acc.signer && acc.pda.is_none() && pdas.contains(&acc.name) {
+                diagnostics.push(IdlLintDiagnostic {
+                    lint: IdlLint::SignerPdaWithoutSeeds,
+                    path: format!("instructions.{}{path}", ix.name),
+                    message: format!(
+                        "Signer `{}` is a PDA in other instructions, but its seeds are not \
+                        validated",
+                        acc.name
+                    ),
+                });
+            }
+        });
+    }
+
+    // Conflicting type names are kept as their full path
+    for ty in idl.types.iter().filter(|ty| ty.name.contains("::")) {
+        let name = ty.name.rsplit("::").next().unwrap();
+        diagnostics.push(IdlLintDiagnostic {
+            lint: IdlLint::TypeNameCollision,
+            path: format!("types.{}", ty.name),
+            message: format!(
+                "Type `{name}` is defined in multiple modules, the full path is used as its name"
+            ),
+        });
+    }
+
+    diagnostics
+}
+
+fn visit_accounts(
+    items: &[IdlInstructionAccountItem],
+    path: &str,
+    f: &mut impl FnMut(&str, &IdlInstructionAccount),
+) {
+    for item in items {
+        match item {
+            IdlInstructionAccountItem::Single(acc) => f(&format!("{path}.{}", acc.name), acc),
+            IdlInstructionAccountItem::Composite(accs) => {
+                visit_accounts(&accs.accounts, &format!("{path}.{}", accs.name), f)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn idl(value: serde_json::Value) -> Idl {
+        let mut idl = serde_json::json!({
+            "address": "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS",
+            "metadata": { "name": "test", "version": "0.1.0", "spec": "0.1.0" },
+            "instructions": []
+        });
+        idl.as_object_mut()
+            .unwrap()
+            .extend(value.as_object().unwrap().clone());
+        serde_json::from_value(idl).unwrap()
+    }
+
+    fn lints(diagnostics: &[IdlLintDiagnostic]) -> Vec<(IdlLint, &str)> {
+        diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.lint, diagnostic.path.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn signer_pda_without_seeds() {
+        let idl = idl(serde_json::json!({
+            "instructions": [
+                {
+                    "name": "create",
+                    "discriminator": [0],
+                    "accounts": [{
+                        "name": "vault",
+                        "writable": true,
+                        "pda": { "seeds": [{ "kind": "const", "value": [1] }] }
+                    }],
+                    "args": []
+                },
+                {
+                    "name": "withdraw",
+                    "discriminator": [1],
+                    "accounts": [{
+                        "name": "nested",
+                        "accounts": [{ "name": "vault", "signer": true }]
+                    }],
+                    "args": []
+                }
+            ]
+        }));
+        assert_eq!(
+            lints(&lint_idl(&idl)),
+            [(
+                IdlLint::SignerPdaWithoutSeeds,
+                "instructions.withdraw.nested.vault"
+            )]
+        );
+    }
+
+    #[test]
+    fn type_name_collision() {
+        let idl = idl(serde_json::json!({
+            "types": [
+                { "name": "a::State", "type": { "kind": "struct" } },
+                { "name": "b::State", "type": { "kind": "struct" } },
+                { "name": "Other", "type": { "kind": "struct" } }
+            ]
+        }));
+        assert_eq!(
+            lints(&lint_idl(&idl)),
+            [
+                (IdlLint::TypeNameCollision, "types.a::State"),
+                (IdlLint::TypeNameCollision, "types.b::State")
+            ]
+        );
+    }
+
+    #[test]
+    fn lint_levels() {
+        let levels: IdlLintLevels = serde_json::from_value(serde_json::json!({
+            "unchecked-writable": "deny",
+            "unused-instruction-arg": "allow"
+        }))
+        .unwrap();
+        assert_eq!(levels[&IdlLint::UncheckedWritable], IdlLintLevel::Deny);
+        assert_eq!(levels[&IdlLint::UnusedInstructionArg], IdlLintLevel::Allow);
+        assert_eq!(IdlLint::DuplicateMutable.to_string(), "duplicate-mutable");
+    }
+}
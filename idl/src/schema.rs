@@ -0,0 +1,590 @@
+//! JSON Schema (draft 2020-12) generation for IDL-defined types.
+//!
+//! The schemas describe the JSON representation of Borsh serialized values:
+//!
+//! - `u64`, `i64`, `u128`, `i128`, `u256` and `i256` are decimal strings, as they don't fit in
+//!   the safe integer range of most JSON parsers
+//! - Smaller integers are numbers with their range as bounds
+//! - `pubkey` is a base58 string
+//! - `bytes` is an array of numbers
+//! - `Option<T>` is either `null` or the value of `T`
+//! - Arrays, vectors, sets and tuples are arrays
+//! - Maps are objects with the keys as strings
+//! - Structs with named fields are objects, tuple structs are arrays
+//! - Enums are tagged unions, i.e. objects with a single key that is the variant name, e.g.
+//!   `{ "Unit": {} }`, `{ "Named": { "a": 1 } }` or `{ "Tuple": [1, 2] }`
+//!
+//! Defined types are put in `$defs` and referenced with `$ref`, except for generic types, which
+//! are expanded in place with the given generic arguments.
+
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use crate::types::{
+    Idl, IdlArrayLen, IdlDefinedFields, IdlField, IdlGenericArg, IdlType, IdlTypeDef,
+    IdlTypeDefGeneric, IdlTypeDefTy,
+};
+
+/// JSON Schema dialect of the generated schemas.
+pub const JSON_SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Pattern of base58 encoded public keys.
+pub const PUBKEY_PATTERN: &str = "^[1-9A-HJ-NP-Za-km-z]{32,44}$";
+
+const UNSIGNED_PATTERN: &str = "^[0-9]+$";
+const SIGNED_PATTERN: &str = "^-?[0-9]+$";
+
+/// JSON Schemas of all types, accounts and instruction arguments of an IDL.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct IdlSchema {
+    pub types: BTreeMap<String, Value>,
+    pub accounts: BTreeMap<String, Value>,
+    pub instructions: BTreeMap<String, Value>,
+}
+
+/// Generate the JSON Schemas of every type definition, account and instruction arguments object
+/// of the IDL.
+pub fn idl_schema(idl: &Idl) -> Result<IdlSchema> {
+    Ok(IdlSchema {
+        types: idl
+            .types
+            .iter()
+            .map(|ty| Ok((ty.name.to_owned(), type_def_schema(idl, &ty.name)?)))
+            .collect::<Result<_>>()?,
+        accounts: idl
+            .accounts
+            .iter()
+            .map(|acc| Ok((acc.name.to_owned(), account_schema(idl, &acc.name)?)))
+            .collect::<Result<_>>()?,
+        instructions: idl
+            .instructions
+            .iter()
+            .map(|ix| Ok((ix.name.to_owned(), instruction_args_schema(idl, &ix.name)?)))
+            .collect::<Result<_>>()?,
+    })
+}
+
+/// Generate the JSON Schema of the type definition with the given name.
+///
+/// Generic parameters of the type are unconstrained.
+pub fn type_def_schema(idl: &Idl, name: &str) -> Result<Value> {
+    let mut generator = SchemaGenerator::new(idl);
+    let ty_def = generator.find_type_def(name)?;
+    let schema = generator.type_def(ty_def, &[])?;
+    Ok(generator.finish(name, &ty_def.docs, schema))
+}
+
+/// Generate the JSON Schema of the account with the given name.
+pub fn account_schema(idl: &Idl, name: &str) -> Result<Value> {
+    idl.accounts
+        .iter()
+        .find(|acc| acc.name == name)
+        .ok_or_else(|| anyhow!("Account `{name}` not found"))?;
+    type_def_schema(idl, name)
+}
+
+/// Generate the JSON Schema of the arguments of the instruction with the given name.
+///
+/// The arguments are represented as an object with the argument names as keys.
+pub fn instruction_args_schema(idl: &Idl, name: &str) -> Result<Value> {
+    let ix = idl
+        .instructions
+        .iter()
+        .find(|ix| ix.name == name)
+        .ok_or_else(|| anyhow!("Instruction `{name}` not found"))?;
+
+    let mut generator = SchemaGenerator::new(idl);
+    let schema = generator.named_fields(&ix.args, &[])?;
+    Ok(generator.finish(name, &ix.docs, schema))
+}
+
+/// Resolved value of a generic parameter.
+#[derive(Clone)]
+enum GenericValue {
+    Type(Value),
+    Const(String),
+}
+
+type Generics<'a> = [(&'a str, GenericValue)];
+
+struct SchemaGenerator<'a> {
+    idl: &'a Idl,
+    defs: BTreeMap<String, Value>,
+}
+
+impl<'a> SchemaGenerator<'a> {
+    fn new(idl: &'a Idl) -> Self {
+        Self {
+            idl,
+            defs: BTreeMap::new(),
+        }
+    }
+
+    fn finish(self, title: &str, docs: &[String], schema: Value) -> Value {
+        let mut root = Map::new();
+        root.insert("$schema".into(), JSON_SCHEMA_DIALECT.into());
+        root.insert("title".into(), title.into());
+        if !docs.is_empty() {
+            root.insert("description".into(), docs.join("\n").into());
+        }
+        match schema {
+            Value::Object(schema) => root.extend(schema),
+            _ => unreachable!("Schemas are always objects"),
+        }
+        if !self.defs.is_empty() {
+            root.insert("$defs".into(), json!(self.defs));
+        }
+
+        Value::Object(root)
+    }
+
+    fn find_type_def(&self, name: &str) -> Result<&'a IdlTypeDef> {
+        self.idl
+            .types
+            .iter()
+            .find(|ty| ty.name == name)
+            .ok_or_else(|| anyhow!("Type `{name}` not found"))
+    }
+
+    fn ty(&mut self, ty: &IdlType, generics: &Generics) -> Result<Value> {
+        let schema = match ty {
+            IdlType::Bool => json!({ "type": "boolean" }),
+            IdlType::U8 => integer(u8::MIN, u8::MAX),
+            IdlType::I8 => integer(i8::MIN, i8::MAX),
+            IdlType::U16 => integer(u16::MIN, u16::MAX),
+            IdlType::I16 => integer(i16::MIN, i16::MAX),
+            IdlType::U32 => integer(u32::MIN, u32::MAX),
+            IdlType::I32 => integer(i32::MIN, i32::MAX),
+            IdlType::F32 | IdlType::F64 => json!({ "type": "number" }),
+            IdlType::U64 | IdlType::U128 | IdlType::U256 => {
+                json!({ "type": "string", "pattern": UNSIGNED_PATTERN })
+            }
+            IdlType::I64 | IdlType::I128 | IdlType::I256 => {
+                json!({ "type": "string", "pattern": SIGNED_PATTERN })
+            }
+            IdlType::Bytes => json!({ "type": "array", "items": integer(u8::MIN, u8::MAX) }),
+            IdlType::String => json!({ "type": "string" }),
+            IdlType::Pubkey => json!({ "type": "string", "pattern": PUBKEY_PATTERN }),
+            IdlType::Option(ty) => json!({ "anyOf": [{ "type": "null" }, self.ty(ty, generics)?] }),
+            IdlType::Vec(ty) => json!({ "type": "array", "items": self.ty(ty, generics)? }),
+            IdlType::Set(ty) => json!({
+                "type": "array",
+                "items": self.ty(ty, generics)?,
+                "uniqueItems": true,
+            }),
+            IdlType::Array(ty, len) => {
+                let mut schema = json!({ "type": "array", "items": self.ty(ty, generics)? });
+                if let Some(len) = resolve_array_len(len, generics)? {
+                    schema["minItems"] = len.into();
+                    schema["maxItems"] = len.into();
+                }
+                schema
+            }
+            IdlType::Tuple(tys) => self.tuple(tys, generics)?,
+            IdlType::Map { key, value } => {
+                let mut schema = json!({
+                    "type": "object",
+                    "additionalProperties": self.ty(value, generics)?,
+                });
+                if let Some(key) = self.map_key(key, generics)? {
+                    schema["propertyNames"] = key;
+                }
+                schema
+            }
+            IdlType::Defined {
+                name,
+                generics: args,
+            } if args.is_empty() => {
+                self.add_def(name)?;
+                // Escape the name as a JSON pointer token
+                let name = name.replace('~', "~0").replace('/', "~1");
+                json!({ "$ref": format!("#/$defs/{name}") })
+            }
+            IdlType::Defined {
+                name,
+                generics: args,
+            } => {
+                let ty_def = self.find_type_def(name)?;
+                let args = args
+                    .iter()
+                    .map(|arg| match arg {
+                        IdlGenericArg::Type { ty } => {
+                            Ok(GenericValue::Type(self.ty(ty, generics)?))
+                        }
+                        IdlGenericArg::Const { value } => Ok(GenericValue::Const(
+                            resolve_const(value, generics).to_owned(),
+                        )),
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                let generics = ty_def
+                    .generics
+                    .iter()
+                    .map(|generic| match generic {
+                        IdlTypeDefGeneric::Type { name } => name.as_str(),
+                        IdlTypeDefGeneric::Const { name, .. } => name.as_str(),
+                    })
+                    .zip(args)
+                    .collect::<Vec<_>>();
+                self.type_def(ty_def, &generics)?
+            }
+            IdlType::Generic(name) => match find_generic(name, generics) {
+                Some(GenericValue::Type(schema)) => schema.to_owned(),
+                // Unresolved generic parameters can be anything
+                _ => json!({}),
+            },
+            _ => return Err(anyhow!("Unsupported IDL type: {ty:?}")),
+        };
+
+        Ok(schema)
+    }
+
+    /// Add the non-generic type definition with the given name to `$defs`.
+    fn add_def(&mut self, name: &str) -> Result<()> {
+        if self.defs.contains_key(name) {
+            return Ok(());
+        }
+
+        // Insert a placeholder first to support recursive types
+        self.defs.insert(name.to_owned(), Value::Null);
+        let ty_def = self.find_type_def(name)?;
+        let mut schema = self.type_def(ty_def, &[])?;
+        if !ty_def.docs.is_empty() {
+            schema["description"] = ty_def.docs.join("\n").into();
+        }
+        self.defs.insert(name.to_owned(), schema);
+
+        Ok(())
+    }
+
+    fn type_def(&mut self, ty_def: &IdlTypeDef, generics: &Generics) -> Result<Value> {
+        match &ty_def.ty {
+            IdlTypeDefTy::Struct { fields } => self.fields(fields.as_ref(), generics),
+            IdlTypeDefTy::Enum { variants } => {
+                let variants = variants
+                    .iter()
+                    .map(|variant| {
+                        let fields = self.fields(variant.fields.as_ref(), generics)?;
+                        Ok(json!({
+                            "type": "object",
+                            "properties": { &variant.name: fields },
+                            "required": [&variant.name],
+                            "additionalProperties": false,
+                        }))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(json!({ "oneOf": variants }))
+            }
+            IdlTypeDefTy::Type { alias } => self.ty(alias, generics),
+        }
+    }
+
+    fn fields(&mut self, fields: Option<&IdlDefinedFields>, generics: &Generics) -> Result<Value> {
+        match fields {
+            Some(IdlDefinedFields::Named(fields)) => self.named_fields(fields, generics),
+            Some(IdlDefinedFields::Tuple(tys)) => self.tuple(tys, generics),
+            None => self.named_fields(&[], generics),
+        }
+    }
+
+    fn named_fields(&mut self, fields: &[IdlField], generics: &Generics) -> Result<Value> {
+        let mut properties = Map::new();
+        for field in fields {
+            let mut schema = self.ty(&field.ty, generics)?;
+            if !field.docs.is_empty() {
+                schema["description"] = field.docs.join("\n").into();
+            }
+            properties.insert(field.name.to_owned(), schema);
+        }
+
+        Ok(json!({
+            "type": "object",
+            "properties": properties,
+            "required": fields.iter().map(|field| &field.name).collect::<Vec<_>>(),
+            "additionalProperties": false,
+        }))
+    }
+
+    fn tuple(&mut self, tys: &[IdlType], generics: &Generics) -> Result<Value> {
+        Ok(json!({
+            "type": "array",
+            "prefixItems": tys
+                .iter()
+                .map(|ty| self.ty(ty, generics))
+                .collect::<Result<Vec<_>>>()?,
+            "items": false,
+            "minItems": tys.len(),
+        }))
+    }
+
+    /// Schema of the string representation of map keys, if the key type has one.
+    fn map_key(&mut self, key: &IdlType, generics: &Generics) -> Result<Option<Value>> {
+        let schema = match key {
+            IdlType::U8 | IdlType::U16 | IdlType::U32 => json!({ "pattern": UNSIGNED_PATTERN }),
+            IdlType::I8 | IdlType::I16 | IdlType::I32 => json!({ "pattern": SIGNED_PATTERN }),
+            IdlType::Bool => json!({ "enum": ["true", "false"] }),
+            _ => match self.ty(key, generics)? {
+                schema if schema["type"] == "string" => schema,
+                _ => return Ok(None),
+            },
+        };
+
+        Ok(Some(schema))
+    }
+}
+
+fn integer<T: Serialize>(min: T, max: T) -> Value {
+    json!({ "type": "integer", "minimum": min, "maximum": max })
+}
+
+fn find_generic<'a>(name: &str, generics: &'a Generics) -> Option<&'a GenericValue> {
+    generics
+        .iter()
+        .find(|(generic, _)| *generic == name)
+        .map(|(_, value)| value)
+}
+
+/// Resolve the value of a const generic argument, which can refer to an outer generic parameter.
+fn resolve_const<'a>(value: &'a str, generics: &'a Generics) -> &'a str {
+    match find_generic(value, generics) {
+        Some(GenericValue::Const(value)) => value,
+        _ => value,
+    }
+}
+
+fn resolve_array_len(len: &IdlArrayLen, generics: &Generics) -> Result<Option<usize>> {
+    match len {
+        IdlArrayLen::Value(len) => Ok(Some(*len)),
+        IdlArrayLen::Generic(name) => match find_generic(name, generics) {
+            Some(GenericValue::Const(value)) => value
+                .parse()
+                .map(Some)
+                .map_err(|_| anyhow!("Invalid array length: `{value}`")),
+            _ => Ok(None),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn idl(value: Value) -> Idl {
+        let mut idl = json!({
+            "address": "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS",
+            "metadata": { "name": "test", "version": "0.1.0", "spec": "0.1.0" },
+            "instructions": []
+        });
+        idl.as_object_mut()
+            .unwrap()
+            .extend(value.as_object().unwrap().clone());
+        serde_json::from_value(idl).unwrap()
+    }
+
+    #[test]
+    fn primitives() {
+        let idl = idl(json!({
+            "types": [{
+                "name": "Primitives",
+                "type": {
+                    "kind": "struct",
+                    "fields": [
+                        { "name": "a", "type": "u8" },
+                        { "name": "b", "type": "i32" },
+                        { "name": "c", "type": "u64" },
+                        { "name": "d", "type": "i128" },
+                        { "name": "e", "type": "pubkey" },
+                        { "name": "f", "type": { "option": "string" } },
+                        { "name": "g", "type": { "array": ["bool", 2] } }
+                    ]
+                }
+            }]
+        }));
+
+        let schema = type_def_schema(&idl, "Primitives").unwrap();
+        assert_eq!(schema["$schema"], JSON_SCHEMA_DIALECT);
+        assert_eq!(schema["title"], "Primitives");
+        assert_eq!(
+            schema["required"],
+            json!(["a", "b", "c", "d", "e", "f", "g"])
+        );
+        assert_eq!(schema["additionalProperties"], false);
+
+        let properties = &schema["properties"];
+        assert_eq!(
+            properties["a"],
+            json!({ "type": "integer", "minimum": 0, "maximum": 255 })
+        );
+        assert_eq!(properties["b"]["minimum"], i32::MIN);
+        assert_eq!(
+            properties["c"],
+            json!({ "type": "string", "pattern": UNSIGNED_PATTERN })
+        );
+        assert_eq!(
+            properties["d"],
+            json!({ "type": "string", "pattern": SIGNED_PATTERN })
+        );
+        assert_eq!(
+            properties["e"],
+            json!({ "type": "string", "pattern": PUBKEY_PATTERN })
+        );
+        assert_eq!(
+            properties["f"],
+            json!({ "anyOf": [{ "type": "null" }, { "type": "string" }] })
+        );
+        assert_eq!(properties["g"]["minItems"], 2);
+        assert_eq!(properties["g"]["maxItems"], 2);
+    }
+
+    #[test]
+    fn enums() {
+        let idl = idl(json!({
+            "types": [{
+                "name": "MyEnum",
+                "type": {
+                    "kind": "enum",
+                    "variants": [
+                        { "name": "Unit" },
+                        { "name": "Named", "fields": [{ "name": "a", "type": "u8" }] },
+                        { "name": "Tuple", "fields": ["u8", "string"] }
+                    ]
+                }
+            }]
+        }));
+
+        let schema = type_def_schema(&idl, "MyEnum").unwrap();
+        let variants = schema["oneOf"].as_array().unwrap();
+        assert_eq!(variants.len(), 3);
+        assert_eq!(variants[0]["required"], json!(["Unit"]));
+        assert_eq!(variants[0]["properties"]["Unit"]["type"], "object");
+        assert_eq!(variants[1]["properties"]["Named"]["required"], json!(["a"]));
+        assert_eq!(variants[2]["properties"]["Tuple"]["type"], "array");
+        assert_eq!(variants[2]["properties"]["Tuple"]["items"], false);
+    }
+
+    #[test]
+    fn defined_and_generic_types() {
+        let idl = idl(json!({
+            "instructions": [{
+                "name": "init",
+                "discriminator": [0, 0, 0, 0, 0, 0, 0, 0],
+                "accounts": [],
+                "args": [
+                    { "name": "inner", "type": { "defined": { "name": "Inner" } } },
+                    {
+                        "name": "wrapper",
+                        "type": {
+                            "defined": {
+                                "name": "Wrapper",
+                                "generics": [
+                                    { "kind": "type", "type": "u16" },
+                                    { "kind": "const", "value": "3" }
+                                ]
+                            }
+                        }
+                    }
+                ]
+            }],
+            "types": [
+                {
+                    "name": "Inner",
+                    "docs": ["Inner type"],
+                    "type": { "kind": "struct", "fields": [{ "name": "x", "type": "u8" }] }
+                },
+                {
+                    "name": "Wrapper",
+                    "generics": [
+                        { "kind": "type", "name": "T" },
+                        { "kind": "const", "name": "N", "type": "usize" }
+                    ],
+                    "type": {
+                        "kind": "struct",
+                        "fields": [
+                            { "name": "value", "type": { "generic": "T" } },
+                            { "name": "values", "type": { "array": [{ "generic": "T" }, { "generic": "N" }] } }
+                        ]
+                    }
+                }
+            ]
+        }));
+
+        let schema = instruction_args_schema(&idl, "init").unwrap();
+        assert_eq!(
+            schema["properties"]["inner"],
+            json!({ "$ref": "#/$defs/Inner" })
+        );
+        assert_eq!(schema["$defs"]["Inner"]["description"], "Inner type");
+        assert!(schema["$defs"].get("Wrapper").is_none());
+
+        let wrapper = &schema["properties"]["wrapper"]["properties"];
+        assert_eq!(wrapper["value"]["maximum"], u16::MAX);
+        assert_eq!(wrapper["values"]["items"]["maximum"], u16::MAX);
+        assert_eq!(wrapper["values"]["maxItems"], 3);
+
+        // Unresolved generics
+        let schema = type_def_schema(&idl, "Wrapper").unwrap();
+        assert_eq!(schema["properties"]["value"], json!({}));
+        assert!(schema["properties"]["values"].get("maxItems").is_none());
+    }
+
+    #[test]
+    fn collections() {
+        let idl = idl(json!({
+            "types": [{
+                "name": "Collections",
+                "type": {
+                    "kind": "struct",
+                    "fields": [
+                        { "name": "map", "type": { "map": { "key": "u32", "value": "u64" } } },
+                        { "name": "set", "type": { "set": "pubkey" } },
+                        { "name": "tuple", "type": { "tuple": ["u8", "bool"] } }
+                    ]
+                }
+            }]
+        }));
+
+        let schema = type_def_schema(&idl, "Collections").unwrap();
+        let properties = &schema["properties"];
+        assert_eq!(properties["map"]["type"], "object");
+        assert_eq!(
+            properties["map"]["propertyNames"],
+            json!({ "pattern": UNSIGNED_PATTERN })
+        );
+        assert_eq!(properties["map"]["additionalProperties"]["type"], "string");
+        assert_eq!(properties["set"]["uniqueItems"], true);
+        assert_eq!(properties["tuple"]["prefixItems"][1]["type"], "boolean");
+    }
+
+    #[test]
+    fn recursive_types() {
+        let idl = idl(json!({
+            "accounts": [{ "name": "Node", "discriminator": [0, 0, 0, 0, 0, 0, 0, 0] }],
+            "types": [{
+                "name": "Node",
+                "type": {
+                    "kind": "struct",
+                    "fields": [
+                        { "name": "children", "type": { "vec": { "defined": { "name": "Node" } } } }
+                    ]
+                }
+            }]
+        }));
+
+        let schema = account_schema(&idl, "Node").unwrap();
+        assert_eq!(
+            schema["properties"]["children"]["items"],
+            json!({ "$ref": "#/$defs/Node" })
+        );
+        assert_eq!(schema["$defs"]["Node"]["type"], "object");
+    }
+
+    #[test]
+    fn not_found() {
+        let idl = idl(json!({}));
+        assert!(type_def_schema(&idl, "Missing").is_err());
+        assert!(account_schema(&idl, "Missing").is_err());
+        assert!(instruction_args_schema(&idl, "missing").is_err());
+    }
+}
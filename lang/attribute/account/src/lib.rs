@@ -346,7 +346,7 @@ impl Parse for AccountArg {
     }
 }
 
-#[proc_macro_derive(ZeroCopyAccessor, attributes(accessor))]
+#[proc_macro_derive(ZeroCopyAccessor, attributes(accessor, idl))]
 pub fn derive_zero_copy_accessor(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let account_strct = parse_macro_input!(item as syn::ItemStruct);
     let account_name = &account_strct.ident;
@@ -108,6 +108,88 @@ pub fn emit(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     })
 }
 
+/// Implements [`AnchorEvents`](trait@anchor_lang::AnchorEvents) for an enum whose variants each
+/// wrap a single event, which allows decoding any of the events without knowing its type
+/// beforehand, e.g. when subscribing to all events of a program.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use anchor_lang::prelude::*;
+///
+/// #[derive(AnchorEvents)]
+/// pub enum MyEvents {
+///     Deposit(DepositEvent),
+///     Withdraw(WithdrawEvent),
+/// }
+///
+/// #[event]
+/// pub struct DepositEvent {
+///     pub amount: u64,
+/// }
+///
+/// #[event]
+/// pub struct WithdrawEvent {
+///     pub amount: u64,
+/// }
+/// ```
+#[proc_macro_derive(AnchorEvents)]
+pub fn derive_anchor_events(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as syn::DeriveInput);
+    gen_anchor_events(&input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+fn gen_anchor_events(input: &syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let data = match &input.data {
+        syn::Data::Enum(data) => data,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "`AnchorEvents` can only be derived for enums",
+            ))
+        }
+    };
+
+    let if_statements = data
+        .variants
+        .iter()
+        .map(|variant| {
+            let ty = match &variant.fields {
+                syn::Fields::Unnamed(fields) if fields.unnamed.len() == 1 => &fields.unnamed[0].ty,
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        variant,
+                        "Variants must wrap exactly one event, e.g. `MyEvent(MyEvent)`",
+                    ))
+                }
+            };
+            let name = &variant.ident;
+            Ok(quote! {
+                if data.starts_with(<#ty as anchor_lang::Discriminator>::DISCRIMINATOR) {
+                    let mut data =
+                        &data[<#ty as anchor_lang::Discriminator>::DISCRIMINATOR.len()..];
+                    return <#ty as anchor_lang::AnchorDeserialize>::deserialize(&mut data)
+                        .map(|event| Some(Self::#name(event)))
+                        .map_err(Into::into);
+                }
+            })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics anchor_lang::AnchorEvents for #name #ty_generics #where_clause {
+            fn try_from_event_data(data: &[u8]) -> anchor_lang::Result<Option<Self>> {
+                #(#if_statements)*
+                Ok(None)
+            }
+        }
+    })
+}
+
 /// Log an event by making a self-CPI that can be subscribed to by clients.
 ///
 /// This way of logging events is more reliable than [`emit!`](emit!) because RPCs are less likely
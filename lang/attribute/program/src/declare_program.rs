@@ -0,0 +1,83 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use anchor_lang_idl::{convert::convert_idl, types::Idl};
+use anchor_syn::codegen::declare_program::gen_program;
+use anyhow::anyhow;
+use quote::ToTokens;
+use syn::parse::{Parse, ParseStream};
+
+pub struct DeclareProgram {
+    name: syn::Ident,
+    idl: Idl,
+    programs: Vec<(String, Idl)>,
+}
+
+impl Parse for DeclareProgram {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name: syn::Ident = input.parse()?;
+        let idl_dir = get_idl_dir().map_err(|e| syn::Error::new(name.span(), e))?;
+        let idl = get_idl(&idl_dir, &name).map_err(|e| syn::Error::new(name.span(), e))?;
+        let programs = get_programs(&idl_dir, &name);
+        Ok(Self {
+            name,
+            idl,
+            programs,
+        })
+    }
+}
+
+impl ToTokens for DeclareProgram {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let program = gen_program(&self.idl, &self.name, &self.programs);
+        tokens.extend(program)
+    }
+}
+
+fn get_idl_dir() -> anyhow::Result<PathBuf> {
+    env::var("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .map_err(|e| anyhow!("Failed to get environment variable `CARGO_MANIFEST_DIR`: {e}"))?
+        .ancestors()
+        .find_map(|ancestor| {
+            let idl_dir = ancestor.join("idls");
+            idl_dir.exists().then_some(idl_dir)
+        })
+        .ok_or_else(|| anyhow!("`idls` directory not found"))
+}
+
+fn get_idl(idl_dir: &Path, name: &syn::Ident) -> anyhow::Result<Idl> {
+    fs::read(idl_dir.join(name.to_string()).with_extension("json"))
+        .map_err(|e| anyhow!("Failed to read IDL `{name}`: {e}"))
+        .map(|buf| convert_idl(&buf))?
+}
+
+/// Get the other programs in the `idls` directory, as their module name and IDL.
+///
+/// Types that are defined by these programs are re-exported from their modules, which requires
+/// the programs to be declared in the same module.
+fn get_programs(idl_dir: &Path, name: &syn::Ident) -> Vec<(String, Idl)> {
+    fs::read_dir(idl_dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.extension()
+                        .map(|ext| ext == "json")
+                        .unwrap_or_default()
+                })
+                .filter_map(|path| {
+                    let module = path.file_stem()?.to_str()?.to_owned();
+                    if *name == module {
+                        return None;
+                    }
+                    let idl = convert_idl(&fs::read(&path).ok()?).ok()?;
+                    Some((module, idl))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
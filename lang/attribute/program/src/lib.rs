@@ -158,3 +158,42 @@ pub fn instruction(
     // the arguments to transform the instruction.
     input
 }
+
+/// This attribute is used to declare invariants of program instructions.
+///
+/// The invariants are checked after the exit routine of the instruction, only in test builds and
+/// with the `debug-invariants` feature of the program. The expression can refer to the
+/// instruction arguments by name, and to the instruction accounts as `accounts`. A violation
+/// returns an `InvariantViolated` error that names the invariant.
+///
+/// The accounts are checked as they were deserialized, so accounts that the handler modifies
+/// with a CPI, e.g. the token accounts of a transfer, are stale unless the handler calls
+/// `reload()` on them.
+///
+/// Invariants can also be declared on the accounts struct, see
+/// [`Accounts`](./derive.Accounts.html).
+///
+/// # Example
+///
+/// ```ignore
+/// #[program]
+/// pub mod pool {
+///     use super::*;
+///
+///     #[invariant(accounts.pool.total_shares <= accounts.pool.max_shares)]
+///     #[invariant(accounts.pool.total_shares >= amount)]
+///     pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
+///         // ...
+///         Ok(())
+///     }
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn invariant(
+    _args: proc_macro::TokenStream,
+    input: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    // This macro itself is a no-op, but the `#[program]` macro will detect this attribute and
+    // check the invariants after the instruction.
+    input
+}
@@ -9,6 +9,7 @@ use syn::parse_macro_input;
 ///
 /// # Table of Contents
 /// - [Instruction Attribute](#instruction-attribute)
+/// - [Invariant Attribute](#invariant-attribute)
 /// - [Constraints](#constraints)
 ///
 /// # Instruction Attribute
@@ -34,6 +35,31 @@ use syn::parse_macro_input;
 /// }
 /// ```
 ///
+/// # Invariant Attribute
+///
+/// You can declare invariants of the accounts with the
+/// `#[invariant(<expr>)]` attribute, where the expression can
+/// refer to the fields by name. Invariants are checked at the end of
+/// the exit routine, only in test builds and with the `debug-invariants`
+/// feature of the program, and a violation returns an
+/// `InvariantViolated` error that names the invariant.
+///
+/// The fields are checked as they were deserialized, so accounts that
+/// the instruction modifies with a CPI, e.g. the token accounts of a
+/// transfer, are stale unless the handler calls `reload()` on them.
+///
+/// # Example
+///
+/// ```ignore
+/// #[derive(Accounts)]
+/// #[invariant(pool.total_shares <= pool.max_shares)]
+/// pub struct Deposit<'info> {
+///     #[account(mut)]
+///     pub pool: Account<'info, Pool>,
+///     pub depositor: Signer<'info>,
+/// }
+/// ```
+///
 /// # Constraints
 ///
 /// There are different types of constraints that can be applied with the `#[account(..)]` attribute.
@@ -628,7 +654,7 @@ use syn::parse_macro_input;
 ///         </tr>
 ///     <tbody>
 /// </table>
-#[proc_macro_derive(Accounts, attributes(account, instruction))]
+#[proc_macro_derive(Accounts, attributes(account, instruction, invariant))]
 pub fn derive_accounts(item: TokenStream) -> TokenStream {
     parse_macro_input!(item as anchor_syn::AccountsStruct)
         .to_token_stream()
@@ -26,7 +26,7 @@ fn gen_borsh_serialize(input: TokenStream) -> TokenStream2 {
     }
 }
 
-#[proc_macro_derive(AnchorSerialize, attributes(borsh_skip))]
+#[proc_macro_derive(AnchorSerialize, attributes(borsh_skip, idl))]
 pub fn anchor_serialize(input: TokenStream) -> TokenStream {
     #[cfg(not(feature = "idl-build"))]
     let ret = gen_borsh_serialize(input);
@@ -44,6 +44,15 @@ pub enum ErrorCode {
     /// 1002 - IDL Account must be empty in order to resize
     #[msg("IDL account must be empty in order to resize, try closing first")]
     IdlAccountNotEmpty,
+    /// 1003 - Program metadata key must be between 1 and 32 bytes
+    #[msg("Program metadata key must be between 1 and 32 bytes")]
+    IdlMetadataInvalidKey,
+    /// 1004 - Canonical program metadata requires the upgrade authority of the program
+    #[msg("Canonical program metadata requires the upgrade authority of the program")]
+    IdlMetadataNotUpgradeAuthority,
+    /// 1005 - Program metadata must be written without gaps
+    #[msg("Program metadata must be written without gaps")]
+    IdlMetadataInvalidOffset,
 
     // Event instructions
     /// 1500 - The program was compiled without `event-cpi` feature
@@ -200,6 +209,9 @@ pub enum ErrorCode {
     /// 2506 - A require_gte expression was violated
     #[msg("A require_gte expression was violated")]
     RequireGteViolated,
+    /// 2507 - An invariant was violated
+    #[msg("An invariant was violated")]
+    InvariantViolated,
 
     // Accounts.
     /// 3000 - The account discriminator was already set on this account
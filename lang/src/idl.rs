@@ -14,6 +14,21 @@
 //! multiple transactions via the `Write` instruction to continuously append to
 //! the account's IDL data buffer.
 //!
+//! The IDL data is zlib-compressed JSON. Starting with version 1 of the data
+//! format, it is prefixed with an [`IdlDataHeader`] that carries the IDL spec
+//! version and a hash of the uncompressed IDL, which allows clients to verify
+//! the integrity of IDLs that were written and read in multiple chunks.
+//!
+//! Besides the IDL, a program can have other metadata such as its security.txt,
+//! source URL or build hash. Each metadata entry is stored in a
+//! [`ProgramMetadata`] account at a program-derived address of its key, see
+//! [`ProgramMetadata::address`]. Canonical entries can only be created by the
+//! upgrade authority of the program, while anyone can create non-canonical
+//! entries whose address is also derived from their authority, which allows
+//! publishing metadata for programs whose upgrade authority was burned. The
+//! data of an entry is written with `IdlInstruction::WriteMetadata`, signed by
+//! the entry's authority.
+//!
 //! Note that IDL account instructions are automatically inserted into all
 //! Anchor programs. To remove them, one can use the `no-idl` feature.
 
@@ -46,6 +61,15 @@ pub enum IdlInstruction {
     Close,
     // Increases account size for accounts that need over 10kb.
     Resize { data_len: u64 },
+    // Creates the program metadata account of the given key. Canonical metadata
+    // can only be created by the upgrade authority of the program.
+    CreateMetadata { key: String, canonical: bool },
+    // Writes the given data to the metadata account at the given offset, and
+    // truncates the metadata to the end of the written data.
+    WriteMetadata { offset: u32, data: Vec<u8> },
+    // Sets a new authority on the metadata account.
+    SetMetadataAuthority { new_authority: Pubkey },
+    CloseMetadata,
 }
 
 // The account holding a program's IDL. This is stored on chain so that clients
@@ -69,6 +93,9 @@ pub struct IdlAccount {
 }
 
 impl IdlAccount {
+    /// Offset of the IDL data in the account: discriminator || authority || data length.
+    pub const DATA_OFFSET: usize = 8 + 32 + 4;
+
     pub fn address(program_id: &Pubkey) -> Pubkey {
         let program_signer = Pubkey::find_program_address(&[], program_id).0;
         Pubkey::create_with_seed(&program_signer, IdlAccount::seed(), program_id)
@@ -79,5 +106,185 @@ impl IdlAccount {
     }
 }
 
+// Seed of the program metadata account addresses.
+pub const METADATA_SEED: &[u8] = b"anchor:metadata";
+
+// Maximum length of a program metadata key, which is the maximum length of a seed.
+pub const MAX_METADATA_KEY_LEN: usize = 32;
+
+/// Well-known program metadata keys.
+pub mod metadata_key {
+    /// IDL of the program, stored in the same format as the data of an [`IdlAccount`].
+    ///
+    /// [`IdlAccount`]: super::IdlAccount
+    pub const IDL: &str = "idl";
+    /// Contents of the program's security.txt.
+    pub const SECURITY_TXT: &str = "security.txt";
+    /// URL of the program's source code.
+    pub const SOURCE_URL: &str = "source-url";
+    /// Hash of the program's verifiable build.
+    pub const BUILD_HASH: &str = "build-hash";
+}
+
+// The account holding a metadata entry of a program.
+//
+// Note: like `IdlAccount`, the account that is used by the program is defined
+//       inside the program itself, see program/idl.rs.
+#[account("internal")]
+#[derive(Debug)]
+pub struct ProgramMetadata {
+    // Address that can modify the metadata.
+    pub authority: Pubkey,
+    // Whether the metadata was created by the upgrade authority of the program.
+    pub canonical: bool,
+    // Key of the metadata, padded with zeros.
+    pub key: [u8; 32],
+    // Length of the metadata bytes.
+    pub data_len: u32,
+    // Followed by metadata bytes.
+}
+
+impl ProgramMetadata {
+    /// Offset of the metadata in the account: discriminator || authority || canonical || key ||
+    /// data length.
+    pub const DATA_OFFSET: usize = 8 + 32 + 1 + 32 + 4;
+
+    /// Address of the metadata account of the given key.
+    ///
+    /// The address of canonical metadata is derived from the key only, and the address of
+    /// non-canonical metadata is derived from the authority and the key.
+    pub fn address(program_id: &Pubkey, key: &str, authority: Option<&Pubkey>) -> Pubkey {
+        Self::find_address(program_id, key, authority).0
+    }
+
+    /// Address and bump of the metadata account of the given key, see [`Self::address`].
+    pub fn find_address(
+        program_id: &Pubkey,
+        key: &str,
+        authority: Option<&Pubkey>,
+    ) -> (Pubkey, u8) {
+        match authority {
+            Some(authority) => Pubkey::find_program_address(
+                &[METADATA_SEED, authority.as_ref(), key.as_bytes()],
+                program_id,
+            ),
+            None => Pubkey::find_program_address(&[METADATA_SEED, key.as_bytes()], program_id),
+        }
+    }
+
+    /// Key of the metadata without padding.
+    pub fn key(&self) -> &str {
+        let len = self
+            .key
+            .iter()
+            .position(|b| *b == 0)
+            .unwrap_or(self.key.len());
+        std::str::from_utf8(&self.key[..len]).unwrap_or_default()
+    }
+
+    /// Pad the key to its stored form.
+    pub fn pad_key(key: &str) -> Result<[u8; 32]> {
+        if key.is_empty() || key.len() > MAX_METADATA_KEY_LEN || key.contains('\0') {
+            return Err(ErrorCode::IdlMetadataInvalidKey.into());
+        }
+
+        let mut padded = [0; 32];
+        padded[..key.len()].copy_from_slice(key.as_bytes());
+        Ok(padded)
+    }
+}
+
+/// Prefix of IDL data that starts with an [`IdlDataHeader`]. Legacy IDL data starts
+/// directly with the compressed IDL, whose first byte is always 0x78 (zlib).
+pub const IDL_DATA_MAGIC: &[u8] = b"AIDL";
+
+/// Current version of the IDL data format.
+pub const IDL_DATA_VERSION: u8 = 1;
+
+/// Header of the IDL data stored in an [`IdlAccount`], followed by the compressed IDL.
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct IdlDataHeader {
+    /// Version of the IDL data format.
+    pub version: u8,
+    /// Version of the IDL spec, e.g. `0.1.0`.
+    pub spec: String,
+    /// SHA-256 hash of the uncompressed IDL.
+    pub hash: [u8; 32],
+}
+
+impl IdlDataHeader {
+    pub fn new(spec: impl Into<String>, idl: &[u8]) -> Self {
+        Self {
+            version: IDL_DATA_VERSION,
+            spec: spec.into(),
+            hash: crate::solana_program::hash::hash(idl).to_bytes(),
+        }
+    }
+
+    /// Whether the hash of the given uncompressed IDL matches the header.
+    pub fn verify(&self, idl: &[u8]) -> bool {
+        crate::solana_program::hash::hash(idl).to_bytes() == self.hash
+    }
+
+    /// Serialize the header including the [`IDL_DATA_MAGIC`] prefix.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = IDL_DATA_MAGIC.to_vec();
+        self.serialize(&mut data).unwrap();
+        data
+    }
+
+    /// Split the IDL data into its header and the compressed IDL.
+    ///
+    /// The header is `None` for legacy IDL data.
+    pub fn split(data: &[u8]) -> Result<(Option<Self>, &[u8])> {
+        match data.strip_prefix(IDL_DATA_MAGIC) {
+            Some(mut data) => {
+                let header = Self::deserialize(&mut data)?;
+                if header.version != IDL_DATA_VERSION {
+                    return Err(ProgramError::InvalidAccountData.into());
+                }
+                Ok((Some(header), data))
+            }
+            None => Ok((None, data)),
+        }
+    }
+}
+
 #[cfg(feature = "idl-build")]
 pub use anchor_lang_idl::{build::IdlBuild, *};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn program_metadata_key() {
+        let key = ProgramMetadata::pad_key(metadata_key::SECURITY_TXT).unwrap();
+        let metadata = ProgramMetadata {
+            authority: Pubkey::default(),
+            canonical: true,
+            key,
+            data_len: 0,
+        };
+        assert_eq!(metadata.key(), metadata_key::SECURITY_TXT);
+
+        assert!(ProgramMetadata::pad_key("").is_err());
+        assert!(ProgramMetadata::pad_key(&"a".repeat(MAX_METADATA_KEY_LEN)).is_ok());
+        assert!(ProgramMetadata::pad_key(&"a".repeat(MAX_METADATA_KEY_LEN + 1)).is_err());
+    }
+
+    #[test]
+    fn program_metadata_address() {
+        let program_id = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let canonical = ProgramMetadata::address(&program_id, metadata_key::IDL, None);
+        let non_canonical =
+            ProgramMetadata::address(&program_id, metadata_key::IDL, Some(&authority));
+        assert_ne!(canonical, non_canonical);
+        assert_ne!(canonical, IdlAccount::address(&program_id));
+        assert_eq!(
+            canonical,
+            Pubkey::find_program_address(&[b"anchor:metadata", b"idl"], &program_id).0
+        );
+    }
+}
@@ -54,8 +54,8 @@ pub use anchor_attribute_access_control::access_control;
 pub use anchor_attribute_account::{account, declare_id, pubkey, zero_copy};
 pub use anchor_attribute_constant::constant;
 pub use anchor_attribute_error::*;
-pub use anchor_attribute_event::{emit, event};
-pub use anchor_attribute_program::{declare_program, instruction, program};
+pub use anchor_attribute_event::{emit, event, AnchorEvents};
+pub use anchor_attribute_program::{declare_program, instruction, invariant, program};
 pub use anchor_derive_accounts::Accounts;
 pub use anchor_derive_serde::{AnchorDeserialize, AnchorSerialize};
 pub use anchor_derive_space::InitSpace;
@@ -302,6 +302,21 @@ pub trait Event: AnchorSerialize + AnchorDeserialize + Discriminator {
     fn data(&self) -> Vec<u8>;
 }
 
+/// A set of events, usually all events of a program, that can be decoded without knowing the
+/// type of the event beforehand.
+///
+/// This trait is implemented for the `utils::Event` enum generated by
+/// [`declare_program!`](crate::declare_program), and it can be derived for enums whose variants
+/// each wrap a single [`Event`] with [`#[derive(AnchorEvents)]`](derive@AnchorEvents).
+pub trait AnchorEvents: Sized {
+    /// Try to decode an event from the given data, i.e. the discriminator of the event followed
+    /// by the serialized event.
+    ///
+    /// Returns `Ok(None)` if the discriminator doesn't match any of the events, and an error if
+    /// the deserialization fails.
+    fn try_from_event_data(data: &[u8]) -> Result<Option<Self>>;
+}
+
 /// Unique identifier for a type.
 ///
 /// This is not a trait you should derive manually, as various Anchor macros already derive it
@@ -408,13 +423,13 @@ pub mod prelude {
         accounts::signer::Signer, accounts::system_account::SystemAccount,
         accounts::sysvar::Sysvar, accounts::unchecked_account::UncheckedAccount, constant,
         context::Context, context::CpiContext, declare_id, declare_program, emit, err, error,
-        event, instruction, program, pubkey, require, require_eq, require_gt, require_gte,
-        require_keys_eq, require_keys_neq, require_neq,
+        event, instruction, invariant, program, pubkey, require, require_eq, require_gt,
+        require_gte, require_keys_eq, require_keys_neq, require_neq,
         solana_program::bpf_loader_upgradeable::UpgradeableLoaderState, source,
         system_program::System, zero_copy, AccountDeserialize, AccountSerialize, Accounts,
-        AccountsClose, AccountsExit, AnchorDeserialize, AnchorSerialize, Discriminator, Id,
-        InitSpace, Key, Lamports, Owner, ProgramData, Result, Space, ToAccountInfo, ToAccountInfos,
-        ToAccountMetas,
+        AccountsClose, AccountsExit, AnchorDeserialize, AnchorEvents, AnchorSerialize,
+        Discriminator, Id, InitSpace, Key, Lamports, Owner, ProgramData, Result, Space,
+        ToAccountInfo, ToAccountInfos, ToAccountMetas,
     };
     pub use anchor_attribute_error::*;
     pub use borsh;
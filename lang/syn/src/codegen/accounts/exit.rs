@@ -1,5 +1,6 @@
 use crate::accounts_codegen::constraints::OptionalCheckScope;
 use crate::codegen::accounts::{generics, ParsedGenerics};
+use crate::codegen::invariants;
 use crate::{AccountField, AccountsStruct, Ty};
 use quote::quote;
 
@@ -62,11 +63,23 @@ pub fn generate(accs: &AccountsStruct) -> proc_macro2::TokenStream {
             }
         })
         .collect();
+
+    // Invariants refer to the fields by name.
+    let bindings = accs.fields.iter().map(|af| {
+        let ident = af.ident();
+        quote! {
+            #[allow(unused_variables)]
+            let #ident = &self.#ident;
+        }
+    });
+    let invariants = invariants::generate(quote! { #(#bindings)* }, &accs.invariants);
+
     quote! {
         #[automatically_derived]
         impl<#combined_generics> anchor_lang::AccountsExit<#trait_generics> for #name<#struct_generics> #where_clause{
             fn exit(&self, program_id: &anchor_lang::solana_program::pubkey::Pubkey) -> anchor_lang::Result<()> {
                 #(#on_save)*
+                #invariants
                 Ok(())
             }
         }
@@ -38,9 +38,11 @@ pub fn generate(accs: &AccountsStruct) -> proc_macro2::TokenStream {
     #[cfg(feature = "idl-build")]
     {
         let idl_build_impl = crate::idl::gen_idl_build_impl_accounts_struct(accs);
+        let idl_lints = crate::idl::gen_idl_print_fn_lints_accounts(accs);
         return quote! {
             #ret
             #idl_build_impl
+            #idl_lints
         };
     }
 
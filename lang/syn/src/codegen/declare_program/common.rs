@@ -1,6 +1,6 @@
 use anchor_lang_idl::types::{
-    Idl, IdlArrayLen, IdlDefinedFields, IdlField, IdlGenericArg, IdlRepr, IdlSerialization,
-    IdlType, IdlTypeDef, IdlTypeDefGeneric, IdlTypeDefTy,
+    Idl, IdlArrayLen, IdlDefinedFields, IdlDeprecation, IdlField, IdlGenericArg, IdlRepr,
+    IdlSerialization, IdlType, IdlTypeDef, IdlTypeDefGeneric, IdlTypeDefTy,
 };
 use proc_macro2::Literal;
 use quote::{format_ident, quote};
@@ -20,6 +20,43 @@ pub fn gen_docs(docs: &[String]) -> proc_macro2::TokenStream {
     quote! { #(#docs)* }
 }
 
+/// Generate the docs of a field, including its metadata.
+///
+/// The metadata is documented instead of using `#[deprecated]`, as the fields are also used in
+/// the generated (de)serialization code.
+pub fn gen_field_docs(field: &IdlField) -> proc_macro2::TokenStream {
+    let mut docs = field.docs.clone();
+    if let Some(deprecated) = &field.deprecated {
+        docs.push(match &deprecated.note {
+            Some(note) => format!("Deprecated: {note}"),
+            None => "Deprecated".into(),
+        });
+    }
+    if let Some(unit) = &field.unit {
+        docs.push(format!("Unit: {unit}"));
+    }
+    if let Some(decimals) = field.decimals {
+        docs.push(format!("Decimals: {decimals}"));
+    }
+    gen_docs(&docs)
+}
+
+pub fn gen_deprecated(deprecated: Option<&IdlDeprecation>) -> proc_macro2::TokenStream {
+    match deprecated {
+        Some(IdlDeprecation { since, note }) => {
+            let since = since.as_ref().map(|since| quote! { since = #since });
+            let note = note.as_ref().map(|note| quote! { note = #note });
+            let args = since.into_iter().chain(note).collect::<Vec<_>>();
+            if args.is_empty() {
+                quote! { #[deprecated] }
+            } else {
+                quote! { #[deprecated(#(#args),*)] }
+            }
+        }
+        None => quote! {},
+    }
+}
+
 pub fn gen_discriminator(disc: &[u8]) -> proc_macro2::TokenStream {
     quote! { [#(#disc), *] }
 }
@@ -65,6 +102,22 @@ pub fn convert_idl_type_to_str(ty: &IdlType) -> String {
         IdlType::Pubkey => "Pubkey".into(),
         IdlType::Option(ty) => format!("Option<{}>", convert_idl_type_to_str(ty)),
         IdlType::Vec(ty) => format!("Vec<{}>", convert_idl_type_to_str(ty)),
+        IdlType::Tuple(tys) => {
+            let tys = tys.iter().map(convert_idl_type_to_str).collect::<Vec<_>>();
+            match tys.as_slice() {
+                [ty] => format!("({ty},)"),
+                _ => format!("({})", tys.join(", ")),
+            }
+        }
+        IdlType::Map { key, value } => format!(
+            "std::collections::BTreeMap<{}, {}>",
+            convert_idl_type_to_str(key),
+            convert_idl_type_to_str(value)
+        ),
+        IdlType::Set(ty) => format!(
+            "std::collections::BTreeSet<{}>",
+            convert_idl_type_to_str(ty)
+        ),
         IdlType::Array(ty, len) => format!(
             "[{}; {}]",
             convert_idl_type_to_str(ty),
@@ -202,9 +255,10 @@ pub fn convert_idl_type_def_to_ts(
                 || quote! { #declare_struct; },
                 |fields| {
                     let fields = fields.iter().map(|field| {
+                        let docs = gen_field_docs(field);
                         let name = format_ident!("{}", field.name);
                         let ty = convert_idl_type_to_syn_type(&field.ty);
-                        quote! { pub #name : #ty }
+                        quote! { #docs pub #name : #ty }
                     });
                     quote! {
                         #declare_struct {
@@ -239,9 +293,10 @@ pub fn convert_idl_type_def_to_ts(
                     || quote! { #variant_name },
                     |fields| {
                         let fields = fields.iter().map(|field| {
+                            let docs = gen_field_docs(field);
                             let name = format_ident!("{}", field.name);
                             let ty = convert_idl_type_to_syn_type(&field.ty);
-                            quote! { #name : #ty }
+                            quote! { #docs #name : #ty }
                         });
                         quote! {
                             #variant_name {
@@ -303,6 +358,9 @@ fn can_derive_default(ty_def: &IdlTypeDef, ty_defs: &[IdlTypeDef]) -> bool {
 fn can_derive_copy_ty(ty: &IdlType, ty_defs: &[IdlTypeDef]) -> bool {
     match ty {
         IdlType::Option(inner) => can_derive_copy_ty(inner, ty_defs),
+        IdlType::Tuple(inners) => inners
+            .iter()
+            .all(|inner| can_derive_copy_ty(inner, ty_defs)),
         IdlType::Array(inner, len) => {
             if !can_derive_copy_ty(inner, ty_defs) {
                 return false;
@@ -318,7 +376,12 @@ fn can_derive_copy_ty(ty: &IdlType, ty_defs: &[IdlTypeDef]) -> bool {
             .find(|ty_def| &ty_def.name == name)
             .map(|ty_def| can_derive_copy(ty_def, ty_defs))
             .expect("Type def must exist"),
-        IdlType::Bytes | IdlType::String | IdlType::Vec(_) | IdlType::Generic(_) => false,
+        IdlType::Bytes
+        | IdlType::String
+        | IdlType::Vec(_)
+        | IdlType::Map { .. }
+        | IdlType::Set(_)
+        | IdlType::Generic(_) => false,
         _ => true,
     }
 }
@@ -327,6 +390,13 @@ fn can_derive_default_ty(ty: &IdlType, ty_defs: &[IdlTypeDef]) -> bool {
     match ty {
         IdlType::Option(inner) => can_derive_default_ty(inner, ty_defs),
         IdlType::Vec(inner) => can_derive_default_ty(inner, ty_defs),
+        // `Default` is only implemented for tuples with up to 12 elements
+        IdlType::Tuple(inners) => {
+            inners.len() <= 12
+                && inners
+                    .iter()
+                    .all(|inner| can_derive_default_ty(inner, ty_defs))
+        }
         IdlType::Array(inner, len) => {
             if !can_derive_default_ty(inner, ty_defs) {
                 return false;
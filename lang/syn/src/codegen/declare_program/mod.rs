@@ -0,0 +1,118 @@
+mod common;
+mod mods;
+
+use anchor_lang_idl::types::Idl;
+use quote::quote;
+
+use common::gen_docs;
+use mods::{
+    accounts::gen_accounts_mod, client::gen_client_mod, constants::gen_constants_mod,
+    cpi::gen_cpi_mod, errors::gen_errors_mod, events::gen_events_mod,
+    instructions::gen_instructions_mod, internal::gen_internal_mod, pda::gen_pda_mod,
+    program::gen_program_mod, types::gen_types_mod, utils::gen_utils_mod,
+};
+
+/// Generate the declaration of an external program with the given module name from its IDL.
+///
+/// `programs` are the other programs that are declared in the same module, as their module name
+/// and IDL. Types that are defined by these programs are re-exported from their modules.
+pub fn gen_program(
+    idl: &Idl,
+    name: &syn::Ident,
+    programs: &[(String, Idl)],
+) -> proc_macro2::TokenStream {
+    let docs = gen_program_docs(idl);
+    let items = gen_items(idl, programs);
+
+    quote! {
+        #docs
+        pub mod #name {
+            #items
+        }
+    }
+}
+
+/// Generate the items of an external program declaration without the enclosing module.
+///
+/// This is useful for generating the root of a standalone client crate.
+pub fn gen_program_items(idl: &Idl) -> proc_macro2::TokenStream {
+    gen_items(idl, &[])
+}
+
+fn gen_items(idl: &Idl, programs: &[(String, Idl)]) -> proc_macro2::TokenStream {
+    let id = gen_id(idl);
+    let program_mod = gen_program_mod(&idl.metadata.name);
+
+    // Defined
+    let constants_mod = gen_constants_mod(idl);
+    let accounts_mod = gen_accounts_mod(idl);
+    let events_mod = gen_events_mod(idl);
+    let types_mod = gen_types_mod(idl, programs);
+    let errors_mod = gen_errors_mod(idl);
+
+    // Clients
+    let cpi_mod = gen_cpi_mod(idl);
+    let client_mod = gen_client_mod(idl);
+    let instructions_mod = gen_instructions_mod(idl);
+    let internal_mod = gen_internal_mod(idl);
+
+    // Utils
+    let pda_mod = gen_pda_mod(idl);
+    let utils_mod = gen_utils_mod(idl);
+
+    quote! {
+        use anchor_lang::prelude::*;
+        use accounts::*;
+        use events::*;
+        use types::*;
+
+        #id
+        #program_mod
+
+        #constants_mod
+        #accounts_mod
+        #events_mod
+        #types_mod
+        #errors_mod
+
+        #cpi_mod
+        #client_mod
+        #instructions_mod
+        #internal_mod
+
+        #pda_mod
+        #utils_mod
+    }
+}
+
+fn gen_program_docs(idl: &Idl) -> proc_macro2::TokenStream {
+    let docs: &[String] = &[
+        format!(
+            "Generated external program declaration of program `{}`.",
+            idl.metadata.name
+        ),
+        String::default(),
+    ];
+    let docs = [docs, &idl.docs].concat();
+    gen_docs(&docs)
+}
+
+fn gen_id(idl: &Idl) -> proc_macro2::TokenStream {
+    let address_bytes = bs58::decode(&idl.address)
+        .into_vec()
+        .expect("Invalid `idl.address`");
+    let doc = format!("Program ID of program `{}`.", idl.metadata.name);
+
+    quote! {
+        #[doc = #doc]
+        pub static ID: Pubkey = __ID;
+
+        /// Const version of `ID`
+        pub const ID_CONST: Pubkey = __ID_CONST;
+
+        /// The name is intentionally prefixed with `__` in order to reduce to possibility of name
+        /// clashes with the crate's `ID`.
+        static __ID: Pubkey = Pubkey::new_from_array([#(#address_bytes,)*]);
+        const __ID_CONST : Pubkey = Pubkey::new_from_array([#(#address_bytes,)*]);
+    }
+}
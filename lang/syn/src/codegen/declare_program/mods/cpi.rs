@@ -2,7 +2,7 @@ use anchor_lang_idl::types::Idl;
 use heck::CamelCase;
 use quote::{format_ident, quote};
 
-use super::common::{convert_idl_type_to_syn_type, gen_accounts_common};
+use super::common::{convert_idl_type_to_syn_type, gen_accounts_common, gen_deprecated, gen_docs};
 
 pub fn gen_cpi_mod(idl: &Idl) -> proc_macro2::TokenStream {
     let cpi_instructions = gen_cpi_instructions(idl);
@@ -25,6 +25,8 @@ fn gen_cpi_instructions(idl: &Idl) -> proc_macro2::TokenStream {
     let ixs = idl.instructions.iter().map(|ix| {
         let method_name = format_ident!("{}", ix.name);
         let accounts_ident = format_ident!("{}", ix.name.to_camel_case());
+        let docs = gen_docs(&ix.docs);
+        let deprecated = gen_deprecated(ix.deprecated.as_ref());
 
         let accounts_generic = if ix.accounts.is_empty() {
            quote!()
@@ -64,6 +66,8 @@ fn gen_cpi_instructions(idl: &Idl) -> proc_macro2::TokenStream {
         };
 
         quote! {
+            #docs
+            #deprecated
             pub fn #method_name<'a, 'b, 'c, 'info>(
                 ctx: anchor_lang::context::CpiContext<'a, 'b, 'c, 'info, accounts::#accounts_ident #accounts_generic>,
                 #(#args),*
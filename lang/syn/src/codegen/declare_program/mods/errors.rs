@@ -0,0 +1,76 @@
+use anchor_lang_idl::types::Idl;
+use quote::{format_ident, quote};
+
+pub fn gen_errors_mod(idl: &Idl) -> proc_macro2::TokenStream {
+    // Enums with no variants can't have a `repr` attribute
+    if idl.errors.is_empty() {
+        return quote! {
+            /// Program error definitions.
+            pub mod errors {}
+        };
+    }
+
+    let variants = idl.errors.iter().map(|err| {
+        let name = format_ident!("{}", err.name);
+        let code = err.code;
+        let docs = err.msg.as_ref().map(|msg| quote! { #[doc = #msg] });
+        quote! {
+            #docs
+            #name = #code
+        }
+    });
+    let msgs = idl.errors.iter().map(|err| {
+        let name = format_ident!("{}", err.name);
+        let msg = err.msg.as_ref().unwrap_or(&err.name);
+        quote! { Self::#name => #msg }
+    });
+    let from_codes = idl.errors.iter().map(|err| {
+        let name = format_ident!("{}", err.name);
+        let code = err.code;
+        quote! { #code => Ok(Self::#name) }
+    });
+
+    quote! {
+        /// Program error definitions.
+        pub mod errors {
+            /// Program error codes.
+            #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+            #[repr(u32)]
+            pub enum ErrorCode {
+                #(#variants,)*
+            }
+
+            impl ErrorCode {
+                /// Get the error message, or the name of the error if it doesn't have a message.
+                pub fn msg(&self) -> &'static str {
+                    match self {
+                        #(#msgs,)*
+                    }
+                }
+            }
+
+            impl From<ErrorCode> for u32 {
+                fn from(err: ErrorCode) -> Self {
+                    err as u32
+                }
+            }
+
+            impl TryFrom<u32> for ErrorCode {
+                type Error = u32;
+
+                fn try_from(code: u32) -> Result<Self, Self::Error> {
+                    match code {
+                        #(#from_codes,)*
+                        _ => Err(code),
+                    }
+                }
+            }
+
+            impl std::fmt::Display for ErrorCode {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    f.write_str(self.msg())
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use anchor_lang_idl::types::Idl;
+use heck::CamelCase;
+use quote::{format_ident, quote};
+
+use super::common::{convert_idl_type_to_syn_type, gen_docs, get_canonical_program_id};
+
+pub fn gen_instructions_mod(idl: &Idl) -> proc_macro2::TokenStream {
+    let program_id = get_canonical_program_id();
+    let ixs = idl.instructions.iter().map(|ix| {
+        let method_name = format_ident!("{}", ix.name);
+        let ident = format_ident!("{}", ix.name.to_camel_case());
+        let docs = gen_docs(&ix.docs);
+
+        let args = ix.args.iter().map(|arg| {
+            let name = format_ident!("{}", arg.name);
+            let ty = convert_idl_type_to_syn_type(&arg.ty);
+            quote! { #name: #ty }
+        });
+
+        let arg_value = if ix.args.is_empty() {
+            quote! { #ident }
+        } else {
+            let fields = ix.args.iter().map(|arg| format_ident!("{}", arg.name));
+            quote! {
+                #ident {
+                    #(#fields),*
+                }
+            }
+        };
+
+        quote! {
+            #docs
+            pub fn #method_name(
+                accounts: client::accounts::#ident,
+                #(#args),*
+            ) -> anchor_lang::solana_program::instruction::Instruction {
+                anchor_lang::solana_program::instruction::Instruction {
+                    program_id: #program_id,
+                    accounts: accounts.to_account_metas(None),
+                    data: anchor_lang::InstructionData::data(&client::args::#arg_value),
+                }
+            }
+        }
+    });
+
+    quote! {
+        /// Off-chain instruction builders.
+        pub mod instructions {
+            use super::*;
+
+            #(#ixs)*
+        }
+    }
+}
@@ -1,11 +1,11 @@
-use anchor_lang_idl::types::{
-    Idl, IdlInstruction, IdlInstructionAccountItem, IdlInstructionAccounts,
-};
-use anchor_syn::{
+use crate::{
     codegen::accounts::{__client_accounts, __cpi_client_accounts},
     parser::accounts,
     AccountsStruct,
 };
+use anchor_lang_idl::types::{
+    Idl, IdlInstruction, IdlInstructionAccountItem, IdlInstructionAccounts,
+};
 use heck::CamelCase;
 use quote::{format_ident, quote};
 
@@ -149,6 +149,7 @@ fn gen_internal_accounts_common(
             discriminator: Default::default(),
             docs: Default::default(),
             returns: Default::default(),
+            deprecated: Default::default(),
         })
         .chain(idl.instructions.iter().cloned())
         .collect::<Vec<_>>();
@@ -2,8 +2,11 @@ pub mod accounts;
 pub mod client;
 pub mod constants;
 pub mod cpi;
+pub mod errors;
 pub mod events;
+pub mod instructions;
 pub mod internal;
+pub mod pda;
 pub mod program;
 pub mod types;
 pub mod utils;
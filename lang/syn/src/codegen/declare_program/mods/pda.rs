@@ -0,0 +1,131 @@
+use anchor_lang_idl::types::{
+    Idl, IdlInstructionAccount, IdlInstructionAccountItem, IdlPda, IdlSeed, IdlType,
+};
+use quote::{format_ident, quote};
+
+use super::common::{convert_idl_type_to_syn_type, get_canonical_program_id};
+
+pub fn gen_pda_mod(idl: &Idl) -> proc_macro2::TokenStream {
+    fn flatten_accounts(accs: &[IdlInstructionAccountItem]) -> Vec<&IdlInstructionAccount> {
+        accs.iter()
+            .flat_map(|acc| match acc {
+                IdlInstructionAccountItem::Single(acc) => vec![acc],
+                IdlInstructionAccountItem::Composite(accs) => flatten_accounts(&accs.accounts),
+            })
+            .collect()
+    }
+
+    // Account names are not unique across instructions, the instruction name is used as a prefix
+    // in the case of a conflicting PDA definition
+    let mut pdas: Vec<(String, &IdlPda)> = vec![];
+    for ix in &idl.instructions {
+        for acc in flatten_accounts(&ix.accounts) {
+            let pda = match acc.pda.as_ref() {
+                Some(pda) => pda,
+                None => continue,
+            };
+
+            match pdas.iter().find(|(name, _)| name == &acc.name) {
+                Some((_, other)) if *other == pda => {}
+                Some(_) => pdas.push((format!("{}_{}", ix.name, acc.name), pda)),
+                None => pdas.push((acc.name.to_owned(), pda)),
+            }
+        }
+    }
+
+    let helpers = pdas
+        .iter()
+        .filter_map(|(name, pda)| gen_pda_helper(idl, name, pda));
+
+    quote! {
+        /// Program derived address (PDA) helpers.
+        pub mod pda {
+            use super::*;
+
+            #(#helpers)*
+        }
+    }
+}
+
+/// Generate the PDA helper function, or `None` if the seeds can't be derived from the instruction
+/// arguments and account addresses, e.g. when a seed is a field of an account.
+fn gen_pda_helper(idl: &Idl, name: &str, pda: &IdlPda) -> Option<proc_macro2::TokenStream> {
+    let mut params: Vec<(String, proc_macro2::TokenStream)> = vec![];
+    let mut seeds = vec![];
+    for seed in &pda.seeds {
+        match seed {
+            IdlSeed::Const(seed) => {
+                let value = &seed.value;
+                seeds.push(quote! { &[#(#value),*] });
+            }
+            IdlSeed::Arg(seed) => {
+                let ty = idl
+                    .instructions
+                    .iter()
+                    .flat_map(|ix| &ix.args)
+                    .find(|arg| arg.name == seed.path)
+                    .map(|arg| &arg.ty)?;
+                let param = format_ident!("{}", seed.path);
+                let (param_ty, seed_expr) = match ty {
+                    IdlType::Pubkey => (quote!(&Pubkey), quote!(#param.as_ref())),
+                    IdlType::String => (quote!(&str), quote!(#param.as_bytes())),
+                    IdlType::Bytes => (quote!(&[u8]), quote!(#param)),
+                    IdlType::U8
+                    | IdlType::I8
+                    | IdlType::U16
+                    | IdlType::I16
+                    | IdlType::U32
+                    | IdlType::I32
+                    | IdlType::U64
+                    | IdlType::I64
+                    | IdlType::U128
+                    | IdlType::I128 => {
+                        let ty = convert_idl_type_to_syn_type(ty);
+                        (quote!(#ty), quote!(&#param.to_le_bytes()))
+                    }
+                    _ => return None,
+                };
+                params.push((seed.path.to_owned(), param_ty));
+                seeds.push(seed_expr);
+            }
+            IdlSeed::Account(seed) if !seed.path.contains('.') => {
+                let param = format_ident!("{}", seed.path);
+                params.push((seed.path.to_owned(), quote!(&Pubkey)));
+                seeds.push(quote!(#param.as_ref()));
+            }
+            IdlSeed::Account(_) => return None,
+        }
+    }
+
+    let program_id = match &pda.program {
+        None => get_canonical_program_id(),
+        Some(IdlSeed::Const(seed)) if seed.value.len() == 32 => {
+            let value = &seed.value;
+            quote! { Pubkey::new_from_array([#(#value),*]) }
+        }
+        Some(_) => return None,
+    };
+
+    let mut unique_params: Vec<&(String, proc_macro2::TokenStream)> = vec![];
+    for param in &params {
+        match unique_params.iter().find(|(name, _)| name == &param.0) {
+            Some((_, ty)) if ty.to_string() != param.1.to_string() => return None,
+            Some(_) => {}
+            None => unique_params.push(param),
+        }
+    }
+    let params = unique_params.into_iter().map(|(name, ty)| {
+        let name = format_ident!("{}", name);
+        quote! { #name: #ty }
+    });
+
+    let fn_name = format_ident!("find_{}_address", name);
+    let doc = format!("Find the address of the `{name}` PDA and its bump.");
+
+    Some(quote! {
+        #[doc = #doc]
+        pub fn #fn_name(#(#params),*) -> (Pubkey, u8) {
+            Pubkey::find_program_address(&[#(#seeds),*], &#program_id)
+        }
+    })
+}
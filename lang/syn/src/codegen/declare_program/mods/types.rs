@@ -0,0 +1,67 @@
+use anchor_lang_idl::types::{Idl, IdlTypeDef};
+use quote::{format_ident, quote};
+
+use super::common::convert_idl_type_def_to_ts;
+
+/// Generate the types module.
+///
+/// Types that are defined by one of the given programs are re-exported from the module of that
+/// program instead of being defined again, so that they resolve to a single definition. The
+/// programs are given as their module name and IDL.
+pub fn gen_types_mod(idl: &Idl, programs: &[(String, Idl)]) -> proc_macro2::TokenStream {
+    let types = idl
+        .types
+        .iter()
+        .filter(|ty| {
+            // Skip accounts and events
+            !(idl.accounts.iter().any(|acc| acc.name == ty.name)
+                || idl.events.iter().any(|ev| ev.name == ty.name))
+        })
+        .map(|ty| match gen_re_export(ty, programs) {
+            Some(re_export) => re_export,
+            None => convert_idl_type_def_to_ts(ty, &idl.types),
+        });
+
+    quote! {
+        /// Program type definitions.
+        ///
+        /// Note that account and event type definitions are not included in this module, as they
+        /// have their own dedicated modules.
+        pub mod types {
+            use super::*;
+
+            #(#types)*
+        }
+    }
+}
+
+/// Generate the re-export of the type from the program that defines it, if the program is
+/// declared.
+fn gen_re_export(ty: &IdlTypeDef, programs: &[(String, Idl)]) -> Option<proc_macro2::TokenStream> {
+    let origin = ty.origin.as_ref()?;
+    let (program_mod, program_idl) = programs
+        .iter()
+        .find(|(_, idl)| idl.address == origin.program)?;
+    if !program_idl.types.iter().any(|ty| ty.name == origin.name) {
+        return None;
+    }
+
+    let kind = if program_idl
+        .accounts
+        .iter()
+        .any(|acc| acc.name == origin.name)
+    {
+        "accounts"
+    } else if program_idl.events.iter().any(|ev| ev.name == origin.name) {
+        "events"
+    } else {
+        "types"
+    };
+    let program_mod = format_ident!("{program_mod}");
+    let kind = format_ident!("{kind}");
+    let origin_name = format_ident!("{}", origin.name);
+    let name = format_ident!("{}", ty.name);
+    Some(quote! {
+        pub use super::super::#program_mod::#kind::#origin_name as #name;
+    })
+}
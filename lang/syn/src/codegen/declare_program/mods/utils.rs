@@ -68,21 +68,12 @@ fn gen_event(idl: &Idl) -> proc_macro2::TokenStream {
         .iter()
         .map(|ev| format_ident!("{}", ev.name))
         .map(|name| quote! { #name(#name) });
-    let if_statements = idl.events.iter().map(|ev| {
-        let name = format_ident!("{}", ev.name);
-        quote! {
-            if value.starts_with(#name::DISCRIMINATOR) {
-                return #name::try_from_slice(&value[#name::DISCRIMINATOR.len()..])
-                    .map(Self::#name)
-                    .map_err(Into::into)
-            }
-        }
-    });
 
     quote! {
         /// An enum that includes all events of the declared program as a tuple variant.
         ///
         /// See [`Self::try_from_bytes`] to create an instance from bytes.
+        #[derive(AnchorEvents)]
         pub enum Event {
             #(#variants,)*
         }
@@ -101,8 +92,8 @@ fn gen_event(idl: &Idl) -> proc_macro2::TokenStream {
             type Error = anchor_lang::error::Error;
 
             fn try_from(value: &[u8]) -> Result<Self> {
-                #(#if_statements)*
-                Err(ProgramError::InvalidArgument.into())
+                Self::try_from_event_data(value)?
+                    .ok_or_else(|| ProgramError::InvalidArgument.into())
             }
         }
     }
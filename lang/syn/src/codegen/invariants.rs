@@ -0,0 +1,51 @@
+use crate::Invariant;
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
+
+// Generates the checks of the `#[invariant]` attributes, which only run in test builds and with
+// the `debug-invariants` feature of the program. `bindings` declares the variables that the
+// invariants can refer to.
+pub fn generate(
+    bindings: proc_macro2::TokenStream,
+    invariants: &[Invariant],
+) -> proc_macro2::TokenStream {
+    if invariants.is_empty() {
+        return quote! {};
+    }
+
+    let checks = invariants.iter().map(|Invariant { expr, name }| {
+        // Report the line of the invariant
+        let line = quote_spanned! {expr.span()=> line!() };
+        quote! {
+            let __invariant: bool = #expr;
+            if !__invariant {
+                return Err(anchor_lang::error::Error::from(
+                    anchor_lang::error::AnchorError {
+                        error_name: anchor_lang::error::ErrorCode::InvariantViolated.name(),
+                        error_code_number: anchor_lang::error::ErrorCode::InvariantViolated.into(),
+                        error_msg: format!(
+                            "{}: {}",
+                            anchor_lang::error::ErrorCode::InvariantViolated,
+                            #name,
+                        ),
+                        error_origin: Some(anchor_lang::error::ErrorOrigin::Source(
+                            anchor_lang::error::Source {
+                                filename: file!(),
+                                line: #line,
+                            },
+                        )),
+                        compared_values: None,
+                    },
+                ));
+            }
+        }
+    });
+
+    quote! {
+        #[cfg(any(test, feature = "debug-invariants"))]
+        {
+            #bindings
+            #(#checks)*
+        }
+    }
+}
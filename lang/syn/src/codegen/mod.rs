@@ -1,3 +1,6 @@
 pub mod accounts;
+#[cfg(feature = "declare-program")]
+pub mod declare_program;
 pub mod error;
+pub mod invariants;
 pub mod program;
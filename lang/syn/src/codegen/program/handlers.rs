@@ -1,3 +1,4 @@
+use crate::codegen::invariants;
 use crate::codegen::program::common::*;
 use crate::program_codegen::idl::idl_accounts_and_functions;
 use crate::Program;
@@ -83,6 +84,38 @@ pub fn generate(program: &Program) -> proc_macro2::TokenStream {
                         __idl_set_buffer(program_id, &mut accounts)?;
                         accounts.exit(program_id)?;
                     },
+                    anchor_lang::idl::IdlInstruction::CreateMetadata { key, canonical } => {
+                        let mut bumps = <IdlCreateMetadata as anchor_lang::Bumps>::Bumps::default();
+                        let mut reallocs = std::collections::BTreeSet::new();
+                        let mut accounts =
+                            IdlCreateMetadata::try_accounts(program_id, &mut accounts, &[], &mut bumps, &mut reallocs)?;
+                        __idl_create_metadata(program_id, &mut accounts, key, canonical)?;
+                        accounts.exit(program_id)?;
+                    },
+                    anchor_lang::idl::IdlInstruction::WriteMetadata { offset, data } => {
+                        let mut bumps = <IdlWriteMetadata as anchor_lang::Bumps>::Bumps::default();
+                        let mut reallocs = std::collections::BTreeSet::new();
+                        let mut accounts =
+                            IdlWriteMetadata::try_accounts(program_id, &mut accounts, &[], &mut bumps, &mut reallocs)?;
+                        __idl_write_metadata(program_id, &mut accounts, offset, data)?;
+                        accounts.exit(program_id)?;
+                    },
+                    anchor_lang::idl::IdlInstruction::SetMetadataAuthority { new_authority } => {
+                        let mut bumps = <IdlMetadataAccounts as anchor_lang::Bumps>::Bumps::default();
+                        let mut reallocs = std::collections::BTreeSet::new();
+                        let mut accounts =
+                            IdlMetadataAccounts::try_accounts(program_id, &mut accounts, &[], &mut bumps, &mut reallocs)?;
+                        __idl_set_metadata_authority(program_id, &mut accounts, new_authority)?;
+                        accounts.exit(program_id)?;
+                    },
+                    anchor_lang::idl::IdlInstruction::CloseMetadata => {
+                        let mut bumps = <IdlCloseMetadata as anchor_lang::Bumps>::Bumps::default();
+                        let mut reallocs = std::collections::BTreeSet::new();
+                        let mut accounts =
+                            IdlCloseMetadata::try_accounts(program_id, &mut accounts, &[], &mut bumps, &mut reallocs)?;
+                        __idl_close_metadata(program_id, &mut accounts)?;
+                        accounts.exit(program_id)?;
+                    },
                 }
                 Ok(())
             }
@@ -105,6 +138,11 @@ pub fn generate(program: &Program) -> proc_macro2::TokenStream {
             let anchor = &ix.anchor_ident;
             let ret_type = &ix.returns.ty.to_token_stream();
             let cfgs = &ix.cfgs;
+            // Calling a deprecated instruction handler is expected
+            let maybe_allow_deprecated = match ix.metadata.deprecated {
+                Some(_) => quote! { #[allow(deprecated)] },
+                None => quote! {},
+            };
             let maybe_set_return_data = match ret_type.to_string().as_str() {
                 "()" => quote! {},
                 _ => quote! {
@@ -113,8 +151,29 @@ pub fn generate(program: &Program) -> proc_macro2::TokenStream {
                     anchor_lang::solana_program::program::set_return_data(&return_data);
                 },
             };
+            // Invariants refer to the accounts and the instruction arguments, which are deserialized
+            // again because the handler takes them by value.
+            let invariants = invariants::generate(
+                quote! {
+                    #[allow(unused_variables)]
+                    let accounts = &__accounts;
+                    #[allow(unused_variables)]
+                    let instruction::#variant_arm = instruction::#ix_name::deserialize(&mut &__ix_data[..])
+                        .map_err(|_| anchor_lang::error::ErrorCode::InstructionDidNotDeserialize)?;
+                },
+                &ix.invariants,
+            );
+            let exit_routine = match ix.invariants.is_empty() {
+                true => quote! { __accounts.exit(__program_id) },
+                false => quote! {
+                    __accounts.exit(__program_id)?;
+                    #invariants
+                    Ok(())
+                },
+            };
             quote! {
                 #(#cfgs)*
+                #maybe_allow_deprecated
                 #[inline(never)]
                 pub fn #ix_method_name<'info>(
                     __program_id: &Pubkey,
@@ -159,7 +218,7 @@ pub fn generate(program: &Program) -> proc_macro2::TokenStream {
                     #maybe_set_return_data
 
                     // Exit routine.
-                    __accounts.exit(__program_id)
+                    #exit_routine
                 }
             }
         })
@@ -33,6 +33,26 @@ pub fn idl_accounts_and_functions() -> proc_macro2::TokenStream {
             }
         }
 
+        #[account("internal")]
+        #[derive(Debug)]
+        pub struct ProgramMetadata {
+            // Address that can modify the metadata.
+            pub authority: Pubkey,
+            // Whether the metadata was created by the upgrade authority of the program.
+            pub canonical: bool,
+            // Key of the metadata, padded with zeros.
+            pub key: [u8; 32],
+            // Length of the metadata bytes.
+            pub data_len: u32,
+            // Followed by metadata bytes.
+        }
+
+        impl anchor_lang::Owner for ProgramMetadata {
+            fn owner() -> Pubkey {
+                crate::ID
+            }
+        }
+
         // Accounts for the Create instruction.
         #[derive(Accounts)]
         pub struct IdlCreateAccounts<'info> {
@@ -106,6 +126,55 @@ pub fn idl_accounts_and_functions() -> proc_macro2::TokenStream {
             pub sol_destination: AccountInfo<'info>,
         }
 
+        // Accounts for creating a program metadata account.
+        #[derive(Accounts)]
+        pub struct IdlCreateMetadata<'info> {
+            // Payer of the transaction.
+            #[account(mut)]
+            pub payer: Signer<'info>,
+            // Authority of the metadata.
+            pub authority: Signer<'info>,
+            // The metadata account being created at its program-derived address.
+            #[account(mut)]
+            pub metadata: AccountInfo<'info>,
+            // The program whose metadata is being created.
+            #[account(executable)]
+            pub program: AccountInfo<'info>,
+            // The program data account of the program. Only required for canonical metadata.
+            pub program_data: Option<AccountInfo<'info>>,
+            pub system_program: Program<'info, System>,
+        }
+
+        // Accounts for writing to a program metadata account.
+        #[derive(Accounts)]
+        pub struct IdlWriteMetadata<'info> {
+            #[account(mut, has_one = authority)]
+            pub metadata: Account<'info, ProgramMetadata>,
+            // Authority of the metadata, which also pays for the increased account size.
+            #[account(mut, constraint = authority.key != &ERASED_AUTHORITY)]
+            pub authority: Signer<'info>,
+            pub system_program: Program<'info, System>,
+        }
+
+        // Accounts for setting the authority of a program metadata account.
+        #[derive(Accounts)]
+        pub struct IdlMetadataAccounts<'info> {
+            #[account(mut, has_one = authority)]
+            pub metadata: Account<'info, ProgramMetadata>,
+            #[account(constraint = authority.key != &ERASED_AUTHORITY)]
+            pub authority: Signer<'info>,
+        }
+
+        // Accounts for closing a program metadata account.
+        #[derive(Accounts)]
+        pub struct IdlCloseMetadata<'info> {
+            #[account(mut, has_one = authority, close = sol_destination)]
+            pub metadata: Account<'info, ProgramMetadata>,
+            #[account(constraint = authority.key != &ERASED_AUTHORITY)]
+            pub authority: Signer<'info>,
+            #[account(mut)]
+            pub sol_destination: AccountInfo<'info>,
+        }
 
         use std::cell::{Ref, RefMut};
 
@@ -322,5 +391,205 @@ pub fn idl_accounts_and_functions() -> proc_macro2::TokenStream {
 
             Ok(())
         }
+
+        #[inline(never)]
+        pub fn __idl_create_metadata(
+            program_id: &Pubkey,
+            accounts: &mut IdlCreateMetadata,
+            key: String,
+            canonical: bool,
+        ) -> anchor_lang::Result<()> {
+            #[cfg(not(feature = "no-log-ix-name"))]
+            anchor_lang::prelude::msg!("Instruction: IdlCreateMetadata");
+
+            if program_id != accounts.program.key {
+                return Err(anchor_lang::error::ErrorCode::IdlInstructionInvalidProgram.into());
+            }
+            let padded_key = anchor_lang::idl::ProgramMetadata::pad_key(&key)?;
+
+            // Canonical metadata requires the upgrade authority of the program.
+            if canonical {
+                use anchor_lang::solana_program::bpf_loader_upgradeable::{self, UpgradeableLoaderState};
+
+                let program_data = accounts
+                    .program_data
+                    .as_ref()
+                    .ok_or(anchor_lang::error::ErrorCode::IdlMetadataNotUpgradeAuthority)?;
+                if accounts.program.owner != &bpf_loader_upgradeable::ID {
+                    return Err(anchor_lang::error::ErrorCode::IdlMetadataNotUpgradeAuthority.into());
+                }
+                let programdata_address = match UpgradeableLoaderState::try_deserialize(
+                    &mut &accounts.program.try_borrow_data()?[..],
+                )? {
+                    UpgradeableLoaderState::Program { programdata_address } => programdata_address,
+                    _ => return Err(anchor_lang::error::ErrorCode::IdlMetadataNotUpgradeAuthority.into()),
+                };
+                if program_data.key != &programdata_address {
+                    return Err(anchor_lang::error::ErrorCode::IdlMetadataNotUpgradeAuthority.into());
+                }
+                let upgrade_authority = anchor_lang::prelude::ProgramData::try_deserialize(
+                    &mut &program_data.try_borrow_data()?[..],
+                )?
+                .upgrade_authority_address;
+                if upgrade_authority != Some(*accounts.authority.key) {
+                    return Err(anchor_lang::error::ErrorCode::IdlMetadataNotUpgradeAuthority.into());
+                }
+            }
+
+            // Create the metadata account.
+            let authority_seed = (!canonical).then_some(accounts.authority.key);
+            let (address, bump) =
+                anchor_lang::idl::ProgramMetadata::find_address(program_id, &key, authority_seed);
+            if accounts.metadata.key != &address {
+                return Err(anchor_lang::error::ErrorCode::ConstraintSeeds.into());
+            }
+            let bump = [bump];
+            let seeds: &[&[u8]] = match authority_seed {
+                Some(authority) => &[
+                    anchor_lang::idl::METADATA_SEED,
+                    authority.as_ref(),
+                    key.as_bytes(),
+                    &bump,
+                ],
+                None => &[anchor_lang::idl::METADATA_SEED, key.as_bytes(), &bump],
+            };
+            let space = anchor_lang::idl::ProgramMetadata::DATA_OFFSET;
+            let lamports = Rent::get()?.minimum_balance(space);
+            let current_lamports = accounts.metadata.lamports();
+            if current_lamports == 0 {
+                anchor_lang::system_program::create_account(
+                    anchor_lang::context::CpiContext::new_with_signer(
+                        accounts.system_program.to_account_info(),
+                        anchor_lang::system_program::CreateAccount {
+                            from: accounts.payer.to_account_info(),
+                            to: accounts.metadata.clone(),
+                        },
+                        &[seeds],
+                    ),
+                    lamports,
+                    space as u64,
+                    program_id,
+                )?;
+            } else {
+                // The address is public, so anyone can fund it before it's created, in which case
+                // `create_account` would fail. Fund only the missing rent instead.
+                let required_lamports = lamports.saturating_sub(current_lamports);
+                if required_lamports > 0 {
+                    anchor_lang::system_program::transfer(
+                        anchor_lang::context::CpiContext::new(
+                            accounts.system_program.to_account_info(),
+                            anchor_lang::system_program::Transfer {
+                                from: accounts.payer.to_account_info(),
+                                to: accounts.metadata.clone(),
+                            },
+                        ),
+                        required_lamports,
+                    )?;
+                }
+                anchor_lang::system_program::allocate(
+                    anchor_lang::context::CpiContext::new_with_signer(
+                        accounts.system_program.to_account_info(),
+                        anchor_lang::system_program::Allocate {
+                            account_to_allocate: accounts.metadata.clone(),
+                        },
+                        &[seeds],
+                    ),
+                    space as u64,
+                )?;
+                anchor_lang::system_program::assign(
+                    anchor_lang::context::CpiContext::new_with_signer(
+                        accounts.system_program.to_account_info(),
+                        anchor_lang::system_program::Assign {
+                            account_to_assign: accounts.metadata.clone(),
+                        },
+                        &[seeds],
+                    ),
+                    program_id,
+                )?;
+            }
+
+            // Store the new account data.
+            let metadata = ProgramMetadata {
+                authority: *accounts.authority.key,
+                canonical,
+                key: padded_key,
+                data_len: 0,
+            };
+            let mut data = accounts.metadata.try_borrow_mut_data()?;
+            let dst: &mut [u8] = &mut data;
+            let mut cursor = std::io::Cursor::new(dst);
+            metadata.try_serialize(&mut cursor)?;
+
+            Ok(())
+        }
+
+        #[inline(never)]
+        pub fn __idl_write_metadata(
+            program_id: &Pubkey,
+            accounts: &mut IdlWriteMetadata,
+            offset: u32,
+            metadata_data: Vec<u8>,
+        ) -> anchor_lang::Result<()> {
+            #[cfg(not(feature = "no-log-ix-name"))]
+            anchor_lang::prelude::msg!("Instruction: IdlWriteMetadata");
+
+            if offset > accounts.metadata.data_len {
+                return Err(anchor_lang::error::ErrorCode::IdlMetadataInvalidOffset.into());
+            }
+            let offset: usize = ::std::convert::TryInto::<usize>::try_into(offset).unwrap();
+            let new_len = offset.checked_add(metadata_data.len()).unwrap();
+
+            // Grow the account if the data doesn't fit.
+            let metadata_ref = AsRef::<AccountInfo>::as_ref(&accounts.metadata);
+            let space = anchor_lang::idl::ProgramMetadata::DATA_OFFSET + new_len;
+            if space > metadata_ref.data_len() {
+                let rent_minimum = Rent::get()?.minimum_balance(space);
+                if rent_minimum > metadata_ref.lamports() {
+                    anchor_lang::system_program::transfer(
+                        anchor_lang::context::CpiContext::new(
+                            accounts.system_program.to_account_info(),
+                            anchor_lang::system_program::Transfer {
+                                from: accounts.authority.to_account_info(),
+                                to: accounts.metadata.to_account_info(),
+                            },
+                        ),
+                        rent_minimum - metadata_ref.lamports(),
+                    )?;
+                }
+                metadata_ref.realloc(space, false)?;
+            }
+
+            let mut data = metadata_ref.try_borrow_mut_data()?;
+            data[anchor_lang::idl::ProgramMetadata::DATA_OFFSET..][offset..new_len]
+                .copy_from_slice(&metadata_data);
+            drop(data);
+            accounts.metadata.data_len = ::std::convert::TryInto::<u32>::try_into(new_len).unwrap();
+
+            Ok(())
+        }
+
+        #[inline(never)]
+        pub fn __idl_set_metadata_authority(
+            program_id: &Pubkey,
+            accounts: &mut IdlMetadataAccounts,
+            new_authority: Pubkey,
+        ) -> anchor_lang::Result<()> {
+            #[cfg(not(feature = "no-log-ix-name"))]
+            anchor_lang::prelude::msg!("Instruction: IdlSetMetadataAuthority");
+
+            accounts.metadata.authority = new_authority;
+            Ok(())
+        }
+
+        #[inline(never)]
+        pub fn __idl_close_metadata(
+            program_id: &Pubkey,
+            accounts: &mut IdlCloseMetadata,
+        ) -> anchor_lang::Result<()> {
+            #[cfg(not(feature = "no-log-ix-name"))]
+            anchor_lang::prelude::msg!("Instruction: IdlCloseMetadata");
+
+            Ok(())
+        }
     }
 }
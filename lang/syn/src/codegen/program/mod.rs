@@ -40,9 +40,11 @@ pub fn generate(program: &Program) -> proc_macro2::TokenStream {
     #[cfg(feature = "idl-build")]
     {
         let idl_build_impl = crate::idl::gen_idl_print_fn_program(program);
+        let idl_lints = crate::idl::gen_idl_print_fn_lints_program(program);
         return quote! {
             #ret
             #idl_build_impl
+            #idl_lints
         };
     };
 
@@ -1,14 +1,17 @@
 use proc_macro2::TokenStream;
-use quote::quote;
+use quote::{format_ident, quote};
 
-use super::common::gen_print_section;
+use super::common::gen_write_fragment;
 
 pub fn gen_idl_print_fn_address(address: String) -> TokenStream {
-    let fn_body = gen_print_section("address", quote! { #address });
+    // The address is the string representation of the `declare_id!` input, e.g. `"Abc..."`
+    let address = address.replace(|c: char| !c.is_alphanumeric(), "");
+    let fn_name = format_ident!("__anchor_private_print_idl_address");
+    let fn_body = gen_write_fragment(&fn_name, quote! { Address { address: #address.into() } });
 
     quote! {
         #[test]
-        pub fn __anchor_private_print_idl_address() {
+        pub fn #fn_name() {
             #fn_body
         }
     }
@@ -1,9 +1,11 @@
 use std::path::{Path, PathBuf};
 
 use anyhow::{anyhow, Result};
-use proc_macro2::TokenStream;
+use proc_macro2::{Ident, TokenStream};
 use quote::{quote, ToTokens};
 
+use crate::parser::metadata::{Deprecation, Metadata};
+
 pub fn find_path(name: &str, path: impl AsRef<Path>) -> Result<PathBuf> {
     let path = path.as_ref();
     for ancestor in path.ancestors() {
@@ -28,19 +30,74 @@ pub fn get_program_path() -> Result<PathBuf> {
         .map_err(|_| anyhow!("Failed to get program path"))
 }
 
+/// Get the path of the source file that is currently being expanded.
+///
+/// Getting the source file requires `procmacro2_semver_exempt`, which is only available on
+/// nightly. Otherwise, the root file (`src/lib.rs`) of the crate that is being compiled is used.
+pub fn get_source_path() -> PathBuf {
+    #[cfg(procmacro2_semver_exempt)]
+    {
+        proc_macro2::Span::call_site().source_file().path()
+    }
+    #[cfg(not(procmacro2_semver_exempt))]
+    {
+        std::env::var("CARGO_MANIFEST_DIR")
+            .map(|path| Path::new(&path).join("src").join("lib.rs"))
+            .unwrap_or_default()
+    }
+}
+
 pub fn get_idl_module_path() -> TokenStream {
     quote!(anchor_lang::idl::types)
 }
 
-pub fn get_serde_json_module_path() -> TokenStream {
-    quote!(anchor_lang::idl::serde_json)
+pub fn get_idl_build_module_path() -> TokenStream {
+    quote!(anchor_lang::idl::build)
+}
+
+/// Generate code that writes the given IDL build fragment, e.g. `Const { constant: .. }`.
+///
+/// The name of the function the code is generated in is used to identify the fragment.
+pub fn gen_write_fragment(fn_name: &Ident, fragment: impl ToTokens) -> TokenStream {
+    let idl_build = get_idl_build_module_path();
+    quote! {
+        #idl_build::IdlBuildFragment::#fragment
+            .write(concat!(module_path!(), "::", stringify!(#fn_name)))
+            .unwrap();
+    }
+}
+
+/// Generate the `Option<IdlDeprecation>` expression of the given deprecation.
+pub fn gen_deprecation(deprecated: Option<&Deprecation>) -> TokenStream {
+    let idl = get_idl_module_path();
+    let gen_str = |value: &Option<String>| match value {
+        Some(value) => quote! { Some(#value.into()) },
+        None => quote! { None },
+    };
+    match deprecated {
+        Some(deprecation) => {
+            let since = gen_str(&deprecation.since);
+            let note = gen_str(&deprecation.note);
+            quote! { Some(#idl::IdlDeprecation { since: #since, note: #note }) }
+        }
+        None => quote! { None },
+    }
 }
 
-pub fn gen_print_section(name: &str, value: impl ToTokens) -> TokenStream {
-    let serde_json = get_serde_json_module_path();
+/// Generate the metadata fields of `IdlField`, i.e. `deprecated`, `unit` and `decimals`.
+pub fn gen_field_metadata(metadata: &Metadata) -> TokenStream {
+    let deprecated = gen_deprecation(metadata.deprecated.as_ref());
+    let unit = match &metadata.unit {
+        Some(unit) => quote! { Some(#unit.into()) },
+        None => quote! { None },
+    };
+    let decimals = match metadata.decimals {
+        Some(decimals) => quote! { Some(#decimals) },
+        None => quote! { None },
+    };
     quote! {
-        println!("--- IDL begin {} ---", #name);
-        println!("{}", #serde_json::to_string_pretty(&{ #value }).unwrap());
-        println!("--- IDL end {} ---", #name);
+        deprecated: #deprecated,
+        unit: #unit,
+        decimals: #decimals,
     }
 }
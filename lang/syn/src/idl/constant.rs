@@ -3,7 +3,7 @@ use proc_macro2::TokenStream;
 use quote::{format_ident, quote};
 
 use super::{
-    common::{gen_print_section, get_idl_module_path, get_no_docs},
+    common::{gen_write_fragment, get_idl_module_path, get_no_docs},
     defined::gen_idl_type,
 };
 use crate::parser::docs;
@@ -22,14 +22,16 @@ pub fn gen_idl_print_fn_constant(item: &syn::ItemConst) -> TokenStream {
     };
 
     let fn_body = match gen_idl_type(&item.ty, &[]) {
-        Ok((ty, _)) => gen_print_section(
-            "const",
+        Ok((ty, _)) => gen_write_fragment(
+            &fn_name,
             quote! {
-                #idl::IdlConst {
-                    name: #name.into(),
-                    docs: #docs,
-                    ty: #ty,
-                    value: format!("{:?}", #expr),
+                Const {
+                    constant: #idl::IdlConst {
+                        name: #name.into(),
+                        docs: #docs,
+                        ty: #ty,
+                        value: format!("{:?}", #expr),
+                    },
                 }
             },
         ),
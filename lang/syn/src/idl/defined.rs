@@ -2,8 +2,8 @@ use anyhow::{anyhow, Result};
 use proc_macro2::TokenStream;
 use quote::quote;
 
-use super::common::{get_idl_module_path, get_no_docs};
-use crate::parser::docs;
+use super::common::{gen_deprecation, gen_field_metadata, get_idl_module_path, get_no_docs};
+use crate::parser::{docs, metadata};
 
 /// Generate `IdlBuild` impl for a struct.
 pub fn impl_idl_build_struct(item: &syn::ItemStruct) -> TokenStream {
@@ -131,6 +131,10 @@ fn gen_idl_type_def_enum(enm: &syn::ItemEnum) -> Result<(TokenStream, Vec<syn::T
             .iter()
             .map(|variant| {
                 let name = variant.ident.to_string();
+                let deprecated = match metadata::parse(&variant.attrs) {
+                    Ok(metadata) => gen_deprecation(metadata.deprecated.as_ref()),
+                    Err(e) => e.to_compile_error(),
+                };
                 let (fields, defined) = match &variant.fields {
                     syn::Fields::Unit => (quote! { None }, vec![]),
                     syn::Fields::Named(fields) => {
@@ -166,7 +170,13 @@ fn gen_idl_type_def_enum(enm: &syn::ItemEnum) -> Result<(TokenStream, Vec<syn::T
                 };
 
                 Ok((
-                    quote! { #idl::IdlEnumVariant { name: #name.into(), fields: #fields } },
+                    quote! {
+                        #idl::IdlEnumVariant {
+                            name: #name.into(),
+                            fields: #fields,
+                            deprecated: #deprecated,
+                        }
+                    },
                     defined,
                 ))
             })
@@ -297,6 +307,7 @@ where
                 repr: #repr,
                 generics: vec![#(#generics.into()),*],
                 ty: #ty,
+                origin: None,
             }
         },
         defined,
@@ -336,6 +347,10 @@ fn gen_idl_field(
         _ => quote! { vec![] },
     };
     let (ty, defined) = gen_idl_type(&field.ty, generic_params)?;
+    let metadata = match metadata::parse(&field.attrs) {
+        Ok(metadata) => gen_field_metadata(&metadata),
+        Err(e) => return Ok((e.to_compile_error(), defined)),
+    };
 
     Ok((
         quote! {
@@ -343,6 +358,7 @@ fn gen_idl_field(
                 name: #name.into(),
                 docs: #docs,
                 ty: #ty,
+                #metadata
             }
         },
         defined,
@@ -362,6 +378,19 @@ pub fn gen_idl_type(
         return get_first_segment(path).ident == cmp;
     }
 
+    /// Type arguments of a collection named one of `names`, or none if the type is not one of
+    /// those collections, e.g. a user type with the same name but without type arguments.
+    fn get_collection_type_args<'a>(path: &'a syn::TypePath, names: &[&str]) -> Vec<&'a syn::Type> {
+        let segment = path.path.segments.last().unwrap();
+        if !names.iter().any(|name| segment.ident == name) {
+            return vec![];
+        }
+        match &segment.arguments {
+            syn::PathArguments::AngleBracketed(_) => get_angle_bracketed_type_args(segment),
+            _ => vec![],
+        }
+    }
+
     fn get_angle_bracketed_type_args(seg: &syn::PathSegment) -> Vec<&syn::Type> {
         match &seg.arguments {
             syn::PathArguments::AngleBracketed(ab) => ab
@@ -448,6 +477,37 @@ pub fn gen_idl_type(
             let (inner, defined) = gen_idl_type(arg, generic_params)?;
             Ok((quote! { #idl::IdlType::Vec(Box::new(#inner)) }, defined))
         }
+        syn::Type::Path(path)
+            if get_collection_type_args(path, &["HashMap", "BTreeMap"]).len() >= 2 =>
+        {
+            let (key, value) =
+                match get_collection_type_args(path, &["HashMap", "BTreeMap"]).as_slice() {
+                    [key, value, ..] => (*key, *value),
+                    _ => unreachable!("Maps have a key and a value type"),
+                };
+            let (key, mut defined) = gen_idl_type(key, generic_params)?;
+            let (value, value_defined) = gen_idl_type(value, generic_params)?;
+            defined.extend(value_defined);
+            Ok((
+                quote! {
+                    #idl::IdlType::Map {
+                        key: Box::new(#key),
+                        value: Box::new(#value),
+                    }
+                },
+                defined,
+            ))
+        }
+        syn::Type::Path(path)
+            if !get_collection_type_args(path, &["HashSet", "BTreeSet"]).is_empty() =>
+        {
+            let inner = match get_collection_type_args(path, &["HashSet", "BTreeSet"]).as_slice() {
+                [inner, ..] => *inner,
+                _ => unreachable!("Sets have an element type"),
+            };
+            let (inner, defined) = gen_idl_type(inner, generic_params)?;
+            Ok((quote! { #idl::IdlType::Set(Box::new(#inner)) }, defined))
+        }
         syn::Type::Path(path) if the_only_segment_is(path, "Box") => {
             let segment = get_first_segment(path);
             let arg = get_angle_bracketed_type_args(segment)
@@ -490,13 +550,15 @@ pub fn gen_idl_type(
             }
 
             // Handle type aliases and external types
-            #[cfg(procmacro2_semver_exempt)]
             {
-                use super::{common::find_path, external::get_external_type};
+                use super::{
+                    common::{find_path, get_source_path},
+                    external::get_external_type,
+                };
                 use crate::parser::context::CrateContext;
                 use quote::ToTokens;
 
-                let source_path = proc_macro2::Span::call_site().source_file().path();
+                let source_path = get_source_path();
                 if let Ok(Ok(ctx)) = find_path("lib.rs", &source_path).map(CrateContext::parse) {
                     let name = path.path.segments.last().unwrap().ident.to_string();
                     let alias = ctx.type_aliases().find(|ty| ty.ident == name);
@@ -556,7 +618,7 @@ pub fn gen_idl_type(
                         };
 
                         // Non-generic type alias e.g. `type UnixTimestamp = i64`
-                        return gen_idl_type(&*alias.ty, generic_params);
+                        return gen_idl_type(&alias.ty, generic_params);
                     }
 
                     // Handle external types
@@ -625,6 +687,18 @@ pub fn gen_idl_type(
                 defined,
             ))
         }
+        // Unit type is not supported
+        syn::Type::Tuple(tuple) if !tuple.elems.is_empty() => {
+            let (elems, defined) = tuple
+                .elems
+                .iter()
+                .map(|elem| gen_idl_type(elem, generic_params))
+                .collect::<Result<Vec<_>>>()?
+                .into_iter()
+                .unzip::<_, _, Vec<_>, Vec<_>>();
+            let defined = defined.into_iter().flatten().collect();
+            Ok((quote! { #idl::IdlType::Tuple(vec![#(#elems),*]) }, defined))
+        }
         syn::Type::Reference(reference) => match reference.elem.as_ref() {
             syn::Type::Slice(slice) if matches!(&*slice.elem, syn::Type::Path(path) if the_only_segment_is(path, "u8")) => {
                 Ok((quote! {#idl::IdlType::Bytes}, vec![]))
@@ -2,11 +2,12 @@ use heck::SnakeCase;
 use proc_macro2::TokenStream;
 use quote::{format_ident, quote};
 
-use super::common::{gen_print_section, get_idl_module_path};
+use super::common::{gen_write_fragment, get_idl_module_path, get_no_docs};
 use crate::Error;
 
 pub fn gen_idl_print_fn_error(error: &Error) -> TokenStream {
     let idl = get_idl_module_path();
+    let no_docs = get_no_docs();
 
     let fn_name = format_ident!(
         "__anchor_private_print_idl_error_{}",
@@ -19,6 +20,10 @@ pub fn gen_idl_print_fn_error(error: &Error) -> TokenStream {
         .map(|code| {
             let id = code.id;
             let name = code.ident.to_string();
+            let docs = match &code.docs {
+                Some(docs) if !no_docs => quote! { vec![#(#docs.into()),*] },
+                _ => quote! { vec![] },
+            };
             let msg = match &code.msg {
                 Some(msg) => quote! { Some(#msg.into()) },
                 None => quote! { None },
@@ -28,12 +33,16 @@ pub fn gen_idl_print_fn_error(error: &Error) -> TokenStream {
                 #idl::IdlErrorCode {
                     code: anchor_lang::error::ERROR_CODE_OFFSET + #id,
                     name: #name.into(),
+                    docs: #docs,
                     msg: #msg,
                 }
             }
         })
         .collect::<Vec<_>>();
-    let fn_body = gen_print_section("errors", quote! { vec![#(#error_codes),*] });
+    let fn_body = gen_write_fragment(
+        &fn_name,
+        quote! { Errors { errors: vec![#(#error_codes),*] } },
+    );
 
     quote! {
         #[test]
@@ -3,13 +3,12 @@ use proc_macro2::TokenStream;
 use quote::{format_ident, quote};
 
 use super::{
-    common::{gen_print_section, get_idl_module_path, get_serde_json_module_path},
+    common::{gen_write_fragment, get_idl_module_path},
     defined::gen_idl_type_def_struct,
 };
 
 pub fn gen_idl_print_fn_event(event_struct: &syn::ItemStruct) -> TokenStream {
     let idl = get_idl_module_path();
-    let serde_json = get_serde_json_module_path();
 
     let ident = &event_struct.ident;
     let fn_name = format_ident!(
@@ -18,13 +17,13 @@ pub fn gen_idl_print_fn_event(event_struct: &syn::ItemStruct) -> TokenStream {
     );
     let idl_build_impl = impl_idl_build_event(event_struct);
 
-    let print_ts = gen_print_section(
-        "event",
+    let write_ts = gen_write_fragment(
+        &fn_name,
         quote! {
-            #serde_json::json!({
-                "event": event,
-                "types": types.into_values().collect::<Vec<_>>()
-            })
+            Event {
+                event,
+                types: types.into_values().collect(),
+            }
         },
     );
 
@@ -36,7 +35,7 @@ pub fn gen_idl_print_fn_event(event_struct: &syn::ItemStruct) -> TokenStream {
             let mut types: std::collections::BTreeMap<String, #idl::IdlTypeDef> =
                 std::collections::BTreeMap::new();
             if let Some(event) = #ident::__anchor_private_gen_idl_event(&mut types) {
-                #print_ts
+                #write_ts
             }
         }
     }
@@ -0,0 +1,233 @@
+use heck::SnakeCase;
+use proc_macro2::{Ident, TokenStream, TokenTree};
+use quote::{format_ident, quote, ToTokens};
+
+use super::common::gen_write_fragment;
+use crate::{AccountField, AccountsStruct, Field, Program, Ty};
+
+/// Accounts structs of the IDL instructions that are generated by the `#[program]` macro.
+///
+/// These accounts are validated in the generated instruction handlers instead.
+const IDL_ACCOUNTS_STRUCTS: &[&str] = &[
+    "IdlCreateAccounts",
+    "IdlAccounts",
+    "IdlResizeAccount",
+    "IdlCreateBuffer",
+    "IdlSetBuffer",
+    "IdlCloseAccount",
+];
+
+/// A lint violation of the program source.
+struct Lint {
+    /// Variant name of `IdlLint`
+    lint: &'static str,
+    path: String,
+    message: String,
+}
+
+/// Generate the IDL build print function for the lints of the Accounts struct.
+pub fn gen_idl_print_fn_lints_accounts(accounts: &AccountsStruct) -> TokenStream {
+    let name = accounts.ident.to_string();
+    if IDL_ACCOUNTS_STRUCTS.contains(&name.as_str()) {
+        return quote! {};
+    }
+
+    let fields = accounts
+        .fields
+        .iter()
+        .filter_map(|af| match af {
+            AccountField::Field(f) => Some(f),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let mut lints = vec![];
+    for (i, field) in fields.iter().enumerate() {
+        let path = format!("{name}.{}", field.ident);
+        let constraints = &field.constraints;
+
+        // Writable unchecked accounts must be validated with a constraint
+        let is_unchecked = matches!(field.ty, Ty::AccountInfo | Ty::UncheckedAccount);
+        let is_validated = constraints.owner.is_some()
+            || constraints.seeds.is_some()
+            || constraints.address.is_some()
+            || constraints.init.is_some()
+            || constraints.is_zeroed()
+            || constraints.is_signer()
+            || is_has_one_target(field, accounts)
+            || is_close_target(field, accounts);
+        if is_unchecked && constraints.is_mutable() && !is_validated {
+            lints.push(Lint {
+                lint: "UncheckedWritable",
+                path: path.clone(),
+                message: "Writable account is not validated with an `owner`, `has_one`, `seeds` \
+                    or `address` constraint"
+                    .into(),
+            });
+        }
+
+        // `init_if_needed` accounts can be re-initialized unless they are guarded
+        let is_init_if_needed = constraints
+            .init
+            .as_ref()
+            .map(|init| init.if_needed)
+            .unwrap_or_default();
+        if is_init_if_needed && constraints.raw.is_empty() {
+            lints.push(Lint {
+                lint: "InitIfNeeded",
+                path: path.clone(),
+                message: "`init_if_needed` account doesn't have a `constraint` that guards \
+                    against re-initialization"
+                    .into(),
+            });
+        }
+
+        // Mutable accounts of the same type must be checked to be different accounts
+        for other in fields.iter().skip(i + 1) {
+            if could_alias(field, other) {
+                lints.push(Lint {
+                    lint: "DuplicateMutable",
+                    path: path.clone(),
+                    message: format!(
+                        "Mutable accounts `{}` and `{}` have the same type and could be the same \
+                        account",
+                        field.ident, other.ident
+                    ),
+                });
+            }
+        }
+    }
+
+    let fn_name = format_ident!("__anchor_private_print_idl_lints_{}", name.to_snake_case());
+    gen_lints_fn(&fn_name, lints)
+}
+
+/// Generate the IDL build print function for the lints of the program module.
+pub fn gen_idl_print_fn_lints_program(program: &Program) -> TokenStream {
+    let lints = program
+        .ixs
+        .iter()
+        .flat_map(|ix| {
+            ix.args
+                .iter()
+                .filter(|arg| !arg.name.to_string().starts_with('_'))
+                .filter(|arg| !contains_ident(&ix.raw_method.block, &arg.name))
+                .map(|arg| Lint {
+                    lint: "UnusedInstructionArg",
+                    path: format!("instructions.{}.{}", ix.ident, arg.name),
+                    message: format!(
+                        "Argument `{}` is not used in the instruction handler",
+                        arg.name
+                    ),
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    let fn_name = format_ident!("__anchor_private_print_idl_lints_program");
+    gen_lints_fn(&fn_name, lints)
+}
+
+fn gen_lints_fn(fn_name: &Ident, lints: Vec<Lint>) -> TokenStream {
+    let skip_lint = option_env!("ANCHOR_IDL_BUILD_SKIP_LINT")
+        .map(|val| val == "TRUE")
+        .unwrap_or_default();
+    if skip_lint || lints.is_empty() {
+        return quote! {};
+    }
+
+    let lints = lints.into_iter().map(|lint| {
+        let variant = format_ident!("{}", lint.lint);
+        let path = lint.path;
+        let message = lint.message;
+        quote! {
+            anchor_lang::idl::lint::IdlLintDiagnostic {
+                lint: anchor_lang::idl::lint::IdlLint::#variant,
+                path: #path.into(),
+                message: #message.into(),
+            }
+        }
+    });
+    let fn_body = gen_write_fragment(fn_name, quote! { Lints { lints: vec![#(#lints),*] } });
+
+    quote! {
+        #[test]
+        pub fn #fn_name() {
+            #fn_body
+        }
+    }
+}
+
+/// Whether the field is the target of a `has_one` constraint of another field.
+fn is_has_one_target(field: &Field, accounts: &AccountsStruct) -> bool {
+    accounts.fields.iter().any(|af| match af {
+        AccountField::Field(f) => f.constraints.has_one.iter().any(|c| match &c.join_target {
+            syn::Expr::Path(path) => path
+                .path
+                .segments
+                .first()
+                .map(|seg| seg.ident == field.ident)
+                .unwrap_or_default(),
+            _ => false,
+        }),
+        _ => false,
+    })
+}
+
+/// Whether the field receives the lamports of an account that is closed.
+fn is_close_target(field: &Field, accounts: &AccountsStruct) -> bool {
+    accounts.fields.iter().any(|af| match af {
+        AccountField::Field(f) => f
+            .constraints
+            .close
+            .as_ref()
+            .map(|c| c.sol_dest == field.ident)
+            .unwrap_or_default(),
+        _ => false,
+    })
+}
+
+/// Whether the given fields could be the same mutable account.
+///
+/// Accounts with fixed addresses, PDAs and new accounts are not considered, and neither are
+/// accounts that are compared in a `constraint` of either field.
+fn could_alias(a: &Field, b: &Field) -> bool {
+    let is_candidate = |f: &Field| {
+        let constraints = &f.constraints;
+        constraints.is_mutable()
+            && constraints.init.is_none()
+            && constraints.seeds.is_none()
+            && constraints.address.is_none()
+            && matches!(
+                f.ty,
+                Ty::Account(_)
+                    | Ty::AccountLoader(_)
+                    | Ty::LazyAccount(_)
+                    | Ty::InterfaceAccount(_)
+            )
+    };
+    let is_compared = |f: &Field, other: &Ident| {
+        f.constraints
+            .raw
+            .iter()
+            .any(|c| contains_ident(&c.raw, other))
+    };
+
+    is_candidate(a)
+        && is_candidate(b)
+        && a.ty == b.ty
+        && !is_compared(a, &b.ident)
+        && !is_compared(b, &a.ident)
+}
+
+fn contains_ident(tokens: &impl ToTokens, ident: &Ident) -> bool {
+    fn contains(tokens: TokenStream, ident: &Ident) -> bool {
+        tokens.into_iter().any(|tt| match tt {
+            TokenTree::Ident(i) => &i == ident,
+            TokenTree::Group(group) => contains(group.stream(), ident),
+            _ => false,
+        })
+    }
+
+    contains(tokens.to_token_stream(), ident)
+}
@@ -8,6 +8,7 @@ mod defined;
 mod error;
 mod event;
 mod external;
+mod lint;
 mod program;
 
 pub use accounts::gen_idl_build_impl_accounts_struct;
@@ -16,4 +17,5 @@ pub use constant::gen_idl_print_fn_constant;
 pub use defined::{impl_idl_build_enum, impl_idl_build_struct, impl_idl_build_union};
 pub use error::gen_idl_print_fn_error;
 pub use event::gen_idl_print_fn_event;
+pub use lint::{gen_idl_print_fn_lints_accounts, gen_idl_print_fn_lints_program};
 pub use program::gen_idl_print_fn_program;
@@ -5,7 +5,10 @@ use quote::{format_ident, quote};
 use syn::spanned::Spanned;
 
 use super::{
-    common::{gen_print_section, get_idl_module_path, get_no_docs, get_program_path},
+    common::{
+        gen_deprecation, gen_field_metadata, gen_write_fragment, get_idl_module_path, get_no_docs,
+        get_program_path,
+    },
     defined::gen_idl_type,
 };
 use crate::{
@@ -51,6 +54,7 @@ pub fn gen_idl_print_fn_program(program: &Program) -> TokenStream {
                     };
                     let (ty, defined) = gen_idl_type(&arg.raw_arg.ty, &[])
                         .map_err(|_| syn::Error::new(arg.raw_arg.ty.span(), "Unsupported type"))?;
+                    let metadata = gen_field_metadata(&arg.metadata);
 
                     Ok((
                         quote! {
@@ -58,6 +62,7 @@ pub fn gen_idl_print_fn_program(program: &Program) -> TokenStream {
                                 name: #name.into(),
                                 docs: #docs,
                                 ty: #ty,
+                                #metadata
                             }
                         },
                         defined,
@@ -74,6 +79,7 @@ pub fn gen_idl_print_fn_program(program: &Program) -> TokenStream {
                 }
                 _ => quote! { None },
             };
+            let deprecated = gen_deprecation(ix.metadata.deprecated.as_ref());
 
             Ok((
                 quote! {
@@ -88,6 +94,7 @@ pub fn gen_idl_print_fn_program(program: &Program) -> TokenStream {
                         ),
                         args: vec![#(#args),*],
                         returns: #returns,
+                        deprecated: #deprecated,
                     }
                 },
                 defined,
@@ -100,51 +107,56 @@ pub fn gen_idl_print_fn_program(program: &Program) -> TokenStream {
     };
     let defined = defined.into_iter().flatten().flatten().collect::<Vec<_>>();
 
-    let fn_body = gen_print_section(
-        "program",
+    let fn_name = format_ident!("__anchor_private_print_idl_program");
+    let fn_body = gen_write_fragment(
+        &fn_name,
         quote! {
-            let mut accounts: std::collections::BTreeMap<String, #idl::IdlAccount> =
-                std::collections::BTreeMap::new();
-            let mut types: std::collections::BTreeMap<String, #idl::IdlTypeDef> =
-                std::collections::BTreeMap::new();
-
-            #(
-                if let Some(ty) = <#defined>::create_type() {
-                    types.insert(<#defined>::get_full_path(), ty);
-                    <#defined>::insert_types(&mut types);
-                }
-            );*
-
-            #idl::Idl {
-                address: Default::default(),
-                metadata: #idl::IdlMetadata {
-                    name: #name.into(),
-                    version: env!("CARGO_PKG_VERSION").into(),
-                    spec: #idl::IDL_SPEC.into(),
-                    description: option_env!("CARGO_PKG_DESCRIPTION")
-                        .filter(|d| !d.is_empty())
-                        .map(|d| d.into()),
-                    repository: option_env!("CARGO_PKG_REPOSITORY")
-                        .filter(|r| !r.is_empty())
-                        .map(|r| r.into()),
-                    dependencies: Default::default(),
-                    contact: Default::default(),
-                    deployments: Default::default(),
+            Program {
+                idl: {
+                    let mut accounts: std::collections::BTreeMap<String, #idl::IdlAccount> =
+                        std::collections::BTreeMap::new();
+                    let mut types: std::collections::BTreeMap<String, #idl::IdlTypeDef> =
+                        std::collections::BTreeMap::new();
+
+                    #(
+                        if let Some(ty) = <#defined>::create_type() {
+                            types.insert(<#defined>::get_full_path(), ty);
+                            <#defined>::insert_types(&mut types);
+                        }
+                    );*
+
+                    #idl::Idl {
+                        address: Default::default(),
+                        metadata: #idl::IdlMetadata {
+                            name: #name.into(),
+                            version: env!("CARGO_PKG_VERSION").into(),
+                            spec: #idl::IDL_SPEC.into(),
+                            description: option_env!("CARGO_PKG_DESCRIPTION")
+                                .filter(|d| !d.is_empty())
+                                .map(|d| d.into()),
+                            repository: option_env!("CARGO_PKG_REPOSITORY")
+                                .filter(|r| !r.is_empty())
+                                .map(|r| r.into()),
+                            dependencies: Default::default(),
+                            contact: Default::default(),
+                            deployments: Default::default(),
+                        },
+                        docs: #docs,
+                        instructions: vec![#(#instructions),*],
+                        accounts: accounts.into_values().collect(),
+                        events: Default::default(),
+                        errors: Default::default(),
+                        types: types.into_values().collect(),
+                        constants: Default::default(),
+                    }
                 },
-                docs: #docs,
-                instructions: vec![#(#instructions),*],
-                accounts: accounts.into_values().collect(),
-                events: Default::default(),
-                errors: Default::default(),
-                types: types.into_values().collect(),
-                constants: Default::default(),
             }
         },
     );
 
     quote! {
         #[test]
-        pub fn __anchor_private_print_idl_program() {
+        pub fn #fn_name() {
             #fn_body
         }
     }
@@ -14,6 +14,7 @@ pub(crate) mod hash;
 use codegen::accounts as accounts_codegen;
 use codegen::program as program_codegen;
 use parser::accounts as accounts_parser;
+use parser::metadata::Metadata;
 use parser::program as program_parser;
 use proc_macro2::{Span, TokenStream};
 use quote::quote;
@@ -65,6 +66,7 @@ pub struct Ix {
     pub raw_method: ItemFn,
     pub ident: Ident,
     pub docs: Option<Vec<String>>,
+    pub metadata: Metadata,
     pub cfgs: Vec<Attribute>,
     pub args: Vec<IxArg>,
     pub returns: IxReturn,
@@ -75,6 +77,16 @@ pub struct Ix {
     pub interface_discriminator: Option<[u8; 8]>,
     /// Overrides coming from the `#[instruction]` attribute
     pub overrides: Option<Overrides>,
+    /// Invariants checked after the exit routine, from the `#[invariant]` attributes
+    pub invariants: Vec<Invariant>,
+}
+
+/// Invariant declared with the `#[invariant]` attribute
+#[derive(Debug)]
+pub struct Invariant {
+    pub expr: Expr,
+    /// Source of the expression, which names the invariant in errors
+    pub name: String,
 }
 
 /// Common overrides for the `#[instruction]`, `#[account]` and `#[event]` attributes
@@ -129,6 +141,7 @@ impl Parse for NamedArg {
 pub struct IxArg {
     pub name: Ident,
     pub docs: Option<Vec<String>>,
+    pub metadata: Metadata,
     pub raw_arg: PatType,
 }
 
@@ -152,6 +165,8 @@ pub struct AccountsStruct {
     pub fields: Vec<AccountField>,
     // Instruction data api expression.
     instruction_api: Option<Punctuated<Expr, Comma>>,
+    // Invariants checked on exit, from the `#[invariant]` attributes.
+    pub invariants: Vec<Invariant>,
 }
 
 impl Parse for AccountsStruct {
@@ -178,6 +193,7 @@ impl AccountsStruct {
         strct: ItemStruct,
         fields: Vec<AccountField>,
         instruction_api: Option<Punctuated<Expr, Comma>>,
+        invariants: Vec<Invariant>,
     ) -> Self {
         let ident = strct.ident.clone();
         let generics = strct.generics;
@@ -186,6 +202,7 @@ impl AccountsStruct {
             generics,
             fields,
             instruction_api,
+            invariants,
         }
     }
 
@@ -669,6 +686,7 @@ impl Parse for ErrorArgs {
 pub struct ErrorCode {
     pub id: u32,
     pub ident: Ident,
+    pub docs: Option<Vec<String>>,
     pub msg: Option<String>,
 }
 
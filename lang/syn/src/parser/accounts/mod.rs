@@ -2,7 +2,7 @@ pub mod constraints;
 #[cfg(feature = "event-cpi")]
 pub mod event_cpi;
 
-use crate::parser::docs;
+use crate::parser::{docs, invariants};
 use crate::*;
 use syn::parse::{Error as ParseError, Result as ParseResult};
 use syn::Path;
@@ -51,10 +51,13 @@ pub fn parse(accounts_struct: &syn::ItemStruct) -> ParseResult<AccountsStruct> {
 
     constraints_cross_checks(&fields)?;
 
+    let invariants = invariants::parse(&accounts_struct.attrs)?;
+
     Ok(AccountsStruct::new(
         accounts_struct,
         fields,
         instruction_api,
+        invariants,
     ))
 }
 
@@ -1,3 +1,4 @@
+use crate::parser::docs;
 use crate::{Error, ErrorArgs, ErrorCode};
 use syn::parse::{Parse, Result as ParseResult};
 use syn::Expr;
@@ -11,6 +12,7 @@ pub fn parse(error_enum: &mut syn::ItemEnum, args: Option<ErrorArgs>) -> Error {
         .iter_mut()
         .map(|variant: &mut syn::Variant| {
             let msg = parse_error_attribute(variant);
+            let docs = docs::parse(&variant.attrs);
             let ident = variant.ident.clone();
             let id = match &variant.discriminant {
                 None => last_discriminant,
@@ -31,7 +33,12 @@ pub fn parse(error_enum: &mut syn::ItemEnum, args: Option<ErrorArgs>) -> Error {
                 .attrs
                 .retain(|attr| attr.path.segments[0].ident == "doc");
 
-            ErrorCode { id, ident, msg }
+            ErrorCode {
+                id,
+                ident,
+                docs,
+                msg,
+            }
         })
         .collect();
     Error {
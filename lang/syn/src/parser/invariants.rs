@@ -0,0 +1,32 @@
+use crate::parser::tts_to_string;
+use crate::Invariant;
+use proc_macro2::TokenTree;
+use syn::parse::Result as ParseResult;
+use syn::Attribute;
+
+// Parse the `#[invariant(...)]` attributes.
+pub fn parse(attrs: &[Attribute]) -> ParseResult<Vec<Invariant>> {
+    attrs
+        .iter()
+        .filter(|attr| match attr.path.segments.last() {
+            Some(seg) => seg.ident == "invariant",
+            _ => false,
+        })
+        .map(|attr| {
+            let expr = attr.parse_args()?;
+            // The span of the expression only covers its first token, unlike the span of the
+            // parenthesized arguments
+            let name = match attr.tokens.clone().into_iter().next() {
+                Some(TokenTree::Group(group)) => group.span().source_text().and_then(|source| {
+                    let source = source.strip_prefix('(')?.strip_suffix(')')?;
+                    Some(source.trim().to_owned())
+                }),
+                _ => None,
+            };
+            Ok(Invariant {
+                name: name.unwrap_or_else(|| tts_to_string(&expr)),
+                expr,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,108 @@
+use syn::{spanned::Spanned, Lit, Meta, NestedMeta};
+
+/// Deprecation info from the `#[deprecated]` attribute.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Deprecation {
+    pub since: Option<String>,
+    pub note: Option<String>,
+}
+
+/// IDL metadata of an item, parsed from the `#[deprecated]` and `#[idl(..)]` attributes.
+///
+/// Deprecation can also be specified with `#[idl(deprecated)]` or `#[idl(deprecated = "note")]`
+/// for items that don't support the `#[deprecated]` attribute, e.g. instruction arguments.
+///
+/// # Example
+///
+/// ```ignore
+/// #[idl(unit = "lamports", decimals = 9)]
+/// pub amount: u64,
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Metadata {
+    pub deprecated: Option<Deprecation>,
+    pub unit: Option<String>,
+    pub decimals: Option<u8>,
+}
+
+/// Whether the attribute is the `#[idl(..)]` attribute.
+pub fn is_idl_attr(attr: &syn::Attribute) -> bool {
+    attr.path.is_ident("idl")
+}
+
+pub fn parse(attrs: &[syn::Attribute]) -> syn::Result<Metadata> {
+    let mut metadata = Metadata::default();
+    for attr in attrs {
+        if attr.path.is_ident("deprecated") {
+            metadata.deprecated = Some(parse_deprecated(attr.parse_meta()?)?);
+        } else if is_idl_attr(attr) {
+            let list = match attr.parse_meta()? {
+                Meta::List(list) => list,
+                meta => return Err(syn::Error::new(meta.span(), "Expected `#[idl(..)]`")),
+            };
+            for nested in list.nested {
+                match nested {
+                    NestedMeta::Meta(Meta::Path(path)) if path.is_ident("deprecated") => {
+                        metadata.deprecated = Some(Deprecation::default());
+                    }
+                    NestedMeta::Meta(Meta::NameValue(nv)) => {
+                        let key = nv.path.get_ident().map(ToString::to_string);
+                        match (key.as_deref(), &nv.lit) {
+                            (Some("deprecated"), Lit::Str(note)) => {
+                                metadata.deprecated = Some(Deprecation {
+                                    since: None,
+                                    note: Some(note.value()),
+                                })
+                            }
+                            (Some("unit"), Lit::Str(unit)) => metadata.unit = Some(unit.value()),
+                            (Some("decimals"), Lit::Int(decimals)) => {
+                                metadata.decimals = Some(decimals.base10_parse()?)
+                            }
+                            _ => return Err(syn::Error::new(nv.span(), "Invalid `idl` argument")),
+                        }
+                    }
+                    nested => return Err(syn::Error::new(nested.span(), "Invalid `idl` argument")),
+                }
+            }
+        }
+    }
+
+    Ok(metadata)
+}
+
+/// Parse `#[deprecated]`, `#[deprecated = "note"]` or `#[deprecated(since = "..", note = "..")]`.
+fn parse_deprecated(meta: Meta) -> syn::Result<Deprecation> {
+    let mut deprecation = Deprecation::default();
+    match meta {
+        Meta::Path(_) => {}
+        Meta::NameValue(nv) => match nv.lit {
+            Lit::Str(note) => deprecation.note = Some(note.value()),
+            lit => return Err(syn::Error::new(lit.span(), "Expected a string")),
+        },
+        Meta::List(list) => {
+            for nested in list.nested {
+                match nested {
+                    NestedMeta::Meta(Meta::NameValue(nv)) => match &nv.lit {
+                        Lit::Str(value) if nv.path.is_ident("since") => {
+                            deprecation.since = Some(value.value())
+                        }
+                        Lit::Str(value) if nv.path.is_ident("note") => {
+                            deprecation.note = Some(value.value())
+                        }
+                        _ => {
+                            return Err(syn::Error::new(nv.span(), "Invalid `deprecated` argument"))
+                        }
+                    },
+                    nested => {
+                        return Err(syn::Error::new(
+                            nested.span(),
+                            "Invalid `deprecated` argument",
+                        ))
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(deprecation)
+}
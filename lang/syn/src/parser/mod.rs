@@ -2,6 +2,8 @@ pub mod accounts;
 pub mod context;
 pub mod docs;
 pub mod error;
+pub mod invariants;
+pub mod metadata;
 pub mod program;
 pub mod spl_interface;
 
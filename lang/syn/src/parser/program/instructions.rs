@@ -1,6 +1,6 @@
-use crate::parser::docs;
 use crate::parser::program::ctx_accounts_ident;
 use crate::parser::spl_interface;
+use crate::parser::{docs, invariants, metadata};
 use crate::{FallbackFn, Ix, IxArg, IxReturn, Overrides};
 use syn::parse::{Error as ParseError, Result as ParseResult};
 use syn::spanned::Spanned;
@@ -29,19 +29,23 @@ pub fn parse(program_mod: &syn::ItemMod) -> ParseResult<(Vec<Ix>, Option<Fallbac
             let overrides = parse_overrides(&method.attrs)?;
             let interface_discriminator = spl_interface::parse(&method.attrs);
             let docs = docs::parse(&method.attrs);
+            let metadata = metadata::parse(&method.attrs)?;
             let cfgs = parse_cfg(method);
             let returns = parse_return(method)?;
             let anchor_ident = ctx_accounts_ident(&ctx.raw_arg)?;
+            let invariants = invariants::parse(&method.attrs)?;
             Ok(Ix {
                 raw_method: method.clone(),
                 ident: method.sig.ident.clone(),
                 docs,
+                metadata,
                 cfgs,
                 args,
                 anchor_ident,
                 returns,
                 interface_discriminator,
                 overrides,
+                invariants,
             })
         })
         .collect::<ParseResult<Vec<Ix>>>()?;
@@ -96,14 +100,19 @@ pub fn parse_args(method: &syn::ItemFn) -> ParseResult<(IxArg, Vec<IxArg>)> {
         .map(|arg: &syn::FnArg| match arg {
             syn::FnArg::Typed(arg) => {
                 let docs = docs::parse(&arg.attrs);
+                let metadata = metadata::parse(&arg.attrs)?;
                 let ident = match &*arg.pat {
                     syn::Pat::Ident(ident) => &ident.ident,
                     _ => return Err(ParseError::new(arg.pat.span(), "expected argument name")),
                 };
+                // `#[idl]` is not a valid attribute on function parameters
+                let mut raw_arg = arg.clone();
+                raw_arg.attrs.retain(|attr| !metadata::is_idl_attr(attr));
                 Ok(IxArg {
                     name: ident.clone(),
                     docs,
-                    raw_arg: arg.clone(),
+                    metadata,
+                    raw_arg,
                 })
             }
             syn::FnArg::Receiver(_) => Err(ParseError::new(
@@ -1,13 +1,14 @@
-use crate::parser::docs;
+use crate::parser::{docs, metadata};
 use crate::Program;
 use syn::parse::{Error as ParseError, Result as ParseResult};
 use syn::spanned::Spanned;
 
 mod instructions;
 
-pub fn parse(program_mod: syn::ItemMod) -> ParseResult<Program> {
+pub fn parse(mut program_mod: syn::ItemMod) -> ParseResult<Program> {
     let docs = docs::parse(&program_mod.attrs);
     let (ixs, fallback_fn) = instructions::parse(&program_mod)?;
+    strip_idl_attrs(&mut program_mod);
     Ok(Program {
         ixs,
         name: program_mod.ident.clone(),
@@ -17,6 +18,21 @@ pub fn parse(program_mod: syn::ItemMod) -> ParseResult<Program> {
     })
 }
 
+/// Remove the `#[idl]` attributes of the function parameters, as they are only used for the IDL.
+fn strip_idl_attrs(program_mod: &mut syn::ItemMod) {
+    if let Some((_, items)) = &mut program_mod.content {
+        for item in items {
+            if let syn::Item::Fn(item_fn) = item {
+                for input in &mut item_fn.sig.inputs {
+                    if let syn::FnArg::Typed(arg) = input {
+                        arg.attrs.retain(|attr| !metadata::is_idl_attr(attr));
+                    }
+                }
+            }
+        }
+    }
+}
+
 fn ctx_accounts_ident(path_ty: &syn::PatType) -> ParseResult<proc_macro2::Ident> {
     let p = match &*path_ty.ty {
         syn::Type::Path(p) => &p.path,
@@ -0,0 +1,41 @@
+use anchor_lang::prelude::*;
+use anchor_lang::Event;
+
+#[event]
+#[derive(Debug, PartialEq)]
+pub struct Deposit {
+    pub amount: u64,
+}
+
+#[event(discriminator = [1, 2])]
+#[derive(Debug, PartialEq)]
+pub struct Withdraw {
+    pub amount: u32,
+}
+
+#[derive(Debug, PartialEq, AnchorEvents)]
+pub enum MyEvents {
+    Deposit(Deposit),
+    Withdraw(Withdraw),
+}
+
+#[test]
+fn test_anchor_events_derive() {
+    let deposit = Deposit { amount: 1 };
+    assert_eq!(
+        MyEvents::try_from_event_data(&deposit.data()).unwrap(),
+        Some(MyEvents::Deposit(deposit))
+    );
+
+    let withdraw = Withdraw { amount: 2 };
+    assert_eq!(
+        MyEvents::try_from_event_data(&withdraw.data()).unwrap(),
+        Some(MyEvents::Withdraw(withdraw))
+    );
+
+    // Unknown discriminator
+    assert_eq!(MyEvents::try_from_event_data(&[3, 4, 5]).unwrap(), None);
+
+    // Known discriminator but invalid data
+    assert!(MyEvents::try_from_event_data(Withdraw::DISCRIMINATOR).is_err());
+}
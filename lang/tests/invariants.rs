@@ -0,0 +1,149 @@
+// `debug-invariants` is a feature of the programs using the invariants
+#![allow(unexpected_cfgs)]
+
+use anchor_lang::error::{Error, ErrorCode};
+use anchor_lang::prelude::*;
+use anchor_lang::InstructionData;
+use solana_program::clock::Epoch;
+
+// Needed to declare accounts.
+declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+#[derive(Accounts)]
+#[invariant(vault.lamports() >= pool.lamports())]
+pub struct Deposit<'info> {
+    /// CHECK: Test account.
+    pub vault: UncheckedAccount<'info>,
+    /// CHECK: Test account.
+    pub pool: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[invariant(payer.key() != common.vault.key())]
+pub struct Nested<'info> {
+    pub common: Deposit<'info>,
+    /// CHECK: Test account.
+    pub payer: UncheckedAccount<'info>,
+}
+
+#[program]
+pub mod vault {
+    use super::*;
+
+    #[invariant(accounts.common.vault.lamports() >= amount)]
+    pub fn withdraw(_ctx: Context<Nested>, amount: u64) -> Result<()> {
+        // The withdrawal is only checked by the invariant
+        let _ = amount;
+        Ok(())
+    }
+}
+
+fn exit(vault_lamports: u64, pool_lamports: u64, payer_is_vault: bool) -> Result<()> {
+    let keys = [Pubkey::new_unique(), Pubkey::new_unique()];
+    let mut lamports = [vault_lamports, pool_lamports];
+    let [vault_lamports, pool_lamports] = &mut lamports;
+    let mut vault_data = vec![];
+    let mut pool_data = vec![];
+    let vault = AccountInfo::new(
+        &keys[0],
+        false,
+        false,
+        vault_lamports,
+        &mut vault_data,
+        &ID,
+        false,
+        Epoch::default(),
+    );
+    let pool = AccountInfo::new(
+        &keys[1],
+        false,
+        false,
+        pool_lamports,
+        &mut pool_data,
+        &ID,
+        false,
+        Epoch::default(),
+    );
+    let payer = match payer_is_vault {
+        true => vault.clone(),
+        false => pool.clone(),
+    };
+    let accounts = Nested {
+        common: Deposit {
+            vault: UncheckedAccount::try_from(&vault),
+            pool: UncheckedAccount::try_from(&pool),
+        },
+        payer: UncheckedAccount::try_from(&payer),
+    };
+    accounts.exit(&ID)
+}
+
+fn invariant_violated(result: Result<()>) -> String {
+    match result {
+        Err(Error::AnchorError(e)) => {
+            assert_eq!(e.error_name, ErrorCode::InvariantViolated.name());
+            e.error_msg
+        }
+        result => panic!("Unexpected result: {result:?}"),
+    }
+}
+
+#[test]
+fn test_invariants_hold() {
+    assert!(exit(2, 1, false).is_ok());
+    assert!(exit(1, 1, false).is_ok());
+}
+
+#[test]
+fn test_invariant_violated() {
+    assert_eq!(
+        invariant_violated(exit(1, 2, false)),
+        "An invariant was violated: vault.lamports() >= pool.lamports()"
+    );
+}
+
+#[test]
+fn test_composite_invariant_violated() {
+    assert_eq!(
+        invariant_violated(exit(2, 1, true)),
+        "An invariant was violated: payer.key() != common.vault.key()"
+    );
+}
+
+#[test]
+fn test_instruction_invariants() {
+    let withdraw = |vault_lamports, amount| {
+        let keys = [
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        ];
+        let mut lamports = [vault_lamports, 0, 0];
+        let mut data = [vec![], vec![], vec![]];
+        let accounts = keys
+            .iter()
+            .zip(&mut lamports)
+            .zip(&mut data)
+            .map(|((key, lamports), data)| {
+                AccountInfo::new(
+                    key,
+                    false,
+                    false,
+                    lamports,
+                    data,
+                    &ID,
+                    false,
+                    Epoch::default(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let ix_data = instruction::Withdraw { amount }.data();
+        entry(&ID, &accounts, &ix_data)
+    };
+
+    assert!(withdraw(2, 1).is_ok());
+    assert_eq!(
+        withdraw(1, 2),
+        Err(ProgramError::Custom(ErrorCode::InvariantViolated.into()))
+    );
+}
@@ -84,4 +84,72 @@ describe("Test CLI IDL commands", () => {
     );
     assert.deepEqual(idlActual, idlExpected);
   });
+
+  it("Can write and fetch program metadata via the CLI", async () => {
+    fs.writeFileSync(
+      "target/source-url.txt",
+      "https://github.com/coral-xyz/anchor"
+    );
+    execSync(
+      `anchor idl metadata write --key source-url --filepath target/source-url.txt ${programTwo.programId}`,
+      { stdio: "inherit" }
+    );
+    const sourceUrl = execSync(
+      `anchor idl metadata fetch --key source-url ${programTwo.programId}`
+    )
+      .toString()
+      .trim();
+    assert.equal(sourceUrl, "https://github.com/coral-xyz/anchor");
+  });
+
+  it("Can fetch an IDL from the program metadata", async () => {
+    execSync(
+      `anchor idl metadata write --key idl --filepath target/idl/idl_commands_two.json ${programTwo.programId}`,
+      { stdio: "inherit" }
+    );
+    const idl = await anchor.Program.fetchIdl(programTwo.programId, provider);
+    assert.deepEqual(idl, programTwo.rawIdl);
+  });
+
+  it("Can write program metadata to a prefunded address", async () => {
+    // Anyone can send lamports to the address before the metadata is created
+    const [metadata] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("anchor:metadata"), Buffer.from("prefunded")],
+      programTwo.programId
+    );
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.transfer({
+          fromPubkey: provider.wallet.publicKey,
+          toPubkey: metadata,
+          lamports:
+            await provider.connection.getMinimumBalanceForRentExemption(0),
+        })
+      )
+    );
+
+    execSync(
+      `anchor idl metadata write --key prefunded --filepath target/source-url.txt ${programTwo.programId}`,
+      { stdio: "inherit" }
+    );
+    const sourceUrl = execSync(
+      `anchor idl metadata fetch --key prefunded ${programTwo.programId}`
+    )
+      .toString()
+      .trim();
+    assert.equal(sourceUrl, "https://github.com/coral-xyz/anchor");
+  });
+
+  it("Can write non-canonical program metadata", async () => {
+    execSync(
+      `anchor idl metadata write --non-canonical --key source-url --filepath target/source-url.txt ${programTwo.programId}`,
+      { stdio: "inherit" }
+    );
+    const sourceUrl = execSync(
+      `anchor idl metadata fetch --key source-url --authority ${provider.wallet.publicKey} ${programTwo.programId}`
+    )
+      .toString()
+      .trim();
+    assert.equal(sourceUrl, "https://github.com/coral-xyz/anchor");
+  });
 });
@@ -12,6 +12,10 @@ declare_program!(external_legacy);
 // https://github.com/raydium-io/raydium-idl/blob/c8507c78618eda1de96ff5e43bd29daefa7e9307/raydium_clmm/amm_v3.json
 declare_program!(amm_v3);
 
+// Compilation check for types that are defined by another declared program
+declare_program!(external_shared);
+const _: fn(external::accounts::MyAccount) -> external_shared::types::MyAccount = |value| value;
+
 #[program]
 pub mod declare_program {
     use super::*;
@@ -19,6 +23,10 @@ pub mod declare_program {
     pub fn cpi(ctx: Context<Cpi>, value: u32) -> Result<()> {
         let cpi_my_account = &mut ctx.accounts.cpi_my_account;
         require_keys_eq!(external::accounts::MyAccount::owner(), external::ID);
+        require_keys_eq!(
+            cpi_my_account.key(),
+            external::pda::find_my_account_address(ctx.accounts.authority.key).0
+        );
         require_eq!(cpi_my_account.field, 0);
 
         let cpi_ctx = CpiContext::new(
@@ -116,6 +124,17 @@ pub mod declare_program {
             Err(e) => return Err(e.into()),
         }
 
+        // Unknown discriminator
+        if Event::try_from_event_data(&[0; 8])?.is_some() {
+            return Err(ProgramError::Custom(2).into());
+        }
+
+        // Decoding without knowing the event type
+        match Event::try_from_event_data(&[DISC, &[2, 0, 0, 0]].concat())? {
+            Some(Event::MyEvent(my_event)) => require_eq!(my_event.value, 2),
+            None => return Err(ProgramError::Custom(3).into()),
+        }
+
         Ok(())
     }
 }
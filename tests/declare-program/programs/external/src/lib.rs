@@ -1,5 +1,7 @@
 #![allow(unused_variables)]
 
+use std::collections::{BTreeMap, BTreeSet};
+
 use anchor_lang::prelude::*;
 
 declare_id!("Externa111111111111111111111111111111111111");
@@ -56,6 +58,16 @@ pub mod external {
         Ok(())
     }
 
+    // Compilation test for tuple, map and set types
+    pub fn test_compilation_collection_types(
+        _ctx: Context<TestCompilation>,
+        _tuple: (u8, String),
+        _map: BTreeMap<Pubkey, u64>,
+        _set: BTreeSet<u16>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
     // Compilation test for an instruction with no accounts
     pub fn test_compilation_no_accounts(_ctx: Context<TestCompilationNoAccounts>) -> Result<()> {
         Ok(())
@@ -17,6 +17,16 @@ pub mod docs {
     pub fn test_idl_doc_parse(_ctx: Context<TestIdlDocParse>) -> Result<()> {
         Ok(())
     }
+
+    #[deprecated(since = "0.2.0", note = "Use `test_idl_doc_parse` instead")]
+    pub fn test_idl_metadata_parse(
+        _ctx: Context<TestIdlDocParse>,
+        #[idl(unit = "lamports", decimals = 9)]
+        _amount: u64,
+        #[idl(deprecated = "Unused")] _memo: String,
+    ) -> Result<()> {
+        err!(DocsError::Deprecated)
+    }
 }
 
 /// Custom account doc comment should appear in the IDL
@@ -24,6 +34,26 @@ pub mod docs {
 pub struct DataWithDoc {
     /// Account attribute doc comment should appear in the IDL
     pub data: u16,
+    /// Fee of the account
+    #[idl(unit = "basis_points")]
+    pub fee: u16,
+    #[deprecated = "Use `data` instead"]
+    pub old_data: u16,
+    pub version: Version,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub enum Version {
+    #[deprecated]
+    V1,
+    V2,
+}
+
+#[error_code]
+pub enum DocsError {
+    /// Error doc comment should appear in the IDL
+    #[msg("Instruction is deprecated")]
+    Deprecated,
 }
 
 #[derive(Accounts)]
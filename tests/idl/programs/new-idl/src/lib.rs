@@ -1,3 +1,5 @@
+use std::collections::{BTreeMap, BTreeSet};
+
 use anchor_lang::prelude::*;
 
 declare_id!("Newid11111111111111111111111111111111111111");
@@ -60,6 +62,18 @@ pub mod new_idl {
         Ok(())
     }
 
+    pub fn collection_types(
+        ctx: Context<CollectionTypes>,
+        tuple: (u8, String),
+        map: BTreeMap<u8, Pubkey>,
+        set: BTreeSet<u16>,
+    ) -> Result<()> {
+        ctx.accounts.account.tuple = tuple;
+        ctx.accounts.account.map = map;
+        ctx.accounts.account.set = set;
+        Ok(())
+    }
+
     pub fn strct(
         ctx: Context<Struct>,
         unit: UnitStruct,
@@ -212,6 +226,19 @@ pub struct UnsizedAccount {
     pub bytes: Vec<u8>,
 }
 
+#[derive(Accounts)]
+pub struct CollectionTypes<'info> {
+    #[account(zero)]
+    pub account: Account<'info, CollectionAccount>,
+}
+
+#[account]
+pub struct CollectionAccount {
+    pub tuple: (u8, String),
+    pub map: BTreeMap<u8, Pubkey>,
+    pub set: BTreeSet<u16>,
+}
+
 #[derive(Accounts)]
 pub struct Struct<'info> {
     #[account(zero)]
@@ -456,12 +483,16 @@ mod wrapped {
                         name: "activated_at".into(),
                         ty: IdlType::Option(Box::new(IdlType::U64)),
                         docs: Default::default(),
+                        deprecated: Default::default(),
+                        unit: Default::default(),
+                        decimals: Default::default(),
                     }])),
                 },
                 docs: Default::default(),
                 generics: Default::default(),
                 serialization: Default::default(),
                 repr: Default::default(),
+                origin: Default::default(),
             })
         }
     }
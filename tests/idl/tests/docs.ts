@@ -47,4 +47,42 @@ describe("Docs", () => {
     const myConst = program.idl.constants.find((c) => c.name === "myConst")!;
     assert.deepEqual(myConst.docs, ["Documentation comment for constant"]);
   });
+
+  it("includes error doc comment", () => {
+    const error = program.idl.errors.find((e) => e.name === "deprecated")!;
+    assert.deepEqual(error.docs, [
+      "Error doc comment should appear in the IDL",
+    ]);
+  });
+
+  describe("Metadata", () => {
+    const instruction = program.idl.instructions.find(
+      (i) => i.name === "testIdlMetadataParse"
+    )!;
+
+    it("includes instruction deprecation", () => {
+      assert.deepEqual(instruction.deprecated, {
+        since: "0.2.0",
+        note: "Use `test_idl_doc_parse` instead",
+      });
+    });
+
+    it("includes argument metadata", () => {
+      const [amount, memo] = instruction.args;
+      assert.strictEqual(amount.unit, "lamports");
+      assert.strictEqual(amount.decimals, 9);
+      assert.deepEqual(memo.deprecated, { note: "Unused" });
+    });
+
+    it("includes field metadata", () => {
+      const [, fee, oldData] = dataWithDoc.type.fields;
+      assert.strictEqual(fee.unit, "basis_points");
+      assert.deepEqual(oldData.deprecated, { note: "Use `data` instead" });
+    });
+
+    it("includes enum variant deprecation", () => {
+      const version = program.idl.types.find((ty) => ty.name === "version")!;
+      assert.deepEqual(version.type.variants[0].deprecated, {});
+    });
+  });
 });
@@ -151,6 +151,29 @@ describe("New IDL", () => {
       assert(account.bytes.equals(bytes));
     });
 
+    it("Can use tuple, map and set types", async () => {
+      const tuple = [1, "tuple"] as const;
+      const map = new Map([[2, anchor.web3.PublicKey.default]]);
+      const set = new Set([3, 4]);
+
+      const kp = anchor.web3.Keypair.generate();
+      await program.methods
+        .collectionTypes(tuple, map, set)
+        .accounts({ account: kp.publicKey })
+        .signers([kp])
+        .preInstructions([
+          await program.account.collectionAccount.createInstruction(kp, 1024),
+        ])
+        .rpc();
+
+      const account = await program.account.collectionAccount.fetch(
+        kp.publicKey
+      );
+      assert.deepEqual(account.tuple, tuple);
+      assert.deepEqual(account.map, map);
+      assert.deepEqual(account.set, set);
+    });
+
     it("Can use struct", async () => {
       const unitStructArg = {} as const;
       const namedStructArg = {
@@ -17,6 +17,12 @@ export const ANCHOR_ERROR__IDL_INSTRUCTION_STUB = 1000;
 export const ANCHOR_ERROR__IDL_INSTRUCTION_INVALID_PROGRAM = 1001;
 /** IDL account must be empty in order to resize, try closing first. */
 export const ANCHOR_ERROR__IDL_ACCOUNT_NOT_EMPTY = 1002;
+/** Program metadata key must be between 1 and 32 bytes. */
+export const ANCHOR_ERROR__IDL_METADATA_INVALID_KEY = 1003;
+/** Canonical program metadata requires the upgrade authority of the program. */
+export const ANCHOR_ERROR__IDL_METADATA_NOT_UPGRADE_AUTHORITY = 1004;
+/** Program metadata must be written without gaps. */
+export const ANCHOR_ERROR__IDL_METADATA_INVALID_OFFSET = 1005;
 
 // Event instructions.
 
@@ -122,6 +128,8 @@ export const ANCHOR_ERROR__REQUIRE_KEYS_NEQ_VIOLATED = 2504;
 export const ANCHOR_ERROR__REQUIRE_GT_VIOLATED = 2505;
 /** A require_gte expression was violated. */
 export const ANCHOR_ERROR__REQUIRE_GTE_VIOLATED = 2506;
+/** An invariant was violated. */
+export const ANCHOR_ERROR__INVARIANT_VIOLATED = 2507;
 
 // Account errors.
 
@@ -185,6 +193,9 @@ export type AnchorErrorCode =
   | typeof ANCHOR_ERROR__IDL_INSTRUCTION_STUB
   | typeof ANCHOR_ERROR__IDL_INSTRUCTION_INVALID_PROGRAM
   | typeof ANCHOR_ERROR__IDL_ACCOUNT_NOT_EMPTY
+  | typeof ANCHOR_ERROR__IDL_METADATA_INVALID_KEY
+  | typeof ANCHOR_ERROR__IDL_METADATA_NOT_UPGRADE_AUTHORITY
+  | typeof ANCHOR_ERROR__IDL_METADATA_INVALID_OFFSET
   | typeof ANCHOR_ERROR__EVENT_INSTRUCTION_STUB
   | typeof ANCHOR_ERROR__CONSTRAINT_MUT
   | typeof ANCHOR_ERROR__CONSTRAINT_HAS_ONE
@@ -233,6 +244,7 @@ export type AnchorErrorCode =
   | typeof ANCHOR_ERROR__REQUIRE_KEYS_NEQ_VIOLATED
   | typeof ANCHOR_ERROR__REQUIRE_GT_VIOLATED
   | typeof ANCHOR_ERROR__REQUIRE_GTE_VIOLATED
+  | typeof ANCHOR_ERROR__INVARIANT_VIOLATED
   | typeof ANCHOR_ERROR__ACCOUNT_DISCRIMINATOR_ALREADY_SET
   | typeof ANCHOR_ERROR__ACCOUNT_DISCRIMINATOR_NOT_FOUND
   | typeof ANCHOR_ERROR__ACCOUNT_DISCRIMINATOR_MISMATCH
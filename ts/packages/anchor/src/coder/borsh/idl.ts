@@ -102,6 +102,35 @@ export class IdlCoder {
             fieldName
           );
         }
+        if ("tuple" in field.type) {
+          return borsh.tuple(
+            field.type.tuple.map((type) =>
+              IdlCoder.fieldLayout({ type }, types, genericArgs)
+            ),
+            fieldName
+          );
+        }
+        if ("map" in field.type) {
+          return borsh.map(
+            IdlCoder.fieldLayout(
+              { type: field.type.map.key },
+              types,
+              genericArgs
+            ),
+            IdlCoder.fieldLayout(
+              { type: field.type.map.value },
+              types,
+              genericArgs
+            ),
+            fieldName
+          );
+        }
+        if ("set" in field.type) {
+          return borsh.set(
+            IdlCoder.fieldLayout({ type: field.type.set }, types, genericArgs),
+            fieldName
+          );
+        }
         if ("defined" in field.type) {
           if (!types) {
             throw new IdlError("User defined types not provided");
@@ -300,6 +329,14 @@ export class IdlCoder {
           len = IdlCoder.resolveArrayLen(len, genericArgs);
           return IdlCoder.typeSize(type, idl, genericArgs) * len;
         }
+        if ("tuple" in ty) {
+          return ty.tuple
+            .map((type) => IdlCoder.typeSize(type, idl, genericArgs))
+            .reduce((acc, size) => acc + size, 0);
+        }
+        if ("map" in ty || "set" in ty) {
+          return 1;
+        }
         if ("defined" in ty) {
           const typeDef = idl.types?.find((t) => t.name === ty.defined.name);
           if (!typeDef) {
@@ -174,6 +174,17 @@ class InstructionFormatter {
     if ("array" in idlType) {
       return `Array<${idlType.array[0]}; ${idlType.array[1]}>`;
     }
+    if ("tuple" in idlType) {
+      return `(${idlType.tuple.map((ty) => this.formatIdlType(ty)).join(", ")})`;
+    }
+    if ("map" in idlType) {
+      return `Map<${this.formatIdlType(idlType.map.key)}, ${this.formatIdlType(
+        idlType.map.value
+      )}>`;
+    }
+    if ("set" in idlType) {
+      return `Set<${this.formatIdlType(idlType.set)}>`;
+    }
     if ("defined" in idlType) {
       const name = idlType.defined.name;
       if (idlType.defined.generics) {
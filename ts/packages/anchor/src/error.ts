@@ -321,6 +321,10 @@ export const LangErrorCode = {
   IdlInstructionInvalidProgram:
     errors.ANCHOR_ERROR__IDL_INSTRUCTION_INVALID_PROGRAM,
   IdlAccountNotEmpty: errors.ANCHOR_ERROR__IDL_ACCOUNT_NOT_EMPTY,
+  IdlMetadataInvalidKey: errors.ANCHOR_ERROR__IDL_METADATA_INVALID_KEY,
+  IdlMetadataNotUpgradeAuthority:
+    errors.ANCHOR_ERROR__IDL_METADATA_NOT_UPGRADE_AUTHORITY,
+  IdlMetadataInvalidOffset: errors.ANCHOR_ERROR__IDL_METADATA_INVALID_OFFSET,
 
   // Event instructions.
   EventInstructionStub: errors.ANCHOR_ERROR__EVENT_INSTRUCTION_STUB,
@@ -396,6 +400,7 @@ export const LangErrorCode = {
   RequireKeysNeqViolated: errors.ANCHOR_ERROR__REQUIRE_KEYS_NEQ_VIOLATED,
   RequireGtViolated: errors.ANCHOR_ERROR__REQUIRE_GT_VIOLATED,
   RequireGteViolated: errors.ANCHOR_ERROR__REQUIRE_GTE_VIOLATED,
+  InvariantViolated: errors.ANCHOR_ERROR__INVARIANT_VIOLATED,
 
   // Accounts.
   AccountDiscriminatorAlreadySet:
@@ -462,6 +467,18 @@ export const LangErrorMessage = new Map<number, string>([
     LangErrorCode.IdlAccountNotEmpty,
     "IDL account must be empty in order to resize, try closing first",
   ],
+  [
+    LangErrorCode.IdlMetadataInvalidKey,
+    "Program metadata key must be between 1 and 32 bytes",
+  ],
+  [
+    LangErrorCode.IdlMetadataNotUpgradeAuthority,
+    "Canonical program metadata requires the upgrade authority of the program",
+  ],
+  [
+    LangErrorCode.IdlMetadataInvalidOffset,
+    "Program metadata must be written without gaps",
+  ],
 
   // Event instructions.
   [
@@ -603,6 +620,7 @@ export const LangErrorMessage = new Map<number, string>([
   ],
   [LangErrorCode.RequireGtViolated, "A require_gt expression was violated"],
   [LangErrorCode.RequireGteViolated, "A require_gte expression was violated"],
+  [LangErrorCode.InvariantViolated, "An invariant was violated"],
 
   // Accounts.
   [
@@ -2,6 +2,8 @@ import camelCase from "camelcase";
 import { Buffer } from "buffer";
 import { PublicKey } from "@solana/web3.js";
 import * as borsh from "@coral-xyz/borsh";
+import { sha256 } from "@noble/hashes/sha256";
+import { inflate } from "pako";
 
 export type Idl = {
   address: string;
@@ -45,6 +47,12 @@ export type IdlInstruction = {
   accounts: IdlInstructionAccountItem[];
   args: IdlField[];
   returns?: IdlType;
+  deprecated?: IdlDeprecation;
+};
+
+export type IdlDeprecation = {
+  since?: string;
+  note?: string;
 };
 
 export type IdlInstructionAccountItem =
@@ -109,6 +117,7 @@ export type IdlConst = {
 export type IdlErrorCode = {
   name: string;
   code: number;
+  docs?: string[];
   msg?: string;
 };
 
@@ -116,6 +125,9 @@ export type IdlField = {
   name: string;
   docs?: string[];
   type: IdlType;
+  deprecated?: IdlDeprecation;
+  unit?: string;
+  decimals?: number;
 };
 
 export type IdlTypeDef = {
@@ -125,6 +137,12 @@ export type IdlTypeDef = {
   repr?: IdlRepr;
   generics?: IdlTypeDefGeneric[];
   type: IdlTypeDefTy;
+  origin?: IdlTypeDefOrigin;
+};
+
+export type IdlTypeDefOrigin = {
+  program: string;
+  name: string;
 };
 
 export type IdlSerialization =
@@ -188,6 +206,7 @@ export type IdlTypeDefTyType = {
 export type IdlEnumVariant = {
   name: string;
   fields?: IdlDefinedFields;
+  deprecated?: IdlDeprecation;
 };
 
 export type IdlDefinedFields = IdlDefinedFieldsNamed | IdlDefinedFieldsTuple;
@@ -233,6 +252,9 @@ export type IdlType =
   | IdlTypeCOption
   | IdlTypeVec
   | IdlTypeArray
+  | IdlTypeTuple
+  | IdlTypeMap
+  | IdlTypeSet
   | IdlTypeDefined
   | IdlTypeGeneric;
 
@@ -252,6 +274,18 @@ export type IdlTypeArray = {
   array: [idlType: IdlType, size: IdlArrayLen];
 };
 
+export type IdlTypeTuple = {
+  tuple: IdlType[];
+};
+
+export type IdlTypeMap = {
+  map: { key: IdlType; value: IdlType };
+};
+
+export type IdlTypeSet = {
+  set: IdlType;
+};
+
 export type IdlTypeDefined = {
   defined: {
     name: string;
@@ -265,6 +299,34 @@ export type IdlTypeGeneric = {
 
 export type IdlDiscriminator = number[];
 
+export type IdlBundle = {
+  idls: Idl[];
+};
+
+/**
+ * Resolve the types of the IDL that are defined by another program of the
+ * bundle to the definition of that program.
+ *
+ * Types whose origin is not in the bundle are kept as is.
+ */
+export function resolveIdlTypes<I extends Idl>(idl: I, bundle: IdlBundle): I {
+  if (!idl.types) return idl;
+
+  const types = idl.types.map((tyDef) => {
+    if (!tyDef.origin) return tyDef;
+
+    const { program, name } = tyDef.origin;
+    const originTyDef = bundle.idls
+      .find((other) => other.address === program)
+      ?.types?.find((other) => other.name === name && !other.origin);
+    if (!originTyDef) return tyDef;
+
+    return { ...originTyDef, name: tyDef.name, origin: tyDef.origin };
+  });
+
+  return { ...idl, types };
+}
+
 export function isCompositeAccounts(
   accountItem: IdlInstructionAccountItem
 ): accountItem is IdlInstructionAccounts {
@@ -282,6 +344,33 @@ export function seed(): string {
   return "anchor:idl";
 }
 
+// Address of the program metadata account of the given key. Canonical metadata
+// is derived from the key only, and non-canonical metadata is also derived
+// from its authority.
+export function metadataAddress(
+  programId: PublicKey,
+  key: string,
+  authority?: PublicKey
+): PublicKey {
+  const seeds = [Buffer.from("anchor:metadata")];
+  if (authority) seeds.push(authority.toBuffer());
+  seeds.push(Buffer.from(key));
+  return PublicKey.findProgramAddressSync(seeds, programId)[0];
+}
+
+// Offset of the metadata in the program metadata account:
+// discriminator || authority || canonical || key || data length.
+export const PROGRAM_METADATA_DATA_OFFSET = 8 + 32 + 1 + 32 + 4;
+
+// Get the metadata bytes of a program metadata account.
+export function decodeProgramMetadata(data: Buffer): Buffer {
+  const dataLen = data.readUInt32LE(PROGRAM_METADATA_DATA_OFFSET - 4);
+  return data.subarray(
+    PROGRAM_METADATA_DATA_OFFSET,
+    PROGRAM_METADATA_DATA_OFFSET + dataLen
+  );
+}
+
 // The on-chain account of the IDL.
 export interface IdlProgramAccount {
   authority: PublicKey;
@@ -303,6 +392,51 @@ export function encodeIdlAccount(acc: IdlProgramAccount): Buffer {
   return buffer.slice(0, len);
 }
 
+// Magic bytes that prefix the header of the IDL data stored in IDL accounts.
+const IDL_DATA_MAGIC = Buffer.from("AIDL");
+
+// Supported version of the IDL data header.
+const IDL_DATA_VERSION = 1;
+
+// Header of the IDL data, stored before the compressed IDL.
+export interface IdlDataHeader {
+  version: number;
+  spec: string;
+  hash: number[];
+}
+
+const IDL_DATA_HEADER_LAYOUT: borsh.Layout<IdlDataHeader> = borsh.struct([
+  borsh.u8("version"),
+  borsh.str("spec"),
+  borsh.array(borsh.u8(), 32, "hash"),
+]);
+
+/**
+ * Decompress the IDL data stored in an IDL account and verify its hash.
+ *
+ * IDL data written without a header (by older versions of the CLI) is
+ * decompressed without verification.
+ */
+export function decodeIdlData(data: Buffer): Buffer {
+  if (!data.subarray(0, IDL_DATA_MAGIC.length).equals(IDL_DATA_MAGIC)) {
+    return Buffer.from(inflate(data));
+  }
+
+  data = data.subarray(IDL_DATA_MAGIC.length);
+  const header = IDL_DATA_HEADER_LAYOUT.decode(data);
+  if (header.version !== IDL_DATA_VERSION) {
+    throw new Error(`Unsupported IDL data version: ${header.version}`);
+  }
+
+  const headerLen = IDL_DATA_HEADER_LAYOUT.getSpan(data);
+  const idl = Buffer.from(inflate(data.subarray(headerLen)));
+  if (!Buffer.from(sha256(idl)).equals(Buffer.from(header.hash))) {
+    throw new Error("IDL hash mismatch");
+  }
+
+  return idl;
+}
+
 /**
  * Convert the given IDL to camelCase.
  *
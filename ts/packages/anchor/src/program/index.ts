@@ -1,12 +1,14 @@
 import { Commitment, PublicKey } from "@solana/web3.js";
-import { inflate } from "pako";
 import { BorshCoder, Coder } from "../coder/index.js";
 import {
   Idl,
   IdlInstruction,
   convertIdlToCamelCase,
   decodeIdlAccount,
+  decodeIdlData,
+  decodeProgramMetadata,
   idlAddress,
+  metadataAddress,
 } from "../idl.js";
 import Provider, { getProvider } from "../provider.js";
 import { utf8 } from "../utils/bytes/index.js";
@@ -316,7 +318,8 @@ export class Program<IDL extends Idl = Idl> {
    * Generates a Program client by fetching the IDL from the network.
    *
    * In order to use this method, an IDL must have been previously initialized
-   * via the anchor CLI's `anchor idl init` command.
+   * via the anchor CLI's `anchor idl init` command, or written to the program's
+   * canonical `idl` metadata via `anchor idl metadata write`.
    *
    * @param programId The on-chain address of the program.
    * @param provider  The network and wallet context.
@@ -339,7 +342,8 @@ export class Program<IDL extends Idl = Idl> {
    * Fetches an idl from the blockchain.
    *
    * In order to use this method, an IDL must have been previously initialized
-   * via the anchor CLI's `anchor idl init` command.
+   * via the anchor CLI's `anchor idl init` command, or written to the program's
+   * canonical `idl` metadata via `anchor idl metadata write`.
    *
    * @param programId The on-chain address of the program.
    * @param provider  The network and wallet context.
@@ -354,12 +358,21 @@ export class Program<IDL extends Idl = Idl> {
     const idlAddr = await idlAddress(programId);
     const accountInfo = await provider.connection.getAccountInfo(idlAddr);
     if (!accountInfo) {
-      return null;
+      // Fall back to the canonical IDL metadata of the program.
+      const metadataAddr = metadataAddress(programId, "idl");
+      const metadataInfo = await provider.connection.getAccountInfo(
+        metadataAddr
+      );
+      if (!metadataInfo) {
+        return null;
+      }
+      const idl = decodeIdlData(decodeProgramMetadata(metadataInfo.data));
+      return JSON.parse(utf8.decode(idl));
     }
     // Chop off account discriminator.
     let idlAccount = decodeIdlAccount(accountInfo.data.slice(8));
-    const inflatedIdl = inflate(idlAccount.data);
-    return JSON.parse(utf8.decode(inflatedIdl));
+    const idl = decodeIdlData(idlAccount.data);
+    return JSON.parse(utf8.decode(idl));
   }
 
   /**
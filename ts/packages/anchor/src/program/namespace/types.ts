@@ -145,6 +145,15 @@ export type DecodeType<T extends IdlType, Defined> = IdlType extends T
   ? DecodeType<T["vec"], Defined>[]
   : T extends { array: [defined: IdlType, size: IdlArrayLen] }
   ? DecodeType<T["array"][0], Defined>[]
+  : T extends { tuple: IdlType[] }
+  ? { [K in keyof T["tuple"]]: DecodeType<T["tuple"][K], Defined> }
+  : T extends { map: { key: IdlType; value: IdlType } }
+  ? Map<
+      DecodeType<T["map"]["key"], Defined>,
+      DecodeType<T["map"]["value"], Defined>
+    >
+  : T extends { set: IdlType }
+  ? Set<DecodeType<T["set"], Defined>>
   : unknown;
 
 /**
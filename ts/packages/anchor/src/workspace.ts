@@ -2,7 +2,7 @@ import * as toml from "toml";
 import camelcase from "camelcase";
 import { Program } from "./program/index.js";
 import { isBrowser } from "./utils/common.js";
-import { Idl } from "./idl.js";
+import { Idl, IdlBundle, resolveIdlTypes } from "./idl.js";
 
 /**
  * The `workspace` namespace provides a convenience API to automatically
@@ -71,7 +71,15 @@ const workspace = new Proxy(
         );
       }
 
-      const idl: Idl = JSON.parse(fs.readFileSync(idlPath));
+      let idl: Idl = JSON.parse(fs.readFileSync(idlPath));
+
+      // Resolve the types that are defined by other programs of the workspace
+      const bundlePath = path.join("target", "idl-bundle.json");
+      if (fs.existsSync(bundlePath)) {
+        const bundle: IdlBundle = JSON.parse(fs.readFileSync(bundlePath));
+        idl = resolveIdlTypes(idl, bundle);
+      }
+
       if (programId) {
         idl.address = programId;
       }
@@ -349,3 +349,30 @@ export function map<K, V>(
     property
   );
 }
+
+export function tuple<T extends unknown[]>(
+  layouts: Layout<unknown>[],
+  property?: string
+): Layout<T> {
+  const fields = layouts.map((layout, i) => layout.replicate(i.toString()));
+  const layout: Layout<Record<string, unknown>> = struct(fields);
+  return new WrappedLayout(
+    layout,
+    (data) => fields.map((_, i) => data[i.toString()]) as T,
+    (values) =>
+      Object.fromEntries(values.map((value, i) => [i.toString(), value])),
+    property
+  );
+}
+
+export function set<T>(
+  elementLayout: Layout<T>,
+  property?: string
+): Layout<Set<T>> {
+  return new WrappedLayout(
+    vec(elementLayout),
+    (values) => new Set(values),
+    (values) => Array.from(values),
+    property
+  );
+}